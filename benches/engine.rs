@@ -0,0 +1,51 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dynterm::control::Command;
+use dynterm::scenario::Scenario;
+use dynterm::atmosphere::{atmo_density, isa_relative_density, AtmosphereTable};
+
+// Length of a full run and the step it's sampled at, s
+const RUN_TIME: f64 = 180.0;
+const DT: f64 = 0.01;
+
+fn dynamics(c: &mut Criterion) {
+    let vehicle = Scenario::default().vehicle.build();
+    c.bench_function("calculate_dynamics", |b| {
+        b.iter(|| vehicle.calculate_dynamics(black_box(&vehicle.position), black_box(&vehicle.motion)))
+    });
+}
+
+fn interpolate(c: &mut Criterion) {
+    let vehicle = Scenario::default().vehicle.build();
+    let (cl, _, _) = vehicle.wing.tables();
+    c.bench_function("Linear::interpolate", |b| {
+        b.iter(|| cl.interpolate(black_box(7.3)))
+    });
+}
+
+fn atmosphere(c: &mut Criterion) {
+    let table = AtmosphereTable::new(-1000.0, 50000.0, 10.0);
+    c.bench_function("atmo_density", |b| b.iter(|| atmo_density(black_box(6_300.0))));
+    c.bench_function("isa_relative_density", |b| b.iter(|| isa_relative_density(black_box(6_300.0))));
+    c.bench_function("AtmosphereTable::density", |b| b.iter(|| table.density(black_box(6_300.0))));
+}
+
+fn full_run(c: &mut Criterion) {
+    let scenario = Scenario { seed: Some(0), ..Scenario::default() };
+    let mut group = c.benchmark_group("run");
+    group.sample_size(10);
+    group.bench_function("180 s", |b| {
+        b.iter(|| {
+            let mut sim = scenario.build();
+            for _ in 0..(RUN_TIME / DT).round() as usize {
+                Command::pull_up(&sim.vehicle).apply(&mut sim.vehicle);
+                sim.step(DT);
+                if sim.vehicle.position.y() <= 0.0 { break; }
+            }
+            sim.stats
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dynamics, interpolate, atmosphere, full_run);
+criterion_main!(benches);
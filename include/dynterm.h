@@ -0,0 +1,66 @@
+#ifndef DYNTERM_H
+#define DYNTERM_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Number of values written by `dynterm_state`: time, x, y, vx, vy, pitch,
+ pitch rate, and angle of attack, in that order.
+ */
+#define DYNTERM_STATE_LEN 8
+
+typedef struct dynterm_simulation dynterm_simulation;
+
+/*
+ Creates a simulation from a JSON vehicle config, or with the default
+ vehicle if `config_json` is null. Fields left out of the config take
+ their defaults. Returns null if the config can't be parsed or the
+ vehicle can't be built, with the reason left for `dynterm_last_error`.
+
+ # Safety
+ `config_json` must be null or a valid NUL-terminated string.
+ */
+struct dynterm_simulation *dynterm_new(const char *config_json);
+
+/*
+ Advances the simulation by `dt` seconds. Does nothing if the step
+ fails, leaving the reason for `dynterm_last_error`.
+
+ # Safety
+ `sim` must have come from `dynterm_new` and not yet been freed.
+ */
+void dynterm_step(struct dynterm_simulation *sim, double dt);
+
+/*
+ Returns why the last call on this thread that failed did, or null if
+ none has. The string belongs to the library and stays valid until the
+ next call that fails on the same thread.
+ */
+const char *dynterm_last_error(void);
+
+/*
+ Writes up to `len` state values (see `DYNTERM_STATE_LEN`) to `out` and
+ returns how many were written.
+
+ # Safety
+ `sim` must have come from `dynterm_new` and not yet been freed, and `out`
+ must point to at least `len` writable doubles.
+ */
+size_t dynterm_state(const struct dynterm_simulation *sim, double *out, size_t len);
+
+/*
+ Destroys a simulation. Passing null is a no-op.
+
+ # Safety
+ `sim` must be null or have come from `dynterm_new`, and must not be used
+ again afterwards.
+ */
+void dynterm_free(struct dynterm_simulation *sim);
+
+#endif  /* DYNTERM_H */
@@ -1,10 +1,28 @@
-use crate::vec::{Vector, Angle, Kinematics};
+use crate::vec::{Vector, Angle, Kinematics, Rotation2};
 use crate::interpolate::Linear;
-use crate::rk4::rk4;
-use crate::util::*;
+use crate::rk4::{implicit_midpoint_step, rk4, Integrator};
+use crate::scalar::Scalar;
+use crate::atmosphere::*;
+use crate::derivatives::Derivatives;
+use crate::earth::Earth;
+use crate::electric::Electric;
+use crate::engine::{Engine, Rating};
+use crate::gravity::Gravity;
+use crate::icing;
+use crate::lifters::{Parafoil, Rotor};
+use crate::propeller::Propeller;
+use crate::retro::Retro;
+use crate::updraft::Updrafts;
+use crate::wind::WindGrid;
 
-use std::f64::consts::PI;
-use std::cell::UnsafeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use serde::{Deserialize, Serialize};
+
+// Altitude, m, below which the built-in pull-up holds the elevator up and
+// the autothrottle gives full thrust
+pub const PULL_UP_ALTITUDE: f64 = 7_300.0;
 
 /// `Aerofoil` represents a simplified airfoil or control surface with 
 /// specified aerodynamic coefficients. Properties include area and pitch
@@ -12,203 +30,1449 @@ use std::cell::UnsafeCell;
 /// using interpolation objects. The `Aerofoil` struct provides methods 
 /// for calculating aerodynamic forces and moments acting on the airfoil
 /// when attached to a `Vehicle`. It also allows setting the pitch angle
-/// of the airfoil, simulating control surface deflection.
-pub struct Aerofoil<'a> { 
-    area: f64,
-    chord: f64,
-    pitch: Angle,
-    cl: &'a Linear<'a>,
-    cd: &'a Linear<'a>,
-    cm: &'a Linear<'a>,
+/// of the airfoil, simulating control surface deflection. The coefficient
+/// tables are shared, so several surfaces can use the same section data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "S: Scalar"))]
+pub struct Aerofoil<S = f64> { 
+    area: S,
+    chord: S,
+    pitch: Angle<S>,
+    cl: Arc<Linear>,
+    cd: Arc<Linear>,
+    cm: Arc<Linear>,
+    #[serde(default)]
+    wave_drag: WaveDrag,
+    // Contamination by ice, from 0 for a clean surface to 1
+    #[serde(default)]
+    ice: f64,
+    #[serde(default)]
+    ground_effect: Option<GroundEffect>,
+    #[serde(default)]
+    hinge: Option<Hinge>,
+    #[serde(default)]
+    flaps: Option<Flaps>,
+}
+
+// Mach number past which the drag rise gives way to supersonic wave drag
+const SUPERSONIC: f64 = 1.2;
+
+// The drag coefficient added to a surface's incompressible polar as the flow
+// over it goes transonic, so dives past Mach 0.8 or so slow down as they
+// should. The tables are taken at low speed and know nothing of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveDrag {
+    // The polar alone
+    #[default]
+    None,
+    // Lock's fourth-power law, rising from the critical Mach number to pass a
+    // slope of 0.1 at the drag-divergence Mach number `divergence`, and held
+    // to `peak`. Past Mach 1.2 it falls away with 1/sqrt(M^2 - 1), as linear
+    // supersonic theory has it.
+    Rise { divergence: f64, peak: f64 },
+    // Increment against Mach number, held at its ends
+    Table(Arc<Linear>),
+}
+
+impl WaveDrag {
+
+    // Returns the drag coefficient increment at Mach `mach`
+    pub fn increment<S: Scalar>(&self, mach: S) -> S {
+        match self {
+            WaveDrag::None => S::ZERO,
+            WaveDrag::Rise { divergence, peak } => {
+                // Where 20 (M - Mcr)^4 has a slope of 0.1
+                let critical = S::from_f64(divergence - libm::cbrt(0.1 / 80.0));
+                if mach <= critical { return S::ZERO; }
+
+                let rise = S::from_f64(20.0) * (mach - critical).powi(4);
+                let rise = if rise < S::from_f64(*peak) { rise } else { S::from_f64(*peak) };
+                if mach <= S::from_f64(SUPERSONIC) { return rise; }
+                rise * S::from_f64((SUPERSONIC * SUPERSONIC - 1.0).sqrt()) / (mach * mach - S::ONE).sqrt()
+            }
+            WaveDrag::Table(table) => {
+                let points = table.points();
+                let (first, last) = (points[0], points[points.len() - 1]);
+                if mach <= S::from_f64(first.0) { return S::from_f64(first.1); }
+                if mach >= S::from_f64(last.0) { return S::from_f64(last.1); }
+                table.interpolate(mach)
+            }
+        }
+    }
+}
+
+// The hinge moment coefficient of a control surface, about its hinge line
+// and on its own area and chord: a table against the deflection, signed
+// degrees, held at its ends, plus a slope per degree of the angle of attack
+// the body meets the air at, and another per degree of trim tab. Positive
+// moments drive the deflection up. With the stick held they're only loads
+// the actuator has to hold, not the flight path, so they're left out of the
+// dynamics. Let go, the surface is `free` and floats to where they balance,
+// the tab setting where that is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hinge {
+    pub deflection: Arc<Linear>,
+    #[serde(default)]
+    pub alpha: f64,
+    #[serde(default)]
+    pub tab_effectiveness: f64,
+    // Trim tab setting, degrees
+    #[serde(default)]
+    pub tab: f64,
+    #[serde(default)]
+    pub free: bool,
+}
+
+impl Hinge {
+
+    // Returns the hinge moment coefficient at a `deflection` and the body's
+    // angle of attack `alpha`, both degrees
+    pub fn coefficient<S: Scalar>(&self, deflection: S, alpha: S) -> S {
+        let points = self.deflection.points();
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let ch = if deflection <= S::from_f64(first.0) {
+            S::from_f64(first.1)
+        } else if deflection >= S::from_f64(last.0) {
+            S::from_f64(last.1)
+        } else {
+            self.deflection.interpolate(deflection)
+        };
+        ch + S::from_f64(self.alpha) * alpha + S::from_f64(self.tab_effectiveness * self.tab)
+    }
+
+    // Returns the deflection the surface floats to at the body's angle of
+    // attack `alpha`, degrees, where the hinge moment is nil. The table's
+    // ends are the surface's stops, and it rests on whichever stop it's
+    // pushed against if nothing between balances.
+    pub fn float<S: Scalar>(&self, alpha: S) -> S {
+        let points = self.deflection.points();
+        let bias = S::from_f64(self.alpha) * alpha + S::from_f64(self.tab_effectiveness * self.tab);
+        let ch = |i: usize| S::from_f64(points[i].1) + bias;
+        let balance = (1..points.len()).find(|&i| (ch(i - 1) <= S::ZERO) != (ch(i) <= S::ZERO));
+        match balance {
+            Some(i) => {
+                let (x0, x1) = (S::from_f64(points[i - 1].0), S::from_f64(points[i].0));
+                x0 - ch(i - 1) * (x1 - x0) / (ch(i) - ch(i - 1))
+            }
+            // Driven up past the top stop, or down past the bottom one
+            None if ch(0) > S::ZERO => S::from_f64(points[points.len() - 1].0),
+            None => S::from_f64(points[0].0),
+        }
+    }
+}
+
+// Where the flaps are set
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlapSetting {
+    #[default]
+    Clean,
+    Takeoff,
+    Landing,
+}
+
+impl core::str::FromStr for FlapSetting {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<FlapSetting, ()> {
+        match s {
+            "clean" => Ok(FlapSetting::Clean),
+            "takeoff" => Ok(FlapSetting::Takeoff),
+            "landing" => Ok(FlapSetting::Landing),
+            _ => Err(()),
+        }
+    }
 }
 
-impl<'a> Aerofoil<'a> {
+// The aerodynamics of a surface with its flaps out: tables of its own in
+// place of the clean section's, or increments on the clean coefficients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlapAero {
+    Tables { cl: Arc<Linear>, cd: Arc<Linear>, cm: Arc<Linear> },
+    Increments { cl: f64, cd: f64, cm: f64 },
+}
+
+// Flaps on a surface, with the aerodynamics of each setting other than clean
+// and the one it's at. A setting with nothing given flies as clean. The
+// flaps move straight to a new setting, which is quick enough beside the
+// approach they're set for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flaps {
+    pub takeoff: Option<FlapAero>,
+    pub landing: Option<FlapAero>,
+    pub setting: FlapSetting,
+}
+
+impl Flaps {
+
+    // Returns the aerodynamics at the current setting, or None when clean
+    pub fn aero(&self) -> Option<&FlapAero> {
+        match self.setting {
+            FlapSetting::Clean => None,
+            FlapSetting::Takeoff => self.takeoff.as_ref(),
+            FlapSetting::Landing => self.landing.as_ref(),
+        }
+    }
+}
+
+// Oswald efficiency taken for the induced drag of a wing
+const OSWALD: f64 = 0.8;
+
+// A wing flying within a span of the ground, which blocks its downwash and
+// so takes away some of its induced drag and steepens its lift curve. Both
+// follow McCormick's interference factor (16 h/b)^2 / (1 + (16 h/b)^2) at a
+// height h above the ground for a span b, which scales the induced drag and
+// divides into the aspect ratio that sets the lift-curve slope. It shapes
+// the flare, and skips off the air close to the ground. Further up it's too
+// small to matter and is left out.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct GroundEffect {
+    pub span: f64,
+    pub aspect_ratio: f64,
+}
+
+impl GroundEffect {
+
+    // Constructor, for a wing of `span`, m, and `area`, m^2
+    pub fn new(span: f64, area: f64) -> GroundEffect {
+        GroundEffect { span, aspect_ratio: span * span / area }
+    }
+
+    // Gets the interference factor at `height` above the ground, from 0 on
+    // the ground to 1 clear of it
+    pub fn factor<S: Scalar>(&self, height: S) -> S {
+        let height = if height > S::ZERO { height } else { S::ZERO };
+        let x = (S::from_f64(16.0 / self.span) * height).powi(2);
+        x / (S::ONE + x)
+    }
+
+    // Corrects the lift and drag coefficients of the wing in free air for
+    // flying `height` above the ground. The tables may not hold as much
+    // induced drag as is taken away, so the drag is held above `floor`.
+    pub fn correct<S: Scalar>(&self, height: S, (cl, cd): (S, S), floor: f64) -> (S, S) {
+        if height >= S::from_f64(self.span) { return (cl, cd); }
+
+        let phi = self.factor(height);
+        let (ar, two) = (S::from_f64(self.aspect_ratio), S::from_f64(2.0));
+        let induced = |cl: S| cl * cl / S::from_f64(core::f64::consts::PI * OSWALD * self.aspect_ratio);
+        let lift = cl * (ar + two) / (ar + two * phi);
+        let drag = cd + phi * induced(lift) - induced(cl);
+        (lift, if drag > S::from_f64(floor) { drag } else { S::from_f64(floor) })
+    }
+}
+
+// The settings of a surface that change in flight, as `Aerofoil::snapshot`
+// takes them: its deflection, the ice on it, where its flaps are set, if it
+// has them, and its trim tab and whether it's let go, if it has a hinge
+#[derive(Debug, Copy, Clone)]
+pub struct AerofoilState<S = f64> {
+    pub pitch: Angle<S>,
+    pub ice: f64,
+    pub flaps: Option<FlapSetting>,
+    pub tab: Option<(f64, bool)>,
+}
+
+impl<S: Scalar> Aerofoil<S> {
 
     // Constructor
     pub fn new(
-        area: f64, 
-        chord: f64, 
-        pitch: Angle, 
-        cl: &'a Linear<'a>, 
-        cd: &'a Linear<'a>, 
-        cm: &'a Linear<'a>) -> Aerofoil<'a> {
-        Aerofoil { area, chord, pitch, cl, cd, cm }
+        area: S, 
+        chord: S, 
+        pitch: Angle<S>, 
+        cl: Arc<Linear>, 
+        cd: Arc<Linear>, 
+        cm: Arc<Linear>) -> Aerofoil<S> {
+        Aerofoil { area, chord, pitch, cl, cd, cm, wave_drag: WaveDrag::None, ice: 0.0, ground_effect: None, hinge: None, flaps: None }
     }
 
     // Control function
-    pub fn set_pitch(&mut self, pitch: Angle) {
+    pub fn set_pitch(&mut self, pitch: Angle<S>) {
         self.pitch = pitch;
     }
 
-    // Gets the angle of attack relative to a body vehicle
-    #[inline] pub fn aoa(&self, k: &Kinematics, dk: &Kinematics) -> Angle {
-        (k.angle() + self.pitch) - dk.direction()
+    pub fn set_wave_drag(&mut self, wave_drag: WaveDrag) {
+        self.wave_drag = wave_drag;
     }
 
-    // Calculates the dynamic pressure experienced, using altitude from k &
-    // speed from magnitude of dk
-    #[inline] pub fn dyn_pressure(&self, k: &Kinematics, dk: &Kinematics) -> f64 {
-        0.5 * atmo_density(k.y()) * dk.magnitude().powi(2)
+    #[inline] pub fn wave_drag(&self) -> &WaveDrag {
+        &self.wave_drag
     }
 
-    // Calculates the lift force if attached to a body vehicle. This is always
-    // normal to the direction of motion
-    pub fn lift_force(&self, k: &Kinematics, dk: &Kinematics) -> Vector {
+    pub fn set_ice(&mut self, ice: f64) {
+        self.ice = ice;
+    }
 
-        // Get the lift coefficient from the angle of attack 
-        // (use orientation from k & velocity from dk)
-        let lift_coeff = self.cl.interpolate(self.aoa(k, dk).deg());
+    #[inline] pub fn ice(&self) -> f64 {
+        self.ice
+    }
 
-        Vector::from_radians(
-            self.area * lift_coeff * self.dyn_pressure(k, dk), 
-            dk.direction().rad() + (PI/2.0) // Normal to direction of motion
-        )
+    pub fn set_ground_effect(&mut self, ground_effect: Option<GroundEffect>) {
+        self.ground_effect = ground_effect;
+    }
+
+    #[inline] pub fn ground_effect(&self) -> Option<&GroundEffect> {
+        self.ground_effect.as_ref()
+    }
+
+    pub fn set_hinge(&mut self, hinge: Option<Hinge>) {
+        self.hinge = hinge;
+    }
+
+    #[inline] pub fn hinge(&self) -> Option<&Hinge> {
+        self.hinge.as_ref()
+    }
+
+    pub fn set_flaps(&mut self, flaps: Option<Flaps>) {
+        self.flaps = flaps;
+    }
+
+    #[inline] pub fn flaps(&self) -> Option<&Flaps> {
+        self.flaps.as_ref()
+    }
+
+    // Sets the flaps, for a surface that has them
+    pub fn set_flap_setting(&mut self, setting: FlapSetting) {
+        if let Some(flaps) = &mut self.flaps {
+            flaps.setting = setting;
+        }
+    }
+
+    // Returns the settings that change in flight
+    pub fn snapshot(&self) -> AerofoilState<S> {
+        AerofoilState {
+            pitch: self.pitch,
+            ice: self.ice,
+            flaps: self.flaps.as_ref().map(|flaps| flaps.setting),
+            tab: self.hinge.as_ref().map(|hinge| (hinge.tab, hinge.free)),
+        }
+    }
+
+    // Puts the settings back as `snapshot` took them. Flaps and a hinge
+    // the surface doesn't have are left out.
+    pub fn restore(&mut self, state: &AerofoilState<S>) {
+        self.pitch = state.pitch;
+        self.ice = state.ice;
+        if let (Some(flaps), Some(setting)) = (&mut self.flaps, state.flaps) {
+            flaps.setting = setting;
+        }
+        if let (Some(hinge), Some((tab, free))) = (&mut self.hinge, state.tab) {
+            hinge.tab = tab;
+            hinge.free = free;
+        }
+    }
+
+    // Gets the hinge moment if attached to a body vehicle at `attitude`,
+    // N m, for a surface with hinge moment coefficients
+    pub fn hinge_moment(&self, attitude: Angle<S>, air: &Airdata<S>) -> Option<S> {
+        let hinge = self.hinge.as_ref()?;
+        let alpha = (attitude - air.direction).nice_deg();
+        let ch = hinge.coefficient(self.pitch.nice_deg(), alpha);
+        Some(air.qbar * S::from_f64(RHO0) * self.area * self.chord * ch)
+    }
+
+    // Corrects lift and drag coefficients for flying `height` above the
+    // ground, for a surface that feels ground effect
+    pub fn in_ground_effect(&self, height: S, coefficients: (S, S)) -> (S, S) {
+        match &self.ground_effect {
+            None => coefficients,
+            Some(ground_effect) => {
+                let floor = self.section().1.points().iter().fold(f64::INFINITY, |floor, p| floor.min(p.1));
+                ground_effect.correct(height, coefficients, floor)
+            }
+        }
+    }
+
+    #[inline] pub fn pitch(&self) -> Angle<S> {
+        self.pitch
+    }
+    #[inline] pub fn area(&self) -> S {
+        self.area
+    }
+    #[inline] pub fn chord(&self) -> S {
+        self.chord
+    }
+
+    // Returns the clean lift, drag, and moment coefficient tables
+    pub fn tables(&self) -> (&Linear, &Linear, &Linear) {
+        (&self.cl, &self.cd, &self.cm)
+    }
+
+    // Returns the lift, drag, and moment coefficient tables at the current
+    // flap setting
+    fn section(&self) -> (&Linear, &Linear, &Linear) {
+        match self.flaps.as_ref().and_then(Flaps::aero) {
+            Some(FlapAero::Tables { cl, cd, cm }) => (cl, cd, cm),
+            _ => self.tables(),
+        }
+    }
+
+    // Helper function
+    fn lookup(&self, aoa: S) -> (S, S, S) {
+        let (cl, cd, cm) = self.section();
+        let coefficients = (cl.interpolate(aoa), cd.interpolate(aoa), cm.interpolate(aoa));
+        match self.flaps.as_ref().and_then(Flaps::aero) {
+            Some(FlapAero::Increments { cl, cd, cm }) => {
+                let (l, d, m) = coefficients;
+                (l + S::from_f64(*cl), d + S::from_f64(*cd), m + S::from_f64(*cm))
+            }
+            _ => coefficients,
+        }
+    }
+
+    // Returns the lift, drag, and moment coefficients with the body it's
+    // attached to at the given angle of attack, for models that work out the
+    // airflow themselves
+    pub fn coefficients(&self, aoa: Angle<S>) -> (S, S, S) {
+        let aoa = (aoa + self.pitch).deg();
+        self.contaminate(self.lookup(aoa))
+    }
+
+    // Helper function
+    fn contaminate(&self, coefficients: (S, S, S)) -> (S, S, S) {
+        if self.ice == 0.0 { return coefficients; }
+        icing::degrade(self.ice, self.section().0.range(), coefficients)
     }
 
-    // Calcuates the lift force if attached to a body vehicle. This is always
-    // against the direction of motion
-    pub fn drag_force(&self, k: &Kinematics, dk: &Kinematics) -> Vector {
+    // Gets the angle of attack relative to a body vehicle at `attitude`
+    #[inline] pub fn aoa(&self, attitude: Angle<S>, air: &Airdata<S>) -> Angle<S> {
+        (attitude + self.pitch) - air.direction
+    }
+
+    // Gets the angles of attack at which the clean section, or the flapped
+    // one, stalls, degrees, above and below zero: the break points of its
+    // lift curve, where lift stops growing with angle of attack either way
+    pub fn stall_angles(&self) -> (f64, f64) {
+        let lift = self.section().0;
+        let signed = |(aoa, cl): &(f64, f64)| (Angle::from_degrees(*aoa).nice_deg(), *cl);
+        let mut above: Vec<(f64, f64)> = lift.points().iter().map(signed)
+            .filter(|(aoa, _)| (0.0..=90.0).contains(aoa))
+            .collect();
+        let mut below: Vec<(f64, f64)> = lift.points().iter().map(signed)
+            .filter(|(aoa, _)| (-90.0..=0.0).contains(aoa))
+            .collect();
+        above.sort_by(|a, b| a.0.total_cmp(&b.0));
+        below.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // Follow the lift curve out from zero until it turns back
+        let break_point = |points: &[(f64, f64)], rising: fn(f64, f64) -> bool| points.windows(2)
+            .find(|pair| !rising(pair[0].1, pair[1].1))
+            .or(points.windows(2).last())
+            .map_or(0.0, |pair| pair[0].0);
+        (break_point(&above, |a, b| b > a), break_point(&below, |a, b| b < a))
+    }
+
+    // Gets the margin to stall if attached to a body vehicle at `attitude`,
+    // degrees of angle of attack to the nearer break point, and negative
+    // once past it
+    pub fn stall_margin(&self, attitude: Angle<S>, air: &Airdata<S>) -> S {
+        let (above, below) = self.stall_angles();
+        let aoa = self.aoa(attitude, air).nice_deg();
+        let (up, down) = (S::from_f64(above) - aoa, aoa - S::from_f64(below));
+        if up < down { up } else { down }
+    }
+
+    // Gets the Reynolds number, on this surface's own chord
+    #[inline] pub fn reynolds(&self, air: &Airdata<S>) -> S {
+        air.reynolds * self.chord
+    }
 
-        // Get the drag coefficient from the angle of attack 
-        // (use orientation from k & velocity from dk)
-        let drag_coeff = self.cd.interpolate(self.aoa(k, dk).deg());
+    // Calculates the lift force, drag force, and pitching moment if attached
+    // to a body vehicle at `attitude`. Lift is always normal to the airflow
+    // and drag always against it. The moment is a free moment.
+    pub fn loads(&self, attitude: Angle<S>, air: &Airdata<S>) -> (Vector<S>, Vector<S>, S) {
 
-        Vector::from_radians(
-            self.area * drag_coeff * self.dyn_pressure(k, dk), 
-            dk.direction().rad() + PI // Antitangent direction of motion
+        // Get the coefficients from the angle of attack
+        let aoa = self.aoa(attitude, air).deg();
+        let (lift_coeff, drag_coeff, pitch_coeff) = self.contaminate(self.lookup(aoa));
+        let (lift_coeff, drag_coeff) = self.in_ground_effect(air.height, (lift_coeff, drag_coeff));
+        let drag_coeff = drag_coeff + self.wave_drag.increment(air.mach);
+
+        let flow = air.velocity.unit();
+        (
+            (Rotation2::quarter() * flow) * (self.area * lift_coeff * air.qbar),
+            -flow * (self.area * drag_coeff * air.qbar),
+            self.area * pitch_coeff * air.qbar * self.chord,
         )
     }
+}
 
-    // Calculates the pitching moment generated by the airstream over the wing.
-    // This is a free moment.
-    pub fn pitching_moment(&self, k: &Kinematics, dk: &Kinematics) -> f64 {
+/// `Airdata` describes the airflow past a body at one instant: its velocity
+/// relative to the air (inertial velocity less the wind) and the atmosphere
+/// at its altitude. It's worked out once per evaluation of the dynamics and
+/// shared by every surface, so wind and compressibility only need handling
+/// here. The dynamic pressure is taken with the relative density σ, as the
+/// aerodynamic coefficients expect. Reynolds number is per metre of
+/// reference length. Height is above the ground, which is the altitude
+/// unless there's terrain beneath.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "S: Scalar"))]
+pub struct Airdata<S = f64> {
+    pub velocity: Vector<S>,
+    pub direction: Angle<S>,
+    pub tas: S,
+    pub alpha: Angle<S>,
+    pub sigma: S,
+    pub qbar: S,
+    pub mach: S,
+    pub reynolds: S,
+    pub height: S,
+}
 
-        // Get the pitching moment coefficient from the angle of attack 
-        // (use orientation from k & velocity from dk)
-        let pitch_coeff = self.cm.interpolate(self.aoa(k, dk).deg());
+impl<S: Scalar> Airdata<S> {
 
-        self.area * pitch_coeff * self.dyn_pressure(k, dk) * self.chord
+    // Constructor, for a body at `attitude` and `altitude` moving at
+    // `velocity` through a `wind` in an `atmosphere`
+    pub fn new(attitude: Angle<S>, altitude: S, velocity: Vector<S>, wind: Vector<S>, atmosphere: &Atmosphere) -> Airdata<S> {
+        let velocity = velocity - wind;
+        let direction = velocity.orientation();
+        let tas = velocity.magnitude();
+        let sigma = atmosphere.density(altitude);
+
+        Airdata {
+            velocity,
+            direction,
+            tas,
+            alpha: attitude - direction,
+            sigma,
+            qbar: S::from_f64(0.5) * sigma * tas.powi(2),
+            mach: tas / atmosphere.speed_of_sound(altitude),
+            reynolds: sigma * S::from_f64(RHO0) * tas / dynamic_viscosity(atmosphere.temperature(altitude)),
+            height: altitude,
+        }
     }
 }
 
+// Sutton–Graves constant for air, kg^0.5/m
+const SUTTON_GRAVES: f64 = 1.7415e-4;
+
+impl<S: Scalar> Airdata<S> {
+
+    // Estimates the convective heat flux at the stagnation point of a nose of
+    // radius `nose_radius`, m, by the Sutton–Graves relation, W/m^2
+    pub fn heat_flux(&self, nose_radius: S) -> S {
+        let rho = self.sigma * S::from_f64(RHO0);
+        S::from_f64(SUTTON_GRAVES) * (rho / nose_radius).sqrt() * self.tas.powi(3)
+    }
+}
+
+// A torsional spring and damper between the forward body and the tail, the
+// first flexible pitch mode of a slender body brought down to one joint. The
+// tail twists about the joint, `arm` m ahead of the elevator, against a
+// spring and damper giving it a natural frequency in Hz and a damping ratio,
+// driven by the elevator's aerodynamic moment about the joint, and the twist
+// adds to the elevator's incidence. The tail is light beside the body, so
+// the body still takes the tail's loads whole, as if rigid, and the twist
+// isn't driven by the body's own pitching.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Flex {
+    pub frequency: f64,
+    pub damping_ratio: f64,
+    // Pitch moment of inertia of the tail about the joint
+    pub inertia: f64,
+    pub arm: f64,
+    // Twist of the tail nose up against the body, rad, and its rate, rad/s
+    #[serde(default)]
+    pub twist: f64,
+    #[serde(default)]
+    pub twist_rate: f64,
+}
+
+impl Flex {
+
+    // Steps the twist on by `dt` under a moment about the joint. Taken
+    // semi-implicitly, which holds steady as long as the steps are well
+    // inside the mode's period.
+    pub fn step(&mut self, moment: f64, dt: f64) {
+        let omega = 2.0 * core::f64::consts::PI * self.frequency;
+        let stiffness = self.inertia * omega * omega;
+        let damping = 2.0 * self.damping_ratio * self.inertia * omega;
+        let accel = (moment - stiffness * self.twist - damping * self.twist_rate) / self.inertia;
+        self.twist_rate += accel * dt;
+        self.twist += self.twist_rate * dt;
+    }
+}
+
+// A drag brake or spoiler, which adds drag area with no lift or moment of
+// its own as it opens. Its drag area CdS, m^2, is tabulated against the
+// fraction it's deployed, 0 stowed to 1 fully open, and it runs towards the
+// commanded deployment at no more than `rate` a second. The drag acts
+// through the centre of mass, and the autothrottle leaves it be rather than
+// throttling up against it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Speedbrake {
+    pub cds: Arc<Linear>,
+    pub rate: f64,
+    #[serde(default)]
+    pub deployment: f64,
+    #[serde(default)]
+    pub command: f64,
+}
+
+impl Speedbrake {
+
+    // Sets the deployment to run towards, held within 0 and 1
+    pub fn set_command(&mut self, command: f64) {
+        self.command = command.clamp(0.0, 1.0);
+    }
+
+    // Runs the deployment on by `dt` towards the command
+    pub fn step(&mut self, dt: f64) {
+        let travel = self.rate * dt;
+        self.deployment += (self.command - self.deployment).clamp(-travel, travel);
+    }
+
+    // Returns the drag area at the current deployment, m^2
+    pub fn drag_area(&self) -> f64 {
+        let points = self.cds.points();
+        let (first, last) = (points[0], points[points.len() - 1]);
+        if self.deployment <= first.0 { return first.1; }
+        if self.deployment >= last.0 { return last.1; }
+        self.cds.interpolate(self.deployment)
+    }
+}
+
+/// `Forces` breaks down everything acting on a `Vehicle` at one instant:
+/// the lift and drag of each surface, thrust, and weight, in world axes, and
+/// the pitching moments about the centre of mass. The tail moment is the one
+/// the elevator's force makes at the end of the body, apart from its own
+/// free pitching moment, and the wing arm moment likewise the wing's, for a
+/// centre of mass off the wing.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Forces<S = f64> {
+    pub wing_lift: Vector<S>,
+    pub wing_drag: Vector<S>,
+    pub elevator_lift: Vector<S>,
+    pub elevator_drag: Vector<S>,
+    pub speedbrake_drag: Vector<S>,
+    pub thrust: Vector<S>,
+    pub weight: Vector<S>,
+    pub wing_moment: S,
+    pub elevator_moment: S,
+    pub tail_moment: S,
+    pub wing_arm_moment: S,
+    pub damping_moment: S,
+    // Sums of the forces and moments registered on the vehicle from outside
+    // its aerodynamics
+    pub external: Vector<S>,
+    pub external_moment: S,
+}
+
+impl<S: Scalar> Forces<S> {
+
+    // Returns the resultant force
+    #[inline] pub fn total(&self) -> Vector<S> {
+        (self.wing_lift + self.wing_drag) + (self.elevator_lift + self.elevator_drag)
+            + self.speedbrake_drag + self.thrust + self.weight + self.external
+    }
+
+    // Returns the resultant pitching moment
+    #[inline] pub fn total_moment(&self) -> S {
+        self.wing_moment + self.elevator_moment + self.tail_moment + self.wing_arm_moment
+            + self.damping_moment + self.external_moment
+    }
+}
+
+/// `StepDiagnostics` is what the dynamics came to the last time a step
+/// evaluated them: the thrust the vehicle gave along its thrust line, the
+/// lift and drag of each surface in world axes, and the angle of attack and
+/// dynamic pressure, over the sea-level density, they were found at.
+/// `apply_dynamics` returns it, and the vehicle keeps it until the next
+/// step, so the model can be observed without evaluating it again.
+#[derive(Debug, Copy, Clone)]
+pub struct StepDiagnostics<S = f64> {
+    pub thrust: S,
+    pub wing_lift: Vector<S>,
+    pub wing_drag: Vector<S>,
+    pub elevator_lift: Vector<S>,
+    pub elevator_drag: Vector<S>,
+    pub aoa: Angle<S>,
+    pub qbar: S,
+}
+
+impl<S: Scalar> StepDiagnostics<S> {
+
+    // Constructor, for a vehicle that hasn't been stepped yet, whose thrust,
+    // angle of attack, and dynamic pressure aren't known
+    pub fn unknown() -> StepDiagnostics<S> {
+        let nan = S::from_f64(f64::NAN);
+        StepDiagnostics {
+            thrust: nan,
+            wing_lift: Vector::zero(),
+            wing_drag: Vector::zero(),
+            elevator_lift: Vector::zero(),
+            elevator_drag: Vector::zero(),
+            aoa: Angle::from_radians(nan),
+            qbar: nan,
+        }
+    }
+}
+
+/// `ExternalForce` is a force from outside the aerodynamics that a library
+/// user registers on a `Vehicle`: a magnet, a tether, the drag of an
+/// experimental device. Each is summed into the dynamics, and carries a name
+/// its contribution is logged under. The moment is about the centre of mass.
+pub trait ExternalForce<S: Scalar>: Send + Sync {
+    fn name(&self) -> &str;
+    fn load(&self, k: &Kinematics<S>, dk: &Kinematics<S>, air: &Airdata<S>) -> (Vector<S>, S);
+}
+
+// Shown by name, which is all that's known of it
+impl<S: Scalar> core::fmt::Debug for dyn ExternalForce<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// `ForceModel` is what turns the airflow past a `Vehicle` into its
+/// aerodynamic loads. Whichever model is flown, the vehicle's wing gives the
+/// reference area and chord for any that need them and its elevator setting
+/// is the pitch control. Lifters other than the wing put their loads in its
+/// place. The loads come back as `Forces` with the thrust, weight, and speedbrake
+/// drag left nil, for the vehicle to add.
+pub trait ForceModel<S: Scalar> {
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S>;
+}
+
+// Pitching moment from the pitch damping derivative `cmq` turning at `rate`,
+// on a reference `area` and `chord`, N m
+fn damping_moment<S: Scalar>(cmq: f64, rate: S, area: S, chord: S, air: &Airdata<S>) -> S {
+    if cmq == 0.0 || air.tas == S::ZERO { return S::ZERO; }
+    let q_hat = rate * chord / (S::from_f64(2.0) * air.tas);
+    S::from_f64(cmq) * q_hat * air.qbar * area * chord
+}
+
+// The wing and elevator each working out their own loads from their
+// coefficient tables, the elevator's at the end of the body
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Surfaces;
+
+impl<S: Scalar> ForceModel<S> for Surfaces {
+    #[allow(non_snake_case)]
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S> {
+
+        // Aerofoil references for wing and elevator
+        let w: &Aerofoil<S> = &vehicle.wing;
+        let e: &Aerofoil<S> = &vehicle.elev;
+
+        // Position vectors of the elevator and the wing from the centre of
+        // mass
+        let r_e = Vector::new(-vehicle.length/S::from_f64(2.0) + vehicle.cg, S::ZERO).to_world(k.angle());
+        let r_w = Vector::new(vehicle.cg, S::ZERO).to_world(k.angle());
+
+        // Aerodynamic forces and moments acting on the wing and elevator
+        let (L_w, D_w, M_w) = w.loads(k.angle(), air);
+        // A free elevator floats, whatever it's set to
+        let tail = vehicle.tail_attitude(k);
+        let tail = match vehicle.float_angle(tail, air) {
+            Some(float) => tail + (float - e.pitch()),
+            None => tail,
+        };
+        let (L_e, D_e, M_e) = e.loads(tail, air);
+
+        Forces {
+            wing_lift: L_w,
+            wing_drag: D_w,
+            elevator_lift: L_e,
+            elevator_drag: D_e,
+            speedbrake_drag: Vector::zero(),
+            thrust: Vector::zero(),
+            weight: Vector::zero(),
+            external: Vector::zero(),
+            external_moment: S::ZERO,
+            wing_moment: M_w,
+            elevator_moment: M_e,
+            tail_moment: r_e.cross(L_e + D_e),
+            wing_arm_moment: r_w.cross(L_w + D_w),
+            damping_moment: damping_moment(vehicle.pitch_damping, dk.angular_velocity(), w.area(), w.chord(), air),
+        }
+    }
+}
+
+// The whole vehicle's loads straight from its stability derivatives, as one
+// virtual aerofoil at the middle of the body, with the elevator setting
+// taken as its deflection. Ice, flaps, ground effect, and the tail's flex
+// and hinge don't come into it; the loads are all put on the wing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DerivativeModel {
+    pub derivatives: Derivatives,
+}
+
+impl DerivativeModel {
+
+    // Constructor
+    pub fn new(derivatives: Derivatives) -> DerivativeModel {
+        DerivativeModel { derivatives }
+    }
+}
+
+impl<S: Scalar> ForceModel<S> for DerivativeModel {
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S> {
+
+        let (area, chord) = (vehicle.wing.area(), vehicle.wing.chord());
+        let (cl, cd, cm) = self.derivatives.coefficients(air.alpha.signed_rad(), vehicle.elev.pitch().signed_rad());
+
+        let flow = air.velocity.unit();
+        let lift = (Rotation2::quarter() * flow) * (area * cl * air.qbar);
+        let drag = -flow * (area * cd * air.qbar);
+        let arm = Vector::new(vehicle.cg, S::ZERO).to_world(k.angle());
+
+        Forces {
+            wing_lift: lift,
+            wing_drag: drag,
+            elevator_lift: Vector::zero(),
+            elevator_drag: Vector::zero(),
+            speedbrake_drag: Vector::zero(),
+            thrust: Vector::zero(),
+            weight: Vector::zero(),
+            external: Vector::zero(),
+            external_moment: S::ZERO,
+            wing_moment: area * cm * air.qbar * chord,
+            elevator_moment: S::ZERO,
+            tail_moment: S::ZERO,
+            wing_arm_moment: arm.cross(lift + drag),
+            damping_moment: damping_moment(self.derivatives.cm_q, dk.angular_velocity(), area, chord, air),
+        }
+    }
+}
+
+// The force model a vehicle flies with, chosen per scenario
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aerodynamics {
+    #[default]
+    Surfaces,
+    Derivatives(DerivativeModel),
+    Rotor(Rotor),
+    Parafoil(Parafoil),
+}
+
+impl<S: Scalar> ForceModel<S> for Aerodynamics {
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S> {
+        match self {
+            Aerodynamics::Surfaces => Surfaces.loads(vehicle, k, dk, air),
+            Aerodynamics::Derivatives(model) => model.loads(vehicle, k, dk, air),
+            Aerodynamics::Rotor(rotor) => rotor.loads(vehicle, k, dk, air),
+            Aerodynamics::Parafoil(parafoil) => parafoil.loads(vehicle, k, dk, air),
+        }
+    }
+}
+
+/// `VehicleSnapshot` is everything about a `Vehicle` that changes as it
+/// flies: its state and mass, the controls, its surfaces' settings, the
+/// tail's twist, the speedbrake's deployment and command, the battery's
+/// charge, the engine's rating and fuel, and the retro-rocket's fuel and
+/// whether it's burning. Restoring one puts a vehicle back where it was
+/// without rebuilding it, for an optimiser or a campaign to fly the same
+/// vehicle case after case.
+#[derive(Debug, Clone)]
+pub struct VehicleSnapshot<S = f64> {
+    pub mass: S,
+    pub position: Kinematics<S>,
+    pub motion: Kinematics<S>,
+    pub throttle: Option<S>,
+    pub thrust_angle: Angle<S>,
+    pub wing: AerofoilState<S>,
+    pub elev: AerofoilState<S>,
+    pub flex: Option<Flex>,
+    pub speedbrake: Option<(f64, f64)>,
+    pub charge: Option<f64>,
+    pub engine: Option<(Rating, Option<f64>)>,
+    pub retro: Option<(f64, bool)>,
+    pub diagnostics: StepDiagnostics<S>,
+}
+
 /// `Vehicle` represents a simplified aerospace vehicle with a massless main wing 
 /// and stabilator. The `Vehicle` struct provides methods for applying forces and
-/// moments to the vehicle using RK4.
-/// 'a is a lifetime marker, indicating that the references to wings must be valid 
-/// for the lifetime of the vehicle.
-pub struct Vehicle<'a> {
-    pub mass: f64,
-    pub length: f64,
-    pub moment: f64,
-    pub position: Kinematics,
-    pub motion: Kinematics,
-    pub wing: Aerofoil<'a>,
-    pub elev: Aerofoil<'a>,
-    pub max_thrust: f64,
-    pub last_thrust: UnsafeCell<f64>,
+/// moments to the vehicle using RK4. Its aerodynamic loads come from a
+/// `ForceModel`, the surfaces themselves unless another is given.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "S: Scalar, M: Deserialize<'de> + Default"))]
+pub struct Vehicle<S = f64, M = Aerodynamics> {
+    pub mass: S,
+    pub length: S,
+    // Pitch moment of inertia, which angular acceleration is taken against
+    pub moment: S,
+    // Centre of mass, m aft of the middle of the body, where the wing is
+    #[serde(default = "mid_body")]
+    pub cg: S,
+    pub position: Kinematics<S>,
+    pub motion: Kinematics<S>,
+    pub wing: Aerofoil<S>,
+    pub elev: Aerofoil<S>,
+    pub max_thrust: S,
+    // Radius of the nose, m, for the heating estimate
+    #[serde(default = "unit_radius")]
+    pub nose_radius: S,
+    // Throttle setting as a fraction of max thrust. None leaves thrust to 
+    // the built-in autothrottle
+    pub throttle: Option<S>,
+    // Steady wind the vehicle flies through
+    #[serde(default = "Vector::zero")]
+    pub wind: Vector<S>,
+    // Thermals and slope lift laid over the steady wind
+    #[serde(default)]
+    pub updrafts: Updrafts,
+    // Wind varying downrange and with altitude, laid over it too, if any
+    #[serde(default)]
+    pub wind_grid: Option<Arc<WindGrid>>,
+    // Gravity field the vehicle falls in
+    #[serde(default)]
+    pub gravity: Gravity,
+    // Air the vehicle flies through
+    #[serde(default)]
+    pub atmosphere: Atmosphere,
+    // Shape of the earth the position is measured over
+    #[serde(default)]
+    pub earth: Earth,
+    // Torsional flexibility between the body and the tail, if any
+    #[serde(default)]
+    pub flex: Option<Flex>,
+    // Drag brake, if any
+    #[serde(default)]
+    pub speedbrake: Option<Speedbrake>,
+    // Propeller giving the thrust, if any, in place of `max_thrust`
+    #[serde(default)]
+    pub propeller: Option<Propeller>,
+    // Electric motor and battery driving the thrust, if any
+    #[serde(default)]
+    pub electric: Option<Electric>,
+    // Jet engine giving the thrust, if any, in place of `max_thrust`
+    #[serde(default)]
+    pub engine: Option<Engine>,
+    // Retro-rocket braking it down to the ground, if any
+    #[serde(default)]
+    pub retro: Option<Retro>,
+    // Angle the thrust is turned up from the body axis, through the centre
+    // of mass, for hovering and transitioning on it
+    #[serde(default = "along_body")]
+    pub thrust_angle: Angle<S>,
+    // Pitch damping derivative Cmq, per radian of q c / 2V, on the wing's
+    // area and chord, for aerodynamics that don't come from the surfaces
+    // alone
+    #[serde(default)]
+    pub pitch_damping: f64,
+    // Where the aerodynamic loads come from
+    #[serde(default)]
+    pub model: M,
+    // Forces registered from outside the aerodynamics, which can't be saved
+    #[serde(skip)]
+    pub external: Vec<Arc<dyn ExternalForce<S>>>,
+    // What the dynamics came to over the last step
+    #[serde(skip, default = "StepDiagnostics::unknown")]
+    pub diagnostics: StepDiagnostics<S>,
+    // How the state is stepped on
+    #[serde(default)]
+    pub integrator: Integrator,
+    // Evaluations of the dynamics by the integrator so far, for profiling
+    #[serde(skip)]
+    pub evaluations: u64,
+    // Implicit steps taken so far without their Newton iteration converging
+    #[serde(skip)]
+    pub unconverged: u64,
+}
+
+// Written out by hand, as the external forces can only be shown, by name,
+// for a `Scalar`
+impl<S: Scalar, M: core::fmt::Debug> core::fmt::Debug for Vehicle<S, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Vehicle")
+            .field("mass", &self.mass)
+            .field("length", &self.length)
+            .field("moment", &self.moment)
+            .field("cg", &self.cg)
+            .field("position", &self.position)
+            .field("motion", &self.motion)
+            .field("wing", &self.wing)
+            .field("elev", &self.elev)
+            .field("max_thrust", &self.max_thrust)
+            .field("nose_radius", &self.nose_radius)
+            .field("throttle", &self.throttle)
+            .field("wind", &self.wind)
+            .field("updrafts", &self.updrafts)
+            .field("wind_grid", &self.wind_grid)
+            .field("gravity", &self.gravity)
+            .field("atmosphere", &self.atmosphere)
+            .field("earth", &self.earth)
+            .field("flex", &self.flex)
+            .field("speedbrake", &self.speedbrake)
+            .field("propeller", &self.propeller)
+            .field("electric", &self.electric)
+            .field("engine", &self.engine)
+            .field("retro", &self.retro)
+            .field("thrust_angle", &self.thrust_angle)
+            .field("pitch_damping", &self.pitch_damping)
+            .field("model", &self.model)
+            .field("external", &self.external)
+            .field("diagnostics", &self.diagnostics)
+            .field("integrator", &self.integrator)
+            .field("evaluations", &self.evaluations)
+            .field("unconverged", &self.unconverged)
+            .finish()
+    }
+}
+
+// Centre of mass of vehicles saved before it was kept
+fn mid_body<S: Scalar>() -> S {
+    S::ZERO
+}
+
+// Thrust line of vehicles saved before it could be turned
+fn along_body<S: Scalar>() -> Angle<S> {
+    Angle::from_radians(S::ZERO)
+}
+
+// Nose radius of vehicles saved before it was kept
+fn unit_radius<S: Scalar>() -> S {
+    S::ONE
 }
 
 // Implementation block for the Vehicle structure
-impl<'a> Vehicle<'a> {
+impl<S: Scalar, M: ForceModel<S> + Default> Vehicle<S, M> {
     
     // Constructor for a new Vehicle instance
     // Takes in the mass, length, initial position, initial motion, and wing and 
     // elevator aerofoils
     pub fn new(
-        mass: f64, 
-        length: f64, 
-        position: Kinematics, 
-        motion: Kinematics, 
-        wing: Aerofoil<'a>, 
-        elev: Aerofoil<'a>,
-        max_thrust: f64) -> Vehicle<'a> {
+        mass: S, 
+        length: S, 
+        position: Kinematics<S>, 
+        motion: Kinematics<S>, 
+        wing: Aerofoil<S>, 
+        elev: Aerofoil<S>,
+        max_thrust: S) -> Vehicle<S, M> {
         Vehicle { 
             mass,    // Mass of the vehicle
             length,  // Length of the vehicle
             // Moment of inertia is computed using the formula for a rod 
-            // rotated about its center
-            moment: mass * length.powi(2) / 12.0,
+            // rotated about its center. Set `moment` afterwards for anything
+            // else
+            moment: mass * length.powi(2) / S::from_f64(12.0),
+            cg: S::ZERO,
             position, // Initial position of the vehicle
             motion,   // Initial motion of the vehicle
             wing,     // Wing aerofoil
             elev,     // Elevator aerofoil
             max_thrust,
-            last_thrust: UnsafeCell::new(f64::NAN), // Evil
+            nose_radius: S::ONE,
+            throttle: None,
+            wind: Vector::zero(),
+            updrafts: Updrafts::default(),
+            wind_grid: None,
+            gravity: Gravity::default(),
+            atmosphere: Atmosphere::default(),
+            earth: Earth::default(),
+            flex: None,
+            speedbrake: None,
+            propeller: None,
+            electric: None,
+            engine: None,
+            retro: None,
+            thrust_angle: along_body(),
+            pitch_damping: 0.0,
+            model: M::default(),
+            external: Vec::new(),
+            diagnostics: StepDiagnostics::unknown(),
+            integrator: Integrator::Rk4,
+            evaluations: 0,
+            unconverged: 0,
         }
     }
+}
+
+impl<S: Scalar, M: ForceModel<S>> Vehicle<S, M> {
     
     // Returns the angle of attack, the difference between the angle of the 
-    // vehicle and the direction of its motion
-    #[inline] pub fn aoa(&self) -> Angle {
-        self.position.angle() - self.motion.direction()
+    // vehicle and the direction of its motion through the air
+    #[inline] pub fn aoa(&self) -> Angle<S> {
+        let direction = (self.motion.vec - self.wind_at(&self.position)).orientation();
+        self.position.angle() - direction
     }
 
-    // Calculates the dynamics of the vehicle given its current position and velocity
-    #[allow(non_snake_case)]
-    fn calculate_dynamics(
+    // Returns the wind at the pose `k`: the steady wind, with any measured
+    // in the atmosphere, any from the wind grid, and any updraft
+    #[inline] pub fn wind_at(&self, k: &Kinematics<S>) -> Vector<S> {
+        let mut wind = match self.atmosphere.wind(k.y()) {
+            Some((along, _)) => self.wind + Vector::new(along, S::ZERO),
+            None => self.wind,
+        };
+        if let Some(grid) = &self.wind_grid {
+            let (along, up) = grid.at(k.x(), k.y());
+            wind += Vector::new(along, up);
+        }
+        if self.updrafts.is_empty() { return wind; }
+        let up = self.updrafts.at(k.x(), k.y(), S::ZERO, wind.x());
+        wind + Vector::new(S::ZERO, up)
+    }
+
+    // Returns the height above the ground at the pose `k`, over any ridges
+    #[inline] pub fn height(&self, k: &Kinematics<S>) -> S {
+        k.y() - self.updrafts.ground(k.x())
+    }
+
+    // Whether the vehicle has come down onto the ground, or a ridge
+    #[inline] pub fn grounded(&self) -> bool {
+        self.height(&self.position) <= S::ZERO
+    }
+
+    // Returns the airflow at the pose `k` moving with `dk`
+    pub fn airdata_at(&self, k: &Kinematics<S>, dk: &Kinematics<S>) -> Airdata<S> {
+        let air = Airdata::new(k.angle(), k.y(), dk.vec, self.wind_at(k), &self.atmosphere);
+        Airdata { height: self.height(k), ..air }
+    }
+
+    // Returns the airflow at the current state
+    pub fn airdata(&self) -> Airdata<S> {
+        self.airdata_at(&self.position, &self.motion)
+    }
+
+    // Returns the wing's margin to stall at the current state, degrees of
+    // angle of attack
+    pub fn stall_margin(&self) -> S {
+        self.wing.stall_margin(self.position.angle(), &self.airdata())
+    }
+
+    // Returns the stagnation-point heat flux at the current state, W/m^2
+    pub fn heat_flux(&self) -> S {
+        self.airdata().heat_flux(self.nose_radius)
+    }
+
+    // Returns the elevator's hinge moment at the current state, N m, if it
+    // has hinge moment coefficients
+    pub fn hinge_moment(&self) -> Option<S> {
+        let attitude = self.tail_attitude(&self.position);
+        let air = self.airdata();
+        match self.float_angle(attitude, &air) {
+            Some(_) => Some(S::ZERO),
+            None => self.elev.hinge_moment(attitude, &air),
+        }
+    }
+
+    // Returns the deflection the elevator floats to at the current state,
+    // if it's free
+    pub fn elevator_float(&self) -> Option<Angle<S>> {
+        self.float_angle(self.tail_attitude(&self.position), &self.airdata())
+    }
+
+    // Helper function
+    fn float_angle(&self, attitude: Angle<S>, air: &Airdata<S>) -> Option<Angle<S>> {
+        let hinge = self.elev.hinge().filter(|hinge| hinge.free)?;
+        let alpha = (attitude - air.direction).nice_deg();
+        Some(Angle::from_degrees(hinge.float(alpha)))
+    }
+
+    // Helper function
+    fn tail_attitude(&self, k: &Kinematics<S>) -> Angle<S> {
+        match &self.flex {
+            Some(flex) => k.angle() + Angle::from_radians(S::from_f64(flex.twist)),
+            None => k.angle(),
+        }
+    }
+
+    // Returns the Reynolds numbers of the wing and the elevator at the
+    // current state
+    pub fn reynolds(&self) -> (S, S) {
+        let air = self.airdata();
+        (self.wing.reynolds(&air), self.elev.reynolds(&air))
+    }
+
+    // Calculates each of the forces and moments acting on the vehicle given
+    // its current position and velocity
+    pub fn forces(
         &self, 
-        k: &Kinematics, 
-        dk: &Kinematics) -> Kinematics {
+        k: &Kinematics<S>, 
+        dk: &Kinematics<S>) -> Forces<S> {
+        self.observed_forces(k, dk).0
+    }
 
-        // Aerofoil references for wing and elevator
-        let w: &Aerofoil = &self.wing;
-        let e: &Aerofoil = &self.elev;
-        
-        // Position vector of the elevator
-        let r_e = Vector::from_radians(
-            self.length/2.0, k.angle().rad() + PI);        
+    // Calculates the forces and moments as `forces` does, with what a step
+    // would report of them
+    #[allow(non_snake_case)]
+    fn observed_forces(
+        &self,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>) -> (Forces<S>, StepDiagnostics<S>) {
 
         // Gravitational force acting on the body
-        let W = self.mass * Vector::new(0.0, -9.81);
+        let W = Vector::new(S::ZERO, -self.gravity.at(k.y())) * self.mass;
 
-        // Aerodynamic forces acting on the wing and elevator
-        let F_w: Vector = w.lift_force(k, dk) + w.drag_force(k, dk);
-        let F_e: Vector = e.lift_force(k, dk) + e.drag_force(k, dk);
+        // Aerodynamic forces and moments, from whichever model is flown
+        let air = self.airdata_at(k, dk);
+        let aero = self.model.loads(self, k, dk, &air);
+        let D_b = match &self.speedbrake {
+            Some(speedbrake) => -air.velocity.unit() * (S::from_f64(speedbrake.drag_area()) * air.qbar),
+            None => Vector::zero(),
+        };
         
         // Control force to counteract drag?
-        let thrust = if self.position.y() < 7_300.0 { self.max_thrust }
-         else { (-(F_w + F_e).dot(k.angle().unit())).clamp(0.0, self.max_thrust) };
-        let T = Vector::from_radians(thrust, k.angle().rad());
-        unsafe { *self.last_thrust.get() = thrust; } // Evil
+        let available = |throttle: S| self.available_thrust(k, &air, throttle);
+        let thrust = match self.throttle {
+            Some(throttle) => available(throttle.clamp(S::ZERO, S::ONE)),
+            None if self.position.y() < S::from_f64(PULL_UP_ALTITUDE) => available(S::ONE),
+            None => (-((aero.wing_lift + aero.wing_drag) + (aero.elevator_lift + aero.elevator_drag)).dot(k.angle().unit()))
+                .clamp(S::ZERO, available(S::ONE)),
+        };
+        let T = match &self.engine {
+            Some(engine) if engine.flamed_out() =>
+                -air.velocity.unit() * (S::from_f64(engine.windmill_drag) * air.qbar),
+            _ => Vector::new(thrust, S::ZERO).to_world(k.angle() + self.thrust_angle),
+        };
+        let T = match &self.retro {
+            Some(retro) if retro.thrust_now() > 0.0 => T - dk.vec.unit() * S::from_f64(retro.thrust_now()),
+            _ => T,
+        };
+        let diagnostics = StepDiagnostics {
+            thrust,
+            wing_lift: aero.wing_lift,
+            wing_drag: aero.wing_drag,
+            elevator_lift: aero.elevator_lift,
+            elevator_drag: aero.elevator_drag,
+            aoa: k.angle() - air.direction,
+            qbar: air.qbar,
+        };
+
+        // Anything registered from outside
+        let (external, external_moment) = self.external.iter()
+            .map(|force| force.load(k, dk, &air))
+            .fold((Vector::zero(), S::ZERO), |(total, moment), (force, arm)| (total + force, moment + arm));
+
+        (Forces { speedbrake_drag: D_b, thrust: T, weight: W, external, external_moment, ..aero }, diagnostics)
+    }
+
+    // Gets the thrust with the throttle at `throttle`, at `k` through `air`,
+    // along the thrust line. A battery running down holds the throttle back to the power it can
+    // give: a fan's thrust goes with the power, and a propeller's speed with
+    // its cube root
+    pub fn available_thrust(&self, k: &Kinematics<S>, air: &Airdata<S>, throttle: S) -> S {
+        let throttle = match &self.electric {
+            Some(electric) if self.propeller.is_some() => throttle * S::from_f64(libm::cbrt(electric.power_fraction())),
+            Some(electric) => throttle * S::from_f64(electric.power_fraction()),
+            None => throttle,
+        };
+        match &self.propeller {
+            Some(propeller) => {
+                let airspeed = air.velocity.dot((k.angle() + self.thrust_angle).unit());
+                let rpm = propeller.operating_rpm(airspeed, throttle);
+                propeller.loads(airspeed, air.sigma * S::from_f64(RHO0), rpm).0
+            }
+            None => match &self.engine {
+                Some(engine) => throttle * engine.thrust(k.y()),
+                None => throttle * self.max_thrust,
+            },
+        }
+    }
+
+    // Returns the fraction of the thrust it could give that it gave over the
+    // last step, none if it could give none
+    pub fn thrust_fraction(&self) -> f64 {
+        let available = self.available_thrust(&self.position, &self.airdata(), S::ONE).to_f64();
+        let thrust = self.diagnostics.thrust.to_f64();
+        if available > 0.0 && thrust.is_finite() { (thrust / available).clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    // Returns the engine's fuel flow at the current state, kg/s, none
+    // without one
+    pub fn fuel_flow(&self) -> f64 {
+        match &self.engine {
+            Some(engine) => {
+                let mach = self.airdata().mach.to_f64();
+                engine.fuel_flow(self.position.y().to_f64(), mach, self.thrust_fraction())
+            }
+            None => 0.0,
+        }
+    }
+
+    // Registers a force from outside the aerodynamics
+    pub fn add_external_force(&mut self, force: impl ExternalForce<S> + 'static) {
+        self.external.push(Arc::new(force));
+    }
+
+    // Returns each external force by name with its force and moment at the
+    // current state
+    pub fn external_forces(&self) -> Vec<(&str, Vector<S>, S)> {
+        let air = self.airdata();
+        self.external.iter()
+            .map(|force| {
+                let (load, moment) = force.load(&self.position, &self.motion, &air);
+                (force.name(), load, moment)
+            })
+            .collect()
+    }
+
+    // Returns everything about the vehicle that changes as it flies
+    pub fn snapshot(&self) -> VehicleSnapshot<S> {
+        VehicleSnapshot {
+            mass: self.mass,
+            position: self.position,
+            motion: self.motion,
+            throttle: self.throttle,
+            thrust_angle: self.thrust_angle,
+            wing: self.wing.snapshot(),
+            elev: self.elev.snapshot(),
+            flex: self.flex,
+            speedbrake: self.speedbrake.as_ref().map(|speedbrake| (speedbrake.deployment, speedbrake.command)),
+            charge: self.electric.as_ref().map(|electric| electric.battery.charge),
+            engine: self.engine.as_ref().map(|engine| (engine.rating, engine.fuel)),
+            retro: self.retro.as_ref().map(|retro| (retro.fuel, retro.burning)),
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    // Puts the vehicle back as `snapshot` found it. Parts it doesn't have,
+    // such as an engine, are left out, and its configuration is untouched.
+    pub fn restore(&mut self, snapshot: &VehicleSnapshot<S>) {
+        self.mass = snapshot.mass;
+        self.position = snapshot.position;
+        self.motion = snapshot.motion;
+        self.throttle = snapshot.throttle;
+        self.thrust_angle = snapshot.thrust_angle;
+        self.wing.restore(&snapshot.wing);
+        self.elev.restore(&snapshot.elev);
+        if let (Some(flex), Some(state)) = (&mut self.flex, snapshot.flex) {
+            (flex.twist, flex.twist_rate) = (state.twist, state.twist_rate);
+        }
+        if let (Some(speedbrake), Some((deployment, command))) = (&mut self.speedbrake, snapshot.speedbrake) {
+            (speedbrake.deployment, speedbrake.command) = (deployment, command);
+        }
+        if let (Some(electric), Some(charge)) = (&mut self.electric, snapshot.charge) {
+            electric.battery.charge = charge;
+        }
+        if let (Some(engine), Some((rating, fuel))) = (&mut self.engine, snapshot.engine) {
+            (engine.rating, engine.fuel) = (rating, fuel);
+        }
+        if let (Some(retro), Some((fuel, burning))) = (&mut self.retro, snapshot.retro) {
+            (retro.fuel, retro.burning) = (fuel, burning);
+        }
+        self.diagnostics = snapshot.diagnostics;
+    }
+
+    // Calculates the dynamics of the vehicle given its current position and velocity
+    pub fn calculate_dynamics(
+        &self, 
+        k: &Kinematics<S>, 
+        dk: &Kinematics<S>) -> Kinematics<S> {
+
+        self.observed_dynamics(k, dk).0
+    }
+
+    // Calculates the dynamics as `calculate_dynamics` does, with what a step
+    // would report of them
+    fn observed_dynamics(
+        &self,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>) -> (Kinematics<S>, StepDiagnostics<S>) {
+
+        let (forces, diagnostics) = self.observed_forces(k, dk);
 
         // Returns the acceleration and the angular acceleration of the vehicle
-        Kinematics::new_raw(
-            (F_w + F_e + T + W) / self.mass,
-            (w.pitching_moment(k, dk) 
-                    + e.pitching_moment(k, dk) 
-                    + r_e.cross(F_e)) / self.moment
-        ) 
+        (Kinematics::with_rate(
+            forces.total() / self.mass + self.earth.transport(k, dk),
+            forces.total_moment() / self.moment), diagnostics)
+    }
+
+    // Returns the acceleration and angular acceleration at the current state,
+    // straight from the dynamics
+    #[inline] pub fn acceleration(&self) -> Kinematics<S> {
+        self.calculate_dynamics(&self.position, &self.motion)
     }
 
 
-    // Use RK4 to apply the calculated forces and moments to the object over the
-    // duration of a second. The method takes in the number of steps N to discretize
-    // the second into
-    pub fn apply_dynamics(&mut self, dt: f64, n: u16) {
+    // Use the integrator, RK4 unless told otherwise, to apply the calculated
+    // forces and moments to the object over the duration of a second. The
+    // method takes in the number of steps N to discretize the second into,
+    // and returns what the dynamics came to when they were
+    // last evaluated, which the vehicle also keeps as its `diagnostics`
+    pub fn apply_dynamics(&mut self, dt: S, n: u16) -> StepDiagnostics<S> {
 
         // Time step
-        let h: f64 = dt / n as f64;
+        let h: S = dt / S::from_f64(n as f64);
+
+        // The latest evaluation, from inside the integrator
+        let last = Cell::new(self.diagnostics);
 
         // Iterate over the time steps
         for _ in 0..n {
             
-            // Calculate the velocity at each time step using RK4 and the dynamics 
-            // function. The function "f" calculates the derivative of the motion 
-            // (velocity), using the dynamics function to get the acceleration
-            let f = 
-                |_: f64, dk: Kinematics| self.calculate_dynamics(&self.position, &dk);
+            match self.integrator {
+                Integrator::Rk4 => {
+                    // Calculate the velocity at each time step using RK4 and the dynamics 
+                    // function. The function "f" calculates the derivative of the motion 
+                    // (velocity), using the dynamics function to get the acceleration
+                    let f = |_: S, dk: Kinematics<S>| {
+                        let (dynamics, diagnostics) = self.observed_dynamics(&self.position, &dk);
+                        last.set(diagnostics);
+                        dynamics
+                    };
 
-            // RK4 is used to update the vehicle's motion (velocity) based on its 
-            // acceleration...
-            self.motion = rk4(f, self.motion, 0.0, h);
+                    // RK4 is used to update the vehicle's motion (velocity) based on its 
+                    // acceleration...
+                    self.motion = rk4(f, self.motion, S::ZERO, h);
 
-            // The function "f" calculates the derivative of the position (velocity)
-            // It uses the current velocity to get the rate of change of position
-            let f = 
-                |_: f64, _: Kinematics| self.motion;
+                    // The function "f" calculates the derivative of the position (velocity)
+                    // It uses the current velocity to get the rate of change of position
+                    let f = 
+                        |_: S, k: Kinematics<S>| self.earth.pose_rate(&k, &self.motion);
 
-            // ...and RK4 is used to update the vehicle's position based on its velocity
-            self.position = rk4(f, self.position, 0.0, h);
+                    // ...and RK4 is used to update the vehicle's position based on its velocity
+                    self.position = rk4(f, self.position, S::ZERO, h);
+                    self.evaluations += 4;
+                }
+
+                // The pose and motion together, as the implicit rule needs
+                // the whole state at once
+                Integrator::ImplicitMidpoint { .. } => {
+                    let f = |_: S, (k, dk): &(Kinematics<S>, Kinematics<S>)| {
+                        let (dynamics, diagnostics) = self.observed_dynamics(k, dk);
+                        last.set(diagnostics);
+                        (self.earth.pose_rate(k, dk), dynamics)
+                    };
+                    let mut state = (self.position, self.motion);
+                    let (taken, converged) = implicit_midpoint_step(f, &mut state, S::ZERO, h);
+                    (self.position, self.motion) = state;
+                    self.evaluations += taken as u64;
+                    self.unconverged += u64::from(!converged);
+                }
+            }
+
+            // The tail twists under its loads at the new state
+            if let Some(mut flex) = self.flex {
+                let (forces, diagnostics) = self.observed_forces(&self.position, &self.motion);
+                last.set(diagnostics);
+                let arm = Vector::new(S::from_f64(-flex.arm), S::ZERO).to_world(self.position.angle());
+                let moment = forces.elevator_moment + arm.cross(forces.elevator_lift + forces.elevator_drag);
+                flex.step(moment.to_f64(), h.to_f64());
+                self.flex = Some(flex);
+            }
+
+            // The speedbrake runs towards its command
+            if let Some(speedbrake) = &mut self.speedbrake {
+                speedbrake.step(h.to_f64());
+            }
         }
+
+        self.diagnostics = last.get();
+        self.diagnostics
     }
 }
\ No newline at end of file
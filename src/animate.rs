@@ -0,0 +1,225 @@
+use crate::error::DyntermError;
+use crate::util::PlotStyle;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+// Frames per second of the exported animation
+const FPS: f64 = 20.0;
+
+// Length of the attitude marker, in pixels
+const MARKER_PX: f64 = 16.0;
+
+// Simulated seconds per real second, changing along the run: a speed from
+// each start time on, the first from the start of the run. Read from a
+// single speed, such as "10", or from start times and speeds, such as
+// "0:10,40:1,60:10" to slow to real time from 40 s to 60 s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackSpeed {
+    schedule: Vec<(f64, f64)>,
+}
+
+impl PlaybackSpeed {
+
+    // Constructor, for one speed throughout
+    pub fn constant(speed: f64) -> PlaybackSpeed {
+        PlaybackSpeed { schedule: vec![(0.0, speed)] }
+    }
+
+    // The speed at `second` of simulation time
+    pub fn at(&self, second: f64) -> f64 {
+        self.schedule.iter()
+            .take_while(|(start, _)| *start <= second)
+            .last()
+            .map_or(self.schedule[0].1, |&(_, speed)| speed)
+    }
+}
+
+impl Default for PlaybackSpeed {
+    fn default() -> PlaybackSpeed {
+        PlaybackSpeed::constant(10.0)
+    }
+}
+
+impl FromStr for PlaybackSpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PlaybackSpeed, String> {
+        let number = |text: &str| text.trim().parse::<f64>()
+            .map_err(|_| format!("'{}' isn't a number", text.trim()));
+        let schedule = match s.contains(':') {
+            false => vec![(0.0, number(s)?)],
+            true => s.split(',').map(|entry| {
+                let (start, speed) = entry.split_once(':')
+                    .ok_or_else(|| format!("'{}' should be a start time and a speed, as 40:1", entry.trim()))?;
+                Ok((number(start)?, number(speed)?))
+            }).collect::<Result<Vec<_>, String>>()?,
+        };
+        if schedule.iter().any(|(start, speed)| !(start.is_finite() && *speed > 0.0 && speed.is_finite())) {
+            return Err("speeds must be positive".to_owned());
+        }
+        if schedule.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err("start times must increase".to_owned());
+        }
+        Ok(PlaybackSpeed { schedule })
+    }
+}
+
+// Renders the trajectory frame by frame into an animation at `path`, an MP4
+// encoded by ffmpeg, which must be on the PATH, if it ends in .mp4, or an
+// animated GIF otherwise. Each element of `frames` is (x, y, pitch, aoa),
+// with both angles in radians, and they are assumed to be sampled at
+// `sample_rate` per second of simulation time from `start`, s, on, as a
+// resumed run starts part way through. `speed` is the playback
+// speed, i.e. simulated seconds per real second, along the run. The frames
+// are sized and lettered in `style`.
+pub fn animate_trajectory(
+    path: &str,
+    title: &str,
+    frames: &[(f64, f64, f64, f64)],
+    start: f64,
+    sample_rate: f64,
+    speed: &PlaybackSpeed,
+    style: &PlotStyle) -> Result<(), DyntermError> {
+
+    if frames.is_empty() { return Ok(()); }
+
+    // Find the extents of the whole path, so the axes stay fixed
+    let (x_min, x_max) = frames.iter().map(|(x, _, _, _)| x)
+        .fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |acc, &x| (acc.0.min(x), acc.1.max(x))
+        );
+    let (y_min, y_max) = frames.iter().map(|(_, y, _, _)| y)
+        .fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |acc, &y| (acc.0.min(y), acc.1.max(y))
+        );
+
+    // Pad the extents so the marker doesn't leave the chart, and keep the
+    // aspect ratio between 1:1 and 4:1 so the frames stay a sensible size
+    let pad = 0.05 * (x_max - x_min).max(y_max - y_min).max(1.0);
+    let view = Extents {
+        x: (x_min - pad, x_max + pad),
+        y: (y_min - pad, y_max + pad),
+    };
+    let aspect_ratio = ((view.x.1 - view.x.0) / (view.y.1 - view.y.0)).clamp(1.0, 4.0);
+    let dims = style.canvas_size(aspect_ratio);
+
+    // The sample shown in each frame, each frame moving the clock on by the
+    // playback speed where it is, so slowed stretches repeat samples
+    let mut shown = Vec::new();
+    let mut second = start;
+    loop {
+        let sample = ((second - start) * sample_rate).round() as usize;
+        if sample >= frames.len() { break; }
+        shown.push((sample, start + sample as f64 / sample_rate, speed.at(second)));
+        second += speed.at(second) / FPS;
+    }
+
+    if !path.ends_with(".mp4") {
+        let root = BitMapBackend::gif(path, dims, (1000.0 / FPS) as u32)
+            .map_err(|e| DyntermError::Plot(e.to_string()))?
+            .into_drawing_area();
+        for &(sample, second, speed) in &shown {
+            draw_frame(&root, title, frames, sample, second, speed, &view, style)?;
+            root.present()?;
+        }
+        return Ok(());
+    }
+
+    // Pipe the frames to ffmpeg as raw RGB, padded to the even size its
+    // encoder needs
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pixel_format", "rgb24"])
+        .args(["-video_size", &format!("{}x{}", dims.0, dims.1), "-framerate", &FPS.to_string(), "-i", "-"])
+        .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p", path])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| DyntermError::Plot(format!("couldn't start ffmpeg to encode {}: {}", path, e)))?;
+    let mut buffer = vec![0; dims.0 as usize * dims.1 as usize * 3];
+    let written = (|| {
+        let mut input = ffmpeg.stdin.take().ok_or_else(|| DyntermError::Plot("ffmpeg has no input".to_owned()))?;
+        for &(sample, second, speed) in &shown {
+            let root = BitMapBackend::with_buffer(&mut buffer, dims).into_drawing_area();
+            draw_frame(&root, title, frames, sample, second, speed, &view, style)?;
+            root.present()?;
+            drop(root);
+            input.write_all(&buffer)?;
+        }
+        Ok::<(), DyntermError>(())
+    })();
+
+    // ffmpeg's own failure explains a broken pipe better than the pipe does
+    let status = ffmpeg.wait()?;
+    if !status.success() {
+        return Err(DyntermError::Plot(format!("ffmpeg failed to encode {}: {}", path, status)));
+    }
+    written
+}
+
+// The axes' fixed ranges, m
+struct Extents {
+    x: (f64, f64),
+    y: (f64, f64),
+}
+
+// Helper function
+#[allow(clippy::too_many_arguments)]
+fn draw_frame<'a>(
+    root: &DrawingArea<BitMapBackend<'a>, Shift>,
+    title: &str,
+    frames: &[(f64, f64, f64, f64)],
+    end: usize,
+    second: f64,
+    speed: f64,
+    view: &Extents,
+    style: &'a PlotStyle) -> Result<(), DyntermError> {
+
+    let (x_dim, y_dim) = root.dim_in_pixel();
+
+    // Metres per pixel along each axis, used to keep the marker a fixed
+    // on-screen size and at its true on-screen angle
+    let x_scale = MARKER_PX * (view.x.1 - view.x.0) / x_dim as f64;
+    let y_scale = MARKER_PX * (view.y.1 - view.y.0) / y_dim as f64;
+
+    let (x, y, pitch, aoa) = frames[end];
+
+    let mut chart = style.builder(root, &format!("{} (t = {:.1} s, {}x)", title, second, speed))?
+        .build_cartesian_2d(view.x.0..view.x.1, view.y.0..view.y.1)?;
+
+    let mut mesh = chart.configure_mesh();
+    mesh.x_desc("Distance [m]").y_desc("Altitude [m]");
+    style.mesh(&mut mesh);
+    mesh.draw()?;
+
+    // The path flown so far
+    chart.draw_series(LineSeries::new(
+        frames[..=end].iter().map(|(x, y, _, _)| (*x, *y)),
+        &RGBColor(160, 160, 160),
+    ))?;
+
+    // The body axis, pointing along the pitch attitude...
+    chart.draw_series(std::iter::once(PathElement::new(
+        vec![
+            (x - 0.5 * x_scale * pitch.cos(), y - 0.5 * y_scale * pitch.sin()),
+            (x + 0.5 * x_scale * pitch.cos(), y + 0.5 * y_scale * pitch.sin()),
+        ],
+        BLACK.stroke_width(3),
+    )))?;
+
+    // ...and the velocity vector, which lags it by the angle of attack
+    let gamma = pitch - aoa;
+    chart.draw_series(std::iter::once(PathElement::new(
+        vec![
+            (x, y),
+            (x + x_scale * gamma.cos(), y + y_scale * gamma.sin()),
+        ],
+        RED.stroke_width(2),
+    )))?;
+
+    Ok(())
+}
@@ -0,0 +1,455 @@
+use crate::error::DyntermError;
+use crate::scalar::Scalar;
+
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+// https://ntrs.nasa.gov/archive/nasa/casi.ntrs.nasa.gov/19770009539.pdf
+pub fn isa_temperature<S: Scalar>(altitude: S) -> S {
+    const T0: f64 = 288.15; // Temperature at sea level, K
+    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
+
+    if altitude <= S::from_f64(11000.0) {
+        S::from_f64(T0) - S::from_f64(L) * altitude
+    } else {
+        S::from_f64(216.65)
+    }
+}
+
+pub fn isa_pressure<S: Scalar>(altitude: S) -> S {
+    const T0: f64 = 288.15; // Temperature at sea level, K
+    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
+    const G: f64 = 9.80665; // Acceleration due to gravity, m/s^2
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+
+    if altitude <= S::from_f64(11000.0) {
+        S::from_f64(101325.0)
+            * (isa_temperature(altitude) / S::from_f64(T0)).powf(S::from_f64(G / (L * R)))
+    } else {
+        S::from_f64(22632.0)
+            * (S::from_f64(-G) * (altitude - S::from_f64(11000.0)) / S::from_f64(R * 216.65)).exp()
+    } // Pa
+}
+
+pub const RHO0: f64 = 1.225; // Density at sea level, kg/m^3
+
+pub fn isa_density<S: Scalar>(altitude: S) -> S {
+    S::from_f64(RHO0) * (isa_pressure(altitude) / S::from_f64(101325.0))
+}
+
+// Relative density, from the table when the atmosphere-table feature is on
+#[inline] pub fn atmo_density<S: Scalar>(altitude: S) -> S {
+    #[cfg(feature = "atmosphere-table")]
+    return atmosphere_table().density(altitude);
+
+    #[cfg(not(feature = "atmosphere-table"))]
+    isa_relative_density(altitude)
+}
+
+// Relative density straight from the model
+#[inline] pub fn isa_relative_density<S: Scalar>(altitude: S) -> S {
+    isa_density(altitude) / S::from_f64(RHO0)
+}
+
+// Relative density sampled on an evenly spaced altitude grid, so a query is
+// an index and a lerp rather than a powf or exp. Altitudes off the grid fall
+// back to the model.
+pub struct AtmosphereTable {
+    floor: f64,
+    step: f64,
+    sigma: Vec<f64>,
+}
+
+impl AtmosphereTable {
+
+    // Constructor, sampling from `floor` to at least `ceiling` every `step`
+    pub fn new(floor: f64, ceiling: f64, step: f64) -> AtmosphereTable {
+        let n = libm::ceil((ceiling - floor) / step) as usize + 1;
+        let sigma = (0..n).map(|i| isa_relative_density(floor + i as f64 * step)).collect();
+        AtmosphereTable { floor, step, sigma }
+    }
+
+    // Relative density at `altitude`, linearly interpolated
+    pub fn density<S: Scalar>(&self, altitude: S) -> S {
+        let u = (altitude.to_f64() - self.floor) / self.step;
+        if !(u >= 0.0 && u < (self.sigma.len() - 1) as f64) {
+            return isa_relative_density(altitude);
+        }
+
+        let i = u as usize;
+        let (lo, hi) = (self.sigma[i], self.sigma[i + 1]);
+        let t = (altitude - S::from_f64(self.floor + i as f64 * self.step)) / S::from_f64(self.step);
+        S::from_f64(lo) + t * S::from_f64(hi - lo)
+    }
+}
+
+// The table behind atmo_density, built on first use: 10 m steps from below
+// sea level to well above anything the vehicles reach, to within about 3e-7
+// of the model, relatively, but for the 10 m above the tropopause, where it
+// bridges the model's step in pressure of 3e-5
+#[cfg(feature = "atmosphere-table")]
+pub fn atmosphere_table() -> &'static AtmosphereTable {
+    static TABLE: std::sync::OnceLock<AtmosphereTable> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| AtmosphereTable::new(-1000.0, 50000.0, 10.0))
+}
+
+// A day `offset` K hotter than standard, or colder if negative, keeps the
+// standard lapse rate, so the air is thinner or denser throughout and the
+// pressure falls off more slowly or quickly with height. The functions below
+// give the air at a geometric `altitude` on such a day.
+
+// Pressure altitude at `altitude`, the height in the standard atmosphere with
+// the same pressure, by Newton's method on the hydrostatic equation
+pub fn pressure_altitude<S: Scalar>(altitude: S, offset: f64) -> S {
+    const T0: f64 = 288.15; // Temperature at sea level, K
+    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
+    const T11: f64 = 216.65; // Temperature at the tropopause, K
+
+    if offset == 0.0 { return altitude; }
+
+    // Geometric height of the standard pressure altitude `hp` on this day
+    let height = |hp: S| if hp <= S::from_f64(11000.0) {
+        hp + S::from_f64(offset / L) * (S::from_f64(T0) / isa_temperature(hp)).ln()
+    } else {
+        hp + S::from_f64(offset / L * libm::log(T0 / T11))
+            + S::from_f64(offset / T11) * (hp - S::from_f64(11000.0))
+    };
+
+    let mut hp = altitude;
+    for _ in 0..6 {
+        let t = isa_temperature(hp);
+        hp = hp - (height(hp) - altitude) * t / (t + S::from_f64(offset));
+    }
+    hp
+}
+
+// Temperature at `altitude`, K
+pub fn offset_temperature<S: Scalar>(altitude: S, offset: f64) -> S {
+    isa_temperature(pressure_altitude(altitude, offset)) + S::from_f64(offset)
+}
+
+// Relative density at `altitude`, the standard density at the pressure
+// altitude scaled by the ratio of the temperatures
+pub fn offset_density<S: Scalar>(altitude: S, offset: f64) -> S {
+    let hp = pressure_altitude(altitude, offset);
+    let t = isa_temperature(hp);
+    atmo_density(hp) * (t / (t + S::from_f64(offset)))
+}
+
+// Density altitude for a relative density `sigma`, the height in the
+// standard atmosphere with the same density, inverting `isa_density`
+pub fn density_altitude(sigma: f64) -> f64 {
+    const T0: f64 = 288.15; // Temperature at sea level, K
+    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
+    const G: f64 = 9.80665; // Acceleration due to gravity, m/s^2
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+    const T11: f64 = 216.65; // Temperature at the tropopause, K
+
+    let sigma11 = 22632.0 / 101325.0;
+    if sigma >= sigma11 {
+        T0 / L * (1.0 - libm::pow(sigma, L * R / G))
+    } else {
+        11000.0 - R * T11 / G * libm::log(sigma / sigma11)
+    }
+}
+
+// Standard temperature at the pressure altitude of a pressure ratio `delta`,
+// K, inverting `isa_pressure`
+fn pressure_ratio_temperature<S: Scalar>(delta: S) -> S {
+    const T0: f64 = 288.15; // Temperature at sea level, K
+    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
+    const G: f64 = 9.80665; // Acceleration due to gravity, m/s^2
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+    const T11: f64 = 216.65; // Temperature at the tropopause, K
+
+    if delta >= S::from_f64(22632.0 / 101325.0) {
+        S::from_f64(T0) * delta.powf(S::from_f64(L * R / G))
+    } else {
+        S::from_f64(T11)
+    }
+}
+
+pub fn isa_speed_of_sound<S: Scalar>(altitude: S) -> S {
+    const GAMMA: f64 = 1.4; // Ratio of specific heats for air
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+
+    (S::from_f64(GAMMA * R) * isa_temperature(altitude)).sqrt() // m/s
+}
+
+// Dynamic viscosity of air at `temperature`, by Sutherland's law
+pub fn dynamic_viscosity<S: Scalar>(temperature: S) -> S {
+    const SUTHERLAND: f64 = 110.4; // Sutherland's constant, K
+    const BETA: f64 = 1.458e-6; // kg/s/m/K^0.5
+
+    S::from_f64(BETA) * temperature.powf(S::from_f64(1.5)) / (temperature + S::from_f64(SUTHERLAND)) // Ns/m^2
+}
+
+// Speed of sound at `altitude` on a day `offset` K off standard, m/s
+pub fn offset_speed_of_sound<S: Scalar>(altitude: S, offset: f64) -> S {
+    const GAMMA: f64 = 1.4; // Ratio of specific heats for air
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+
+    (S::from_f64(GAMMA * R) * offset_temperature(altitude, offset)).sqrt() // m/s
+}
+
+// Ratio of the molar mass of water to that of dry air
+const WATER_MASS_RATIO: f64 = 0.622;
+
+// Saturation vapour pressure of water at `temperature`, K, by Buck's
+// equation, Pa
+pub fn saturation_pressure<S: Scalar>(temperature: S) -> S {
+    let t = temperature - S::from_f64(273.15); // degC
+    S::from_f64(611.21)
+        * ((S::from_f64(18.678) - t / S::from_f64(234.5)) * (t / (S::from_f64(257.14) + t))).exp()
+}
+
+// Mole fraction of water vapour in air at `temperature`, K, and `pressure`,
+// Pa, with a relative `humidity`
+fn vapour_fraction<S: Scalar>(temperature: S, pressure: S, humidity: f64) -> S {
+    S::from_f64(humidity.clamp(0.0, 1.0)) * saturation_pressure(temperature) / pressure
+}
+
+// Gas constant of moist air with a mole fraction `x` of water vapour, from
+// its mean molar mass, J/(kg K)
+fn moist_gas_constant<S: Scalar>(x: S) -> S {
+    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+
+    S::from_f64(R) / (S::ONE - S::from_f64(1.0 - WATER_MASS_RATIO) * x)
+}
+
+// Speed of sound in moist air at `temperature`, K, with a mole fraction `x`
+// of water vapour, m/s. The ratio of specific heats comes from the heat
+// capacities of the air, diatomic, and the vapour, triatomic, in units of
+// the universal gas constant.
+fn moist_speed_of_sound<S: Scalar>(temperature: S, x: S) -> S {
+    let cv = S::from_f64(2.5) + S::from_f64(0.5) * x;
+    let gamma = (cv + S::ONE) / cv;
+    (gamma * moist_gas_constant(x) * temperature).sqrt()
+}
+
+// One level of a measured profile: its `altitude`, m, `temperature`, K,
+// `pressure`, Pa, relative `humidity`, from 0 to 1, and the wind, m/s,
+// blowing towards the north and east
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub altitude: f64,
+    pub temperature: f64,
+    pub pressure: f64,
+    pub humidity: f64,
+    pub wind_north: f64,
+    pub wind_east: f64,
+}
+
+// The atmosphere as measured on the day, by a radiosonde or taken from a
+// forecast, as levels rising in altitude. Between levels the temperature,
+// humidity, and wind are interpolated linearly and the pressure
+// exponentially. Beyond them they're held, with the pressure falling off as
+// in air of the nearest level's temperature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Levels")]
+pub struct Profile {
+    levels: Vec<Level>,
+}
+
+// The profile as stored, checked and sorted again on reading it
+#[derive(Deserialize)]
+struct Levels {
+    levels: Vec<Level>,
+}
+
+impl TryFrom<Levels> for Profile {
+    type Error = DyntermError;
+
+    fn try_from(levels: Levels) -> Result<Profile, DyntermError> {
+        Profile::new(levels.levels)
+    }
+}
+
+impl Profile {
+
+    // Constructor, from at least one level, each at a finite altitude with a
+    // positive temperature and pressure, sorted by altitude
+    pub fn new(mut levels: Vec<Level>) -> Result<Profile, DyntermError> {
+        if levels.is_empty() {
+            return Err(DyntermError::Config("a profile needs a level".into()));
+        }
+        let impossible = |level: &&Level| !(level.altitude.is_finite() && level.temperature > 0.0 && level.pressure > 0.0);
+        if let Some(level) = levels.iter().find(impossible) {
+            return Err(DyntermError::Config(format!(
+                "impossible air at {} m: {} K and {} Pa", level.altitude, level.temperature, level.pressure)));
+        }
+        levels.sort_by(|a, b| a.altitude.total_cmp(&b.altitude));
+        Ok(Profile { levels })
+    }
+
+    #[inline] pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+
+    // Gets the temperature, pressure, humidity, and (north, east) wind at
+    // `altitude`
+    pub fn at<S: Scalar>(&self, altitude: S) -> (S, S, S, (S, S)) {
+        const G: f64 = 9.80665; // Acceleration due to gravity, m/s^2
+        const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+
+        let levels = &self.levels;
+        let i = levels.partition_point(|level| S::from_f64(level.altitude) <= altitude);
+        let held = |level: &Level| {
+            let rise = altitude - S::from_f64(level.altitude);
+            (
+                S::from_f64(level.temperature),
+                S::from_f64(level.pressure) * (-rise * S::from_f64(G / (R * level.temperature))).exp(),
+                S::from_f64(level.humidity),
+                (S::from_f64(level.wind_north), S::from_f64(level.wind_east)),
+            )
+        };
+        if i == 0 { return held(&levels[0]); }
+        if i == levels.len() { return held(&levels[i - 1]); }
+
+        let (below, above) = (&levels[i - 1], &levels[i]);
+        let t = (altitude - S::from_f64(below.altitude)) / S::from_f64(above.altitude - below.altitude);
+        let lerp = |a: f64, b: f64| S::from_f64(a) + t * S::from_f64(b - a);
+        (
+            lerp(below.temperature, above.temperature),
+            S::from_f64(below.pressure) * (t * S::from_f64(libm::log(above.pressure / below.pressure))).exp(),
+            lerp(below.humidity, above.humidity),
+            (lerp(below.wind_north, above.wind_north), lerp(below.wind_east, above.wind_east)),
+        )
+    }
+}
+
+// The air the vehicles fly through. The standard atmosphere is the earth's,
+// on a standard day or one `offset` K hotter, dry or with a relative
+// `humidity` from 0 to 1, which thins it and speeds up sound. Other bodies
+// get an isothermal atmosphere, with the density falling off exponentially
+// over its `scale_height`, m, from `surface_density`, kg/m^3, at a
+// `temperature`, K, and a gas of `gas_constant`, J/(kg K), and ratio of
+// specific heats `gamma`. A measured atmosphere follows its profile instead,
+// winds and all, with downrange pointing along `heading`, degrees clockwise
+// from north. Its density comes from the same model as the standard
+// atmosphere's, in which the standard density follows the pressure, so a
+// profile of the standard day gives the standard density.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Atmosphere {
+    Standard {
+        offset: f64,
+        #[serde(default)]
+        humidity: f64,
+    },
+    Exponential {
+        surface_density: f64,
+        scale_height: f64,
+        temperature: f64,
+        gas_constant: f64,
+        gamma: f64,
+    },
+    Measured {
+        profile: Arc<Profile>,
+        heading: f64,
+    },
+}
+
+impl Default for Atmosphere {
+    fn default() -> Atmosphere {
+        Atmosphere::Standard { offset: 0.0, humidity: 0.0 }
+    }
+}
+
+impl Atmosphere {
+
+    // Gets the density at `altitude` relative to the earth's at sea level,
+    // which is what the aerodynamics take
+    #[inline] pub fn density<S: Scalar>(&self, altitude: S) -> S {
+        match *self {
+            Atmosphere::Standard { offset, humidity } => {
+                let sigma = offset_density(altitude, offset);
+                if humidity == 0.0 { return sigma; }
+
+                // Water vapour is lighter than the air it displaces
+                let x = vapour_fraction(offset_temperature(altitude, offset), self.pressure(altitude), humidity);
+                sigma * (S::ONE - S::from_f64(1.0 - WATER_MASS_RATIO) * x)
+            }
+            Atmosphere::Exponential { surface_density, scale_height, .. } =>
+                S::from_f64(surface_density / RHO0) * (-altitude / S::from_f64(scale_height)).exp(),
+            // The same model as the standard atmosphere's on a day off
+            // standard: the standard density at the pressure altitude, which
+            // follows the pressure, scaled by the ratio of the standard
+            // temperature there to the measured one
+            Atmosphere::Measured { ref profile, .. } => {
+                let (temperature, pressure, humidity, _) = profile.at(altitude);
+                let delta = pressure / S::from_f64(101325.0);
+                let sigma = delta * (pressure_ratio_temperature(delta) / temperature);
+                let x = vapour_fraction(temperature, pressure, humidity.to_f64());
+                sigma * (S::ONE - S::from_f64(1.0 - WATER_MASS_RATIO) * x)
+            }
+        }
+    }
+
+    // Gets the temperature at `altitude`, K
+    #[inline] pub fn temperature<S: Scalar>(&self, altitude: S) -> S {
+        match *self {
+            Atmosphere::Standard { offset, .. } => offset_temperature(altitude, offset),
+            Atmosphere::Exponential { temperature, .. } => S::from_f64(temperature),
+            Atmosphere::Measured { ref profile, .. } => profile.at(altitude).0,
+        }
+    }
+
+    // Gets the speed of sound at `altitude`, m/s
+    #[inline] pub fn speed_of_sound<S: Scalar>(&self, altitude: S) -> S {
+        match *self {
+            Atmosphere::Standard { offset, humidity } => {
+                if humidity == 0.0 { return offset_speed_of_sound(altitude, offset); }
+                let temperature = offset_temperature(altitude, offset);
+                moist_speed_of_sound(temperature, vapour_fraction(temperature, self.pressure(altitude), humidity))
+            }
+            Atmosphere::Exponential { temperature, gas_constant, gamma, .. } =>
+                S::from_f64(libm::sqrt(gamma * gas_constant * temperature)),
+            Atmosphere::Measured { ref profile, .. } => {
+                let (temperature, pressure, humidity, _) = profile.at(altitude);
+                moist_speed_of_sound(temperature, vapour_fraction(temperature, pressure, humidity.to_f64()))
+            }
+        }
+    }
+
+    // Gets the pressure at `altitude`, Pa
+    pub fn pressure<S: Scalar>(&self, altitude: S) -> S {
+        match *self {
+            Atmosphere::Standard { offset, .. } => isa_pressure(pressure_altitude(altitude, offset)),
+            Atmosphere::Exponential { temperature, gas_constant, .. } =>
+                self.density(altitude) * S::from_f64(RHO0 * gas_constant * temperature),
+            Atmosphere::Measured { ref profile, .. } => profile.at(altitude).1,
+        }
+    }
+
+    // Gets the pressure altitude at `altitude`, the height in the earth's
+    // standard atmosphere with the same pressure, m. The standard density
+    // follows the pressure, so it's the density altitude of the pressure
+    // ratio.
+    pub fn pressure_altitude(&self, altitude: f64) -> f64 {
+        match *self {
+            Atmosphere::Standard { offset, .. } => pressure_altitude(altitude, offset),
+            _ => density_altitude(self.pressure(altitude) / 101325.0),
+        }
+    }
+
+    // Gets the measured wind at `altitude` along downrange and across it to
+    // the right, m/s, or None if the atmosphere carries none
+    #[inline] pub fn wind<S: Scalar>(&self, altitude: S) -> Option<(S, S)> {
+        match *self {
+            Atmosphere::Measured { ref profile, heading } => {
+                let (north, east) = profile.at(altitude).3;
+                let (sin, cos) = libm::sincos(heading.to_radians());
+                let (sin, cos) = (S::from_f64(sin), S::from_f64(cos));
+                Some((north * cos + east * sin, east * cos - north * sin))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn isa_dynamic_viscosity<S: Scalar>(altitude: S) -> S {
+    dynamic_viscosity(isa_temperature(altitude))
+}
@@ -0,0 +1,98 @@
+use crate::atmosphere::{Atmosphere, RHO0};
+use crate::gravity::Gravity;
+use crate::rk4::rk4;
+use crate::vec::Vector;
+
+use serde::{Deserialize, Serialize};
+
+// A body with nothing acting on it but drag and its weight, summed up by its
+// ballistic coefficient β = m / (Cd A), kg/m^2. Any two bodies with the same
+// β fly the same trajectory, so a sweep over β brackets how far and how
+// fast anything unpowered and unlifted can come down from a release.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ballistic {
+    pub beta: f64,
+    pub gravity: Gravity,
+    #[serde(default)]
+    pub atmosphere: Atmosphere,
+}
+
+// How a ballistic body came down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BallisticFlight {
+    pub beta: f64,
+    // To the ground, interpolated within the last step, or to the time limit
+    pub flight_time: f64,
+    pub range: f64,
+    pub final_speed: f64,
+    pub impacted: bool,
+    // Position at each step
+    pub trajectory: Vec<Vector>,
+}
+
+impl Ballistic {
+
+    // Acceleration moving at `velocity` at `altitude`
+    fn acceleration(&self, altitude: f64, velocity: Vector) -> Vector {
+        let rho = self.atmosphere.density(altitude) * RHO0;
+        let drag = -velocity * (0.5 * rho * velocity.magnitude() / self.beta);
+        drag + Vector::new(0.0, -self.gravity.at(altitude))
+    }
+
+    // Flies from `position` moving at `velocity` until it reaches the ground
+    // or `max_time` seconds pass, in steps of `dt`. The velocity is stepped
+    // with RK4 at the held position and the position after it, as for the
+    // vehicles.
+    pub fn fly(&self, position: Vector, velocity: Vector, dt: f64, max_time: f64) -> BallisticFlight {
+        let (mut position, mut velocity, mut time) = (position, velocity, 0.0);
+        let mut trajectory = vec![position];
+
+        while time < max_time && position.y() > 0.0 {
+            let (last_position, last_velocity) = (position, velocity);
+            let f = |_: f64, v: Vector| self.acceleration(position.y(), v);
+            velocity = rk4(f, velocity, 0.0, dt);
+            position += velocity * dt;
+            time += dt;
+
+            // Back up to where it crossed the ground
+            if position.y() <= 0.0 {
+                let t = last_position.y() / (last_position.y() - position.y());
+                position = last_position + (position - last_position) * t;
+                position = Vector::new(position.x(), 0.0);
+                velocity = last_velocity + (velocity - last_velocity) * t;
+                time -= (1.0 - t) * dt;
+            }
+            trajectory.push(position);
+        }
+
+        BallisticFlight {
+            beta: self.beta,
+            flight_time: time,
+            range: position.x(),
+            final_speed: velocity.magnitude(),
+            impacted: position.y() <= 0.0,
+            trajectory,
+        }
+    }
+}
+
+// Ballistic coefficients from `low` to `high`, evenly spaced in their
+// logarithm, since the trajectories change with the ratio between them
+pub fn log_spaced(low: f64, high: f64, count: usize) -> Vec<f64> {
+    let step = (high / low).ln() / (count.max(2) - 1) as f64;
+    (0..count).map(|i| low * (i as f64 * step).exp()).collect()
+}
+
+// Flies a ballistic body for each of `betas` from the same release
+pub fn sweep(
+    betas: &[f64],
+    gravity: Gravity,
+    atmosphere: &Atmosphere,
+    position: Vector,
+    velocity: Vector,
+    dt: f64,
+    max_time: f64) -> Vec<BallisticFlight> {
+    betas.iter()
+        .map(|&beta| Ballistic { beta, gravity, atmosphere: atmosphere.clone() }.fly(position, velocity, dt, max_time))
+        .collect()
+}
@@ -0,0 +1,99 @@
+use crate::control::Command;
+use crate::recorder::Recorder;
+use crate::scenario::Scenario;
+use crate::summary::Summary;
+
+use rayon::prelude::*;
+use rayon::{ThreadPoolBuildError, ThreadPoolBuilder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Longest a run is flown for, s
+pub const MAX_TIME: f64 = 180.0;
+
+// Step between samples, s
+const DT: f64 = 0.01;
+
+// One finished run of a batch, with its own recording
+pub struct BatchRun {
+    pub summary: Summary,
+    pub recorder: Recorder,
+}
+
+// Flies a scenario until it hits the ground or MAX_TIME is up, on the
+// built-in pull-up, or its trim if it started trimmed, recording the same channels as the CLI's planar plots
+pub fn run(scenario: &Scenario) -> BatchRun {
+    let mut sim = scenario.build();
+    let mut recorder = Recorder::new();
+    let trimmed = scenario.trimmed();
+
+    for _ in 0..(MAX_TIME / DT).round() as usize {
+        let second = sim.time;
+        trimmed.unwrap_or_else(|| Command::pull_up(&sim.vehicle)).apply(&mut sim.vehicle);
+        sim.step(DT);
+
+        let v = &sim.vehicle;
+        recorder.record("trajectory", (v.position.x(), v.position.y(), v.aoa().nice_deg().abs()));
+        recorder.record("aoa", (second, v.aoa().nice_deg(), 0.0));
+        recorder.record("om", (second, v.motion.angular_velocity_deg(), 0.0));
+        recorder.record("dx", (second, v.motion.horizontal(), 0.0));
+        recorder.record("dy", (second, v.motion.vertical(), 0.0));
+
+        if v.grounded() { break; }
+    }
+
+    BatchRun { summary: Summary::new(&sim), recorder }
+}
+
+// Flies a scenario's point mass until it hits the ground or MAX_TIME is up,
+// recording the same channels as the CLI's 3-DOF plots
+pub fn run_point_mass(scenario: &Scenario) -> BatchRun {
+    let mut sim = scenario.build_point_mass();
+    let mut recorder = Recorder::new();
+
+    for _ in 0..(MAX_TIME / DT).round() as usize {
+        let second = sim.time;
+        sim.step(DT);
+
+        let p = sim.vehicle.position;
+        recorder.record("trajectory", (p.x(), p.y(), 0.0));
+        recorder.record("track", (p.x(), p.z(), 0.0));
+        recorder.record("crossrange", (second, p.z(), 0.0));
+
+        if sim.vehicle.grounded() { break; }
+    }
+
+    BatchRun { summary: Summary::from_point_mass(&sim), recorder }
+}
+
+// Flies every scenario independently, spread over `threads` threads, or one
+// per core if 0. Results come back in the order of `scenarios`. This is the
+// building block for Monte Carlo campaigns, sweeps, and optimisation.
+pub fn run_batch(scenarios: &[Scenario], threads: usize) -> Result<Vec<BatchRun>, ThreadPoolBuildError> {
+    run_batch_with(scenarios, threads, run)
+}
+
+// As `run_batch`, flying each scenario with `run`, say `run_point_mass`
+pub fn run_batch_with(
+    scenarios: &[Scenario],
+    threads: usize,
+    run: fn(&Scenario) -> BatchRun) -> Result<Vec<BatchRun>, ThreadPoolBuildError> {
+
+    run_batch_watched(scenarios, threads, run, |_| {})
+}
+
+// As `run_batch_with`, calling `done` with how many runs have finished as
+// each one does, from whichever thread flew it, to show progress
+pub fn run_batch_watched(
+    scenarios: &[Scenario],
+    threads: usize,
+    run: fn(&Scenario) -> BatchRun,
+    done: impl Fn(usize) + Sync) -> Result<Vec<BatchRun>, ThreadPoolBuildError> {
+
+    let finished = AtomicUsize::new(0);
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+    Ok(pool.install(|| scenarios.par_iter().map(|scenario| {
+        let result = run(scenario);
+        done(finished.fetch_add(1, Ordering::Relaxed) + 1);
+        result
+    }).collect()))
+}
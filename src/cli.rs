@@ -0,0 +1,390 @@
+use dynterm::animate::PlaybackSpeed;
+use dynterm::logging::LogFormat;
+use dynterm::presets::{self, Preset, PRESETS};
+use dynterm::util::PlotStyle;
+use std::env;
+use std::fmt::Display;
+use std::process;
+use std::str::FromStr;
+use tracing::level_filters::LevelFilter;
+
+// Default address for `dynterm serve`
+const DEFAULT_SERVE_ADDR: &str = "0.0.0.0:50051";
+
+const USAGE: &str = "\
+Usage: dynterm [OPTIONS]
+       dynterm serve [HOST:PORT]
+       dynterm verify [--bless]
+       dynterm sweep [OPTIONS]
+       dynterm cg-sweep [OPTIONS]
+       dynterm monte-carlo [OPTIONS]
+       dynterm compare <FILE>... [--channel <NAME>]...
+
+Commands:
+    serve                   Run the gRPC simulation service (needs the grpc
+                            feature) [default: 0.0.0.0:50051]
+    verify                  Check the simulator against known solutions and
+                            the golden scenario results, or with --bless,
+                            print new golden results for data/golden.json
+    sweep                   Drop ballistic bodies over a range of ballistic
+                            coefficients from the scenario's release, and
+                            plot how they come down against the vehicle
+    cg-sweep                Trim the scenario's vehicle in level flight at
+                            centres of mass across its limits, and report
+                            the elevator and static margin at each
+    monte-carlo             Fly the scenario many times over, its initial
+                            conditions scattered by its dispersions, and
+                            report and plot where the runs come down (needs
+                            the parallel feature)
+    compare                 Overlay channels of several runs recorded with
+                            --record on shared charts, a colour and a
+                            legend entry for each run
+
+Options:
+    --scenario <FILE>       Read the run description from a JSON scenario
+    --preset <NAME>         Fly one of the built-in vehicles from its usual
+                            starting conditions: glider, transport, bomb,
+                            rocket, capsule, or lander (on Mars)
+    --3dof                  Fly the scenario's point mass in three dimensions
+                            instead of the planar vehicle, in a run or a
+                            Monte Carlo campaign
+    --6dof                  Fly the scenario's rigid-body vehicle with full
+                            6-DOF dynamics instead of the planar vehicle
+    --fg-host <HOST:PORT>   Stream native FDM packets to FlightGear over UDP,
+                            paced to wall-clock time
+    --mavlink <HOST:PORT>   Stream MAVLink HIL_STATE_QUATERNION/HIL_SENSOR
+                            over UDP, paced to wall-clock time
+    --sil <HOST:PORT>       Listen for an external controller over TCP and
+                            take elevator/throttle commands from it each step
+    --joystick              Fly manually from a game controller, paced to
+                            wall-clock time (needs the joystick feature).
+                            Start pauses, the bottom face button steps while
+                            paused, and the left bumper rewinds. The state
+                            is shown live on a terminal while flying
+    --rewind <S>            Seconds the left bumper rewinds, up to a minute
+                            [default: 5]
+    --checkpoint <FILE>     Save the full simulation state and the scenario
+                            flown to FILE, at the --checkpoint-at time or at
+                            the end of the run
+    --checkpoint-at <S>     Simulation time to save the checkpoint at
+    --resume <FILE>         Continue from a checkpoint instead of starting
+                            from the initial conditions, flying the
+                            checkpoint's scenario
+    --record <FILE>         Stream the recorded channels to FILE as CSV
+                            rather than holding them in memory, summing
+                            each up rather than plotting it
+    --forces                Also record and plot each force and moment on the
+                            vehicle separately, for diagnosing its handling
+    --log-rate <HZ>         Samples recorded, and logged with --verbose, per
+                            simulated second, up to the step rate
+                            [default: 100]
+    --realtime              Pace the simulation to wall-clock time
+    --speed <MULTIPLIER>    Simulated seconds per wall-clock second when
+                            paced [default: 1]
+    --beta-min <KG/M2>      Smallest ballistic coefficient swept [default: 100]
+    --beta-max <KG/M2>      Largest ballistic coefficient swept
+                            [default: 10000]
+    --beta-count <N>        Ballistic coefficients swept, spaced evenly in
+                            their logarithm [default: 20]
+    --cg-count <N>          Centres of mass swept [default: 11]
+    --runs <N>              Runs in a Monte Carlo campaign [default: 100]
+    --channel <NAME>        Channel to compare, repeated for more
+                            [default: trajectory, aoa, gs]
+    --export <FILE>         Write every recorded channel to FILE as n by 3
+                            arrays, a NumPy archive if it ends in .npz, or a
+                            MATLAB v5 .mat file otherwise
+    --track <FILE>          Write the trajectory over the globe, from the
+                            scenario's origin, to FILE as KML if it ends in
+                            .kml, or GeoJSON otherwise
+    --animation <FILE>      Also write the animated trajectory to FILE, as an
+                            MP4 encoded by ffmpeg if it ends in .mp4, or a
+                            GIF otherwise
+    --playback-speed <X>    Simulated seconds per second of the animation,
+                            one speed or start times and speeds to change
+                            it along the run, as 0:10,40:1,60:10
+                            [default: 10]
+    --report <FILE>         Also write the summary and every chart to FILE
+                            as a self-contained HTML page, zoomable in the
+                            browser
+    --plot-style <FILE>     Read the charts' size, DPI, fonts, colours, and
+                            grid from a JSON plot style
+    --plot-points <N>       Points drawn on each chart at most, picked to
+                            keep its shape, or 0 for every point recorded,
+                            over the plot style's [default: 2000]
+    -q, --quiet             Log errors alone, leaving out the events along
+                            the run and the progress bar
+    -v, --verbose           Also log the state at every recorded step, in
+                            place of the progress bar
+    --log-format <FORMAT>   Write the log as text, or as a JSON object per
+                            line [default: text]
+    -h, --help              Print this message";
+
+// What to do
+#[derive(Debug)]
+pub enum Mode {
+    Simulate,
+    PointMass,
+    SixDof,
+    Serve(String),
+    Verify { bless: bool },
+    Sweep,
+    CgSweep,
+    MonteCarlo { point_mass: bool },
+    Compare { recordings: Vec<String> },
+}
+
+// Command line options
+#[derive(Debug)]
+pub struct Options {
+    pub mode: Mode,
+    pub scenario: Option<String>,
+    pub preset: Option<&'static Preset>,
+    pub fg_host: Option<String>,
+    pub mavlink: Option<String>,
+    pub sil: Option<String>,
+    pub joystick: bool,
+    pub rewind: f64,
+    pub checkpoint: Option<String>,
+    pub checkpoint_at: f64,
+    pub resume: Option<String>,
+    pub record: Option<String>,
+    pub forces: bool,
+    pub log_rate: f64,
+    pub realtime: bool,
+    pub speed: f64,
+    pub beta_min: f64,
+    pub beta_max: f64,
+    pub beta_count: usize,
+    pub cg_count: usize,
+    pub runs: usize,
+    pub channels: Vec<String>,
+    pub export: Option<String>,
+    pub track: Option<String>,
+    pub animation: Option<String>,
+    pub playback_speed: PlaybackSpeed,
+    pub report: Option<String>,
+    pub plot_style: PlotStyle,
+    pub log_level: LevelFilter,
+    pub log_format: LogFormat,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode: Mode::Simulate,
+            scenario: None,
+            preset: None,
+            fg_host: None,
+            mavlink: None,
+            sil: None,
+            joystick: false,
+            rewind: 5.0,
+            checkpoint: None,
+            checkpoint_at: f64::INFINITY,
+            resume: None,
+            record: None,
+            forces: false,
+            log_rate: 100.0,
+            realtime: false,
+            speed: 1.0,
+            beta_min: 100.0,
+            beta_max: 10_000.0,
+            beta_count: 20,
+            cg_count: 11,
+            runs: 100,
+            channels: Vec::new(),
+            export: None,
+            track: None,
+            animation: None,
+            playback_speed: PlaybackSpeed::default(),
+            report: None,
+            plot_style: PlotStyle::default(),
+            log_level: LevelFilter::INFO,
+            log_format: LogFormat::Text,
+        }
+    }
+}
+
+impl Options {
+
+    // Parses the options from the process arguments, printing the usage and
+    // exiting on anything unexpected
+    pub fn from_args() -> Options {
+        match Options::parse(env::args().skip(1)) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("{}\n\n{}", e, USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    // Whether the simulation should be paced to wall-clock time. External
+    // visualisers, autopilots, and human pilots need this to make sense of the stream.
+    pub fn paced(&self) -> bool {
+        self.realtime
+            || self.joystick
+            || self.fg_host.is_some()
+            || self.mavlink.is_some()
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+
+        if args.next_if(|arg| arg == "serve").is_some() {
+            let addr = args.next_if(|arg| !arg.starts_with('-')).unwrap_or(DEFAULT_SERVE_ADDR.to_owned());
+            options.mode = Mode::Serve(addr);
+        } else if args.next_if(|arg| arg == "verify").is_some() {
+            let bless = args.next_if(|arg| arg == "--bless").is_some();
+            options.mode = Mode::Verify { bless };
+        } else if args.next_if(|arg| arg == "sweep").is_some() {
+            options.mode = Mode::Sweep;
+        } else if args.next_if(|arg| arg == "cg-sweep").is_some() {
+            options.mode = Mode::CgSweep;
+        } else if args.next_if(|arg| arg == "monte-carlo").is_some() {
+            options.mode = Mode::MonteCarlo { point_mass: false };
+        } else if args.next_if(|arg| arg == "compare").is_some() {
+            let mut recordings = Vec::new();
+            while let Some(path) = args.next_if(|arg| !arg.starts_with('-')) {
+                recordings.push(path);
+            }
+            if recordings.is_empty() {
+                return Err("compare needs at least one recording".to_owned());
+            }
+            options.mode = Mode::Compare { recordings };
+        }
+
+        // --plot-points holds whichever side of --plot-style it's given
+        let mut plot_points = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scenario" => options.scenario = Some(value(&mut args, &arg)?),
+                "--preset" => {
+                    let name: String = value(&mut args, &arg)?;
+                    options.preset = Some(presets::find(&name).ok_or_else(|| {
+                        let names: Vec<_> = PRESETS.iter().map(|p| p.name).collect();
+                        format!("Unknown preset '{}', expected one of: {}", name, names.join(", "))
+                    })?);
+                }
+                "--3dof" => options.mode = match options.mode {
+                    Mode::MonteCarlo { .. } => Mode::MonteCarlo { point_mass: true },
+                    _ => Mode::PointMass,
+                },
+                "--6dof" => options.mode = Mode::SixDof,
+                "--fg-host" => options.fg_host = Some(value(&mut args, &arg)?),
+                "--mavlink" => options.mavlink = Some(value(&mut args, &arg)?),
+                "--sil" => options.sil = Some(value(&mut args, &arg)?),
+                "--joystick" => options.joystick = true,
+                "--rewind" => {
+                    options.rewind = value(&mut args, &arg)?;
+                    if options.rewind <= 0.0 || options.rewind.is_nan() {
+                        return Err("--rewind must be positive".to_owned());
+                    }
+                }
+                "--checkpoint" => options.checkpoint = Some(value(&mut args, &arg)?),
+                "--checkpoint-at" => options.checkpoint_at = value(&mut args, &arg)?,
+                "--resume" => options.resume = Some(value(&mut args, &arg)?),
+                "--record" => options.record = Some(value(&mut args, &arg)?),
+                "--forces" => options.forces = true,
+                "--log-rate" => {
+                    options.log_rate = value(&mut args, &arg)?;
+                    if options.log_rate <= 0.0 || options.log_rate.is_nan() {
+                        return Err("--log-rate must be positive".to_owned());
+                    }
+                }
+                "--realtime" => options.realtime = true,
+                "--speed" => {
+                    options.speed = value(&mut args, &arg)?;
+                    if options.speed <= 0.0 || options.speed.is_nan() {
+                        return Err("--speed must be positive".to_owned());
+                    }
+                }
+                "--beta-min" => options.beta_min = value(&mut args, &arg)?,
+                "--beta-max" => options.beta_max = value(&mut args, &arg)?,
+                "--beta-count" => options.beta_count = value(&mut args, &arg)?,
+                "--cg-count" => options.cg_count = value(&mut args, &arg)?,
+                "--runs" => options.runs = value(&mut args, &arg)?,
+                "--channel" => options.channels.push(value(&mut args, &arg)?),
+                "--export" => options.export = Some(value(&mut args, &arg)?),
+                "--track" => options.track = Some(value(&mut args, &arg)?),
+                "--animation" => options.animation = Some(value(&mut args, &arg)?),
+                "--playback-speed" => options.playback_speed = value(&mut args, &arg)?,
+                "--report" => options.report = Some(value(&mut args, &arg)?),
+                "--plot-style" => {
+                    let path: String = value(&mut args, &arg)?;
+                    options.plot_style = PlotStyle::load(&path)
+                        .map_err(|e| format!("Couldn't read plot style '{}': {}", path, e))?;
+                }
+                "--plot-points" => plot_points = Some(value(&mut args, &arg)?),
+                "-q" | "--quiet" => options.log_level = LevelFilter::ERROR,
+                "-v" | "--verbose" => options.log_level = LevelFilter::TRACE,
+                "--log-format" => options.log_format = value(&mut args, &arg)?,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                other => return Err(format!("Unknown argument '{}'", other)),
+            }
+        }
+
+        if let Some(points) = plot_points {
+            options.plot_style.points = points;
+        }
+        if !(options.plot_style.size > 0 && options.plot_style.dpi > 0.0) {
+            return Err("The plot style's size and dpi must be positive".to_owned());
+        }
+
+        if options.sil.is_some() && options.joystick {
+            return Err("--sil and --joystick can't be used together".to_owned());
+        }
+
+        if !(options.beta_min > 0.0 && options.beta_max > options.beta_min) {
+            return Err("--beta-min must be positive and less than --beta-max".to_owned());
+        }
+        if options.beta_count < 2 {
+            return Err("--beta-count must be at least 2".to_owned());
+        }
+        if options.cg_count < 2 {
+            return Err("--cg-count must be at least 2".to_owned());
+        }
+        if options.runs < 2 {
+            return Err("--runs must be at least 2".to_owned());
+        }
+
+        if options.preset.is_some() && (options.scenario.is_some() || options.resume.is_some()) {
+            return Err("--preset can't be used with --scenario or --resume".to_owned());
+        }
+        if options.scenario.is_some() && options.resume.is_some() {
+            return Err("--scenario can't be used with --resume, which flies the checkpoint's scenario".to_owned());
+        }
+
+        if matches!(options.mode, Mode::PointMass | Mode::SixDof)
+            && (options.fg_host.is_some()
+                || options.mavlink.is_some()
+                || options.sil.is_some()
+                || options.joystick
+                || options.forces
+                || options.checkpoint.is_some()
+                || options.resume.is_some())
+        {
+            return Err("--3dof and --6dof can't be used with --fg-host, --mavlink, \
+                --sil, --joystick, --forces, --checkpoint, or --resume".to_owned());
+        }
+
+        Ok(options)
+    }
+}
+
+// Takes and parses the value following `flag`
+fn value<T>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    args.next()
+        .ok_or(format!("{} requires a value", flag))?
+        .parse()
+        .map_err(|e| format!("Invalid value for {}: {}", flag, e))
+}
@@ -0,0 +1,95 @@
+use crate::gravity::G0;
+use crate::sensors::specific_force;
+use crate::sim::Simulation;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// Width of the elevator and throttle gauges, characters
+const GAUGE: usize = 21;
+
+// Least time between redraws
+const REDRAW: Duration = Duration::from_millis(100);
+
+// Lines the panel takes, the blank one above it included
+const LINES: usize = 4;
+
+// A live panel of the flight's state on stderr, for flying by hand: the
+// clock, altitude, airspeed, climb, and load, the attitude and flight path,
+// and where the elevator and throttle sit. It's redrawn in place beneath
+// the log, leaving the cursor on the blank line above it so a log line
+// written meanwhile takes that line and pushes the panel down. A hidden
+// panel does nothing, for output that isn't a terminal.
+pub struct Cockpit {
+    visible: bool,
+    // Elevator deflection at either end of its gauge, degrees
+    elevator_span: f64,
+    // When it was last drawn
+    drawn: Option<Instant>,
+}
+
+impl Cockpit {
+
+    // Constructor, for an elevator gauge spanning ±`elevator_span` degrees
+    pub fn new(elevator_span: f64, visible: bool) -> Cockpit {
+        Cockpit { visible, elevator_span, drawn: None }
+    }
+
+    // Shows the simulation's state, if the panel's due to be redrawn
+    pub fn show(&mut self, sim: &Simulation, paused: bool) {
+        if !self.visible { return; }
+        let now = Instant::now();
+        if self.drawn.is_some_and(|last| now - last < REDRAW) { return; }
+        self.drawn = Some(now);
+        let panel = self.panel(sim, paused);
+        // Nowhere to report a failed write to
+        let _ = write!(io::stderr().lock(), "\r\x1b[J{}\x1b[{}A\r", panel, LINES - 1);
+    }
+
+    // Shows the final state, and moves on past the panel
+    pub fn finish(&mut self, sim: &Simulation) {
+        if !self.visible { return; }
+        let panel = self.panel(sim, false);
+        let _ = writeln!(io::stderr().lock(), "\r\x1b[J{}", panel);
+    }
+
+    // Helper function
+    fn panel(&self, sim: &Simulation, paused: bool) -> String {
+        let state = sim.state();
+        let air = sim.vehicle.airdata();
+        let load = specific_force(&sim.vehicle, sim.vehicle.acceleration().vec).y() / G0;
+        let path = state.vy.atan2(state.vx).to_degrees();
+        let elevator = sim.vehicle.elev.pitch().nice_deg();
+        let throttle = match sim.vehicle.throttle {
+            Some(throttle) => format!("{} {:>3.0}%", gauge(throttle), 100.0 * throttle),
+            None => format!("{} auto", gauge(0.0)),
+        };
+        let centred = (elevator / self.elevator_span).clamp(-1.0, 1.0);
+
+        format!(
+            "\n\x1b[K{:>7.2} s  altitude {:>8.1} m  airspeed {:>6.1} m/s  climb {:>6.1} m/s  load {:>5.2} g\
+            \n\x1b[K  pitch {:>6.1}°  aoa {:>6.1}°  path {:>6.1}°  {}\
+            \n\x1b[K  elevator {} {:>+5.1}°  throttle {}",
+            state.time, state.y, air.tas, state.vy, load,
+            state.pitch, state.aoa, path, if paused { "PAUSED" } else { "" },
+            centred_gauge(centred), elevator, throttle)
+    }
+}
+
+// Draws a fraction from 0 to 1 filling a gauge from the left
+fn gauge(fraction: f64) -> String {
+    let filled = (fraction.clamp(0.0, 1.0) * GAUGE as f64).round() as usize;
+    format!("[{}{}]", "=".repeat(filled), " ".repeat(GAUGE - filled))
+}
+
+// Draws a fraction from -1 to 1 filling a gauge out from its middle
+fn centred_gauge(fraction: f64) -> String {
+    let half = GAUGE / 2;
+    let reach = (fraction.abs() * half as f64).round() as usize;
+    let cells: String = (0..GAUGE).map(|i| match i {
+        _ if i == half => '|',
+        _ if fraction < 0.0 && i < half && half - i <= reach => '=',
+        _ if fraction > 0.0 && i > half && i - half <= reach => '=',
+        _ => ' ',
+    }).collect();
+    format!("[{}]", cells)
+}
@@ -0,0 +1,470 @@
+use crate::derivatives::{Derivatives, Tables};
+use crate::aero::{Aerodynamics, Aerofoil, DerivativeModel, Flaps, Flex, GroundEffect, Hinge, Speedbrake, Vehicle, WaveDrag};
+#[cfg(feature = "std")]
+use crate::point_mass::PointMass;
+#[cfg(feature = "std")]
+use crate::six_dof::{Inertia, RigidBody, Vehicle6};
+use crate::interpolate::Linear;
+use crate::lifters::{Parafoil, Rotor};
+use crate::electric::Electric;
+use crate::engine::Engine;
+use crate::propeller::Propeller;
+use crate::retro::Retro;
+use crate::rk4::Integrator;
+use crate::scalar::Scalar;
+use crate::util::parse_string_as_csv;
+use crate::vec::{Angle, Kinematics, Vector};
+#[cfg(feature = "std")]
+use crate::vec::{Quaternion, Vector3};
+
+use alloc::sync::Arc;
+use serde::{Deserialize, Serialize};
+
+// Returns the lift, drag, and moment coefficient tables for the NACA 0012
+// section bundled with the crate. They're built in, so can't fail to read.
+pub fn naca_0012() -> Tables {
+    let table = |csv: &str| Arc::new(Linear::new(parse_string_as_csv(csv).expect("bundled table is malformed")));
+    (
+        table(include_str!("../data/lift.csv")),
+        table(include_str!("../data/drag.csv")),
+        table(include_str!("../data/moment.csv")),
+    )
+}
+
+// Geometry of a single lifting surface. Angles are in degrees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AerofoilConfig {
+    pub area: f64,
+    pub chord: f64,
+    pub incidence: f64,
+}
+
+// Where a vehicle's aerodynamic loads come from: its surfaces' tables, its
+// stability derivatives directly, or a rotor or parafoil holding it up
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AeroModel {
+    #[default]
+    Surfaces,
+    Derivatives,
+    Rotor,
+    Parafoil,
+}
+
+// Everything needed to build a `Vehicle` and its initial state, in a form
+// that can be read from JSON and friends. Angles are in degrees and angular
+// rates in degrees per second. Any field left out takes its default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VehicleConfig {
+    pub mass: f64,
+    pub length: f64,
+    // Pitch moment of inertia, kg m^2. Left out, the body is taken as a
+    // uniform rod of the given mass and length, turning about the centre of
+    // mass.
+    pub moment: Option<f64>,
+    // Centre of mass, m aft of the nose. Left out, it's at mid-length, where
+    // the wing is.
+    pub cg: Option<f64>,
+    // Fore and aft limits on the centre of mass, m aft of the nose, for the
+    // CG sweep. Left out, they're 40% and 60% of the length.
+    pub cg_limits: Option<(f64, f64)>,
+    pub max_thrust: f64,
+    // Radius of the nose, m, for the heating estimate
+    pub nose_radius: f64,
+    // Wingspan, m. Given, the wing feels ground effect near the ground.
+    pub span: Option<f64>,
+
+    // Initial position and attitude
+    pub x: f64,
+    pub altitude: f64,
+    pub pitch: f64,
+
+    // Initial motion
+    pub speed: f64,
+    pub flight_path_angle: f64,
+    pub pitch_rate: f64,
+
+    pub wing: AerofoilConfig,
+    pub elevator: AerofoilConfig,
+    // Transonic drag rise, added to every surface
+    pub wave_drag: WaveDrag,
+    // Torsional flexibility between the body and the tail, if any
+    pub flex: Option<Flex>,
+    // Hinge moment coefficients of the elevator, if any
+    pub hinge: Option<Hinge>,
+    // Drag brake, if any, stowed to start with
+    pub speedbrake: Option<Speedbrake>,
+    // Flaps on the wing, if any
+    pub flaps: Option<Flaps>,
+    // Propeller giving the thrust, if any, in place of `max_thrust`
+    pub propeller: Option<Propeller>,
+    // Electric motor and battery driving the thrust, if any
+    pub electric: Option<Electric>,
+    // Jet engine and its ratings giving the thrust, if any
+    pub engine: Option<Engine>,
+    // Retro-rocket for braking to touchdown, if any
+    pub retro: Option<Retro>,
+    // Stability derivatives, if any, to build the surfaces' tables from in
+    // place of the NACA 0012 section
+    pub derivatives: Option<Derivatives>,
+    // Autorotating rotor and parafoil, if any, to fly in place of the wing
+    pub rotor: Option<Rotor>,
+    pub parafoil: Option<Parafoil>,
+    // Whether to fly the surfaces, the derivatives themselves, the rotor, or
+    // the parafoil, each but the surfaces needing its own given
+    pub aero_model: AeroModel,
+    // How the state is stepped on: RK4, or the implicit midpoint rule in
+    // longer substeps for a heavily damped vehicle
+    pub integrator: Integrator,
+}
+
+impl Default for VehicleConfig {
+    fn default() -> VehicleConfig {
+        VehicleConfig {
+            mass: 100_000.0,
+            length: 46.6,
+            moment: None,
+            cg: None,
+            cg_limits: None,
+            max_thrust: 280_000.0,
+            nose_radius: 1.0,
+            span: None,
+            x: 0.0,
+            altitude: 7_300.0,
+            pitch: -45.5,
+            speed: 280.0,
+            flight_path_angle: -45.0,
+            pitch_rate: 1.4,
+            wing: AerofoilConfig { area: 280.0, chord: 8.0, incidence: 0.0 },
+            elevator: AerofoilConfig { area: 40.0, chord: 4.0, incidence: 0.0 },
+            wave_drag: WaveDrag::None,
+            flex: None,
+            hinge: None,
+            speedbrake: None,
+            flaps: None,
+            propeller: None,
+            electric: None,
+            engine: None,
+            retro: None,
+            derivatives: None,
+            rotor: None,
+            parafoil: None,
+            aero_model: AeroModel::Surfaces,
+            integrator: Integrator::Rk4,
+        }
+    }
+}
+
+impl VehicleConfig {
+
+    // Builds the vehicle, with both surfaces using the NACA 0012 tables, or
+    // the tables that give its stability derivatives
+    pub fn build(&self) -> Vehicle {
+        self.build_as()
+    }
+
+    // Builds the vehicle in another scalar type, e.g. f32, or `Dual` with one
+    // of the fields then seeded as the variable to differentiate by
+    pub fn build_as<S: Scalar>(&self) -> Vehicle<S> {
+        let (wing, elevator) = self.tables();
+        let s = S::from_f64;
+        let surface = |c: &AerofoilConfig, (cl, cd, cm): Tables| {
+            let mut foil = Aerofoil::new(
+                s(c.area),
+                s(c.chord),
+                Angle::from_degrees(s(c.incidence)),
+                cl, cd, cm);
+            foil.set_wave_drag(self.wave_drag.clone());
+            foil
+        };
+
+        let mut vehicle = Vehicle::new(
+            s(self.mass),
+            s(self.length),
+            Kinematics::new(
+                Vector::new(s(self.x), s(self.altitude)),
+                Angle::from_degrees(s(self.pitch))
+            ),
+            Kinematics::with_rate(
+                Vector::from_degrees(s(self.speed), s(self.flight_path_angle)),
+                s(self.pitch_rate.to_radians())
+            ),
+            surface(&self.wing, wing),
+            surface(&self.elevator, elevator),
+            s(self.max_thrust)
+        );
+        vehicle.nose_radius = s(self.nose_radius);
+        vehicle.flex = self.flex;
+        vehicle.elev.set_hinge(self.hinge.clone());
+        vehicle.speedbrake = self.speedbrake.clone();
+        vehicle.propeller = self.propeller.clone();
+        vehicle.electric = self.electric.clone();
+        vehicle.engine = self.engine.clone();
+        vehicle.retro = self.retro.clone();
+        vehicle.integrator = self.integrator;
+        vehicle.pitch_damping = self.derivatives.as_ref().map_or(0.0, |derivatives| derivatives.cm_q);
+        match self.aero_model {
+            AeroModel::Surfaces => {}
+            AeroModel::Derivatives => if let Some(derivatives) = &self.derivatives {
+                vehicle.model = Aerodynamics::Derivatives(DerivativeModel::new(derivatives.clone()));
+            },
+            AeroModel::Rotor => if let Some(rotor) = &self.rotor {
+                vehicle.model = Aerodynamics::Rotor(rotor.clone());
+            },
+            AeroModel::Parafoil => if let Some(parafoil) = &self.parafoil {
+                vehicle.model = Aerodynamics::Parafoil(parafoil.clone());
+            },
+        }
+        vehicle.wing.set_flaps(self.flaps.clone());
+        vehicle.wing.set_ground_effect(self.span.map(|span| GroundEffect::new(span, self.wing.area)));
+        if let Some(cg) = self.cg {
+            let offset = cg - self.length / 2.0;
+            vehicle.cg = s(offset);
+            vehicle.moment += s(self.mass * offset * offset);
+        }
+        if let Some(moment) = self.moment {
+            vehicle.moment = s(moment);
+        }
+        vehicle
+    }
+
+    // Returns the wing's and the elevator's coefficient tables
+    pub fn tables(&self) -> (Tables, Tables) {
+        match &self.derivatives {
+            Some(derivatives) => derivatives.tables(
+                (self.wing.area, self.wing.chord),
+                (self.elevator.area, self.elevator.chord),
+                self.length),
+            None => (naca_0012(), naca_0012()),
+        }
+    }
+
+    // Returns the fore and aft limits on the centre of mass, m aft of the
+    // nose
+    pub fn cg_limits(&self) -> (f64, f64) {
+        self.cg_limits.unwrap_or((0.4 * self.length, 0.6 * self.length))
+    }
+}
+
+// Everything needed to build a `PointMass` for the 3-DOF mode. Angles are in
+// degrees, and the heading is measured from downrange towards +z. Any field
+// left out takes its default.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointMassConfig {
+    pub mass: f64,
+    pub max_thrust: f64,
+    pub throttle: f64,
+    // Wingspan, m. Given, the wing feels ground effect near the ground.
+    pub span: Option<f64>,
+
+    // Initial position
+    pub x: f64,
+    pub altitude: f64,
+    pub crossrange: f64,
+
+    // Initial motion
+    pub speed: f64,
+    pub flight_path_angle: f64,
+    pub heading: f64,
+
+    // Held for the whole run
+    pub aoa: f64,
+    pub bank: f64,
+    pub wind: Vector3,
+
+    pub wing: AerofoilConfig,
+    // Transonic drag rise on the wing
+    pub wave_drag: WaveDrag,
+}
+
+#[cfg(feature = "std")]
+impl Default for PointMassConfig {
+    fn default() -> PointMassConfig {
+        PointMassConfig {
+            mass: 100_000.0,
+            max_thrust: 280_000.0,
+            throttle: 0.0,
+            span: None,
+            x: 0.0,
+            altitude: 7_300.0,
+            crossrange: 0.0,
+            speed: 280.0,
+            flight_path_angle: -45.0,
+            heading: 0.0,
+            aoa: 5.0,
+            bank: 15.0,
+            wind: Vector3::zero(),
+            wing: AerofoilConfig { area: 280.0, chord: 8.0, incidence: 0.0 },
+            wave_drag: WaveDrag::None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl PointMassConfig {
+
+    // Builds the vehicle, with the wing using the NACA 0012 tables
+    pub fn build(&self) -> PointMass {
+        let (cl, cd, cm) = naca_0012();
+        let (gamma, psi) = (self.flight_path_angle.to_radians(), self.heading.to_radians());
+
+        let mut vehicle = PointMass::new(
+            self.mass,
+            Vector3::new(self.x, self.altitude, self.crossrange),
+            self.speed * Vector3::new(
+                gamma.cos() * psi.cos(),
+                gamma.sin(),
+                gamma.cos() * psi.sin()),
+            Aerofoil::new(
+                self.wing.area,
+                self.wing.chord,
+                Angle::from_degrees(self.wing.incidence),
+                cl, cd, cm),
+            self.max_thrust
+        );
+        vehicle.wing.set_wave_drag(self.wave_drag.clone());
+        vehicle.wing.set_ground_effect(self.span.map(|span| GroundEffect::new(span, self.wing.area)));
+        vehicle.throttle = self.throttle;
+        vehicle.aoa = Angle::from_degrees(self.aoa);
+        vehicle.bank = Angle::from_degrees(self.bank);
+        vehicle.wind = self.wind;
+        vehicle
+    }
+}
+
+// Everything needed to build a `Vehicle6` and its initial state. Angles are
+// in degrees and angular rates in degrees per second, with heading measured
+// from downrange towards +z. The wing area covers both halves. Any field
+// left out takes its default.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vehicle6Config {
+    pub mass: f64,
+    pub length: f64,
+    pub span: f64,
+    pub fin_height: f64,
+    pub max_thrust: f64,
+
+    // Moments and products of inertia, kg m^2
+    pub ixx: f64,
+    pub iyy: f64,
+    pub izz: f64,
+    pub ixy: f64,
+    pub ixz: f64,
+    pub iyz: f64,
+
+    // Initial position and attitude
+    pub x: f64,
+    pub altitude: f64,
+    pub crossrange: f64,
+    pub roll: f64,
+    pub pitch: f64,
+    pub heading: f64,
+
+    // Initial motion
+    pub speed: f64,
+    pub flight_path_angle: f64,
+    pub pitch_rate: f64,
+
+    // Held for the whole run
+    pub rudder: f64,
+
+    pub wing: AerofoilConfig,
+    pub elevator: AerofoilConfig,
+    pub fin: AerofoilConfig,
+    // Transonic drag rise, added to every surface
+    pub wave_drag: WaveDrag,
+    // Whether the wing feels ground effect near the ground
+    pub ground_effect: bool,
+}
+
+#[cfg(feature = "std")]
+impl Default for Vehicle6Config {
+    fn default() -> Vehicle6Config {
+        Vehicle6Config {
+            mass: 100_000.0,
+            length: 46.6,
+            span: 40.0,
+            fin_height: 5.0,
+            max_thrust: 280_000.0,
+            ixx: 6.0e6,
+            iyy: 2.4e7,
+            izz: 1.81e7,
+            ixy: 0.0,
+            ixz: 0.0,
+            iyz: 0.0,
+            x: 0.0,
+            altitude: 7_300.0,
+            crossrange: 0.0,
+            roll: 0.0,
+            pitch: -45.5,
+            heading: 0.0,
+            speed: 280.0,
+            flight_path_angle: -45.0,
+            pitch_rate: 1.4,
+            rudder: 0.0,
+            wing: AerofoilConfig { area: 280.0, chord: 8.0, incidence: 0.0 },
+            elevator: AerofoilConfig { area: 40.0, chord: 4.0, incidence: 0.0 },
+            // The bundled tables don't quite pass through zero lift at zero
+            // angle of attack, so the fin is set to be neutral without sideslip
+            fin: AerofoilConfig { area: 50.0, chord: 6.0, incidence: 0.49 },
+            wave_drag: WaveDrag::None,
+            ground_effect: false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Vehicle6Config {
+
+    // Builds the vehicle, with every surface using the NACA 0012 tables
+    pub fn build(&self) -> Vehicle6 {
+        let (cl, cd, cm) = naca_0012();
+        let surface = |c: &AerofoilConfig, area: f64| {
+            let mut foil = Aerofoil::new(
+                area,
+                c.chord,
+                Angle::from_degrees(c.incidence),
+                cl.clone(), cd.clone(), cm.clone());
+            foil.set_wave_drag(self.wave_drag.clone());
+            foil
+        };
+
+        // Yaw about the vertical, then pitch, then roll
+        let (gamma, psi) = (self.flight_path_angle.to_radians(), self.heading.to_radians());
+        let attitude = 
+            Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), -psi)
+            * Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), self.pitch.to_radians())
+            * Quaternion::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), self.roll.to_radians());
+
+        let mut vehicle = Vehicle6::new(
+            self.mass,
+            self.length,
+            self.span,
+            self.fin_height,
+            Inertia::new(self.ixx, self.iyy, self.izz, self.ixy, self.ixz, self.iyz),
+            RigidBody {
+                position: Vector3::new(self.x, self.altitude, self.crossrange),
+                velocity: self.speed * Vector3::new(
+                    gamma.cos() * psi.cos(),
+                    gamma.sin(),
+                    gamma.cos() * psi.sin()),
+                attitude,
+                rates: Vector3::new(0.0, 0.0, self.pitch_rate.to_radians()),
+            },
+            surface(&self.wing, self.wing.area / 2.0),
+            surface(&self.elevator, self.elevator.area),
+            surface(&self.fin, self.fin.area),
+            self.max_thrust
+        );
+        vehicle.fin.set_pitch(Angle::from_degrees(self.fin.incidence + self.rudder));
+        if self.ground_effect {
+            vehicle.wing.set_ground_effect(Some(GroundEffect::new(self.span, self.wing.area)));
+        }
+        vehicle
+    }
+}
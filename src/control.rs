@@ -0,0 +1,282 @@
+use crate::aero::{Airdata, FlapSetting, Vehicle, PULL_UP_ALTITUDE};
+use crate::atmosphere::RHO0;
+use crate::engine::Rating;
+use crate::vec::Angle;
+
+use serde::{Deserialize, Serialize};
+
+// Control inputs for one step, from whichever source is flying the vehicle
+#[derive(Debug, Copy, Clone)]
+pub struct Command {
+    pub elevator: Angle,
+    // None leaves thrust to the autothrottle
+    pub throttle: Option<f64>,
+    // Speedbrake deployment, 0 to 1. None leaves it as last commanded.
+    pub speedbrake: Option<f64>,
+    // Flap setting. None leaves the flaps where they are.
+    pub flaps: Option<FlapSetting>,
+    // Engine rating. None leaves the engine at its rating.
+    pub rating: Option<Rating>,
+    // Angle of the thrust line up from the body axis. None leaves it where
+    // it is.
+    pub thrust_angle: Option<Angle>,
+}
+
+impl Command {
+
+    // The built-in pull-up, for when nothing else is flying: elevator up
+    // below the starting altitude, neutral above it, and the autothrottle
+    pub fn pull_up(vehicle: &Vehicle) -> Command {
+        Command::pull_up_at(vehicle.position.y())
+    }
+
+    // The built-in pull-up, for a vehicle believed to be at `altitude`
+    pub fn pull_up_at(altitude: f64) -> Command {
+        Command {
+            elevator: Angle::from_degrees(
+                if altitude < PULL_UP_ALTITUDE { -3.0 } else { 0.0 }),
+            throttle: None,
+            speedbrake: None,
+            flaps: None,
+            rating: None,
+            thrust_angle: None,
+        }
+    }
+
+    // Holds the elevator at `elevator` and the throttle at `throttle`, or
+    // leaves the thrust to the autothrottle if None, and everything else
+    // where it is
+    pub fn hold(elevator: Angle, throttle: Option<f64>) -> Command {
+        Command {
+            elevator,
+            throttle,
+            speedbrake: None,
+            flaps: None,
+            rating: None,
+            thrust_angle: None,
+        }
+    }
+
+    // Applies the command to the vehicle's controls
+    pub fn apply(&self, vehicle: &mut Vehicle) {
+        vehicle.elev.set_pitch(self.elevator);
+        vehicle.throttle = self.throttle;
+        if let (Some(command), Some(speedbrake)) = (self.speedbrake, &mut vehicle.speedbrake) {
+            speedbrake.set_command(command);
+        }
+        if let Some(setting) = self.flaps {
+            vehicle.wing.set_flap_setting(setting);
+        }
+        if let (Some(rating), Some(engine)) = (self.rating, &mut vehicle.engine) {
+            engine.rating = rating;
+        }
+        if let Some(angle) = self.thrust_angle {
+            vehicle.thrust_angle = angle;
+        }
+    }
+}
+
+// Proportional, integral, and derivative gains
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gains {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+// What a gain schedule is looked up by
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleKey {
+    // Dynamic pressure, Pa
+    DynamicPressure,
+    Mach,
+}
+
+// Gains tabulated against dynamic pressure or Mach, in rising order of the
+// key, interpolated linearly between the points and held beyond the ends.
+// The surfaces' authority grows with dynamic pressure, so one set of gains
+// can't fly the whole of a trajectory that climbs and dives through
+// kilometres at speeds changing threefold; a single point is a fixed set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GainSchedule {
+    pub key: ScheduleKey,
+    pub points: Vec<(f64, Gains)>,
+}
+
+impl GainSchedule {
+
+    // Gains for the flight condition in `airdata`
+    pub fn gains(&self, airdata: &Airdata) -> Gains {
+        let key = match self.key {
+            ScheduleKey::DynamicPressure => airdata.qbar * RHO0,
+            ScheduleKey::Mach => airdata.mach,
+        };
+
+        let i = self.points.partition_point(|(at, _)| *at <= key);
+        let ((a, low), (b, high)) = match i {
+            _ if self.points.is_empty() => return Gains::default(),
+            0 => (self.points[0], self.points[0]),
+            i if i == self.points.len() => (self.points[i - 1], self.points[i - 1]),
+            i => (self.points[i - 1], self.points[i]),
+        };
+
+        let t = if b > a { (key - a) / (b - a) } else { 0.0 };
+        Gains {
+            kp: low.kp + t * (high.kp - low.kp),
+            ki: low.ki + t * (high.ki - low.ki),
+            kd: low.kd + t * (high.kd - low.kd),
+        }
+    }
+}
+
+// A PID loop, whose gains can change from one step to the next. The
+// derivative is of the measurement rather than differenced from the error,
+// so it's free of noise and doesn't kick when the target steps, and the
+// integrator stops winding up while the output is against its limit.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Pid {
+    integral: f64,
+}
+
+impl Pid {
+
+    // Output for `error` changing at `error_rate`, over a step of `dt` s,
+    // held within ±`limit`
+    pub fn update(&mut self, gains: Gains, error: f64, error_rate: f64, dt: f64, limit: f64) -> f64 {
+        let unlimited = |integral: f64| gains.kp * error + gains.ki * integral + gains.kd * error_rate;
+        let integral = self.integral + error * dt;
+        if unlimited(integral).abs() < limit || error * self.integral < 0.0 {
+            self.integral = integral;
+        }
+        unlimited(self.integral).clamp(-limit, limit)
+    }
+}
+
+// A pitch-attitude hold, its PID gains in degrees of elevator per degree of
+// pitch error, scheduled on the flight condition. Thrust is left to the
+// autothrottle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutopilotSpec {
+    // Pitch to hold, degrees
+    pub pitch: f64,
+    pub gains: GainSchedule,
+    pub max_elevator: f64,
+}
+
+impl Default for AutopilotSpec {
+    fn default() -> AutopilotSpec {
+        let gains = |kp: f64| Gains { kp, ki: 0.2 * kp, kd: 0.5 * kp };
+        AutopilotSpec {
+            pitch: 0.0,
+            gains: GainSchedule {
+                key: ScheduleKey::DynamicPressure,
+                points: vec![(5_000.0, gains(1.0)), (20_000.0, gains(0.4)), (60_000.0, gains(0.15))],
+            },
+            max_elevator: 10.0,
+        }
+    }
+}
+
+// Flies an `AutopilotSpec`
+#[derive(Debug, Clone)]
+pub struct Autopilot {
+    pub spec: AutopilotSpec,
+    pid: Pid,
+    last: Option<f64>,
+    gains: Gains,
+}
+
+impl Autopilot {
+
+    // Constructor, with the integrator empty
+    pub fn new(spec: AutopilotSpec) -> Autopilot {
+        Autopilot { spec, pid: Pid::default(), last: None, gains: Gains::default() }
+    }
+
+    // Gains used on the last step
+    #[inline] pub fn gains(&self) -> Gains {
+        self.gains
+    }
+
+    // Holds the pitch at `time`, seen pitched at `pitch` degrees and
+    // pitching at `pitch_rate` degrees a second, in the flight condition of
+    // `airdata`
+    pub fn command(&mut self, time: f64, pitch: f64, pitch_rate: f64, airdata: &Airdata) -> Command {
+        let dt = self.last.map_or(0.0, |last| time - last);
+        self.last = Some(time);
+        self.gains = self.spec.gains.gains(airdata);
+
+        let error = Angle::from_degrees(self.spec.pitch).signed_diff(Angle::from_degrees(pitch)).to_degrees();
+        let output = self.pid.update(self.gains, error, -pitch_rate, dt, self.spec.max_elevator);
+
+        // Negative elevator incidence pitches the nose up
+        Command { elevator: Angle::from_degrees(-output), throttle: None, speedbrake: None, flaps: None, rating: None, thrust_angle: None }
+    }
+}
+
+// A pitch damper. The gain is degrees of elevator per degree a second of
+// pitch rate, the washout's time constant is in seconds, and the authority
+// is the most elevator the damper may add either way, degrees. A washout of
+// 0 leaves it out, damping the pitch rate itself.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SasSpec {
+    pub gain: f64,
+    pub washout: f64,
+    pub authority: f64,
+}
+
+impl Default for SasSpec {
+    fn default() -> SasSpec {
+        SasSpec { gain: 0.2, washout: 1.0, authority: 5.0 }
+    }
+}
+
+// Stability augmentation, feeding pitch rate back to the elevator on top
+// of whatever is flying, so a configuration short of damping can still be
+// flown. The rate goes through a washout, a high-pass filter, so the damper
+// fights pitching oscillations but not the steady pitch rate of a pull-up
+// or a turn, leaving the outer loop free to manoeuvre.
+#[derive(Debug, Clone)]
+pub struct Sas {
+    pub spec: SasSpec,
+    washed: f64,
+    last: Option<(f64, f64)>,
+    elevator: f64,
+}
+
+impl Sas {
+
+    // Constructor, with the washout settled
+    pub fn new(spec: SasSpec) -> Sas {
+        Sas { spec, washed: 0.0, last: None, elevator: 0.0 }
+    }
+
+    // Elevator the damper added on the last step, degrees
+    #[inline] pub fn elevator(&self) -> f64 {
+        self.elevator
+    }
+
+    // Adds the damper's elevator to `command` at `time`, pitching at
+    // `pitch_rate` degrees a second
+    pub fn augment(&mut self, time: f64, pitch_rate: f64, command: Command) -> Command {
+        // y' = -y / tau + q', stepped exactly for q changing linearly
+        self.washed = match self.last {
+            _ if self.spec.washout <= 0.0 => pitch_rate,
+            Some((last_time, last_rate)) => {
+                let decay = (-(time - last_time) / self.spec.washout).exp();
+                let slope = if time > last_time { (pitch_rate - last_rate) / (time - last_time) } else { 0.0 };
+                self.washed * decay + slope * self.spec.washout * (1.0 - decay)
+            }
+            None => 0.0,
+        };
+        self.last = Some((time, pitch_rate));
+
+        // Nose up is met with positive elevator incidence, nose down
+        self.elevator = (self.spec.gain * self.washed).clamp(-self.spec.authority, self.spec.authority);
+        Command { elevator: command.elevator + Angle::from_degrees(self.elevator), ..command }
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(feature = "std")]
+use crate::error::DyntermError;
+use crate::interpolate::Linear;
+use crate::scalar::Scalar;
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use std::{collections::HashMap, path::Path};
+
+// Lift, drag, and moment coefficient tables for one surface, as
+// `config::naca_0012` gives them
+pub type Tables = (Arc<Linear>, Arc<Linear>, Arc<Linear>);
+
+// Classical longitudinal stability derivatives, as AVL and DATCOM give
+// them, for a vehicle whose aerodynamics aren't known surface by surface.
+// Slopes are per radian, and the pitch damping per radian of q c / 2V. All
+// are on the wing's area and chord, with moments about the middle of the
+// body, where the wing is. The drag polar is CD = CD0 + k CL^2. Past `stall`,
+// degrees of angle of attack either way, the lift falls away to a flat
+// plate's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Derivatives {
+    pub cl_alpha: f64,
+    pub cl_elevator: f64,
+    pub cm_alpha: f64,
+    pub cm_q: f64,
+    pub cm_elevator: f64,
+    pub cd0: f64,
+    pub k: f64,
+    #[serde(default = "typical_stall")]
+    pub stall: f64,
+}
+
+// Stall angle of a typical section, degrees
+fn typical_stall() -> f64 {
+    15.0
+}
+
+// Coefficient rising at `slope` with angle of attack `alpha`, radians, up to
+// the stall at `stall`, then over to a flat plate's sin 2α
+fn curve<S: Scalar>(slope: f64, stall: f64, alpha: S) -> S {
+    if alpha.abs() <= S::from_f64(stall) {
+        S::from_f64(slope) * alpha
+    } else {
+        S::from_f64(slope * stall / libm::sin(2.0 * stall)) * (S::from_f64(2.0) * alpha).sin()
+    }
+}
+
+// Drag of the flow separating past the stall at `stall`, growing to a flat
+// plate's side on, at angle of attack `alpha`, radians
+fn separated<S: Scalar>(stall: f64, alpha: S) -> S {
+    if alpha.abs() <= S::from_f64(stall) { return S::ZERO; }
+    let (sin, sin_stall) = (alpha.sin(), libm::sin(stall));
+    S::from_f64(2.0) * (sin * sin - S::from_f64(sin_stall * sin_stall))
+}
+
+impl Derivatives {
+
+    // Returns tables for the wing, of area and chord `wing`, and the
+    // elevator, `elevator`, half of `length` behind it, that between them
+    // give these derivatives. The elevator's lift gives the lift per unit
+    // elevator, and with its own moment the moment per unit elevator; the
+    // wing's lift and moment make up the rest of each slope with angle of
+    // attack. The profile drag is all on the wing, and each surface has the
+    // induced drag of its own lift.
+    pub fn tables(&self, wing: (f64, f64), elevator: (f64, f64), length: f64) -> (Tables, Tables) {
+        let (area, chord) = wing;
+        let (elevator_area, elevator_chord) = elevator;
+        let arm = length / 2.0;
+
+        let wing_lift = self.cl_alpha - self.cl_elevator;
+        let wing_moment = self.cm_alpha - self.cm_elevator;
+        let elevator_lift = self.cl_elevator * area / elevator_area;
+        let elevator_moment = (self.cm_elevator + arm * self.cl_elevator / chord) * area * chord
+            / (elevator_area * elevator_chord);
+
+        (
+            self.section(wing_lift, wing_moment, self.cd0, self.k),
+            self.section(elevator_lift, elevator_moment, 0.0, self.k * elevator_area / area),
+        )
+    }
+
+    // Returns the lift, drag, and moment coefficients of the whole vehicle
+    // at angle of attack `alpha` with the elevator at `elevator`, both
+    // radians, leaving out the pitch damping
+    pub fn coefficients<S: Scalar>(&self, alpha: S, elevator: S) -> (S, S, S) {
+        let stall = self.stall.to_radians();
+        let cl = curve(self.cl_alpha, stall, alpha) + S::from_f64(self.cl_elevator) * elevator;
+        let cd = S::from_f64(self.cd0) + S::from_f64(self.k) * cl * cl + separated(stall, alpha);
+        let cm = curve(self.cm_alpha, stall, alpha) + S::from_f64(self.cm_elevator) * elevator;
+        (cl, cd, cm)
+    }
+
+    // Helper function
+    fn section(&self, lift: f64, moment: f64, cd0: f64, k: f64) -> Tables {
+        let stall = self.stall.to_radians();
+        let drag = |alpha: f64| {
+            let cl = curve(lift, stall, alpha);
+            cd0 + k * cl * cl + separated(stall, alpha)
+        };
+
+        // Every degree round the circle, and the stall either way
+        let mut keys: Vec<f64> = (0..=360).map(f64::from).collect();
+        keys.extend([self.stall, 360.0 - self.stall]);
+        keys.sort_by(f64::total_cmp);
+        keys.dedup();
+        let table = |f: &dyn Fn(f64) -> f64| {
+            let points = keys.iter()
+                .map(|&key| {
+                    let alpha = if key > 180.0 { key - 360.0 } else { key };
+                    (key, f(alpha.to_radians()))
+                })
+                .collect();
+            Arc::new(Linear::new(points))
+        };
+
+        (table(&|alpha| curve(lift, stall, alpha)), table(&drag), table(&|alpha| curve(moment, stall, alpha)))
+    }
+
+    // Reads the derivatives from text of `name = value` pairs, as AVL's
+    // stability derivative output gives them. Besides this crate's own
+    // names, per radian (CLa, CLde, Cma, Cmq, Cmde, CD0, and k), AVL's
+    // control derivatives for the control it labels elevator, or d1, are
+    // taken per degree, its CDvis as the profile drag, and its span
+    // efficiency e, with Bref and Sref, for the induced drag.
+    #[cfg(feature = "std")]
+    pub fn parse(text: &str) -> Result<Derivatives, DyntermError> {
+        let spaced = text.replace('=', " = ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let values: HashMap<&str, f64> = tokens.windows(3)
+            .filter(|window| window[1] == "=")
+            .filter_map(|window| Some((window[0], window[2].parse().ok()?)))
+            .collect();
+
+        // AVL numbers its controls, and names them in the column headings
+        let control = tokens.windows(2)
+            .find(|pair| pair[0].eq_ignore_ascii_case("elevator") && pair[1].starts_with('d'))
+            .map_or("d1", |pair| pair[1]);
+        let per_degree = |name: String| values.get(name.as_str()).map(|value| value.to_degrees());
+
+        let get = |name: &str| values.get(name).copied();
+        let require = |value: Option<f64>, name: &str| value.ok_or_else(|| DyntermError::Parse(format!("no {}", name)));
+        let induced = || {
+            let (e, span, area) = (get("e")?, get("Bref")?, get("Sref")?);
+            Some(area / (core::f64::consts::PI * e * span * span))
+        };
+
+        Ok(Derivatives {
+            cl_alpha: require(get("CLa"), "CLa")?,
+            cl_elevator: require(get("CLde").or_else(|| per_degree(format!("CL{}", control))), "CLde")?,
+            cm_alpha: require(get("Cma"), "Cma")?,
+            cm_q: require(get("Cmq"), "Cmq")?,
+            cm_elevator: require(get("Cmde").or_else(|| per_degree(format!("Cm{}", control))), "Cmde")?,
+            cd0: require(get("CD0").or_else(|| get("CDvis")), "CD0")?,
+            k: require(get("k").or_else(induced), "k")?,
+            stall: typical_stall(),
+        })
+    }
+
+    // Reads the derivatives from a file, as `parse`
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Derivatives, DyntermError> {
+        let text = std::fs::read_to_string(path)?;
+        Derivatives::parse(&text)
+    }
+}
+
+// Stability derivatives to build the vehicle from, read from `path` when
+// the scenario is loaded
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivativesFile {
+    pub path: String,
+}
@@ -0,0 +1,142 @@
+use crate::rng::SimRng;
+use crate::scenario::Scenario;
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt;
+
+// Chi-squared with two degrees of freedom at 95%, the square of the number
+// of standard deviations out to the 95% ellipse
+const CHI_SQUARED_95: f64 = 5.991;
+
+// Spread of the initial conditions over a Monte Carlo campaign, each the
+// standard deviation of normal scatter about the scenario's value: `mass`
+// as a fraction of it, `speed` in m/s, `altitude` in m, and the angles in
+// degrees. The pitch moves with the flight path angle, so `pitch` scatters
+// the angle of attack. The planar vehicle has no heading, so its impacts
+// only scatter downrange; the point mass's spread across it too.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dispersions {
+    pub mass: f64,
+    pub speed: f64,
+    pub altitude: f64,
+    pub flight_path_angle: f64,
+    pub pitch: f64,
+    pub heading: f64,
+}
+
+impl Dispersions {
+
+    // Draws run `run` of a campaign about `scenario`, seeded from the
+    // scenario's seed, so every run differs and the campaign repeats
+    pub fn draw(&self, scenario: &Scenario, run: u64) -> Scenario {
+        let seed = scenario.seed.unwrap_or_default().wrapping_add(run);
+        let mut rng = SimRng::new(seed).stream("dispersions");
+        let mass = 1.0 + self.mass * rng.normal();
+        let speed = self.speed * rng.normal();
+        let altitude = self.altitude * rng.normal();
+        let gamma = self.flight_path_angle * rng.normal();
+        let pitch = self.pitch * rng.normal();
+        let heading = self.heading * rng.normal();
+
+        let mut scenario = Scenario { seed: Some(seed), ..scenario.clone() };
+        let v = &mut scenario.vehicle;
+        v.mass *= mass;
+        v.speed += speed;
+        v.altitude += altitude;
+        v.flight_path_angle += gamma;
+        v.pitch += gamma + pitch;
+
+        let p = &mut scenario.point_mass;
+        p.mass *= mass;
+        p.speed += speed;
+        p.altitude += altitude;
+        p.flight_path_angle += gamma;
+        p.heading += heading;
+        scenario
+    }
+}
+
+// How a campaign's impact points scatter, m downrange and crossrange: about
+// their mean, the standard deviation each way, the circular error probable,
+// the radius holding half of them, and the ellipse holding 95% of them were
+// they normally spread, its semi-axes and the angle its major axis turns
+// from downrange across, degrees
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactDispersion {
+    pub count: usize,
+    pub mean: (f64, f64),
+    pub sigma_downrange: f64,
+    pub sigma_crossrange: f64,
+    pub cep: f64,
+    pub ellipse: (f64, f64, f64),
+}
+
+impl ImpactDispersion {
+
+    // Fits the scatter of `impacts`, if there are at least two
+    pub fn fit(impacts: &[(f64, f64)]) -> Option<ImpactDispersion> {
+        let count = impacts.len();
+        if count < 2 { return None; }
+        let n = count as f64;
+        let mean = (
+            impacts.iter().map(|p| p.0).sum::<f64>() / n,
+            impacts.iter().map(|p| p.1).sum::<f64>() / n,
+        );
+
+        // Sample covariance, and its eigenvalues for the ellipse's axes
+        let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+        for (x, y) in impacts {
+            let (dx, dy) = (x - mean.0, y - mean.1);
+            sxx += dx * dx;
+            syy += dy * dy;
+            sxy += dx * dy;
+        }
+        let (sxx, syy, sxy) = (sxx / (n - 1.0), syy / (n - 1.0), sxy / (n - 1.0));
+        let centre = 0.5 * (sxx + syy);
+        let spread = (0.25 * (sxx - syy).powi(2) + sxy * sxy).sqrt();
+        let axis = |variance: f64| (variance.max(0.0) * CHI_SQUARED_95).sqrt();
+        let angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+
+        let mut radii: Vec<f64> = impacts.iter().map(|(x, y)| (x - mean.0).hypot(y - mean.1)).collect();
+        radii.sort_by(f64::total_cmp);
+        let cep = match count % 2 {
+            0 => 0.5 * (radii[count / 2 - 1] + radii[count / 2]),
+            _ => radii[count / 2],
+        };
+
+        Some(ImpactDispersion {
+            count,
+            mean,
+            sigma_downrange: sxx.sqrt(),
+            sigma_crossrange: syy.sqrt(),
+            cep,
+            ellipse: (axis(centre + spread), axis(centre - spread), angle.to_degrees()),
+        })
+    }
+
+    // Gets `n` points around the 95% ellipse, closed, m downrange and
+    // crossrange
+    pub fn ellipse_outline(&self, n: usize) -> Vec<(f64, f64)> {
+        let (major, minor, angle) = self.ellipse;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        (0..=n).map(|i| {
+            let (s, c) = (2.0 * PI * i as f64 / n as f64).sin_cos();
+            let (a, b) = (major * c, minor * s);
+            (self.mean.0 + a * cos - b * sin, self.mean.1 + a * sin + b * cos)
+        }).collect()
+    }
+}
+
+impl fmt::Display for ImpactDispersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Dispersion")?;
+        writeln!(f, "  Impacts         {}", self.count)?;
+        writeln!(f, "  Mean impact     {:.1} m downrange, {:.1} m across", self.mean.0, self.mean.1)?;
+        writeln!(f, "  Sigma downrange {:.1} m", self.sigma_downrange)?;
+        writeln!(f, "  Sigma across    {:.1} m", self.sigma_crossrange)?;
+        writeln!(f, "  CEP             {:.1} m", self.cep)?;
+        write!(f, "  95% ellipse     {:.1} by {:.1} m, turned {:.1} deg", self.ellipse.0, self.ellipse.1, self.ellipse.2)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::gravity::EARTH_RADIUS;
+use crate::scalar::Scalar;
+use crate::vec::{Kinematics, Vector};
+
+use serde::{Deserialize, Serialize};
+
+// The shape of the earth under a planar vehicle. Over a flat earth, x is
+// downrange along a straight line and pitch is measured from a fixed
+// horizontal. Over a round one, x is the great-circle distance along the
+// surface, and the velocity and pitch are taken against the local horizon,
+// which tips forward as the vehicle travels. Flat is plenty for short
+// flights, but over 100 km or more it overstates range, as the ground
+// falls away beneath a glider.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Earth {
+    #[default]
+    Flat,
+    // A sphere of `radius`, m
+    Round { radius: f64 },
+}
+
+impl Earth {
+
+    // Constructor, for a sphere the mean radius of the earth
+    pub fn round() -> Earth {
+        Earth::Round { radius: EARTH_RADIUS }
+    }
+
+    // Rate of change of the pose `k` when moving with `dk`. Over a round
+    // earth, the ground track shrinks with height and the horizon turns at
+    // the vehicle's angular rate about the centre.
+    #[inline] pub fn pose_rate<S: Scalar>(&self, k: &Kinematics<S>, dk: &Kinematics<S>) -> Kinematics<S> {
+        match *self {
+            Earth::Flat => *dk,
+            Earth::Round { radius } => {
+                let r = S::from_f64(radius);
+                let omega = dk.x() / (r + k.y());
+                Kinematics::with_rate(Vector::new(omega * r, dk.y()), dk.ang - omega)
+            }
+        }
+    }
+
+    // Apparent acceleration of a body at `k` moving with `dk`, from taking its
+    // velocity against a horizon that turns with it. Added to the applied
+    // acceleration, it keeps a body in orbit level.
+    #[inline] pub fn transport<S: Scalar>(&self, k: &Kinematics<S>, dk: &Kinematics<S>) -> Vector<S> {
+        match *self {
+            Earth::Flat => Vector::zero(),
+            Earth::Round { radius } => {
+                let (u, w) = (dk.x(), dk.y());
+                let r = S::from_f64(radius) + k.y();
+                Vector::new(-u * w / r, u * u / r)
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+// Voltage of a lithium-ion cell under load against its state of charge, as
+// a fraction of its voltage full: a knee near empty, then a long plateau
+const DISCHARGE: [(f64, f64); 6] = [
+    (0.0, 0.75),
+    (0.1, 0.86),
+    (0.2, 0.88),
+    (0.5, 0.90),
+    (0.8, 0.95),
+    (1.0, 1.0),
+];
+
+// A battery of `capacity`, Wh, that can give out at most `max_power`, W,
+// when full. The current it can give is limited, so the power falls with
+// the voltage as it runs down. `charge` is its state of charge, from 0 to 1,
+// and below `reserve` it's taken as depleted and gives nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Battery {
+    pub capacity: f64,
+    pub max_power: f64,
+    #[serde(default = "full")]
+    pub charge: f64,
+    #[serde(default)]
+    pub reserve: f64,
+}
+
+// State of charge of a battery fresh off the charger
+fn full() -> f64 {
+    1.0
+}
+
+impl Battery {
+
+    // Gets the voltage under load, as a fraction of the voltage full
+    pub fn voltage(&self) -> f64 {
+        let charge = self.charge.clamp(0.0, 1.0);
+        let i = DISCHARGE.iter().rposition(|&(soc, _)| soc <= charge).unwrap_or(0).min(DISCHARGE.len() - 2);
+        let ((c0, v0), (c1, v1)) = (DISCHARGE[i], DISCHARGE[i + 1]);
+        v0 + (charge - c0) / (c1 - c0) * (v1 - v0)
+    }
+
+    #[inline] pub fn depleted(&self) -> bool {
+        self.charge <= self.reserve
+    }
+
+    // Gets the most power it can give now, W
+    pub fn available(&self) -> f64 {
+        if self.depleted() { 0.0 } else { self.max_power * self.voltage() }
+    }
+}
+
+// An electric motor of `power`, W at the shaft, driving the thrust, whether
+// a propeller or a fan, from a battery, with `efficiency` from the battery
+// to the shaft. The power it can give falls with the battery's, and with
+// the battery depleted the thrust is gone. Optionally the run ends there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Electric {
+    pub battery: Battery,
+    pub power: f64,
+    pub efficiency: f64,
+    #[serde(default)]
+    pub terminate: bool,
+}
+
+impl Electric {
+
+    // Gets the fraction of the motor's power the battery can give now
+    pub fn power_fraction(&self) -> f64 {
+        (self.battery.available() * self.efficiency / self.power).min(1.0)
+    }
+
+    // Runs the battery down over `dt` seconds with the motor giving
+    // `fraction` of the power it can now
+    pub fn drain(&mut self, fraction: f64, dt: f64) {
+        let drawn = fraction.clamp(0.0, 1.0) * self.power_fraction() * self.power / self.efficiency;
+        self.battery.charge = (self.battery.charge - drawn * dt / (self.battery.capacity * 3_600.0)).max(0.0);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::DyntermError;
+use crate::scalar::Scalar;
+
+use alloc::format;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+// Which of its ratings the engine is run at
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Idle,
+    #[default]
+    Military,
+    Afterburner,
+}
+
+impl core::str::FromStr for Rating {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Rating, ()> {
+        match s {
+            "idle" => Ok(Rating::Idle),
+            "military" => Ok(Rating::Military),
+            "afterburner" => Ok(Rating::Afterburner),
+            _ => Err(()),
+        }
+    }
+}
+
+// One rating: `thrust`, N, at sea level, the fraction of it left against
+// altitude, m, and the fuel flow against altitude, kg/s, both at the full
+// rating. Either table left out is taken as no lapse, or no fuel flow. A
+// fuel flow table over throttle, altitude, and Mach number, if given, is
+// flown in place of the fuel flow against altitude.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatingTable {
+    pub thrust: f64,
+    #[serde(default)]
+    pub lapse: Vec<(f64, f64)>,
+    #[serde(default)]
+    pub fuel_flow: Vec<(f64, f64)>,
+    #[serde(default)]
+    pub fuel_table: Option<FuelTable>,
+}
+
+// Fuel flow, kg/s, over a grid of throttle, the fraction of the rating's
+// thrust, altitude, m, and Mach number, each axis rising, looked up
+// trilinearly and held at the edges. `flow` runs through Mach number
+// fastest, then altitude, then throttle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuelTable {
+    pub throttle: Vec<f64>,
+    pub altitude: Vec<f64>,
+    pub mach: Vec<f64>,
+    pub flow: Vec<f64>,
+}
+
+// Looks `x` up in a table of points sorted by x, held at its ends
+fn lookup<S: Scalar>(table: &[(f64, f64)], x: S, empty: f64) -> S {
+    let (Some(&first), Some(&last)) = (table.first(), table.last()) else { return S::from_f64(empty) };
+    if x <= S::from_f64(first.0) { return S::from_f64(first.1); }
+    if x >= S::from_f64(last.0) { return S::from_f64(last.1); }
+    let j = table.partition_point(|&(key, _)| S::from_f64(key) <= x);
+    let ((x0, y0), (x1, y1)) = (table[j - 1], table[j]);
+    S::from_f64(y0) + (x - S::from_f64(x0)) / S::from_f64(x1 - x0) * S::from_f64(y1 - y0)
+}
+
+// Finds the point on `axis` at or below `x` and how far `x` is on to the
+// next, held at the ends
+fn bracket<S: Scalar>(axis: &[f64], x: S) -> (usize, S) {
+    if axis.len() < 2 || x <= S::from_f64(axis[0]) { return (0, S::ZERO); }
+    let i = axis.partition_point(|&key| S::from_f64(key) <= x).clamp(1, axis.len() - 1) - 1;
+    let t = (x - S::from_f64(axis[i])) / S::from_f64(axis[i + 1] - axis[i]);
+    (i, t.min(S::ONE))
+}
+
+impl FuelTable {
+
+    // Checks there's a flow for every point of the grid and the axes rise
+    pub fn check(&self) -> Result<(), DyntermError> {
+        let size = self.throttle.len() * self.altitude.len() * self.mach.len();
+        if size == 0 || self.flow.len() != size {
+            return Err(DyntermError::Config(format!("{} fuel flows for a grid of {}", self.flow.len(), size)));
+        }
+        let rising = |axis: &[f64]| axis.windows(2).all(|pair| pair[1] > pair[0]);
+        if !(rising(&self.throttle) && rising(&self.altitude) && rising(&self.mach)) {
+            return Err(DyntermError::Config("fuel flow table axes have to rise".into()));
+        }
+        Ok(())
+    }
+
+    // Gets the fuel flow at `throttle`, `altitude`, and `mach`, kg/s
+    pub fn at<S: Scalar>(&self, throttle: S, altitude: S, mach: S) -> S {
+        let (na, nm) = (self.altitude.len(), self.mach.len());
+        let axes = [(&self.throttle, throttle), (&self.altitude, altitude), (&self.mach, mach)];
+        let [(i, ti), (j, tj), (k, tk)] = axes.map(|(axis, x)| bracket(axis, x));
+        let next = |index: usize, axis: &[f64]| (index + 1).min(axis.len() - 1);
+        let (i1, j1, k1) = (next(i, &self.throttle), next(j, &self.altitude), next(k, &self.mach));
+
+        let flow = |i: usize, j: usize, k: usize| S::from_f64(self.flow[(i * na + j) * nm + k]);
+        let lerp = |a: S, b: S, t: S| a + (b - a) * t;
+        let plane = |i: usize| lerp(
+            lerp(flow(i, j, k), flow(i, j, k1), tk),
+            lerp(flow(i, j1, k), flow(i, j1, k1), tk),
+            tj,
+        );
+        lerp(plane(i), plane(i1), ti)
+    }
+}
+
+impl RatingTable {
+
+    // Gets the thrust at the full rating at `altitude`, N
+    pub fn thrust<S: Scalar>(&self, altitude: S) -> S {
+        S::from_f64(self.thrust) * lookup(&self.lapse, altitude, 1.0)
+    }
+
+    // Gets the fuel flow giving `fraction` of the rating's thrust at
+    // `altitude` and `mach`, kg/s
+    pub fn fuel_flow<S: Scalar>(&self, altitude: S, mach: S, fraction: S) -> S {
+        match &self.fuel_table {
+            Some(table) => table.at(fraction, altitude, mach),
+            None => lookup(&self.fuel_flow, altitude, 0.0) * fraction,
+        }
+    }
+}
+
+// A jet engine with several ratings, each with its own thrust lapse and
+// fuel flow, giving the thrust in place of `max_thrust`. It runs at
+// `rating`, switched by the controls, with the throttle a fraction of the
+// rating's thrust. A rating left out falls back to military power, but for
+// idle, which gives nothing. With `fuel`, kg, the fuel it burns comes off
+// the vehicle's mass, and once it's gone the engine flames out, giving no
+// thrust and windmilling with `windmill_drag`, its drag area, m^2.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Engine {
+    #[serde(default)]
+    pub idle: Option<RatingTable>,
+    pub military: RatingTable,
+    #[serde(default)]
+    pub afterburner: Option<RatingTable>,
+    #[serde(default)]
+    pub rating: Rating,
+    #[serde(default)]
+    pub fuel: Option<f64>,
+    #[serde(default)]
+    pub windmill_drag: f64,
+}
+
+impl Engine {
+
+    // Gets the table for the rating it runs at, none at idle without one
+    pub fn table(&self) -> Option<&RatingTable> {
+        match self.rating {
+            Rating::Idle => self.idle.as_ref(),
+            Rating::Military => Some(&self.military),
+            Rating::Afterburner => Some(self.afterburner.as_ref().unwrap_or(&self.military)),
+        }
+    }
+
+    #[inline] pub fn flamed_out(&self) -> bool {
+        self.fuel.is_some_and(|fuel| fuel <= 0.0)
+    }
+
+    // Gets the thrust at the full rating at `altitude`, N
+    pub fn thrust<S: Scalar>(&self, altitude: S) -> S {
+        if self.flamed_out() { return S::ZERO; }
+        self.table().map_or(S::ZERO, |table| table.thrust(altitude))
+    }
+
+    // Gets the fuel flow giving `fraction` of the rating's thrust at
+    // `altitude` and `mach`, kg/s
+    pub fn fuel_flow<S: Scalar>(&self, altitude: S, mach: S, fraction: S) -> S {
+        if self.flamed_out() { return S::ZERO; }
+        self.table().map_or(S::ZERO, |table| table.fuel_flow(altitude, mach, fraction))
+    }
+
+    // Checks the ratings' fuel flow tables
+    pub fn check(&self) -> Result<(), DyntermError> {
+        [self.idle.as_ref(), Some(&self.military), self.afterburner.as_ref()].into_iter()
+            .flatten()
+            .filter_map(|table| table.fuel_table.as_ref())
+            .try_for_each(FuelTable::check)
+    }
+
+    // Burns fuel at `flow`, kg/s, over `dt` seconds, returning how much, kg
+    pub fn burn(&mut self, flow: f64, dt: f64) -> f64 {
+        match &mut self.fuel {
+            Some(fuel) => {
+                let burned = (flow * dt).clamp(0.0, *fuel);
+                *fuel -= burned;
+                burned
+            }
+            None => 0.0,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Display;
+use thiserror::Error;
+
+// Everything that can go wrong in the library, for an application embedding
+// it to handle as it sees fit rather than have the simulator panic
+#[derive(Debug, Error)]
+pub enum DyntermError {
+    // A scenario or vehicle that can't be built as given
+    #[error("{0}")]
+    Config(String),
+    // Data that doesn't read as the table, file, or format it should be
+    #[error("{0}")]
+    Parse(String),
+    // A table looked up outside the range its points cover
+    #[error("{x} is outside the table, which runs from {min} to {max}")]
+    Domain { x: f64, min: f64, max: f64 },
+    // A chart or animation that couldn't be drawn
+    #[error("{0}")]
+    Plot(String),
+    // A file or connection that couldn't be read or written
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl DyntermError {
+
+    // The same error, its message led by `place`, such as the file it came
+    // from
+    pub fn within(self, place: impl Display) -> DyntermError {
+        match self {
+            DyntermError::Config(e) => DyntermError::Config(format!("{}: {}", place, e)),
+            DyntermError::Parse(e) => DyntermError::Parse(format!("{}: {}", place, e)),
+            DyntermError::Plot(e) => DyntermError::Plot(format!("{}: {}", place, e)),
+            DyntermError::Domain { .. } => DyntermError::Config(format!("{}: {}", place, self)),
+            #[cfg(feature = "std")]
+            DyntermError::Io(e) => DyntermError::Io(std::io::Error::new(e.kind(), format!("{}: {}", place, e))),
+        }
+    }
+}
+
+// JSON that couldn't be read, as a parse error unless reading failed
+#[cfg(feature = "std")]
+impl From<serde_json::Error> for DyntermError {
+    fn from(e: serde_json::Error) -> DyntermError {
+        match e.is_io() {
+            true => DyntermError::Io(e.into()),
+            false => DyntermError::Parse(e.to_string()),
+        }
+    }
+}
+
+// CSV that couldn't be read, as a parse error unless reading failed
+#[cfg(feature = "std")]
+impl From<csv::Error> for DyntermError {
+    fn from(e: csv::Error) -> DyntermError {
+        match e.is_io_error() {
+            true => DyntermError::Io(e.into()),
+            false => DyntermError::Parse(e.to_string()),
+        }
+    }
+}
+
+// Anything plotters fails to draw
+#[cfg(feature = "plot")]
+impl<E: std::error::Error + Send + Sync> From<plotters::drawing::DrawingAreaErrorKind<E>> for DyntermError {
+    fn from(e: plotters::drawing::DrawingAreaErrorKind<E>) -> DyntermError {
+        DyntermError::Plot(e.to_string())
+    }
+}
+
+// For callers that only deal in I/O errors, such as the file loaders
+// nested in others
+#[cfg(feature = "std")]
+impl From<DyntermError> for std::io::Error {
+    fn from(e: DyntermError) -> std::io::Error {
+        match e {
+            DyntermError::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
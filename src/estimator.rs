@@ -0,0 +1,282 @@
+use crate::atmosphere::Atmosphere;
+use crate::gravity::Gravity;
+use crate::sensors::{ErrorModel, GpsFix, ImuReading, Readings, SensorSpec};
+use crate::sim::State;
+use crate::vec::{Angle, Vector};
+
+use serde::{Deserialize, Serialize};
+
+// Tuning for the `Estimator`. The gains are per second, and the lag is a
+// time constant in seconds.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EstimatorSpec {
+    // How hard the pitch is pulled towards the accelerometers' reading of it
+    pub attitude_gain: f64,
+    // How fast the gyro bias is learnt
+    pub bias_gain: f64,
+    // Standard deviation of the acceleration the tracks allow for beyond
+    // what the accelerometers read, m/s^2
+    pub acceleration_noise: f64,
+    pub airspeed_lag: f64,
+    // Whether the controller flies on the estimate instead of the truth
+    pub feedback: bool,
+}
+
+impl Default for EstimatorSpec {
+    fn default() -> EstimatorSpec {
+        EstimatorSpec {
+            attitude_gain: 0.2,
+            bias_gain: 0.01,
+            acceleration_noise: 0.5,
+            airspeed_lag: 0.2,
+            feedback: false,
+        }
+    }
+}
+
+// What the `Estimator` makes of the vehicle's state. Angles are in radians
+// and the airspeed is true airspeed.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Estimate {
+    pub time: f64,
+    pub x: f64,
+    pub altitude: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub pitch: f64,
+    pub pitch_rate: f64,
+    pub airspeed: f64,
+}
+
+impl Estimate {
+
+    // Returns the estimate as the state a controller sees, taking the angle
+    // of attack against the velocity over the ground
+    pub fn state(&self) -> State {
+        let pitch = Angle::from_radians(self.pitch);
+        let path = Vector::new(self.vx, self.vy).orientation();
+        State {
+            time: self.time,
+            x: self.x,
+            y: self.altitude,
+            vx: self.vx,
+            vy: self.vy,
+            pitch: pitch.nice_deg(),
+            pitch_rate: self.pitch_rate.to_degrees(),
+            aoa: pitch.signed_diff(path).to_degrees(),
+        }
+    }
+}
+
+// A position and velocity along one axis, tracked by a Kalman filter driven
+// by the measured acceleration
+#[derive(Debug, Copy, Clone)]
+struct Track {
+    position: f64,
+    velocity: f64,
+    // Acceleration over the last prediction
+    acceleration: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl Track {
+
+    // Constructor, with the initial uncertainties as standard deviations
+    fn new(position: f64, velocity: f64, (position_sd, velocity_sd): (f64, f64)) -> Track {
+        let covariance = [[position_sd.powi(2), 0.0], [0.0, velocity_sd.powi(2)]];
+        Track { position, velocity, acceleration: 0.0, covariance }
+    }
+
+    // Moves on by `dt` under `acceleration`, give or take `noise`
+    fn predict(&mut self, acceleration: f64, noise: f64, dt: f64) {
+        self.position += self.velocity * dt + 0.5 * acceleration * dt * dt;
+        self.velocity += acceleration * dt;
+        self.acceleration = acceleration;
+
+        let [[pp, pv], [_, vv]] = self.covariance;
+        let q = noise * noise;
+        let pp = pp + 2.0 * dt * pv + dt * dt * vv + q * dt.powi(4) / 4.0;
+        let pv = pv + dt * vv + q * dt.powi(3) / 2.0;
+        let vv = vv + q * dt * dt;
+        self.covariance = [[pp, pv], [pv, vv]];
+    }
+
+    // Corrects by a measurement of the position (axis 0) or the velocity
+    // (axis 1) with the given variance, taken `age` seconds ago
+    fn correct(&mut self, axis: usize, measured: f64, variance: f64, age: f64) {
+        let p = self.covariance;
+        let then = [
+            self.position - self.velocity * age + 0.5 * self.acceleration * age * age,
+            self.velocity - self.acceleration * age,
+        ];
+        let innovation = measured - then[axis];
+        let s = p[axis][axis] + variance;
+        let gain = [p[0][axis] / s, p[1][axis] / s];
+
+        self.position += gain[0] * innovation;
+        self.velocity += gain[1] * innovation;
+        let row = p[axis];
+        for (covariance, k) in self.covariance.iter_mut().zip(gain) {
+            for (c, r) in covariance.iter_mut().zip(row) {
+                *c -= k * r;
+            }
+        }
+    }
+}
+
+// Variance of a reading with the given errors, counting the bias as noise
+fn variance(errors: &ErrorModel) -> f64 {
+    (errors.noise.powi(2) + errors.bias.powi(2)).max(1e-9)
+}
+
+// Longitudinal state estimator working from the simulated sensors. Pitch
+// comes from the gyro, pulled towards the pitch at which the accelerometers'
+// reading would match gravity and the acceleration GPS shows, with the gyro
+// bias learnt from the difference as in a complementary filter. The pull
+// weakens as the force felt falls away, since in a coast or a zero-g dive
+// the accelerometers can't tell which way is up. Downrange and vertical
+// position and velocity are each a Kalman filter driven by the
+// accelerometers and corrected by GPS, and the barometer too vertically,
+// with each reading set against where the track was when it was taken.
+// True airspeed is the pitot's equivalent airspeed at the estimated
+// altitude, lagged to settle the noise. Gravity and the air are the
+// vehicle's own. Nothing is estimated until the first GPS fix arrives.
+#[derive(Debug, Clone)]
+pub struct Estimator {
+    pub spec: EstimatorSpec,
+    sensors: SensorSpec,
+    gravity: Gravity,
+    atmosphere: Atmosphere,
+    pitch: Angle,
+    gyro_bias: f64,
+    pitch_rate: f64,
+    downrange: Option<Track>,
+    vertical: Option<Track>,
+    airspeed: Option<f64>,
+    // Acceleration from the last two GPS fixes, and the last fix
+    gps_acceleration: Vector,
+    last_fix: Option<GpsFix>,
+    last_imu: Option<f64>,
+}
+
+impl Estimator {
+
+    // Constructor, for a vehicle carrying the given sensors and flying in
+    // `gravity` and `atmosphere`
+    pub fn new(spec: EstimatorSpec, sensors: SensorSpec, gravity: Gravity, atmosphere: Atmosphere) -> Estimator {
+        Estimator {
+            spec,
+            sensors,
+            gravity,
+            atmosphere,
+            pitch: Angle::from_radians(0.0),
+            gyro_bias: 0.0,
+            pitch_rate: 0.0,
+            downrange: None,
+            vertical: None,
+            airspeed: None,
+            gps_acceleration: Vector::zero(),
+            last_fix: None,
+            last_imu: None,
+        }
+    }
+
+    // Takes in the readings that arrived during a step
+    pub fn update(&mut self, readings: &Readings) {
+        if let Some(imu) = readings.imu {
+            self.propagate(imu);
+        }
+        if let Some(fix) = readings.gps {
+            self.fix(fix);
+        }
+        if let (Some(altitude), Some(vertical)) = (readings.altitude, &mut self.vertical) {
+            let age = self.last_imu.map_or(0.0, |now| (now - altitude.time).max(0.0));
+            vertical.correct(0, altitude.value, variance(&self.sensors.baro.altitude), age);
+        }
+        if let (Some(airspeed), Some(vertical)) = (readings.airspeed, &self.vertical) {
+            let tas = airspeed.value / self.atmosphere.density(vertical.position).sqrt();
+            self.airspeed = Some(match self.airspeed {
+                None => tas,
+                Some(last) => {
+                    let dt = 1.0 / self.sensors.pitot.rate.max(1.0);
+                    last + (tas - last) * (dt / (self.spec.airspeed_lag + dt))
+                }
+            });
+        }
+    }
+
+    // Returns the estimate at `time`, once there's been a GPS fix
+    pub fn estimate(&self, time: f64) -> Option<Estimate> {
+        let (downrange, vertical) = (self.downrange?, self.vertical?);
+        Some(Estimate {
+            time,
+            x: downrange.position,
+            altitude: vertical.position,
+            vx: downrange.velocity,
+            vy: vertical.velocity,
+            pitch: self.pitch.signed_rad(),
+            pitch_rate: self.pitch_rate,
+            airspeed: self.airspeed.unwrap_or(Vector::new(downrange.velocity, vertical.velocity).magnitude()),
+        })
+    }
+
+    // Helper function
+    fn fix(&mut self, fix: GpsFix) {
+        let (position, velocity) = (&self.sensors.gps.position, &self.sensors.gps.velocity);
+        let spread = (variance(position).sqrt(), variance(velocity).sqrt());
+        let age = self.last_imu.map_or(0.0, |now| (now - fix.time).max(0.0));
+
+        match (&mut self.downrange, &mut self.vertical) {
+            (Some(downrange), Some(vertical)) => {
+                downrange.correct(0, fix.x, variance(position), age);
+                downrange.correct(1, fix.vx, variance(velocity), age);
+                vertical.correct(0, fix.altitude, variance(position), age);
+                vertical.correct(1, fix.vy, variance(velocity), age);
+            }
+            // Start out flying along the path
+            _ => {
+                self.downrange = Some(Track::new(fix.x, fix.vx, spread));
+                self.vertical = Some(Track::new(fix.altitude, fix.vy, spread));
+                self.pitch = Vector::new(fix.vx, fix.vy).orientation();
+            }
+        }
+
+        if let Some(last) = self.last_fix {
+            let dt = fix.time - last.time;
+            if dt > 0.0 {
+                self.gps_acceleration = Vector::new(fix.vx - last.vx, fix.vy - last.vy) / dt;
+            }
+        }
+        self.last_fix = Some(fix);
+    }
+
+    // Helper function
+    fn propagate(&mut self, imu: ImuReading) {
+        let dt = self.last_imu.map_or(0.0, |last| imu.time - last);
+        self.last_imu = Some(imu.time);
+        let Some(vertical) = self.vertical else { return };
+        if dt <= 0.0 { return; }
+        let g = self.gravity.at(vertical.position);
+
+        // The pitch at which what the accelerometers feel is gravity and the
+        // acceleration GPS shows
+        let force = Vector::new(imu.forward, imu.up);
+        let felt = self.gps_acceleration + Vector::new(0.0, g);
+        let error = (felt.orientation() - force.orientation()).signed_diff(self.pitch);
+
+        // Trusted only as far as there's force to go on, and the two agree
+        let agreement = 1.0 - (felt.magnitude() - force.magnitude()).abs() / g;
+        let error = error * (force.magnitude() / g).min(1.0) * agreement.max(0.0);
+
+        self.gyro_bias -= self.spec.bias_gain * error * dt;
+        self.pitch_rate = imu.pitch_rate - self.gyro_bias;
+        self.pitch = Angle::from_radians(self.pitch.rad() + (self.pitch_rate + self.spec.attitude_gain * error) * dt);
+
+        // Acceleration in the world, from the pitch just found
+        let accel = force.to_world(self.pitch) - Vector::new(0.0, g);
+        let noise = self.spec.acceleration_noise;
+        if let Some(downrange) = &mut self.downrange { downrange.predict(accel.x(), noise, dt); }
+        if let Some(vertical) = &mut self.vertical { vertical.predict(accel.y(), noise, dt); }
+    }
+}
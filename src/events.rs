@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// What happened
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    // Locked in at a high angle of attack, barely pitching
+    DeepStall,
+    // Pitching round and round, one way
+    Tumble,
+    // Back out of a deep stall or tumble
+    Recovered,
+    // Close enough to the stall to warn the pilot
+    StallWarning,
+    // Close enough that the wing's wake starts to shake the tail
+    BuffetOnset,
+    // Past the wing's stall angle
+    Stall,
+    // Back clear of the stall warning
+    StallCleared,
+    // Pulling more than a limit load, g
+    LimitLoad { load: f64 },
+    // Back within the limit loads, having pulled `peak` beyond them, g
+    WithinLimits { peak: f64 },
+    // Broken up under more than the structure's ultimate load, g
+    Breakup { load: f64 },
+    // Flying faster than the placard
+    Overspeed,
+    // Back under the placard, having reached `peak` times it
+    UnderPlacard { peak: f64 },
+    // Battery run down to its reserve, with the thrust gone
+    BatteryDepleted,
+    // Out of fuel, with the engine windmilling
+    FlameOut,
+    // Retro-rocket lit, and cut again
+    RetroIgnition,
+    RetroCutoff,
+}
+
+// Something noteworthy during a run, and when it happened, s
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub time: f64,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventKind::DeepStall => f.write_str("deep stall"),
+            EventKind::Tumble => f.write_str("tumble"),
+            EventKind::Recovered => f.write_str("recovered"),
+            EventKind::StallWarning => f.write_str("stall warning"),
+            EventKind::BuffetOnset => f.write_str("buffet onset"),
+            EventKind::Stall => f.write_str("stall"),
+            EventKind::StallCleared => f.write_str("stall warning cleared"),
+            EventKind::LimitLoad { load } => write!(f, "limit load exceeded at {:.2} g", load),
+            EventKind::WithinLimits { peak } => write!(f, "back within limit loads, peak {:.2} g", peak),
+            EventKind::Breakup { load } => write!(f, "structural breakup at {:.2} g", load),
+            EventKind::Overspeed => f.write_str("overspeed"),
+            EventKind::UnderPlacard { peak } => write!(f, "back under the placard, peak {:.1}% of it", peak * 100.0),
+            EventKind::BatteryDepleted => f.write_str("battery depleted"),
+            EventKind::FlameOut => f.write_str("flame-out"),
+            EventKind::RetroIgnition => f.write_str("retro ignition"),
+            EventKind::RetroCutoff => f.write_str("retro cutoff"),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} s: {}", self.time, self.kind)
+    }
+}
+
+// Criteria for a departure from controlled flight, each of which has to
+// hold for `window` seconds. Angles are in degrees and rates in degrees per
+// second.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepartureSpec {
+    // A deep stall is at least this angle of attack either way...
+    pub stall_aoa: f64,
+    // ...pitching no faster than this
+    pub stall_rate: f64,
+    // A tumble is pitching at least this fast, the same way throughout
+    pub tumble_rate: f64,
+    pub window: f64,
+    // Whether to end the run on a departure
+    pub terminate: bool,
+}
+
+impl Default for DepartureSpec {
+    fn default() -> DepartureSpec {
+        DepartureSpec { stall_aoa: 30.0, stall_rate: 10.0, tumble_rate: 90.0, window: 2.0, terminate: false }
+    }
+}
+
+// Watches for sustained autorotation or deep stall, which otherwise only
+// show up as a mess in the plots. Each departure is reported once when its
+// criteria have held for the window, and again when it ends.
+#[derive(Debug, Clone)]
+pub struct Departure {
+    pub spec: DepartureSpec,
+    // When each condition started holding, and for a tumble, which way
+    stalled_since: Option<f64>,
+    tumbling_since: Option<(f64, f64)>,
+    departed: Option<EventKind>,
+}
+
+impl Departure {
+
+    // Constructor, in controlled flight
+    pub fn new(spec: DepartureSpec) -> Departure {
+        Departure { spec, stalled_since: None, tumbling_since: None, departed: None }
+    }
+
+    // Whether the vehicle has departed and not yet recovered
+    #[inline] pub fn departed(&self) -> Option<EventKind> {
+        self.departed
+    }
+
+    // Checks the angle of attack and pitch rate at `time`, and returns an
+    // event if a departure starts or ends
+    pub fn check(&mut self, time: f64, aoa: f64, pitch_rate: f64) -> Option<Event> {
+        let spec = &self.spec;
+        let stalled = aoa.abs() >= spec.stall_aoa && pitch_rate.abs() <= spec.stall_rate;
+        self.stalled_since = if stalled { self.stalled_since.or(Some(time)) } else { None };
+
+        let direction = pitch_rate.signum();
+        let tumbling = pitch_rate.abs() >= spec.tumble_rate;
+        self.tumbling_since = match self.tumbling_since {
+            Some((since, way)) if tumbling && way == direction => Some((since, way)),
+            _ if tumbling => Some((time, direction)),
+            _ => None,
+        };
+
+        let held = |since: Option<f64>| since.is_some_and(|since| time - since >= spec.window);
+        let now = if held(self.tumbling_since.map(|(since, _)| since)) {
+            Some(EventKind::Tumble)
+        } else if held(self.stalled_since) {
+            Some(EventKind::DeepStall)
+        } else {
+            None
+        };
+
+        // A departure lasts until neither condition holds at all
+        let kind = match (self.departed, now) {
+            (Some(was), Some(now)) if was == now => return None,
+            (_, Some(now)) => now,
+            (Some(_), None) if self.stalled_since.is_none() && self.tumbling_since.is_none() => EventKind::Recovered,
+            _ => return None,
+        };
+        self.departed = (kind != EventKind::Recovered).then_some(kind);
+        Some(Event { time, kind })
+    }
+}
+
+// Thresholds on the margin to stall, in degrees of angle of attack to the
+// wing's stall angle. Each warning clears once the margin opens up past its
+// threshold by the hysteresis, so a wing hovering on the edge doesn't chatter.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StallWarningSpec {
+    pub warning: f64,
+    pub buffet: f64,
+    pub hysteresis: f64,
+}
+
+impl Default for StallWarningSpec {
+    fn default() -> StallWarningSpec {
+        StallWarningSpec { warning: 5.0, buffet: 2.0, hysteresis: 0.5 }
+    }
+}
+
+// A stall warning system, in the manner of a stick shaker, raising an event
+// each time the margin to stall crosses into a closer or wider band: clear,
+// warning, buffet, and stalled.
+#[derive(Debug, Clone)]
+pub struct StallWarning {
+    pub spec: StallWarningSpec,
+    band: usize,
+}
+
+impl StallWarning {
+
+    // Constructor, clear of the stall
+    pub fn new(spec: StallWarningSpec) -> StallWarning {
+        StallWarning { spec, band: 0 }
+    }
+
+    // Checks the margin to stall at `time`, degrees, and returns an event if
+    // it's moved into another band
+    pub fn check(&mut self, time: f64, margin: f64) -> Option<Event> {
+        let spec = &self.spec;
+        let thresholds = [spec.warning, spec.buffet, 0.0];
+
+        // Closer bands are entered at their threshold, and left only past it
+        // by the hysteresis
+        let band = thresholds.iter().enumerate()
+            .filter(|&(i, &threshold)| {
+                let threshold = if i < self.band { threshold + spec.hysteresis } else { threshold };
+                margin <= threshold
+            })
+            .map(|(i, _)| i + 1)
+            .max()
+            .unwrap_or(0);
+        if band == self.band { return None; }
+
+        self.band = band;
+        let kind = [EventKind::StallCleared, EventKind::StallWarning, EventKind::BuffetOnset, EventKind::Stall][band];
+        Some(Event { time, kind })
+    }
+}
+
+// Structural limits on the normal load factor, g, up and down. The structure
+// only fails beyond its ultimate loads, the limit loads times the safety
+// factor, and then only if `failure` is set, which ends the run.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadLimitSpec {
+    pub positive: f64,
+    pub negative: f64,
+    pub safety_factor: f64,
+    pub failure: bool,
+}
+
+impl Default for LoadLimitSpec {
+    fn default() -> LoadLimitSpec {
+        LoadLimitSpec { positive: 6.0, negative: -3.0, safety_factor: 1.5, failure: false }
+    }
+}
+
+// Watches the normal load factor against the limit loads, reporting each
+// exceedance as it starts and, with the peak load pulled, as it ends
+#[derive(Debug, Clone)]
+pub struct LoadMonitor {
+    pub spec: LoadLimitSpec,
+    // The furthest beyond a limit so far in this exceedance, g
+    peak: Option<f64>,
+    broken: bool,
+}
+
+impl LoadMonitor {
+
+    // Constructor, within limits
+    pub fn new(spec: LoadLimitSpec) -> LoadMonitor {
+        LoadMonitor { spec, peak: None, broken: false }
+    }
+
+    // Whether the structure has failed
+    #[inline] pub fn broken(&self) -> bool {
+        self.broken
+    }
+
+    // Checks the normal load factor at `time`, g, and returns an event if
+    // an exceedance starts or ends, or the structure fails
+    pub fn check(&mut self, time: f64, load: f64) -> Option<Event> {
+        let spec = &self.spec;
+        if self.broken { return None; }
+
+        if spec.failure && (load > spec.positive * spec.safety_factor || load < spec.negative * spec.safety_factor) {
+            self.broken = true;
+            return Some(Event { time, kind: EventKind::Breakup { load } });
+        }
+
+        let exceeded = load > spec.positive || load < spec.negative;
+        match (self.peak, exceeded) {
+            (None, true) => {
+                self.peak = Some(load);
+                Some(Event { time, kind: EventKind::LimitLoad { load } })
+            }
+            (Some(peak), true) => {
+                if load.abs() > peak.abs() { self.peak = Some(load); }
+                None
+            }
+            (Some(peak), false) => {
+                self.peak = None;
+                Some(Event { time, kind: EventKind::WithinLimits { peak } })
+            }
+            (None, false) => None,
+        }
+    }
+}
+
+// A never-exceed placard, as a dynamic pressure, Pa, a true airspeed, m/s,
+// or both. The airspeed limit stands in for flutter, which goes with true
+// rather than equivalent airspeed and so closes in with altitude.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlacardSpec {
+    pub dynamic_pressure: Option<f64>,
+    pub airspeed: Option<f64>,
+    // Whether to end the run on an overspeed
+    pub terminate: bool,
+}
+
+impl Default for PlacardSpec {
+    fn default() -> PlacardSpec {
+        PlacardSpec { dynamic_pressure: Some(60_000.0), airspeed: None, terminate: false }
+    }
+}
+
+// Watches the speed against the placard, reporting each overspeed as it
+// starts and, with how far over it went, as it ends. It keeps the least
+// margin to the placard over the run, as a fraction of it, for dive-recovery
+// studies to report.
+#[derive(Debug, Clone)]
+pub struct Placard {
+    pub spec: PlacardSpec,
+    // The highest fraction of the placard so far in this overspeed
+    peak: Option<f64>,
+    least_margin: Option<(f64, f64)>,
+}
+
+impl Placard {
+
+    // Constructor, under the placard
+    pub fn new(spec: PlacardSpec) -> Placard {
+        Placard { spec, peak: None, least_margin: None }
+    }
+
+    // Fraction of the placard flown at a dynamic pressure `q`, Pa, and true
+    // airspeed `tas`, m/s, by whichever limit is closer
+    pub fn fraction(&self, q: f64, tas: f64) -> f64 {
+        let q = self.spec.dynamic_pressure.map_or(0.0, |limit| q / limit);
+        let tas = self.spec.airspeed.map_or(0.0, |limit| tas / limit);
+        q.max(tas)
+    }
+
+    // The least margin to the placard so far, as a fraction of it, and
+    // when, negative if it's been exceeded
+    #[inline] pub fn least_margin(&self) -> Option<(f64, f64)> {
+        self.least_margin
+    }
+
+    // Checks the speed at `time`, and returns an event if an overspeed
+    // starts or ends
+    pub fn check(&mut self, time: f64, q: f64, tas: f64) -> Option<Event> {
+        let fraction = self.fraction(q, tas);
+        if self.least_margin.is_none_or(|(margin, _)| 1.0 - fraction < margin) {
+            self.least_margin = Some((1.0 - fraction, time));
+        }
+
+        match (self.peak, fraction > 1.0) {
+            (None, true) => {
+                self.peak = Some(fraction);
+                Some(Event { time, kind: EventKind::Overspeed })
+            }
+            (Some(peak), true) => {
+                self.peak = Some(peak.max(fraction));
+                None
+            }
+            (Some(peak), false) => {
+                self.peak = None;
+                Some(Event { time, kind: EventKind::UnderPlacard { peak } })
+            }
+            (None, false) => None,
+        }
+    }
+}
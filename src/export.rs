@@ -0,0 +1,171 @@
+use crate::recorder::{Recorder, Sample};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// MAT-file data types and array class
+const MI_INT8: u32 = 1;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_DOUBLE: u32 = 9;
+const MI_MATRIX: u32 = 14;
+const MX_DOUBLE_CLASS: u32 = 6;
+
+// Writes every channel of `recorder` to `path` for loading straight into
+// MATLAB or NumPy: as a NumPy .npz archive if the path ends in .npz, and a
+// MATLAB v5 .mat file otherwise. Each channel is an n by 3 array of doubles
+// named after it, a row per sample, its columns the x, y, and z of the
+// samples as plotted. Names are made into identifiers MATLAB accepts, the
+// same in both formats.
+pub fn write_channels(path: impl AsRef<Path>, recorder: &Recorder) -> io::Result<()> {
+    let path = path.as_ref();
+    let channels: Vec<(String, &[Sample])> = recorder.channels()
+        .map(|(name, samples)| (identifier(name), samples))
+        .collect();
+    let npz = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npz"));
+    fs::write(path, if npz { to_npz(&channels)? } else { to_mat(&channels) })
+}
+
+// A MATLAB v5 MAT-file holding each channel as a double matrix
+pub fn to_mat(channels: &[(String, &[Sample])]) -> Vec<u8> {
+    // The header: 116 bytes of text, 8 of subsystem offset, the version,
+    // and the endian indicator, "MI" read the right way round
+    let mut text = format!("MATLAB 5.0 MAT-file, Platform: {}, Created by: dynterm", std::env::consts::OS).into_bytes();
+    text.resize(116, b' ');
+    let mut mat = text;
+    mat.extend_from_slice(&[0; 8]);
+    mat.extend_from_slice(&0x0100u16.to_le_bytes());
+    mat.extend_from_slice(b"IM");
+
+    for (name, samples) in channels {
+        let mut matrix = Vec::new();
+        element(&mut matrix, MI_UINT32, &[MX_DOUBLE_CLASS.to_le_bytes(), [0; 4]].concat());
+        let dimensions = [samples.len() as i32, 3];
+        element(&mut matrix, MI_INT32, &dimensions.iter().flat_map(|d| d.to_le_bytes()).collect::<Vec<_>>());
+        element(&mut matrix, MI_INT8, name.as_bytes());
+        // Column-major, all the x, then the y, then the z
+        let real: Vec<u8> = samples.iter().map(|s| s.0)
+            .chain(samples.iter().map(|s| s.1))
+            .chain(samples.iter().map(|s| s.2))
+            .flat_map(f64::to_le_bytes)
+            .collect();
+        element(&mut matrix, MI_DOUBLE, &real);
+        element(&mut mat, MI_MATRIX, &matrix);
+    }
+    mat
+}
+
+// A NumPy .npz archive holding each channel as a float64 array, stored
+// rather than compressed
+pub fn to_npz(channels: &[(String, &[Sample])]) -> io::Result<Vec<u8>> {
+    let files: Vec<(String, Vec<u8>)> = channels.iter()
+        .map(|(name, samples)| (format!("{}.npy", name), npy(samples)))
+        .collect();
+    zip(&files)
+}
+
+// Appends a MAT-file data element: its type and length, then its data
+// padded out to 8 bytes
+fn element(out: &mut Vec<u8>, kind: u32, data: &[u8]) {
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    out.resize(out.len() + (8 - data.len() % 8) % 8, 0);
+}
+
+// An .npy file of an n by 3 float64 array, version 1.0, its header padded
+// so the data starts on a 64-byte boundary
+fn npy(samples: &[Sample]) -> Vec<u8> {
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 3), }}", samples.len());
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    for &(x, y, z) in samples {
+        for value in [x, y, z] {
+            npy.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    npy
+}
+
+// A zip archive of `files`, stored without compression, dated 1980-01-01
+fn zip(files: &[(String, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "recording too big for a zip archive");
+    let u32_len = |n: usize| u32::try_from(n).map_err(|_| too_big());
+
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = u32_len(archive.len())?;
+        // Fields common to the local header and the directory entry: the
+        // version needed, flags, method, time, date, CRC, sizes, and name
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc32(data).to_le_bytes());
+        common.extend_from_slice(&u32_len(data.len())?.to_le_bytes());
+        common.extend_from_slice(&u32_len(data.len())?.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        archive.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // No comment, on the first disk, no attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let count = u16::try_from(files.len()).map_err(|_| too_big())?;
+    let offset = u32_len(archive.len())?;
+    let size = u32_len(directory.len())?;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&size.to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    Ok(archive)
+}
+
+// The CRC-32 a zip archive checks each file by
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// A channel's name as a MATLAB identifier: letters, digits, and
+// underscores, starting with a letter, at most 63 characters
+fn identifier(name: &str) -> String {
+    let mut id: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id.insert_str(0, "ch_");
+    }
+    id.truncate(63);
+    id
+}
@@ -0,0 +1,126 @@
+// C interface for embedding the simulator. See `include/dynterm.h`, which
+// is generated from this file with `cbindgen --config cbindgen.toml`. The
+// shared library to link against is built with
+//
+//   cargo rustc --lib --release --crate-type cdylib
+
+use crate::config::VehicleConfig;
+use crate::rng::SimRng;
+use crate::sim::Simulation;
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Number of values written by `dynterm_state`: time, x, y, vx, vy, pitch,
+/// pitch rate, and angle of attack, in that order.
+pub const DYNTERM_STATE_LEN: usize = 8;
+
+thread_local! {
+    // What went wrong in the last call on this thread that failed
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Keeps `message` for `dynterm_last_error`
+fn set_last_error(message: String) {
+    // A message can't hold a NUL, so one is cut short at the first
+    let message = CString::new(message).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Runs `f`, keeping what went wrong if it panics rather than unwinding into
+// the host, which would abort it
+fn guarded<T>(f: impl FnOnce() -> T) -> Option<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let reason = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_owned());
+        set_last_error(format!("panicked: {}", reason));
+    }).ok()
+}
+
+/// Creates a simulation from a JSON vehicle config, or with the default
+/// vehicle if `config_json` is null. Fields left out of the config take
+/// their defaults. Returns null if the config can't be parsed or the
+/// vehicle can't be built, with the reason left for `dynterm_last_error`.
+///
+/// # Safety
+/// `config_json` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dynterm_new(config_json: *const c_char) -> *mut Simulation {
+    let config = if config_json.is_null() {
+        VehicleConfig::default()
+    } else {
+        let parsed = CStr::from_ptr(config_json)
+            .to_str()
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str(s).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(config) => config,
+            Err(e) => {
+                set_last_error(format!("invalid config: {}", e));
+                return ptr::null_mut();
+            }
+        }
+    };
+    guarded(|| Box::into_raw(Box::new(Simulation::new(config.build(), SimRng::new(0)))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Advances the simulation by `dt` seconds. Does nothing if the step
+/// fails, leaving the reason for `dynterm_last_error`.
+///
+/// # Safety
+/// `sim` must have come from `dynterm_new` and not yet been freed.
+#[no_mangle]
+pub unsafe extern "C" fn dynterm_step(sim: *mut Simulation, dt: f64) {
+    if let Some(sim) = sim.as_mut() {
+        guarded(|| sim.step(dt));
+    }
+}
+
+/// Returns why the last call on this thread that failed did, or null if
+/// none has. The string belongs to the library and stays valid until the
+/// next call that fails on the same thread.
+#[no_mangle]
+pub extern "C" fn dynterm_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Writes up to `len` state values (see `DYNTERM_STATE_LEN`) to `out` and
+/// returns how many were written.
+///
+/// # Safety
+/// `sim` must have come from `dynterm_new` and not yet been freed, and `out`
+/// must point to at least `len` writable doubles.
+#[no_mangle]
+pub unsafe extern "C" fn dynterm_state(
+    sim: *const Simulation,
+    out: *mut f64,
+    len: usize) -> usize {
+
+    let (Some(sim), false) = (sim.as_ref(), out.is_null()) else { return 0; };
+    let s = sim.state();
+    let values: [f64; DYNTERM_STATE_LEN] =
+        [s.time, s.x, s.y, s.vx, s.vy, s.pitch, s.pitch_rate, s.aoa];
+
+    let n = len.min(DYNTERM_STATE_LEN);
+    ptr::copy_nonoverlapping(values.as_ptr(), out, n);
+    n
+}
+
+/// Destroys a simulation. Passing null is a no-op.
+///
+/// # Safety
+/// `sim` must be null or have come from `dynterm_new`, and must not be used
+/// again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dynterm_free(sim: *mut Simulation) {
+    if !sim.is_null() {
+        drop(Box::from_raw(sim));
+    }
+}
@@ -0,0 +1,416 @@
+use crate::aero::{Aerofoil, GroundEffect, Vehicle, WaveDrag, PULL_UP_ALTITUDE};
+use crate::control::Command;
+use crate::interpolate::Linear;
+use crate::sim::{State, MAX_SUBSTEP};
+use crate::updraft::Updrafts;
+use crate::atmosphere::Atmosphere;
+use crate::earth::Earth;
+use crate::gravity::Gravity;
+use crate::vec::Angle;
+use crate::wind::WindGrid;
+
+use std::sync::Arc;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Vehicles per block. Each quantity for a block sits in one fixed-width
+// array, so the per-lane loops stay in cache and give the compiler
+// something to vectorise. The coefficient table lookups stay scalar.
+pub const LANES: usize = 8;
+
+type Lanes = [f64; LANES];
+
+// Buckets in the index over a `Section`'s breakpoints
+const BUCKETS: usize = 1024;
+
+// A surface's lift, drag, and moment tables merged onto one shared set of
+// breakpoints, which leaves the interpolated values unchanged, with a uniform
+// index over them. A lookup is then a bucket and a short scan, where
+// `Aerofoil::coefficients` does three binary searches.
+#[derive(Clone)]
+struct Section {
+    area: f64,
+    chord: f64,
+    pitch: f64,
+    x: Vec<f64>,
+    c: Vec<[f64; 3]>,
+    // First segment reaching into each bucket
+    start: Vec<usize>,
+    x0: f64,
+    per_bucket: f64,
+    wave_drag: WaveDrag,
+    ground_effect: Option<GroundEffect>,
+    // Least drag coefficient in the tables
+    floor: f64,
+}
+
+impl Section {
+
+    // Constructor
+    fn new(foil: &Aerofoil, pitch: f64) -> Section {
+        let (cl, cd, cm) = foil.tables();
+
+        // Every breakpoint inside the range all three tables cover
+        let (lo, hi) = [cl, cd, cm].iter().fold((f64::NEG_INFINITY, f64::INFINITY), |(lo, hi), t| {
+            let p = t.points();
+            (lo.max(p[0].0), hi.min(p[p.len() - 1].0))
+        });
+        let mut x: Vec<f64> = [cl, cd, cm].iter()
+            .flat_map(|t| t.points().iter().map(|p| p.0))
+            .filter(|&x| x >= lo && x <= hi)
+            .collect();
+        x.sort_by(f64::total_cmp);
+        x.dedup();
+        // A table's own breakpoints are taken as they are, which also keeps
+        // clear of interpolating at its very last point
+        let value = |t: &Linear, x: f64| match t.points().iter().find(|p| p.0 == x) {
+            Some(p) => p.1,
+            None => t.interpolate(x),
+        };
+        let c = x.iter().map(|&x| [value(cl, x), value(cd, x), value(cm, x)]).collect();
+        let floor = cd.points().iter().fold(f64::INFINITY, |floor, p| floor.min(p.1));
+
+        let per_bucket = BUCKETS as f64 / (hi - lo);
+        let mut start = Vec::with_capacity(BUCKETS);
+        let mut i = 0;
+        for bucket in 0..BUCKETS {
+            let edge = lo + bucket as f64 / per_bucket;
+            while i + 2 < x.len() && x[i + 1] <= edge { i += 1; }
+            start.push(i);
+        }
+
+        Section {
+            area: foil.area(),
+            chord: foil.chord(),
+            pitch,
+            x,
+            c,
+            start,
+            x0: lo,
+            per_bucket,
+            wave_drag: foil.wave_drag().clone(),
+            ground_effect: foil.ground_effect().copied(),
+            floor,
+        }
+    }
+
+    // Returns the lift, drag, and moment coefficients for a chord pointing
+    // along (cos, sin) = (c, s) moving along the unit vector (ux, uy), as
+    // `Aerofoil::coefficients`
+    fn coefficients(&self, (s, c): (f64, f64), (ux, uy): (f64, f64)) -> (f64, f64, f64) {
+        let mut aoa = (s * ux - c * uy).atan2(c * ux + s * uy).to_degrees();
+        if aoa < 0.0 { aoa += 360.0; }
+        let bucket = (((aoa - self.x0) * self.per_bucket) as usize).min(BUCKETS - 1);
+        let mut i = self.start[bucket];
+        while i + 2 < self.x.len() && self.x[i + 1] <= aoa { i += 1; }
+
+        let t = (aoa - self.x[i]) / (self.x[i + 1] - self.x[i]);
+        let (a, b) = (self.c[i], self.c[i + 1]);
+        (a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1]), a[2] + t * (b[2] - a[2]))
+    }
+
+    // As `Aerofoil::in_ground_effect`
+    fn in_ground_effect(&self, height: f64, coefficients: (f64, f64)) -> (f64, f64) {
+        match &self.ground_effect {
+            None => coefficients,
+            Some(ground_effect) => ground_effect.correct(height, coefficients, self.floor),
+        }
+    }
+}
+
+// What stays fixed for a lane over an RK4 substep, with the position held:
+// the wind, the air density, speed of sound, gravity, and height above the
+// ground, and the sine and cosine of the body's pitch and of each surface's
+// chord
+struct Held {
+    wind: (f64, f64),
+    height: f64,
+    density: f64,
+    sound: f64,
+    gravity: f64,
+    body: (f64, f64),
+    wing: (f64, f64),
+    elev: (f64, f64),
+}
+
+// Up to LANES planar vehicles, in structure-of-arrays layout
+#[derive(Clone, Default)]
+struct Block {
+    // State, as in the vehicle's position and motion
+    x: Lanes,
+    y: Lanes,
+    pitch: Lanes,
+    vx: Lanes,
+    vy: Lanes,
+    rate: Lanes,
+
+    // Parameters
+    mass: Lanes,
+    moment: Lanes,
+    length: Lanes,
+    cg: Lanes,
+    max_thrust: Lanes,
+    pitch_damping: Lanes,
+    elevator: Lanes,
+    wind_x: Lanes,
+    wind_y: Lanes,
+    gravity: [Gravity; LANES],
+    atmosphere: [Atmosphere; LANES],
+    earth: [Earth; LANES],
+
+    // Lanes holding a vehicle that's still in the air
+    flying: [bool; LANES],
+}
+
+impl Block {
+
+    // Wind at one lane's position, with any updraft, as `Vehicle::wind_at`
+    fn wind(&self, i: usize, air: &Surroundings) -> (f64, f64) {
+        let along = self.atmosphere[i].wind(self.y[i]).map_or(0.0, |(along, _)| along);
+        let (mut wind_x, mut wind_y) = (self.wind_x[i] + along, self.wind_y[i]);
+        if let Some(grid) = &air.wind_grid {
+            let (along, up) = grid.at(self.x[i], self.y[i]);
+            (wind_x, wind_y) = (wind_x + along, wind_y + up);
+        }
+        if air.updrafts.is_empty() { return (wind_x, wind_y); }
+        let up = air.updrafts.at(self.x[i], self.y[i], 0.0, wind_x);
+        (wind_x, wind_y + up)
+    }
+
+    // Linear and angular acceleration of one lane moving at (vx, vy) and
+    // turning at `rate`, as `Vehicle::calculate_dynamics` under the
+    // autothrottle
+    fn acceleration(&self, i: usize, wing: &Section, elev: &Section, held: &Held, (vx, vy, rate): (f64, f64, f64)) -> (f64, f64, f64) {
+        let (s, c) = held.body;
+
+        // Velocity is taken against the local horizon, which turns with the
+        // vehicle over a round earth
+        let (tx, ty) = match self.earth[i] {
+            Earth::Flat => (0.0, 0.0),
+            Earth::Round { radius } => {
+                let r = radius + self.y[i];
+                (-vx * vy / r, vx * vx / r)
+            }
+        };
+
+        let (vx, vy) = (vx - held.wind.0, vy - held.wind.1);
+        let speed = (vx * vx + vy * vy).sqrt();
+        let u = if speed > 0.0 { (vx / speed, vy / speed) } else { (1.0, 0.0) };
+        let (ux, uy) = u;
+        let q = 0.5 * held.density * speed * speed;
+
+        // Lift is normal to the airflow and drag against it
+        let mach = speed / held.sound;
+        let (cl, cd, cm_w) = wing.coefficients(held.wing, u);
+        let (cl, cd) = wing.in_ground_effect(held.height, (cl, cd));
+        let cd = cd + wing.wave_drag.increment(mach);
+        let (fwx, fwy) = (wing.area * q * (-cl * uy - cd * ux), wing.area * q * (cl * ux - cd * uy));
+        let (cl, cd, cm_e) = elev.coefficients(held.elev, u);
+        let (cl, cd) = elev.in_ground_effect(held.height, (cl, cd));
+        let cd = cd + elev.wave_drag.increment(mach);
+        let (fex, fey) = (elev.area * q * (-cl * uy - cd * ux), elev.area * q * (cl * ux - cd * uy));
+
+        let thrust = if self.y[i] < PULL_UP_ALTITUDE {
+            self.max_thrust[i]
+        } else {
+            (-((fwx + fex) * c + (fwy + fey) * s)).clamp(0.0, self.max_thrust[i])
+        };
+
+        // The elevator sits at the end of the body, half a length behind the
+        // wing, and the centre of mass is `cg` behind the wing
+        let arm = -self.length[i] / 2.0 + self.cg[i];
+        let (rx, ry) = (arm * c, arm * s);
+        let (wx, wy) = (self.cg[i] * c, self.cg[i] * s);
+        let moment = wing.area * q * cm_w * wing.chord
+            + elev.area * q * cm_e * elev.chord
+            + rx * fey - ry * fex
+            + (wx * fwy - wy * fwx);
+        let cmq = self.pitch_damping[i];
+        let moment = if cmq == 0.0 || speed == 0.0 { moment } else {
+            moment + cmq * rate * wing.chord / (2.0 * speed) * q * wing.area * wing.chord
+        };
+
+        (
+            (fwx + fex + thrust * c) / self.mass[i] + tx,
+            (fwy + fey + thrust * s) / self.mass[i] - held.gravity + ty,
+            moment / self.moment[i],
+        )
+    }
+
+    // Advances every flying lane by h, with RK4 on the motion and then the
+    // position, as `Vehicle::apply_dynamics` does
+    fn substep(&mut self, wing: &Section, elev: &Section, air: &Surroundings, h: f64) {
+        for i in 0..LANES {
+            if !self.flying[i] { continue; }
+
+            let (vx, vy, rate) = (self.vx[i], self.vy[i], self.rate[i]);
+            let pitch = self.pitch[i];
+            let held = Held {
+                wind: self.wind(i, air),
+                height: self.y[i] - air.updrafts.ground(self.x[i]),
+                density: self.atmosphere[i].density(self.y[i]),
+                sound: self.atmosphere[i].speed_of_sound(self.y[i]),
+                gravity: self.gravity[i].at(self.y[i]),
+                body: pitch.sin_cos(),
+                wing: (pitch + wing.pitch).sin_cos(),
+                elev: (pitch + elev.pitch + self.elevator[i]).sin_cos(),
+            };
+            let f = |vx, vy, rate| self.acceleration(i, wing, elev, &held, (vx, vy, rate));
+            let k1 = f(vx, vy, rate);
+            let k2 = f(vx + 0.5 * h * k1.0, vy + 0.5 * h * k1.1, rate + 0.5 * h * k1.2);
+            let k3 = f(vx + 0.5 * h * k2.0, vy + 0.5 * h * k2.1, rate + 0.5 * h * k2.2);
+            let k4 = f(vx + h * k3.0, vy + h * k3.1, rate + h * k3.2);
+
+            self.vx[i] += h * (k1.0 + 2.0 * k2.0 + 2.0 * k3.0 + k4.0) / 6.0;
+            self.vy[i] += h * (k1.1 + 2.0 * k2.1 + 2.0 * k3.1 + k4.1) / 6.0;
+            self.rate[i] += h * (k1.2 + 2.0 * k2.2 + 2.0 * k3.2 + k4.2) / 6.0;
+
+            // As `Earth::pose_rate`
+            let omega = match self.earth[i] {
+                Earth::Flat => 0.0,
+                Earth::Round { radius } => self.vx[i] / (radius + self.y[i]),
+            };
+            self.x[i] += h * (self.vx[i] - omega * self.y[i]);
+            self.y[i] += h * self.vy[i];
+            self.pitch[i] += h * (self.rate[i] - omega);
+        }
+    }
+
+    // Advances the block by `dt` seconds, grounding any lane that's landed
+    fn step(&mut self, wing: &Section, elev: &Section, air: &Surroundings, dt: f64) {
+        let n = (dt / MAX_SUBSTEP).ceil().max(1.0) as usize;
+        for _ in 0..n {
+            self.substep(wing, elev, air, dt / n as f64);
+        }
+        for i in 0..LANES {
+            if self.y[i] <= air.updrafts.ground(self.x[i]) { self.flying[i] = false; }
+        }
+    }
+}
+
+// The moving air every vehicle of a fleet shares
+struct Surroundings {
+    updrafts: Updrafts,
+    wind_grid: Option<Arc<WindGrid>>,
+}
+
+// Many planar vehicles stepped together, for dispersion studies too large
+// to fly one `Simulation` at a time. Every vehicle shares the wing and
+// elevator aerodynamics, worked out surface by surface whatever model the
+// prototype flies, the updrafts, and the wind grid, and flies on the
+// autothrottle, trading per-vehicle flexibility for throughput. Mass,
+// length, centre of mass, thrust, pitch damping, elevator setting, wind,
+// gravity, the atmosphere, the shape of the earth, and the initial state can
+// all differ. External forces aren't felt, and the thrust is always
+// `max_thrust`'s, whatever propeller, battery, or engine the prototype has.
+// Vehicles stop where they hit the ground, or a ridge.
+pub struct Fleet {
+    wing: Section,
+    elev: Section,
+    air: Surroundings,
+    blocks: Vec<Block>,
+    len: usize,
+    pub time: f64,
+}
+
+impl Fleet {
+
+    // Constructor, for an empty fleet sharing the prototype's surfaces,
+    // updrafts, and wind grid
+    pub fn new(prototype: &Vehicle) -> Fleet {
+        // The elevator's setting is kept per vehicle instead
+        let wing = Section::new(&prototype.wing, prototype.wing.pitch().signed_rad());
+        let elev = Section::new(&prototype.elev, 0.0);
+        let air = Surroundings { updrafts: prototype.updrafts.clone(), wind_grid: prototype.wind_grid.clone() };
+
+        Fleet { wing, elev, air, blocks: Vec::new(), len: 0, time: 0.0 }
+    }
+
+    // Adds a vehicle at its current state. Its surfaces are ignored, apart
+    // from the elevator setting.
+    pub fn push(&mut self, vehicle: &Vehicle) {
+        let i = self.len % LANES;
+        if i == 0 { self.blocks.push(Block::default()); }
+        let b = self.blocks.last_mut().unwrap();
+
+        b.x[i] = vehicle.position.x();
+        b.y[i] = vehicle.position.y();
+        b.pitch[i] = vehicle.position.ang;
+        b.vx[i] = vehicle.motion.x();
+        b.vy[i] = vehicle.motion.y();
+        b.rate[i] = vehicle.motion.angular_velocity();
+        b.mass[i] = vehicle.mass;
+        b.moment[i] = vehicle.moment;
+        b.length[i] = vehicle.length;
+        b.cg[i] = vehicle.cg;
+        b.max_thrust[i] = vehicle.max_thrust;
+        b.pitch_damping[i] = vehicle.pitch_damping;
+        b.elevator[i] = vehicle.elev.pitch().signed_rad();
+        b.wind_x[i] = vehicle.wind.x();
+        b.wind_y[i] = vehicle.wind.y();
+        b.gravity[i] = vehicle.gravity;
+        b.atmosphere[i] = vehicle.atmosphere.clone();
+        b.earth[i] = vehicle.earth;
+        b.flying[i] = vehicle.position.y() > self.air.updrafts.ground(vehicle.position.x());
+        self.len += 1;
+    }
+
+    #[inline] pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline] pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Sets a vehicle's elevator
+    pub fn set_elevator(&mut self, i: usize, elevator: Angle) {
+        self.blocks[i / LANES].elevator[i % LANES] = elevator.signed_rad();
+    }
+
+    // Applies the built-in pull-up of `Command::pull_up` to every vehicle
+    pub fn pull_up(&mut self) {
+        for b in &mut self.blocks {
+            for i in 0..LANES {
+                b.elevator[i] = Command::pull_up_at(b.y[i]).elevator.signed_rad();
+            }
+        }
+    }
+
+    // Advances every vehicle still in the air by `dt` seconds
+    pub fn step(&mut self, dt: f64) {
+        let (wing, elev, air) = (&self.wing, &self.elev, &self.air);
+        self.blocks.iter_mut().for_each(|b| b.step(wing, elev, air, dt));
+        self.time += dt;
+    }
+
+    // As `step`, with the blocks shared out across cores
+    #[cfg(feature = "parallel")]
+    pub fn par_step(&mut self, dt: f64) {
+        let (wing, elev, air) = (&self.wing, &self.elev, &self.air);
+        self.blocks.par_iter_mut().for_each(|b| b.step(wing, elev, air, dt));
+        self.time += dt;
+    }
+
+    // Whether a vehicle is still in the air
+    pub fn flying(&self, i: usize) -> bool {
+        self.blocks[i / LANES].flying[i % LANES]
+    }
+
+    // Returns the state of a vehicle
+    pub fn state(&self, i: usize) -> State {
+        let (b, i) = (&self.blocks[i / LANES], i % LANES);
+        let (wind_x, wind_y) = b.wind(i, &self.air);
+        let direction = (b.vy[i] - wind_y).atan2(b.vx[i] - wind_x);
+        State {
+            time: self.time,
+            x: b.x[i],
+            y: b.y[i],
+            vx: b.vx[i],
+            vy: b.vy[i],
+            pitch: Angle::from_radians(b.pitch[i]).nice_deg(),
+            pitch_rate: b.rate[i].to_degrees(),
+            aoa: Angle::from_radians(b.pitch[i] - direction).nice_deg(),
+        }
+    }
+}
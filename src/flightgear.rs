@@ -0,0 +1,121 @@
+use crate::aero::Vehicle;
+use crate::geo::Origin;
+
+use std::io;
+use std::net::UdpSocket;
+
+// Version of FlightGear's FGNetFDM structure we emit (see net_fdm.hxx)
+const FG_NET_FDM_VERSION: u32 = 24;
+const FG_MAX_ENGINES: usize = 4;
+const FG_MAX_WHEELS: usize = 3;
+const FG_MAX_TANKS: usize = 4;
+
+// Unit conversions
+const FEET_PER_METRE: f64 = 3.280_839_895;
+const KNOTS_PER_MPS: f64 = 1.943_844_492;
+
+// Streams the vehicle state to FlightGear's native FDM interface over UDP.
+// FlightGear should be started with something like
+//   fgfs --fdm=null --native-fdm=socket,in,100,,5500,udp
+pub struct FlightGear {
+    socket: UdpSocket,
+    origin: Origin,
+}
+
+impl FlightGear {
+
+    // Constructor, binds an ephemeral local port and connects it to `host`
+    // (e.g. "localhost:5500"), placing the vehicle on the globe from `origin`
+    pub fn connect(host: &str, origin: Origin) -> io::Result<FlightGear> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(host)?;
+        Ok(FlightGear { socket, origin })
+    }
+
+    // Sends one packet describing the current state of the vehicle. Nobody
+    // listening yet isn't an error, since FlightGear may be started late.
+    pub fn send(&self, vehicle: &Vehicle, second: f64) -> io::Result<()> {
+        match self.socket.send(&net_fdm(&self.origin, vehicle, second)) {
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+// Serialises the vehicle state as an FGNetFDM packet. All fields are in
+// network (big-endian) byte order.
+fn net_fdm(origin: &Origin, vehicle: &Vehicle, second: f64) -> Vec<u8> {
+
+    let mut buf: Vec<u8> = Vec::with_capacity(512);
+    let put_u32 = |buf: &mut Vec<u8>, v: u32| buf.extend_from_slice(&v.to_be_bytes());
+    let put_f32 = |buf: &mut Vec<u8>, v: f64| buf.extend_from_slice(&(v as f32).to_be_bytes());
+    let put_f64 = |buf: &mut Vec<u8>, v: f64| buf.extend_from_slice(&v.to_be_bytes());
+
+    let pos = &vehicle.position;
+    let vel = &vehicle.motion;
+    let pitch = pos.angle().nice_deg().to_radians();
+    let alpha = vehicle.aoa().nice_deg().to_radians();
+    let air = vehicle.airdata();
+    let v_body = vel.to_body(pos.angle());
+
+    // Header
+    put_u32(&mut buf, FG_NET_FDM_VERSION);
+    put_u32(&mut buf, 0); // padding
+
+    // Positions
+    let (latitude, longitude) = origin.locate(pos.x());
+    let (v_north, v_east) = origin.north_east(vel.x());
+    put_f64(&mut buf, longitude.to_radians());
+    put_f64(&mut buf, latitude.to_radians());
+    put_f64(&mut buf, pos.y());
+    put_f32(&mut buf, pos.y()); // agl
+    put_f32(&mut buf, 0.0);     // phi
+    put_f32(&mut buf, pitch);   // theta
+    put_f32(&mut buf, origin.heading_rad()); // psi
+    put_f32(&mut buf, alpha);
+    put_f32(&mut buf, 0.0);     // beta
+
+    // Velocities
+    put_f32(&mut buf, 0.0);     // phidot
+    put_f32(&mut buf, vel.angular_velocity()); // thetadot
+    put_f32(&mut buf, 0.0);     // psidot
+    put_f32(&mut buf, air.tas * air.sigma.sqrt() * KNOTS_PER_MPS); // vcas
+    put_f32(&mut buf, vel.vertical() * FEET_PER_METRE); // climb rate
+    put_f32(&mut buf, v_north * FEET_PER_METRE);
+    put_f32(&mut buf, v_east * FEET_PER_METRE);
+    put_f32(&mut buf, -vel.y() * FEET_PER_METRE); // v_down
+    put_f32(&mut buf, v_body.x() * FEET_PER_METRE);  // v_body_u
+    put_f32(&mut buf, 0.0);     // v_body_v
+    put_f32(&mut buf, -v_body.y() * FEET_PER_METRE); // v_body_w
+
+    // Accelerations (not available from the state alone)
+    for _ in 0..3 { put_f32(&mut buf, 0.0); }
+
+    // Stall warning and slip
+    put_f32(&mut buf, 0.0);
+    put_f32(&mut buf, 0.0);
+
+    // Engines, reported as a single running engine
+    put_u32(&mut buf, 1);
+    for i in 0..FG_MAX_ENGINES { put_u32(&mut buf, if i == 0 { 2 } else { 0 }); }
+    for _ in 0..(9 * FG_MAX_ENGINES) { put_f32(&mut buf, 0.0); }
+
+    // Consumables
+    put_u32(&mut buf, 0);
+    for _ in 0..FG_MAX_TANKS { put_f32(&mut buf, 0.0); }
+
+    // Gear, always up
+    put_u32(&mut buf, 0);
+    for _ in 0..FG_MAX_WHEELS { put_u32(&mut buf, 0); }
+    for _ in 0..(3 * FG_MAX_WHEELS) { put_f32(&mut buf, 0.0); }
+
+    // Environment
+    put_u32(&mut buf, second as u32); // cur_time
+    put_u32(&mut buf, 0);             // warp
+    put_f32(&mut buf, 10_000.0);      // visibility
+
+    // Control surfaces, left neutral
+    for _ in 0..10 { put_f32(&mut buf, 0.0); }
+
+    buf
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Mean radius of the earth, m
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// Where the flat-earth frame is anchored on the globe: the geodetic position
+// of x = 0, and the heading that downrange points along. Degrees throughout.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Origin {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub heading: f64,
+}
+
+impl Default for Origin {
+    // On the equator at the prime meridian, flying due east
+    fn default() -> Origin {
+        Origin { latitude: 0.0, longitude: 0.0, heading: 90.0 }
+    }
+}
+
+impl Origin {
+
+    // Returns the (latitude, longitude) in degrees of a point `x` metres
+    // downrange, treating the earth as locally flat
+    pub fn locate(&self, x: f64) -> (f64, f64) {
+        self.locate_across(x, 0.0)
+    }
+
+    // As `locate`, for a point also `z` metres across, to the right of
+    // downrange as the point mass's heading turns towards +z
+    pub fn locate_across(&self, x: f64, z: f64) -> (f64, f64) {
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+        let latitude = self.latitude.to_radians();
+        let north = x * cos - z * sin;
+        let east = x * sin + z * cos;
+        (
+            self.latitude + (north / EARTH_RADIUS).to_degrees(),
+            self.longitude + (east / (EARTH_RADIUS * latitude.cos())).to_degrees(),
+        )
+    }
+
+    // Splits a horizontal velocity along the heading into (north, east)
+    pub fn north_east(&self, v: f64) -> (f64, f64) {
+        let heading = self.heading.to_radians();
+        (v * heading.cos(), v * heading.sin())
+    }
+
+    // The heading in radians, in (-pi, pi]
+    pub fn heading_rad(&self) -> f64 {
+        let heading = self.heading.to_radians().rem_euclid(2.0 * PI);
+        if heading > PI { heading - 2.0 * PI } else { heading }
+    }
+}
+
+// A trajectory over the ground, each point (latitude, longitude, altitude),
+// degrees and m above the ground, from a track of (downrange, altitude,
+// across) m flown from `origin`
+pub fn geodetic(origin: &Origin, track: &[(f64, f64, f64)]) -> Vec<(f64, f64, f64)> {
+    track.iter().map(|&(x, y, z)| {
+        let (latitude, longitude) = origin.locate_across(x, z);
+        (latitude, longitude, y)
+    }).collect()
+}
+
+// Writes a trajectory from `geodetic` to `path` under the name `name`, as
+// KML for Google Earth if the path ends in .kml and as GeoJSON for GIS tools
+// otherwise. Altitudes are above the ground, which the flat-earth frame
+// takes to be level, so the track comes down on the terrain where it lands.
+pub fn write_track(path: impl AsRef<Path>, name: &str, points: &[(f64, f64, f64)]) -> io::Result<()> {
+    let path = path.as_ref();
+    let kml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("kml"));
+    fs::write(path, if kml { to_kml(name, points) } else { to_geojson(name, points) })
+}
+
+// The track as a KML document: the path, walled down to the ground, and
+// placemarks where it starts and ends
+pub fn to_kml(name: &str, points: &[(f64, f64, f64)]) -> String {
+    let name = name.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut coordinates = String::new();
+    for (latitude, longitude, altitude) in points {
+        // Infallible, writing to a String
+        let _ = write!(coordinates, "{:.7},{:.7},{:.1} ", longitude, latitude, altitude);
+    }
+
+    let mut kml = format!(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n",
+        "<Document>\n",
+        "<name>{name}</name>\n",
+        "<Style id=\"track\"><LineStyle><color>ff0000ff</color><width>3</width></LineStyle>",
+        "<PolyStyle><color>400000ff</color></PolyStyle></Style>\n",
+        "<Placemark>\n",
+        "<name>{name}</name>\n",
+        "<styleUrl>#track</styleUrl>\n",
+        "<LineString><extrude>1</extrude><tessellate>1</tessellate>",
+        "<altitudeMode>relativeToGround</altitudeMode>\n",
+        "<coordinates>{coordinates}</coordinates>\n",
+        "</LineString>\n",
+        "</Placemark>\n"),
+        name = name, coordinates = coordinates.trim_end());
+    let ends = [("Release", points.first()), ("End", points.last())];
+    for (label, point) in ends {
+        if let Some((latitude, longitude, altitude)) = point {
+            let _ = write!(kml, concat!(
+                "<Placemark><name>{}</name><Point><altitudeMode>relativeToGround</altitudeMode>",
+                "<coordinates>{:.7},{:.7},{:.1}</coordinates></Point></Placemark>\n"),
+                label, longitude, latitude, altitude);
+        }
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+// The track as a GeoJSON feature collection: the path as a line string with
+// altitudes, and points where it starts and ends
+pub fn to_geojson(name: &str, points: &[(f64, f64, f64)]) -> String {
+    let position = |&(latitude, longitude, altitude): &(f64, f64, f64)| json!([longitude, latitude, altitude]);
+    let mut features = vec![json!({
+        "type": "Feature",
+        "properties": { "name": name },
+        "geometry": { "type": "LineString", "coordinates": points.iter().map(position).collect::<Vec<_>>() },
+    })];
+    let ends = [("Release", points.first()), ("End", points.last())];
+    for (label, point) in ends {
+        if let Some(point) = point {
+            features.push(json!({
+                "type": "Feature",
+                "properties": { "name": label },
+                "geometry": { "type": "Point", "coordinates": position(point) },
+            }));
+        }
+    }
+    json!({ "type": "FeatureCollection", "features": features }).to_string()
+}
@@ -0,0 +1,48 @@
+use crate::scalar::Scalar;
+
+use serde::{Deserialize, Serialize};
+
+// Gravity at the surface the vehicles have always used, m/s^2
+pub const G0: f64 = 9.81;
+
+// Mean radius of the earth, m
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// The gravity field the vehicles fly in. A constant field is right for
+// anything staying within a few kilometres of the ground; higher up, at
+// 100 km, gravity has already fallen by 3%, which shows in high releases
+// and sounding rockets.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gravity {
+    // The same at every altitude, m/s^2
+    Constant(f64),
+    // Falling off with the square of the distance from the centre of a body
+    // of `radius`, m, with `surface` the gravity at its surface, m/s^2
+    InverseSquare { surface: f64, radius: f64 },
+}
+
+impl Default for Gravity {
+    fn default() -> Gravity {
+        Gravity::Constant(G0)
+    }
+}
+
+impl Gravity {
+
+    // Constructor, for the inverse-square field of the earth
+    pub fn inverse_square() -> Gravity {
+        Gravity::InverseSquare { surface: G0, radius: EARTH_RADIUS }
+    }
+
+    // Gets the gravitational acceleration at `altitude`, m/s^2
+    #[inline] pub fn at<S: Scalar>(&self, altitude: S) -> S {
+        match *self {
+            Gravity::Constant(g) => S::from_f64(g),
+            Gravity::InverseSquare { surface, radius } => {
+                let r = S::from_f64(radius);
+                S::from_f64(surface) * (r / (r + altitude)).powi(2)
+            }
+        }
+    }
+}
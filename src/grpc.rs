@@ -0,0 +1,203 @@
+use crate::config::VehicleConfig;
+use crate::control::Command;
+use crate::rng::SimRng;
+use crate::sim::{self, Simulation};
+use crate::vec::Angle;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+// Generated from proto/dynterm.proto
+pub mod proto {
+    tonic::include_proto!("dynterm");
+}
+
+use proto::simulator_server::{Simulator, SimulatorServer};
+use proto::{
+    ControlRequest, CreateSessionRequest, Empty, Session, State, StepRequest, StreamRequest,
+};
+
+// Longest step a client can ask for at once, s. Steps run on a blocking
+// thread, but one this long would still hold its session for seconds.
+const MAX_DT: f64 = 10.0;
+
+// Furthest a client can set the elevator either way, degrees
+const MAX_ELEVATOR: f64 = 90.0;
+
+// Serves the gRPC simulation service on `addr` (e.g. "0.0.0.0:50051") until
+// the process is killed
+pub fn serve(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = addr.parse()?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    tracing::info!("Serving simulations on {}", addr);
+    runtime.block_on(
+        Server::builder()
+            .add_service(SimulatorServer::new(Service::default()))
+            .serve(addr))?;
+    Ok(())
+}
+
+// The open sessions, keyed by id, each locked on its own so a long step
+// holds up no other
+type Sessions = Arc<Mutex<HashMap<u64, Arc<Mutex<Simulation>>>>>;
+
+#[derive(Default)]
+struct Service {
+    sessions: Sessions,
+    next_id: AtomicU64,
+}
+
+// Runs `f` on the session with the given id. Status is large, but it's
+// what every tonic handler returns anyway.
+#[allow(clippy::result_large_err)]
+fn with_session<T>(
+    sessions: &Sessions,
+    id: u64,
+    f: impl FnOnce(&mut Simulation) -> T) -> Result<T, Status> {
+    let session = sessions.lock().unwrap().get(&id).cloned()
+        .ok_or_else(|| Status::not_found(format!("no session {}", id)))?;
+    let mut sim = session.lock().unwrap();
+    Ok(f(&mut sim))
+}
+
+// Checks the interval a client asked to step by, s
+#[allow(clippy::result_large_err)]
+fn check_dt(dt: f64) -> Result<(), Status> {
+    match dt.is_finite() && dt > 0.0 && dt <= MAX_DT {
+        true => Ok(()),
+        false => Err(Status::invalid_argument(format!("dt must be positive and at most {} s, not {}", MAX_DT, dt))),
+    }
+}
+
+// Checks the controls a client asked for: the elevator, degrees, within
+// MAX_ELEVATOR, and the throttle and speedbrake, where given, from 0 to 1
+#[allow(clippy::result_large_err)]
+fn check_controls(request: &ControlRequest) -> Result<(), Status> {
+    if !(request.elevator.is_finite() && request.elevator.abs() <= MAX_ELEVATOR) {
+        return Err(Status::invalid_argument(format!(
+            "elevator must be within {} degrees either way, not {}", MAX_ELEVATOR, request.elevator)));
+    }
+    for (name, value) in [("throttle", request.throttle), ("speedbrake", request.speedbrake)] {
+        if let Some(value) = value.filter(|value| !(0.0..=1.0).contains(value)) {
+            return Err(Status::invalid_argument(format!("{} must be from 0 to 1, not {}", name, value)));
+        }
+    }
+    Ok(())
+}
+
+// Steps the session with the given id by `dt` seconds on a blocking thread,
+// off the async executor, giving its new state
+#[allow(clippy::result_large_err)]
+async fn step_session(sessions: Sessions, id: u64, dt: f64) -> Result<State, Status> {
+    tokio::task::spawn_blocking(move || with_session(&sessions, id, |sim| {
+        sim.step(dt);
+        sim.state().into()
+    }))
+    .await
+    .map_err(|e| Status::internal(format!("step failed: {}", e)))?
+}
+
+impl From<sim::State> for State {
+    fn from(s: sim::State) -> State {
+        State {
+            time: s.time,
+            x: s.x,
+            y: s.y,
+            vx: s.vx,
+            vy: s.vy,
+            pitch: s.pitch,
+            pitch_rate: s.pitch_rate,
+            aoa: s.aoa,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Simulator for Service {
+
+    async fn create_session(
+        &self,
+        request: Request<CreateSessionRequest>) -> Result<Response<Session>, Status> {
+
+        let json = request.into_inner().config_json;
+        let config: VehicleConfig = if json.trim().is_empty() {
+            VehicleConfig::default()
+        } else {
+            serde_json::from_str(&json)
+                .map_err(|e| Status::invalid_argument(format!("invalid config: {}", e)))?
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let sim = Simulation::new(config.build(), SimRng::new(0));
+        self.sessions.lock().unwrap().insert(id, Arc::new(Mutex::new(sim)));
+        Ok(Response::new(Session { id }))
+    }
+
+    async fn close_session(
+        &self,
+        request: Request<Session>) -> Result<Response<Empty>, Status> {
+
+        let id = request.into_inner().id;
+        self.sessions.lock().unwrap().remove(&id)
+            .ok_or_else(|| Status::not_found(format!("no session {}", id)))?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn set_controls(
+        &self,
+        request: Request<ControlRequest>) -> Result<Response<Empty>, Status> {
+
+        let request = request.into_inner();
+        check_controls(&request)?;
+        let command = Command {
+            elevator: Angle::from_degrees(request.elevator),
+            throttle: request.throttle,
+            speedbrake: request.speedbrake,
+            flaps: None,
+            rating: None,
+            thrust_angle: None,
+        };
+        with_session(&self.sessions, request.session, |sim| command.apply(&mut sim.vehicle))?;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn step(
+        &self,
+        request: Request<StepRequest>) -> Result<Response<State>, Status> {
+
+        let request = request.into_inner();
+        check_dt(request.dt)?;
+        let state = step_session(self.sessions.clone(), request.session, request.dt).await?;
+        Ok(Response::new(state))
+    }
+
+    type StreamStateStream = ReceiverStream<Result<State, Status>>;
+
+    async fn stream_state(
+        &self,
+        request: Request<StreamRequest>) -> Result<Response<Self::StreamStateStream>, Status> {
+
+        let request = request.into_inner();
+        check_dt(request.dt)?;
+        with_session(&self.sessions, request.session, |_| ())?;
+
+        let (tx, rx) = mpsc::channel(64);
+        let sessions = self.sessions.clone();
+        tokio::spawn(async move {
+            for _ in 0..request.steps {
+                let state = step_session(sessions.clone(), request.session, request.dt).await;
+                let closed = state.is_err();
+
+                // Stop once the client hangs up or the session is closed
+                if tx.send(state).await.is_err() || closed { break; }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
@@ -0,0 +1,321 @@
+use crate::aero::Airdata;
+use crate::control::{Command, GainSchedule, Gains, Pid, ScheduleKey};
+use crate::gravity::G0;
+use crate::sim::State;
+use crate::vec::Angle;
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// Gain of the time-of-arrival control, in speedbrake deployment or g of
+// weave per fraction of the time to go it's running early
+fn arrival_gain() -> f64 {
+    20.0
+}
+
+// How the guidance loses time when it's running early
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrivalMethod {
+    // Opening the speedbrake, which the vehicle needs to have
+    Speedbrake,
+    // Weaving across the flight path, a weave every `period` s
+    Weave { period: f64 },
+}
+
+// Time-of-arrival control, for arriving at the target at `time` s, so a
+// salvo can be made to arrive together. The arrival is predicted from the
+// range and the speed it's closing at, and while that's early the guidance
+// loses the time by `method`, in proportion to how early it is as a
+// fraction of the time to go, by `gain`. Running late there's nothing to
+// be done. A weave takes no more than half the load limit, leaving the rest
+// to the guidance, and stops two periods out, to leave it time to put the
+// vehicle back on the line for the impact angle. The path only stretches
+// with the square of the flight path angle the weave swings through, so
+// fast, a weave loses little time.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArrivalSpec {
+    pub time: f64,
+    pub method: ArrivalMethod,
+    #[serde(default = "arrival_gain")]
+    pub gain: f64,
+}
+
+// Where the vehicle passed closest to the target
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosestApproach {
+    // Time, s
+    pub time: f64,
+    // Distance, m
+    pub miss: f64,
+    // Angle of the vehicle's velocity relative to the target below the
+    // horizontal, degrees
+    pub angle: f64,
+    // Speed relative to the target, m/s
+    pub closing_speed: f64,
+}
+
+// Guidance to a target `target` m downrange, at `target_altitude`, moving at
+// `target_velocity`, m/s, downrange and up, hitting it at `impact_angle`
+// degrees below the horizontal. The law is the
+// optimal one for a stationary target with the final flight path angle
+// fixed, proportional navigation with navigation ratio 4 and a bias of 2 on
+// the line of sight's angle off the final flight path over the time to go,
+//
+//   a = 4 Vc dλ/dt + 2 Vc (λ - γf) / tgo,
+//
+// the acceleration across the flight path held to `max_load` g. The
+// elevator follows it through a PID loop on the pitch rate needed to turn
+// the flight path that fast, its gains in degrees of elevator per degree a
+// second of pitch rate error, scheduled on the flight condition. Thrust is
+// left to the autothrottle. With `arrival` it arrives on time, too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuidanceSpec {
+    pub target: f64,
+    pub target_altitude: f64,
+    pub target_velocity: (f64, f64),
+    pub impact_angle: f64,
+    pub max_load: f64,
+    pub gains: GainSchedule,
+    pub max_elevator: f64,
+    pub arrival: Option<ArrivalSpec>,
+}
+
+impl Default for GuidanceSpec {
+    fn default() -> GuidanceSpec {
+        let gains = |kp: f64| Gains { kp, ki: 0.5 * kp, kd: 0.1 * kp };
+        GuidanceSpec {
+            target: 20_000.0,
+            target_altitude: 0.0,
+            target_velocity: (0.0, 0.0),
+            impact_angle: 60.0,
+            max_load: 4.0,
+            gains: GainSchedule {
+                key: ScheduleKey::DynamicPressure,
+                points: vec![(5_000.0, gains(2.0)), (20_000.0, gains(0.8)), (60_000.0, gains(0.3))],
+            },
+            max_elevator: 10.0,
+            arrival: None,
+        }
+    }
+}
+
+// Flies a `GuidanceSpec`
+#[derive(Debug, Clone)]
+pub struct Guidance {
+    pub spec: GuidanceSpec,
+    pid: Pid,
+    last: Option<(f64, f64)>,
+    demand: f64,
+    early: f64,
+    previous: Option<State>,
+    impact: Option<(f64, f64)>,
+    arrival: Option<f64>,
+    closest: Option<ClosestApproach>,
+}
+
+impl Guidance {
+
+    // Constructor, with the integrator empty
+    pub fn new(spec: GuidanceSpec) -> Guidance {
+        Guidance {
+            spec,
+            pid: Pid::default(),
+            last: None,
+            demand: 0.0,
+            early: 0.0,
+            previous: None,
+            impact: None,
+            arrival: None,
+            closest: None,
+        }
+    }
+
+    // Acceleration across the flight path asked for on the last step, m/s^2
+    #[inline] pub fn demand(&self) -> f64 {
+        self.demand
+    }
+
+    // How early the arrival was predicted on the last step, s, late
+    // negative
+    #[inline] pub fn early(&self) -> f64 {
+        self.early
+    }
+
+    // Time the vehicle arrived at the target, s, if it has
+    #[inline] pub fn arrival(&self) -> Option<f64> {
+        self.arrival
+    }
+
+    // Impact angle, degrees below the horizontal, and miss distance, m
+    // downrange of the target, where the vehicle first came down through a
+    // still target's altitude, if it has
+    #[inline] pub fn impact(&self) -> Option<(f64, f64)> {
+        self.impact
+    }
+
+    // Where the vehicle first passed closest to the target, if it has
+    #[inline] pub fn closest_approach(&self) -> Option<ClosestApproach> {
+        self.closest
+    }
+
+    // Gets the target's position at `time`, m downrange and up
+    pub fn target_at(&self, time: f64) -> (f64, f64) {
+        let (vx, vy) = self.spec.target_velocity;
+        (self.spec.target + vx * time, self.spec.target_altitude + vy * time)
+    }
+
+    // Gets the vehicle's position and velocity relative to the target at
+    // `state`
+    fn relative(&self, state: &State) -> ((f64, f64), (f64, f64)) {
+        let (x, y) = self.target_at(state.time);
+        let (vx, vy) = self.spec.target_velocity;
+        ((state.x - x, state.y - y), (state.vx - vx, state.vy - vy))
+    }
+
+    // Watches the vehicle at `state` for it passing the target, to find the
+    // closest approach within the step, and for it coming down through a
+    // still target's altitude, to mark the impact between this state and the
+    // last. A moving target is arrived at when it's passed closest.
+    pub fn observe(&mut self, state: &State) {
+        if let Some(previous) = &self.previous {
+            if self.closest.is_none() {
+                self.closest = closest_approach(previous.time, self.relative(previous), state.time, self.relative(state));
+            }
+            let altitude = self.spec.target_altitude;
+            if self.spec.target_velocity != (0.0, 0.0) {
+                self.arrival = self.closest.map(|closest| closest.time);
+            } else if self.impact.is_none() && previous.y > altitude && state.y <= altitude {
+                let t = (previous.y - altitude) / (previous.y - state.y);
+                let time = previous.time + t * (state.time - previous.time);
+                let x = previous.x + t * (state.x - previous.x);
+                let (vx, vy) = (previous.vx + t * (state.vx - previous.vx), previous.vy + t * (state.vy - previous.vy));
+                self.impact = Some(((-vy).atan2(vx).to_degrees(), x - self.spec.target));
+                self.arrival = Some(time);
+            }
+        }
+        self.previous = Some(*state);
+    }
+
+    // Gets the time to go to the target from `state`, s, at its speed, if
+    // it's closing. The path the guidance flies is longer than the line of
+    // sight by the turn it has to make off the sight line and back onto the
+    // final flight path, as Jeon, Lee, and Tahk have it for this law.
+    pub fn time_to_go(&self, state: &State) -> Option<f64> {
+        let ((x, y), (vx, vy)) = self.relative(state);
+        let (rx, ry) = (-x, -y);
+        let range = rx.hypot(ry);
+        if range < 1.0 || rx * vx + ry * vy <= 0.0 { return None; }
+
+        let los = ry.atan2(rx);
+        let heading = vy.atan2(vx) - los;
+        let turn = -self.spec.impact_angle.to_radians() - los;
+        let stretch = 1.0 + (2.0 * heading * heading - heading * turn + 2.0 * turn * turn) / 30.0;
+        Some(range / vx.hypot(vy) * stretch)
+    }
+
+    // Gets the acceleration across the flight path, m/s^2, turning up, to
+    // fly from `state` into the target at the impact angle
+    pub fn acceleration(&self, state: &State) -> f64 {
+        let ((x, y), (vx, vy)) = self.relative(state);
+        let (rx, ry) = (-x, -y);
+        let range = rx.hypot(ry);
+        if range < 1.0 { return 0.0; }
+
+        let closing = (rx * vx + ry * vy) / range;
+        if closing <= 0.0 { return 0.0; }
+        let los = ry.atan2(rx);
+        let los_rate = (ry * vx - rx * vy) / (range * range);
+        let time_to_go = range / closing;
+        let final_path = -self.spec.impact_angle.to_radians();
+
+        let demand = 4.0 * closing * los_rate + 2.0 * closing * (los - final_path) / time_to_go;
+        demand.clamp(-self.spec.max_load * G0, self.spec.max_load * G0)
+    }
+
+    // Guides from `state`, in the flight condition of `airdata`
+    pub fn command(&mut self, state: &State, airdata: &Airdata) -> Command {
+        self.demand = self.acceleration(state);
+
+        // Lose whatever time it's early by
+        let mut speedbrake = None;
+        if let (Some(arrival), Some(time_to_go)) = (self.spec.arrival, self.time_to_go(state)) {
+            self.early = arrival.time - (state.time + time_to_go);
+            let effort = (arrival.gain * self.early / time_to_go).max(0.0);
+            match arrival.method {
+                ArrivalMethod::Speedbrake => speedbrake = Some(effort.min(1.0)),
+                ArrivalMethod::Weave { period } if time_to_go > 2.0 * period => {
+                    let weave = effort.min(0.5 * self.spec.max_load) * G0 * (2.0 * PI * state.time / period).sin();
+                    self.demand = (self.demand + weave).clamp(-self.spec.max_load * G0, self.spec.max_load * G0);
+                }
+                ArrivalMethod::Weave { .. } => {}
+            }
+        }
+
+        let speed = state.vx.hypot(state.vy).max(1.0);
+        let error = (self.demand / speed).to_degrees() - state.pitch_rate;
+
+        // Differentiating the pitch rate rather than the error, as the PID
+        // expects
+        let (dt, error_rate) = match self.last {
+            Some((time, last)) if state.time > time => {
+                let dt = state.time - time;
+                (dt, -(state.pitch_rate - last) / dt)
+            }
+            _ => (0.0, 0.0),
+        };
+        self.last = Some((state.time, state.pitch_rate));
+
+        let output = self.pid.update(self.spec.gains.gains(airdata), error, error_rate, dt, self.spec.max_elevator);
+        Command {
+            elevator: Angle::from_degrees(-output),
+            throttle: None,
+            speedbrake,
+            flaps: None,
+            rating: None,
+            thrust_angle: None,
+        }
+    }
+}
+
+// Finds where the range between two bodies stops closing within a step, from
+// their relative position and velocity at `t0`, `start`, and at `t1`, `end`.
+// The relative position is taken to follow the cubic that fits the two ends'
+// positions and velocities, and the time the range rate comes through zero
+// is bisected for on it, so the miss is found to well within the distance
+// moved in a step.
+fn closest_approach(
+    t0: f64, start: ((f64, f64), (f64, f64)),
+    t1: f64, end: ((f64, f64), (f64, f64))) -> Option<ClosestApproach> {
+
+    let h = t1 - t0;
+    let (((x0, y0), (vx0, vy0)), ((x1, y1), (vx1, vy1))) = (start, end);
+    let range_rate = |((x, y), (vx, vy)): ((f64, f64), (f64, f64))| x * vx + y * vy;
+    if h <= 0.0 || range_rate(start) >= 0.0 || range_rate(end) < 0.0 { return None; }
+
+    // Cubic Hermite interpolation, and its rate, at `s` of the way through
+    let at = |s: f64| {
+        let (s2, s3) = (s * s, s * s * s);
+        let (h00, h10, h01, h11) = (2.0 * s3 - 3.0 * s2 + 1.0, s3 - 2.0 * s2 + s, -2.0 * s3 + 3.0 * s2, s3 - s2);
+        let (d00, d10, d01, d11) = (6.0 * s2 - 6.0 * s, 3.0 * s2 - 4.0 * s + 1.0, -6.0 * s2 + 6.0 * s, 3.0 * s2 - 2.0 * s);
+        let blend = |p0: f64, v0: f64, p1: f64, v1: f64|
+            (h00 * p0 + h10 * h * v0 + h01 * p1 + h11 * h * v1, (d00 * p0 + d01 * p1) / h + d10 * v0 + d11 * v1);
+        let ((x, vx), (y, vy)) = (blend(x0, vx0, x1, vx1), blend(y0, vy0, y1, vy1));
+        ((x, y), (vx, vy))
+    };
+
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        if range_rate(at(mid)) < 0.0 { low = mid } else { high = mid }
+    }
+    let s = 0.5 * (low + high);
+    let ((x, y), (vx, vy)) = at(s);
+    Some(ClosestApproach {
+        time: t0 + s * h,
+        miss: x.hypot(y),
+        angle: (-vy).atan2(vx).to_degrees(),
+        closing_speed: vx.hypot(vy),
+    })
+}
@@ -0,0 +1,84 @@
+use crate::atmosphere::Atmosphere;
+use crate::scalar::Scalar;
+
+use serde::{Deserialize, Serialize};
+
+// Fractional loss of maximum lift at full contamination
+const LIFT_LOSS: f64 = 0.3;
+
+// Fractional rise in drag at full contamination
+const DRAG_RISE: f64 = 1.0;
+
+// Shift in the moment coefficient at full contamination, nose down
+const MOMENT_SHIFT: f64 = -0.02;
+
+// Freezing point of water, K
+const FREEZING: f64 = 273.15;
+
+// What starts ice building up on the surfaces
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IcingTrigger {
+    // From `start` seconds into the run onwards
+    Time { start: f64 },
+    // While flying through a cloud layer between `base` and `top`, m, where
+    // the air is below freezing
+    Cloud { base: f64, top: f64 },
+}
+
+// Ice accreting on every aerodynamic surface of a vehicle. Once triggered,
+// it builds up over `build_up` seconds to a contamination of `severity`,
+// from 0 for clean surfaces to 1 for the worst case, which costs 30% of the
+// maximum lift, doubles the drag, and pitches the surfaces nose down. Ice
+// doesn't shed, so out of the cloud it stays at what has built up.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Icing {
+    pub severity: f64,
+    pub trigger: IcingTrigger,
+    pub build_up: f64,
+    // Fraction of the full build-up reached so far
+    #[serde(default)]
+    pub accretion: f64,
+}
+
+impl Icing {
+
+    // Constructor, starting clean
+    pub fn new(severity: f64, trigger: IcingTrigger, build_up: f64) -> Icing {
+        Icing { severity, trigger, build_up, accretion: 0.0 }
+    }
+
+    // Whether ice is building up at `time` and `altitude` in `atmosphere`
+    pub fn icing(&self, time: f64, altitude: f64, atmosphere: &Atmosphere) -> bool {
+        match self.trigger {
+            IcingTrigger::Time { start } => time >= start,
+            IcingTrigger::Cloud { base, top } =>
+                (base..=top).contains(&altitude) && atmosphere.temperature(altitude) < FREEZING,
+        }
+    }
+
+    // Builds up ice over a step of `dt` seconds starting at `time` and
+    // `altitude` in `atmosphere`, and returns the contamination after it
+    pub fn step(&mut self, time: f64, altitude: f64, atmosphere: &Atmosphere, dt: f64) -> f64 {
+        if self.icing(time, altitude, atmosphere) {
+            self.accretion = (self.accretion + dt / self.build_up).min(1.0);
+        }
+        self.contamination()
+    }
+
+    // Gets the contamination of the surfaces, from 0 to 1
+    #[inline] pub fn contamination(&self) -> f64 {
+        self.severity.clamp(0.0, 1.0) * self.accretion
+    }
+}
+
+// Degrades a surface's lift, drag, and moment coefficients for a
+// contamination `ice`. Lift is held within the shrunken range of
+// `lift_range`, the least and greatest lift coefficients of the clean
+// section.
+pub fn degrade<S: Scalar>(ice: f64, lift_range: (f64, f64), (cl, cd, cm): (S, S, S)) -> (S, S, S) {
+    let scale = 1.0 - LIFT_LOSS * ice;
+    let (low, high) = (S::from_f64(lift_range.0 * scale), S::from_f64(lift_range.1 * scale));
+    let cl = if cl > high { high } else if cl < low { low } else { cl };
+    (cl, cd * S::from_f64(1.0 + DRAG_RISE * ice), cm + S::from_f64(MOMENT_SHIFT * ice))
+}
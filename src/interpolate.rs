@@ -1,42 +1,107 @@
-pub struct Linear<'a> {
-    // Define a data field that is a reference to a slice of tuples (f64, f64)
-    // The 'a lifetime parameter ensures that the reference remains valid for 
-    // the lifetime of the struct.
-    data: &'a Vec<(f64, f64)>,
+use crate::error::DyntermError;
+use crate::scalar::Scalar;
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "Points")]
+pub struct Linear {
+    // Define a data field that owns a vector of tuples (f64, f64), sorted 
+    // by the first element
+    data: Vec<(f64, f64)>,
+    // Least and greatest of the values, found once when the table's built
+    #[serde(skip)]
+    range: (f64, f64),
+}
+
+// The table as stored, the range worked out again on reading it
+#[derive(Deserialize)]
+struct Points {
+    data: Vec<(f64, f64)>,
+}
+
+impl From<Points> for Linear {
+    fn from(points: Points) -> Linear {
+        Linear::new(points.data)
+    }
 }
 
 // Implement methods for the `Linear` struct.
-impl<'a> Linear<'a> {
+impl Linear {
 
     // Constructor, takes a vector of f64 tuples
-    pub const fn new(data: &'a Vec<(f64, f64)>) -> Self {
-        Linear { data }
+    pub fn new(data: Vec<(f64, f64)>) -> Self {
+        let range = data.iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), p| (low.min(p.1), high.max(p.1)));
+        Linear { data, range }
+    }
+
+    // Returns the points, sorted by x
+    #[inline] pub fn points(&self) -> &[(f64, f64)] {
+        &self.data
+    }
+
+    // Returns the least and greatest values
+    #[inline] pub fn range(&self) -> (f64, f64) {
+        self.range
     }
 
     // Define a public method named `interpolate` that takes 
-    // an f64 value `x` and returns an Option<f64>. The `Option`
-    // type is used to represent a value that might be missing 
-    // (None) or available (Some(value)).
-    pub fn interpolate(&self, x: f64) -> f64 {
+    // a value `x` and returns the table's value there. Any scalar can be
+    // looked up, the table itself stays f64. Off either end, the end
+    // point's value is held, and a NaN `x` or an empty table gives NaN,
+    // so a lookup never panics; `try_interpolate` reports those instead.
+    pub fn interpolate<S: Scalar>(&self, x: S) -> S {
+        match self.try_interpolate(x) {
+            Ok(y) => y,
+            Err(_) => {
+                let key = x.to_f64();
+                match (self.data.first(), self.data.last()) {
+                    (Some(first), _) if key < first.0 => S::from_f64(first.1),
+                    (_, Some(last)) if key > last.0 => S::from_f64(last.1),
+                    _ => S::from_f64(f64::NAN),
+                }
+            }
+        }
+    }
+
+    // As `interpolate`, but an `x` outside the table's points, or a NaN, is
+    // an error
+    pub fn try_interpolate<S: Scalar>(&self, x: S) -> Result<S, DyntermError> {
+        let key = x.to_f64();
+        let (min, max) = match (self.data.first(), self.data.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => (f64::NAN, f64::NAN),
+        };
+        if !(min..=max).contains(&key) {
+            return Err(DyntermError::Domain { x: key, min, max });
+        }
 
         // Find the indices of the two points that will be used 
         // for interpolation. Use the `binary_search_by` method, 
         // which returns a `Result` type. In case the exact value
         // is found, the `Ok` variant contains the index of the value.
         // Otherwise, the `Err` variant contains the index where 
-        // the value would be inserted.
-        let i = self.data.binary_search_by(|probe| probe.0.partial_cmp(&x).unwrap())
+        // the value would be inserted. The key is in range, so it isn't
+        // NaN to compare with.
+        let i = self.data.binary_search_by(|probe| probe.0.partial_cmp(&key).unwrap_or(Ordering::Less))
             // If the exact value is not found, subtract 1 from the index.
-            .unwrap_or_else(|i| i - 1); 
-        let j = i + 1;
+            .unwrap_or_else(|i| i.saturating_sub(1)); 
+        // The last point has no point after it, and a lone point no
+        // neighbour at all
+        let Some(&(x_j, y_j)) = self.data.get(i + 1) else {
+            return Ok(S::from_f64(self.data[i].1));
+        };
 
         // Interpolate between the two points.
         // Calculate the interpolation factor `t` based on the 
         // x-values of the two points.
-        let t = (x - self.data[i].0) / (self.data[j].0 - self.data[i].0);
+        let t = (x - S::from_f64(self.data[i].0)) / S::from_f64(x_j - self.data[i].0);
 
         // Calculate the interpolated y-value based on the y-values 
         // of the two points and the interpolation factor `t`.
-        self.data[i].1 + t * (self.data[j].1 - self.data[i].1)
+        Ok(S::from_f64(self.data[i].1) + t * S::from_f64(y_j - self.data[i].1))
     }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+use crate::control::Command;
+use crate::vec::Angle;
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+// Elevator deflection at full stick, degrees
+pub const MAX_ELEVATOR: f64 = 10.0;
+
+// Sticks inside this band read as centred
+const DEADZONE: f64 = 0.05;
+
+// A button pressed to pause, step, or rewind the run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Press {
+    Pause,
+    Step,
+    Rewind,
+}
+
+// Manual control from the first connected game controller. The left stick
+// pitches (pull back to pitch up) and the right trigger is the throttle.
+// With no controller connected the elevator is centred and thrust is left
+// to the autothrottle. Start pauses and resumes the run, the bottom face
+// button steps it while paused, and the left bumper rewinds it.
+pub struct Joystick {
+    gilrs: Gilrs,
+    // Presses since they were last taken
+    presses: Vec<Press>,
+}
+
+impl Joystick {
+
+    // Constructor, opens the platform's gamepad backend
+    pub fn new() -> Result<Joystick, Box<dyn std::error::Error>> {
+        let gilrs = Gilrs::new()?;
+        for (_, gamepad) in gilrs.gamepads() {
+            tracing::info!("Using controller '{}'", gamepad.name());
+        }
+        Ok(Joystick { gilrs, presses: Vec::new() })
+    }
+
+    // Reads the current stick and trigger positions
+    pub fn poll(&mut self) -> Command {
+
+        // Drain the event queue so the cached gamepad state is current
+        self.poll_buttons();
+
+        let Some((_, gamepad)) = self.gilrs.gamepads().next() else {
+            return Command { elevator: Angle::from_degrees(0.0), throttle: None, speedbrake: None, flaps: None, rating: None, thrust_angle: None };
+        };
+
+        let mut stick = gamepad.value(Axis::LeftStickY) as f64;
+        if stick.abs() < DEADZONE { stick = 0.0; }
+        let throttle = gamepad.button_data(Button::RightTrigger2)
+            .map(|data| data.value() as f64);
+
+        // Stick back reads negative, and negative elevator incidence pitches
+        // the nose up
+        Command {
+            elevator: Angle::from_degrees(stick * MAX_ELEVATOR),
+            throttle,
+            speedbrake: None,
+            flaps: None,
+            rating: None,
+            thrust_angle: None,
+        }
+    }
+
+    // Drains the event queue, keeping any presses, without reading the
+    // sticks, as while the run is paused
+    pub fn poll_buttons(&mut self) {
+        while let Some(event) = self.gilrs.next_event() {
+            let press = match event.event {
+                EventType::ButtonPressed(Button::Start, _) => Press::Pause,
+                EventType::ButtonPressed(Button::South, _) => Press::Step,
+                EventType::ButtonPressed(Button::LeftTrigger, _) => Press::Rewind,
+                _ => continue,
+            };
+            self.presses.push(press);
+        }
+    }
+
+    // Takes the presses since they were last taken
+    pub fn presses(&mut self) -> Vec<Press> {
+        std::mem::take(&mut self.presses)
+    }
+}
@@ -0,0 +1,105 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// Core physics, which only needs `alloc`
+pub mod error;
+pub mod scalar;
+pub mod vec;
+pub mod rk4;
+pub mod interpolate;
+pub mod aero;
+pub mod util;
+pub mod atmosphere;
+pub mod gravity;
+pub mod earth;
+pub mod planet;
+pub mod updraft;
+pub mod wind;
+pub mod propeller;
+pub mod electric;
+pub mod engine;
+pub mod retro;
+pub mod icing;
+pub mod derivatives;
+pub mod lifters;
+pub mod config;
+pub mod trim;
+#[cfg(feature = "std")]
+pub mod point_mass;
+#[cfg(feature = "std")]
+pub mod six_dof;
+#[cfg(feature = "std")]
+pub mod sim;
+#[cfg(feature = "std")]
+pub mod fleet;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod scenario;
+#[cfg(feature = "std")]
+pub mod summary;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod recorder;
+#[cfg(feature = "std")]
+pub mod control;
+#[cfg(feature = "std")]
+pub mod pilot;
+#[cfg(feature = "std")]
+pub mod guidance;
+#[cfg(feature = "std")]
+pub mod playback;
+#[cfg(feature = "std")]
+pub mod sensors;
+#[cfg(feature = "std")]
+pub mod estimator;
+#[cfg(feature = "parallel")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod dispersion;
+#[cfg(feature = "std")]
+pub mod geo;
+#[cfg(feature = "std")]
+pub mod verify;
+#[cfg(feature = "std")]
+pub mod presets;
+#[cfg(feature = "std")]
+pub mod ballistic;
+#[cfg(feature = "std")]
+pub mod sounding;
+
+// Inputs and outputs
+#[cfg(feature = "std")]
+pub mod logging;
+#[cfg(feature = "std")]
+pub mod pacing;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod cockpit;
+#[cfg(feature = "std")]
+pub mod flightgear;
+#[cfg(feature = "std")]
+pub mod mavlink;
+#[cfg(feature = "std")]
+pub mod sil;
+#[cfg(feature = "std")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "plot")]
+pub mod animate;
+#[cfg(feature = "joystick")]
+pub mod joystick;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "uom")]
+pub mod units;
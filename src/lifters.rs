@@ -0,0 +1,173 @@
+use crate::aero::{Airdata, ForceModel, Forces, Surfaces, Vehicle};
+use crate::atmosphere::RHO0;
+use crate::scalar::Scalar;
+use crate::vec::{Kinematics, Rotation2, Vector};
+
+use core::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+// Drag coefficient of a rotor descending straight down through its own
+// wake, on the disc area
+fn descending_disc() -> f64 {
+    1.2
+}
+
+// Lift curve slope of a typical ram-air canopy, per radian
+fn canopy_slope() -> f64 {
+    3.0
+}
+
+// Mast height of a small rotor, m
+fn short_mast() -> f64 {
+    1.0
+}
+
+// An autorotating rotor of `diameter`, m, on a mast `mast` m above the body
+// axis, in place of the wing, with the elevator still working as the tail
+// that keeps it pointed into the flow. Air coming up through the disc turns
+// it and holds it up. Edgewise, by Glauert's momentum theory, the rotor
+// pushes the air through itself down at the speed it's descending through
+// the disc; straight down it drags like a disc, with `drag_coefficient` on
+// the disc area, and between the two the pair are added in quadrature. The
+// disc is square to the mast, and its profile losses drag with
+// `profile_drag` on the disc area. With air coming down through the disc it
+// gives nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rotor {
+    pub diameter: f64,
+    #[serde(default = "descending_disc")]
+    pub drag_coefficient: f64,
+    #[serde(default)]
+    pub profile_drag: f64,
+    #[serde(default = "short_mast")]
+    pub mast: f64,
+}
+
+impl Rotor {
+
+    #[inline] pub fn disc_area(&self) -> f64 {
+        PI * self.diameter * self.diameter / 4.0
+    }
+
+    // Gets the thrust, N, along the disc's axis with air of `density`,
+    // kg/m^3, coming `through` the disc from below and `edgewise` across it
+    pub fn thrust<S: Scalar>(&self, density: S, through: S, edgewise: S) -> S {
+        if through <= S::ZERO { return S::ZERO; }
+        let glauert = S::from_f64(2.0) * edgewise;
+        let disc = S::from_f64(self.drag_coefficient / 2.0) * through;
+        density * S::from_f64(self.disc_area()) * through * (glauert * glauert + disc * disc).sqrt()
+    }
+}
+
+impl<S: Scalar> ForceModel<S> for Rotor {
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S> {
+
+        let hub = Vector::new(vehicle.cg, S::from_f64(self.mast)).to_world(k.angle());
+        let (velocity, qbar) = local_flow(air, hub, dk.angular_velocity());
+
+        let axis = k.angle().unit();
+        let normal = Rotation2::quarter() * axis;
+        let (through, edgewise) = (-velocity.dot(normal), velocity.dot(axis));
+        let thrust = normal * self.thrust(air.sigma * S::from_f64(RHO0), through, edgewise.abs());
+        let drag = -velocity.unit() * (S::from_f64(self.profile_drag * self.disc_area() * RHO0) * qbar);
+
+        // The tail as the surfaces have it, with the rotor for the wing
+        Forces {
+            wing_lift: thrust,
+            wing_drag: drag,
+            wing_moment: S::ZERO,
+            wing_arm_moment: hub.cross(thrust + drag),
+            ..Surfaces.loads(vehicle, k, dk, air)
+        }
+    }
+}
+
+// A ram-air parafoil of `area`, m^2, `line_length` m above the body, rigged
+// to glide at `trim_speed`, m/s equivalent, with the vehicle hanging level
+// under it. Its polar is CD = CD0 + k CL^2, and its lift rises at
+// `lift_slope` per radian with angle of attack about the trim, never below
+// nothing, as the canopy would collapse first. The elevator setting adds to
+// its angle of attack, as symmetric brakes would, slowing it down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parafoil {
+    pub area: f64,
+    pub trim_speed: f64,
+    pub cd0: f64,
+    pub k: f64,
+    #[serde(default = "canopy_slope")]
+    pub lift_slope: f64,
+    pub line_length: f64,
+}
+
+impl Parafoil {
+
+    // Gets the lift coefficient and angle of attack, radians, it trims at
+    // holding up `weight`, N
+    pub fn trim(&self, weight: f64) -> (f64, f64) {
+        let cl = weight / (0.5 * RHO0 * self.trim_speed * self.trim_speed * self.area);
+        (cl, libm::atan((self.cd0 + self.k * cl * cl) / cl))
+    }
+
+    // Gets the lift and drag coefficients at angle of attack `alpha`,
+    // radians, rigged for `weight`, N
+    pub fn coefficients<S: Scalar>(&self, alpha: S, weight: f64) -> (S, S) {
+        let (trim_cl, trim_alpha) = self.trim(weight);
+        let cl = (S::from_f64(trim_cl) + S::from_f64(self.lift_slope) * (alpha - S::from_f64(trim_alpha))).max(S::ZERO);
+        (cl, S::from_f64(self.cd0) + S::from_f64(self.k) * cl * cl)
+    }
+}
+
+impl<S: Scalar> ForceModel<S> for Parafoil {
+    fn loads<M: ForceModel<S>>(
+        &self,
+        vehicle: &Vehicle<S, M>,
+        k: &Kinematics<S>,
+        dk: &Kinematics<S>,
+        air: &Airdata<S>) -> Forces<S> {
+
+        let canopy = Vector::new(vehicle.cg, S::from_f64(self.line_length)).to_world(k.angle());
+        let (velocity, qbar) = local_flow(air, canopy, dk.angular_velocity());
+
+        let weight = (vehicle.mass * vehicle.gravity.at(k.y())).to_f64();
+        let alpha = k.angle().signed_diff(velocity.orientation()) + vehicle.elev.pitch().signed_rad();
+        let (cl, cd) = self.coefficients(alpha, weight);
+
+        let flow = velocity.unit();
+        let q = S::from_f64(self.area * RHO0) * qbar;
+        lifted((Rotation2::quarter() * flow) * (cl * q), -flow * (cd * q), canopy)
+    }
+}
+
+// Velocity through the air and dynamic pressure, as `Airdata` takes it, of
+// a lifter `at` from the centre of mass, swung round as the body pitches at
+// `rate`, so a lifter well above the body damps the pitching
+fn local_flow<S: Scalar>(air: &Airdata<S>, at: Vector<S>, rate: S) -> (Vector<S>, S) {
+    let velocity = air.velocity + Vector::new(-at.y(), at.x()) * rate;
+    (velocity, S::from_f64(0.5) * air.sigma * velocity.magnitude().powi(2))
+}
+
+// Loads of a lifter whose `lift` and `drag` act at `at` from the centre of
+// mass, put on the wing
+fn lifted<S: Scalar>(lift: Vector<S>, drag: Vector<S>, at: Vector<S>) -> Forces<S> {
+    Forces {
+        wing_lift: lift,
+        wing_drag: drag,
+        elevator_lift: Vector::zero(),
+        elevator_drag: Vector::zero(),
+        speedbrake_drag: Vector::zero(),
+        thrust: Vector::zero(),
+        weight: Vector::zero(),
+        external: Vector::zero(),
+        external_moment: S::ZERO,
+        wing_moment: S::ZERO,
+        elevator_moment: S::ZERO,
+        tail_moment: S::ZERO,
+        wing_arm_moment: at.cross(lift + drag),
+        damping_moment: S::ZERO,
+    }
+}
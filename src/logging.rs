@@ -0,0 +1,193 @@
+use serde_json::{json, Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+// How log lines are written: as plain text, each event's message alone,
+// as the simulator has always printed them, or as a JSON object per line
+// with the level, the spans it's in, and every field, for log collectors
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LogFormat, String> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format '{}', expected text or json", other)),
+        }
+    }
+}
+
+thread_local! {
+    // Spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+// A span's name and the fields it was opened with
+struct SpanData {
+    name: &'static str,
+    fields: Map<String, Value>,
+}
+
+// Writes tracing events at or above `level` to the terminal, errors and
+// warnings to stderr and the rest to stdout. Per-step state is logged at
+// TRACE, events along the run at INFO, and failures at ERROR.
+pub struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+}
+
+impl Logger {
+
+    // Constructor
+    pub fn new(level: LevelFilter, format: LogFormat) -> Logger {
+        Logger { level, format, next_id: AtomicU64::new(1), spans: Mutex::new(HashMap::new()) }
+    }
+
+    // Makes this the logger for the whole process, once
+    pub fn install(self) {
+        // Only fails if one is already installed, which is left be
+        let _ = tracing::subscriber::set_global_default(self);
+    }
+
+    // Helper function
+    fn line(&self, event: &Event, fields: Map<String, Value>) -> String {
+        match self.format {
+            LogFormat::Text => match fields.get("message") {
+                Some(Value::String(message)) => message.clone(),
+                _ => fields.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" "),
+            },
+            LogFormat::Json => {
+                let spans = self.spans.lock().map(|spans| ENTERED.with(|entered| entered.borrow().iter()
+                    .filter_map(|id| spans.get(id))
+                    .map(|span| json!({ "name": span.name, "fields": span.fields }))
+                    .collect::<Vec<_>>()))
+                    .unwrap_or_default();
+                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |t| t.as_secs_f64());
+                json!({
+                    "timestamp": timestamp,
+                    "level": event.metadata().level().as_str(),
+                    "target": event.metadata().target(),
+                    "spans": spans,
+                    "fields": fields,
+                }).to_string()
+            }
+        }
+    }
+}
+
+impl Subscriber for Logger {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.enabled(metadata) { Interest::always() } else { Interest::never() }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        *metadata.level() <= self.level
+    }
+
+    fn new_span(&self, attributes: &Attributes) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.insert(id, SpanData { name: attributes.metadata().name(), fields: fields.0 });
+        }
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Ok(mut spans) = self.spans.lock() {
+            if let Some(span) = spans.get_mut(&span.into_u64()) {
+                span.fields.extend(fields.0);
+            }
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let line = self.line(event, fields.0);
+
+        // Nowhere left to report a failed write to
+        let _ = match *event.metadata().level() {
+            Level::ERROR | Level::WARN => writeln!(io::stderr().lock(), "{}", line),
+            _ => writeln!(io::stdout().lock(), "{}", line),
+        };
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|&id| id == span.into_u64()) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.remove(&span.into_u64());
+        }
+        true
+    }
+}
+
+// Collects an event's or a span's fields as JSON values
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_owned(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), json!(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_owned(), json!(format!("{:?}", value)));
+    }
+}
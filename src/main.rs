@@ -1,261 +1,1927 @@
 // Local imports
-mod rk4;
-mod aero; 
-mod vec;
-mod interpolate;
-mod util;
-use crate::aero::{Aerofoil, Vehicle};
-use crate::vec::*;
-use crate::interpolate::Linear;
-use crate::util::*;
-
-use std::f64::consts::PI;
+mod cli;
+use dynterm::aero::Vehicle;
+use dynterm::control::{Autopilot, Command, Sas};
+use dynterm::gravity::G0;
+use dynterm::recorder::{LogRate, Recorder, Sample};
+use dynterm::scenario::{Checkpoint, Scenario};
+use dynterm::sensors::{specific_force, Readings, Sensors};
+use dynterm::estimator::Estimator;
+use dynterm::events::{Departure, Event, EventKind, LoadMonitor, Placard, StallWarning};
+use dynterm::guidance::Guidance;
+use dynterm::pilot::Pilot;
+use dynterm::playback::Playback;
+use dynterm::summary::Summary;
+use dynterm::report::Report;
+use dynterm::geo::{geodetic, write_track, Origin};
+use dynterm::export::write_channels;
+#[cfg(feature = "joystick")]
+use dynterm::sim::{Simulation, Transport};
+#[cfg(feature = "joystick")]
+use dynterm::joystick::{Joystick, Press, MAX_ELEVATOR};
+#[cfg(feature = "joystick")]
+use dynterm::cockpit::Cockpit;
+use dynterm::vec::*;
+use dynterm::util::*;
+use dynterm::animate::animate_trajectory;
+use dynterm::flightgear::FlightGear;
+use dynterm::mavlink::Mavlink;
+use dynterm::pacing::Pacer;
+use dynterm::sil::Sil;
+use crate::cli::{Mode, Options};
+use dynterm::logging::Logger;
+use dynterm::progress::Progress;
+use std::io::IsTerminal;
+use tracing::level_filters::LevelFilter;
+use tracing::{error, info, info_span, trace, warn};
 
 // Timestep values
 const MAX_SECONDS: usize = 180;
 const STEPS_PER_SECOND: usize = 100;
 const MAX_INCREMENTS: usize =  MAX_SECONDS * STEPS_PER_SECOND;
 
+// How far back a flight from the game controller can be rewound, and how
+// often its state is kept for it, s
+#[cfg(feature = "joystick")]
+const REWIND_SPAN: f64 = 60.0;
+#[cfg(feature = "joystick")]
+const REWIND_INTERVAL: f64 = 0.1;
+
 fn main() {
 
-    // Set up aero coeffs
-    let cl_0012_data: Vec<(f64, f64)> = 
-        parse_string_as_csv(include_str!("../data/lift.csv"));
-    let cd_0012_data: Vec<(f64, f64)> = 
-        parse_string_as_csv(include_str!("../data/drag.csv"));
-    let cm_0012_data: Vec<(f64, f64)> = 
-        parse_string_as_csv(include_str!("../data/moment.csv"));
-
-    // Make aero interpolation models
-    let cl: Linear = Linear::new(&cl_0012_data);
-    let cd: Linear = Linear::new(&cd_0012_data);
-    let cm: Linear = Linear::new(&cm_0012_data);
-
-    // Define the vehicle
-    let mut vehicle: Vehicle = Vehicle::new(
-        100_000.0,
-        46.6,
-        Kinematics::new(
-            Vector::new(0.0, 7_300.0), 
-            Angle::from_degrees(-45.5)
-        ),
-        Kinematics::new_raw(
-            Vector::from_degrees(280.0, -45.0), 
-            1.4_f64.to_radians()
-        ),
-        Aerofoil::new(
-            280.0, 
-            8.0, 
-            Angle::from_degrees(0.0), 
-            &cl, &cd, &cm
-        ),
-        Aerofoil::new(
-            40.0, 
-            4.0, 
-            Angle::from_degrees(0.0),
-            &cl, &cd, &cm
-        ),
-        280_000.0
-    );
-
-    // Set up our record of positions
-    let mut data = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-
-    // Set up other trackers
-    let mut aoa = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut om = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut dx = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut dy = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut re = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut gs = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut th = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut ddx = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut ddy = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut ddn = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-    let mut ddt = vec![(f64::NAN, f64::NAN, f64::NAN); MAX_INCREMENTS];
-
-    // For finding acceleration
-    let mut old_motion = vehicle.motion;
+    let options = Options::from_args();
+    Logger::new(options.log_level, options.log_format).install();
+    let _run = info_span!("run", mode = ?options.mode).entered();
+
+    // Serve simulations instead of running one, if asked to
+    if let Mode::Serve(addr) = &options.mode {
+        #[cfg(feature = "grpc")]
+        let result = dynterm::grpc::serve(addr);
+        #[cfg(not(feature = "grpc"))]
+        let result: Result<(), Box<dyn std::error::Error>> =
+            Err("dynterm was built without the grpc feature".into());
+
+        if let Err(e) = result {
+            error!("Error serving on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match options.mode {
+        Mode::Verify { bless } => return verify(bless),
+        Mode::Sweep => return sweep(&options),
+        Mode::CgSweep => return cg_sweep(&options),
+        Mode::MonteCarlo { point_mass } => return monte_carlo(&options, point_mass),
+        Mode::Compare { ref recordings } => return compare(recordings, &options),
+        Mode::PointMass => return run_point_mass(&options),
+        Mode::SixDof => return run_six_dof(&options),
+        _ => {}
+    }
+
+    // Set up the scenario, or pick up a simulation and the scenario it was
+    // flying from a checkpoint
+    let (mut sim, scenario) = match &options.resume {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(path).unwrap_or_else(|e| {
+                error!("Error resuming from {}: {}", path, e);
+                std::process::exit(1);
+            });
+            (checkpoint.simulation, checkpoint.scenario)
+        }
+        None => {
+            let scenario = load_scenario(&options);
+            (scenario.build(), scenario)
+        }
+    };
+
+    // Connect to FlightGear, if asked to
+    let flightgear = options.fg_host.as_deref().map(|host| 
+        FlightGear::connect(host, scenario.origin).unwrap_or_else(|e| {
+            error!("Error connecting to FlightGear at {}: {}", host, e);
+            std::process::exit(1);
+        }));
+
+    // Connect to the autopilot, if asked to
+    let mut mavlink = options.mavlink.as_deref().map(|host|
+        Mavlink::connect(host, scenario.origin).unwrap_or_else(|e| {
+            error!("Error connecting to MAVLink at {}: {}", host, e);
+            std::process::exit(1);
+        }));
+
+    // Wait for an external controller, if asked to
+    let mut sil = options.sil.as_deref().map(|addr|
+        Sil::accept(addr).unwrap_or_else(|e| {
+            error!("Error accepting controller on {}: {}", addr, e);
+            std::process::exit(1);
+        }));
+
+    // Open the game controller, if asked to
+    #[cfg(feature = "joystick")]
+    let mut joystick = options.joystick.then(||
+        dynterm::joystick::Joystick::new().unwrap_or_else(|e| {
+            error!("Error opening game controller: {}", e);
+            std::process::exit(1);
+        }));
+    #[cfg(not(feature = "joystick"))]
+    if options.joystick {
+        error!("dynterm was built without the joystick feature");
+        std::process::exit(1);
+    }
+
+    // Hold the controls where they trim if nothing else is flying, if the
+    // run started trimmed
+    let trimmed = scenario.trimmed();
+
+    // Keep the scenario to save alongside the simulation, if asked to
+    let saved = options.checkpoint.is_some().then(|| scenario.clone());
+
+    let Scenario { sensors, estimator, pilot, script, playback, guidance, autopilot, sas, departure, stall_warning, load_limits, placard, speedbrake_schedule, flap_schedule, rating_schedule, thrust_angle_schedule, origin, .. } = scenario;
+    if estimator.is_some() && sensors.is_none() {
+        error!("The estimator needs the scenario's vehicle to carry sensors");
+        std::process::exit(1);
+    }
+    let mut estimator = estimator.zip(sensors).map(|(spec, sensors)| Estimator::new(spec, sensors, sim.vehicle.gravity, sim.vehicle.atmosphere.clone()));
+    let mut sensors = sensors.map(|spec| Sensors::new(spec, &sim.rng));
+    let mut pilot = pilot.map(Pilot::new);
+    let mut guidance = guidance.map(Guidance::new);
+    let mut autopilot = autopilot.map(Autopilot::new);
+    let mut sas = sas.map(Sas::new);
+    let mut departure = departure.map(Departure::new);
+    let mut stall_warning = stall_warning.map(StallWarning::new);
+    let mut loads = load_limits.map(LoadMonitor::new);
+    let mut placard = placard.map(Placard::new);
+    let mut events = Vec::new();
+    let mut vectored = false;
+    let playback = playback.map(|path| Playback::load(&path).unwrap_or_else(|e| {
+        error!("Error reading control history {}: {}", path, e);
+        std::process::exit(1);
+    }));
+
+    // Load the scripted control law, if there is one
+    #[cfg(feature = "scripting")]
+    let mut script = script.map(|path|
+        dynterm::script::Script::load(&path).unwrap_or_else(|e| {
+            error!("Error loading script {}: {}", path, e);
+            std::process::exit(1);
+        }));
+    #[cfg(not(feature = "scripting"))]
+    if script.is_some() {
+        error!("dynterm was built without the scripting feature");
+        std::process::exit(1);
+    }
+    let mut checkpoint = options.checkpoint.as_deref().zip(saved.as_ref());
+
+    // Set up our record of positions and other trackers
+    let mut recorder = open_recorder(&options);
+    let mut frames: Vec<(f64, f64, f64, f64)> = Vec::new();
+
+    // Optionally pace the loop to wall-clock time
+    let pacer = options.paced().then(|| Pacer::new(options.speed));
+
+    // Record at the logging rate, whatever the step rate
+    let log_rate = options.log_rate.min(STEPS_PER_SECOND as f64);
+    let mut log = LogRate::new(sim.time, log_rate);
+    let start = sim.time;
+
+    // Peak dynamic pressure, Pa, and when
+    let mut max_q = (0.0, 0.0);
+
+    // Show how far through the run we are
+    let progress = flight_progress(&options);
+
+    // Pause, step, and rewind from the game controller
+    #[cfg(feature = "joystick")]
+    let mut transport = joystick.is_some().then(|| Transport::new(REWIND_SPAN, REWIND_INTERVAL));
+
+    // Show the state live while flying by hand
+    #[cfg(feature = "joystick")]
+    let mut cockpit = Cockpit::new(MAX_ELEVATOR, joystick.is_some() && show_progress(&options));
 
     // Loop
-    for i in 0..MAX_INCREMENTS {
+    for _ in 0..MAX_INCREMENTS {
 
-        let second = i as f64 / STEPS_PER_SECOND as f64;
+        #[cfg(feature = "joystick")]
+        if let (Some(joystick), Some(transport)) = (&mut joystick, &mut transport) {
+            if drive_transport(joystick, transport, &mut cockpit, &mut sim, options.rewind) {
+                if let Some(pacer) = &pacer { pacer.resync(sim.time); }
+            }
+        }
+
+        let second = sim.time;
+        if second >= MAX_SECONDS as f64 { break; }
+
+        // The controller flies on the estimate if it's asked to and there's
+        // one yet, or on the truth
+        let observed = estimator.as_ref()
+            .filter(|estimator| estimator.spec.feedback)
+            .and_then(|estimator| estimator.estimate(second))
+            .map(|estimate| estimate.state());
         
-        // Pull up?
-        vehicle.elev.set_pitch(
-            Angle::from_degrees(
-                if vehicle.position.y() < 7_300.0 { -3.0} else { 0.0 }));
+        // Take commands from the external controller...
+        let mut command = None;
+        if let Some(sil) = &mut sil {
+            match sil.exchange(&observed.unwrap_or_else(|| sim.state())) {
+                Ok(c) => command = Some(c),
+                Err(e) => {
+                    error!("Controller error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        // ...or the game controller...
+        #[cfg(feature = "joystick")]
+        if let Some(joystick) = &mut joystick {
+            command = Some(joystick.poll());
+        }
+
+        // ...or the script...
+        #[cfg(feature = "scripting")]
+        if let (None, Some(script)) = (command, &mut script) {
+            match script.command(&observed.unwrap_or_else(|| sim.state())) {
+                Ok(c) => command = Some(c),
+                Err(e) => {
+                    error!("Script error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        // ...or the control history...
+        if let (None, Some(playback)) = (command, &playback) {
+            command = Some(playback.at(second));
+        }
+
+        // ...or the guidance...
+        if let (None, Some(guidance)) = (command, &mut guidance) {
+            let state = observed.unwrap_or_else(|| sim.state());
+            command = Some(guidance.command(&state, &sim.vehicle.airdata()));
+        }
+
+        // ...or the autopilot...
+        if let (None, Some(autopilot)) = (command, &mut autopilot) {
+            let state = observed.unwrap_or_else(|| sim.state());
+            command = Some(autopilot.command(second, state.pitch, state.pitch_rate, &sim.vehicle.airdata()));
+        }
+
+        // ...or the pilot...
+        if let (None, Some(pilot)) = (command, &mut pilot) {
+            let pitch = observed.map_or_else(|| sim.state().pitch, |state| state.pitch);
+            command = Some(pilot.command(second, pitch));
+        }
+
+        // ...or hold the trim, or pull up?
+        let command = command.unwrap_or_else(|| match (trimmed, observed) {
+            (Some(trimmed), _) => trimmed,
+            (None, Some(state)) => Command::pull_up_at(state.y),
+            (None, None) => Command::pull_up(&sim.vehicle),
+        });
+
+        // Open the speedbrake and set the flaps, engine rating, and thrust
+        // line on schedule, unless the command sets them
+        let command = Command {
+            speedbrake: command.speedbrake.or_else(|| scheduled(&speedbrake_schedule, second)),
+            flaps: command.flaps.or_else(|| scheduled(&flap_schedule, second)),
+            rating: command.rating.or_else(|| scheduled(&rating_schedule, second)),
+            thrust_angle: command.thrust_angle
+                .or_else(|| scheduled(&thrust_angle_schedule, second).map(Angle::from_degrees)),
+            ..command
+        };
+
+        // Damp whatever's flying, if there's a damper
+        match &mut sas {
+            Some(sas) => {
+                let pitch_rate = observed.map_or_else(|| sim.state().pitch_rate, |state| state.pitch_rate);
+                sas.augment(second, pitch_rate, command).apply(&mut sim.vehicle);
+            }
+            None => command.apply(&mut sim.vehicle),
+        }
 
         // Iterate using RK4
-        vehicle.apply_dynamics(1.0 / STEPS_PER_SECOND as f64, 50);
+        let charged = sim.vehicle.electric.as_ref().is_some_and(|electric| !electric.battery.depleted());
+        let lit = sim.vehicle.engine.as_ref().is_some_and(|engine| !engine.flamed_out());
+        let braking = sim.vehicle.retro.as_ref().is_some_and(|retro| retro.thrust_now() > 0.0);
+        sim.step(1.0 / STEPS_PER_SECOND as f64);
+        #[cfg(feature = "joystick")]
+        if let Some(transport) = &mut transport {
+            transport.record(&sim);
+            cockpit.show(&sim, false);
+        }
+
+        // Acceleration at the new state, straight from the dynamics
+        let accel: Kinematics = sim.vehicle.acceleration();
+
+        let q = sim.vehicle.airdata().qbar * RHO0;
+        if q > max_q.1 { max_q = (sim.time, q); }
+
+        // Watch for departures, ending the run on one if asked to
+        if let Some(departure) = &mut departure {
+            let aoa = sim.vehicle.aoa().nice_deg();
+            if let Some(event) = departure.check(sim.time, aoa, sim.vehicle.motion.angular_velocity_deg()) {
+                log_event(&event);
+                events.push(event);
+                if departure.spec.terminate && event.kind != EventKind::Recovered { break; }
+            }
+        }
+
+        // Warn of the stall, marking each warning on the margin plot
+        if let Some(stall_warning) = &mut stall_warning {
+            let margin = sim.vehicle.stall_margin();
+            if let Some(event) = stall_warning.check(sim.time, margin) {
+                log_event(&event);
+                recorder.record("stall_events", (sim.time, margin, 90.0));
+                events.push(event);
+            }
+        }
+
+        // Hold the structure to its limit loads, breaking it up past them if
+        // it can fail
+        if let Some(loads) = &mut loads {
+            let load = specific_force(&sim.vehicle, accel.vec).y() / G0;
+            if let Some(event) = loads.check(sim.time, load) {
+                log_event(&event);
+                recorder.record("load_events", (sim.time, load, 90.0));
+                events.push(event);
+            }
+            if loads.broken() { break; }
+        }
+
+        // Hold the speed to the placard, ending the run past it if asked to
+        if let Some(placard) = &mut placard {
+            let air = sim.vehicle.airdata();
+            if let Some(event) = placard.check(sim.time, air.qbar * RHO0, air.tas) {
+                log_event(&event);
+                recorder.record("placard_events", (sim.time, placard.fraction(air.qbar * RHO0, air.tas), 90.0));
+                events.push(event);
+                if placard.spec.terminate && event.kind == EventKind::Overspeed { break; }
+            }
+        }
+
+        // Warn when the battery runs down, ending the run there if asked to
+        if let Some(electric) = &sim.vehicle.electric {
+            if charged && electric.battery.depleted() {
+                let event = Event { time: sim.time, kind: EventKind::BatteryDepleted };
+                log_event(&event);
+                recorder.record("soc_events", (sim.time, electric.battery.charge, 90.0));
+                events.push(event);
+                if electric.terminate { break; }
+            }
+        }
+
+        // Mark the engine flaming out as the fuel runs out
+        if lit && sim.vehicle.engine.as_ref().is_some_and(|engine| engine.flamed_out()) {
+            let event = Event { time: sim.time, kind: EventKind::FlameOut };
+            log_event(&event);
+            events.push(event);
+        }
+
+        // Watch for the guided vehicle reaching its target
+        if let Some(guidance) = &mut guidance {
+            guidance.observe(&sim.state());
+        }
+
+        // Mark the retro-rocket lighting and cutting
+        if let Some(retro) = &sim.vehicle.retro {
+            let kind = match (braking, retro.thrust_now() > 0.0) {
+                (false, true) => Some(EventKind::RetroIgnition),
+                (true, false) => Some(EventKind::RetroCutoff),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                let event = Event { time: sim.time, kind };
+                log_event(&event);
+                events.push(event);
+            }
+        }
+
+        // Measure it as the sensors would
+        if let Some(sensors) = &mut sensors {
+            let readings = sensors.sample(second, &sim.vehicle, accel.vec);
+            record_readings(&mut recorder, second, &readings);
+            if let Some(estimator) = &mut estimator {
+                estimator.update(&readings);
+            }
+        }
 
         // Perform logging & plotting
-        let datum = (
-            vehicle.position.x(), 
-            vehicle.position.y(), 
-            vehicle.aoa().nice_deg().abs()
-        );
-
-        println!("{:.2}: {:.3?}", second, datum);
-
-        data[i] = datum;
-        aoa[i] = (second, vehicle.aoa().nice_deg(), 0.0);
-        om[i] = (second, vehicle.motion.ang, 0.0);
-        dx[i] = (second, vehicle.motion.x(), 0.0);
-        dy[i] = (second, vehicle.motion.y(), 0.0);
-        re[i] = (second, 
-            isa_density(vehicle.position.y()) 
-                * vehicle.motion.magnitude() 
-                * 8.0 
-                / isa_dynamic_viscosity(vehicle.position.y()),
-                0.0);
-
-        // A little evil, but not very
-        th[i] = (second, unsafe { *vehicle.last_thrust.get() }, 0.0);
-
-        let accel: Kinematics = 
-            STEPS_PER_SECOND as f64 * (vehicle.motion - old_motion);
-        gs[i] = (second,
-            Vector::new(accel.x(), accel.y() + 9.81).magnitude() / 9.81, 
-            0.0);
-        ddx[i] = (second, accel.x(), 0.0);
-        ddy[i] = (second, accel.y(), 0.0);
-        ddt[i] = (second, accel.vec.dot(vehicle.position.vec.unit()), 0.0);
-        ddn[i] = (second, 
-            accel.vec.dot(
-                Vector::from_radians(
-                    1.0, 
-                    vehicle.position.direction().rad() + PI/2.0)), 
-            0.0);
-        
-        // Update old motion 
-        old_motion = vehicle.motion;
+        if log.due(second) {
+            let datum = (
+                sim.vehicle.position.x(), 
+                sim.vehicle.position.y(), 
+                sim.vehicle.aoa().nice_deg().abs()
+            );
+
+            trace!(time = second, x = datum.0, y = datum.1, aoa = datum.2, "{:.2}: {:.3?}", second, datum);
+
+            recorder.record("trajectory", datum);
+            frames.push((
+                sim.vehicle.position.x(),
+                sim.vehicle.position.y(),
+                sim.vehicle.position.angle().rad(),
+                sim.vehicle.aoa().rad()));
+            recorder.record("aoa", (second, sim.vehicle.aoa().nice_deg(), 0.0));
+            recorder.record("om", (second, sim.vehicle.motion.angular_velocity_deg(), 0.0));
+            recorder.record("dx", (second, sim.vehicle.motion.horizontal(), 0.0));
+            recorder.record("dy", (second, sim.vehicle.motion.vertical(), 0.0));
+            let air = sim.vehicle.airdata();
+            recorder.record("tas", (second, air.tas, 0.0));
+            recorder.record("mach", (second, air.mach, 0.0));
+            let altitude = sim.vehicle.position.y();
+            recorder.record("pressure_altitude", (second, sim.vehicle.atmosphere.pressure_altitude(altitude), 0.0));
+            recorder.record("density_altitude", (second, density_altitude(air.sigma), 60.0));
+            recorder.record("re", (second, sim.vehicle.wing.reynolds(&air), 0.0));
+            recorder.record("re_elevator", (second, sim.vehicle.elev.reynolds(&air), 0.0));
+            recorder.record("heat_flux", (second, air.heat_flux(sim.vehicle.nose_radius) / 1e4, 0.0));
+            recorder.record("heat_load", (second, sim.heating.load / 1e4, 0.0));
+
+            recorder.record("th", (second, sim.vehicle.diagnostics.thrust / 1_000.0, 0.0));
+
+            // Once the thrust line turns, how much of the weight it holds up
+            vectored |= sim.vehicle.thrust_angle.nice_deg() != 0.0;
+            if vectored {
+                let line = sim.vehicle.position.angle() + sim.vehicle.thrust_angle;
+                let thrust = sim.vehicle.diagnostics.thrust;
+                recorder.record("thrust_angle", (second, sim.vehicle.thrust_angle.nice_deg(), 0.0));
+                recorder.record("thrust_lift", (second, thrust * line.unit().y() / 1_000.0, 0.0));
+            }
+
+            let g = sim.vehicle.gravity.at(sim.vehicle.position.y());
+            recorder.record("gs", (second,
+                Vector::new(accel.x(), accel.y() + g).magnitude() / G0, 
+                0.0));
+            recorder.record("ddx", (second, accel.horizontal(), 0.0));
+            recorder.record("ddy", (second, accel.vertical(), 0.0));
+            let (tangential, normal) = accel.tangent_normal(sim.vehicle.motion.vec);
+            recorder.record("ddt", (second, tangential, 0.0));
+            recorder.record("ddn", (second, normal, 0.0));
+
+            if options.forces {
+                record_forces(&mut recorder, second, &sim.vehicle);
+            }
+            if pilot.is_some() || autopilot.is_some() {
+                recorder.record("pitch", (second, sim.vehicle.position.angle().nice_deg(), 0.0));
+            }
+            if let Some(flex) = &sim.vehicle.flex {
+                recorder.record("twist", (second, flex.twist.to_degrees(), 0.0));
+            }
+            if let Some(speedbrake) = &sim.vehicle.speedbrake {
+                let forces = sim.vehicle.forces(&sim.vehicle.position, &sim.vehicle.motion);
+                recorder.record("speedbrake", (second, speedbrake.deployment, 0.0));
+                recorder.record("speedbrake_command", (second, speedbrake.command, 90.0));
+                recorder.record("speedbrake_drag", (second, forces.speedbrake_drag.magnitude() / 1_000.0, 0.0));
+            }
+            if let Some(flaps) = sim.vehicle.wing.flaps() {
+                recorder.record("flaps", (second, flaps.setting as u8 as f64, 0.0));
+            }
+            if let Some(hinge) = sim.vehicle.hinge_moment() {
+                recorder.record("hinge_moment", (second, hinge / 1_000.0, 0.0));
+            }
+            if let Some(float) = sim.vehicle.elevator_float() {
+                recorder.record("elevator_float", (second, float.nice_deg(), 0.0));
+            }
+            if let Some(sas) = &sas {
+                recorder.record("sas_elevator", (second, sas.elevator(), 0.0));
+            }
+            if let Some(autopilot) = &autopilot {
+                recorder.record("autopilot_target", (second, autopilot.spec.pitch, 90.0));
+                recorder.record("autopilot_kp", (second, autopilot.gains().kp, 0.0));
+            }
+            if let Some(target) = pilot.as_ref().and_then(|pilot| pilot.target(second)) {
+                recorder.record("pilot_target", (second, target, 90.0));
+                recorder.record("pilot_elevator", (second, sim.vehicle.elev.pitch().nice_deg(), 0.0));
+            }
+            if let Some(stall_warning) = &stall_warning {
+                recorder.record("stall_margin", (second, sim.vehicle.stall_margin(), 0.0));
+                recorder.record("stall_thresholds", (second, stall_warning.spec.warning, 30.0));
+                recorder.record("stall_thresholds", (second, stall_warning.spec.buffet, 60.0));
+            }
+            if let Some(loads) = &loads {
+                let load = specific_force(&sim.vehicle, accel.vec).y() / G0;
+                recorder.record("load_factor", (second, load, 0.0));
+                recorder.record("load_limits", (second, loads.spec.positive, 60.0));
+                recorder.record("load_limits", (second, loads.spec.negative, 60.0));
+            }
+            if let Some(placard) = &placard {
+                recorder.record("placard_fraction", (second, placard.fraction(air.qbar * RHO0, air.tas), 0.0));
+                recorder.record("placard_limit", (second, 1.0, 60.0));
+            }
+            if sim.icing.is_some() {
+                recorder.record("ice", (second, sim.vehicle.wing.ice(), 0.0));
+            }
+            if let Some(engine) = &sim.vehicle.engine {
+                recorder.record("rating", (second, engine.rating as u8 as f64, 0.0));
+                recorder.record("fuel_flow", (second, sim.vehicle.fuel_flow(), 0.0));
+                if let Some(fuel) = engine.fuel {
+                    recorder.record("fuel_remaining", (second, fuel, 0.0));
+                }
+            }
+            if let Some(guidance) = &guidance {
+                recorder.record("guidance_demand", (second, guidance.demand() / G0, 0.0));
+                if guidance.spec.arrival.is_some() {
+                    recorder.record("arrival_early", (second, guidance.early(), 0.0));
+                }
+            }
+            if let Some(retro) = &sim.vehicle.retro {
+                recorder.record("retro_thrust", (second, retro.thrust_now() / 1_000.0, 0.0));
+                recorder.record("retro_fuel", (second, retro.fuel, 0.0));
+            }
+            if let Some(electric) = &sim.vehicle.electric {
+                recorder.record("soc", (second, electric.battery.charge, 0.0));
+                recorder.record("soc", (second, electric.battery.reserve, 60.0));
+            }
+            recorder.record("altitude", (second, sim.vehicle.position.y(), 0.0));
+            if sensors.is_some() {
+                let force = specific_force(&sim.vehicle, accel.vec);
+                recorder.record("eas", (second, (2.0 * air.qbar).sqrt(), 0.0));
+                recorder.record("specific_forward", (second, force.x(), 0.0));
+                recorder.record("specific_up", (second, force.y(), 0.0));
+            }
+            if let Some(estimate) = estimator.as_ref().and_then(|estimator| estimator.estimate(second)) {
+                let pitch = Angle::from_radians(estimate.pitch).signed_diff(sim.vehicle.position.angle());
+                recorder.record("error_pitch", (second, pitch.to_degrees(), 0.0));
+                recorder.record("error_altitude", (second, estimate.altitude - sim.vehicle.position.y(), 0.0));
+                recorder.record("error_climb_rate", (second, estimate.vy - sim.vehicle.motion.y(), 0.0));
+                recorder.record("error_airspeed", (second, estimate.airspeed - air.tas, 0.0));
+            }
+        }
+
+        // Wait for the wall clock to catch up
+        if let Some(pacer) = &pacer {
+            pacer.wait(second);
+        }
+
+        // Stream to FlightGear
+        if let Some(fg) = &flightgear {
+            if let Err(e) = fg.send(&sim.vehicle, second) {
+                error!("Error sending to FlightGear: {}", e);
+            }
+        }
+
+        // Stream to the autopilot
+        if let Some(mavlink) = &mut mavlink {
+            if let Err(e) = mavlink.send(&sim.vehicle, second, accel.vec) {
+                error!("Error sending MAVLink: {}", e);
+            }
+        }
+
+        // Save a checkpoint once we reach the requested time
+        if let Some((path, scenario)) = checkpoint {
+            if sim.time >= options.checkpoint_at {
+                if let Err(e) = Checkpoint::save(path, &sim, scenario) {
+                    error!("Error saving checkpoint to {}: {}", path, e);
+                }
+                checkpoint = None;
+            }
+        }
+
+        progress.set(sim.time);
 
         // Terminate if it hits the ground
-        if vehicle.position.y() <= 0.0 { break; }
+        if sim.vehicle.grounded() { break; }
+    }
+    progress.finish(sim.time);
+    #[cfg(feature = "joystick")]
+    cockpit.finish(&sim);
+    if sim.stats.unconverged > 0 {
+        warn!("{} implicit steps didn't converge; a shorter substep may help", sim.stats.unconverged);
+    }
+
+    let placard_margin = placard.as_ref().and_then(Placard::least_margin);
+    let impact = guidance.as_ref()
+        .and_then(|guidance| guidance.impact().map(|(angle, miss)| (angle, guidance.spec.impact_angle, miss)));
+    let arrival = guidance.as_ref()
+        .and_then(|guidance| guidance.spec.arrival.zip(guidance.arrival()))
+        .map(|(spec, time)| (time, time - spec.time));
+    let closest_approach = guidance.as_ref().and_then(|guidance| guidance.closest_approach());
+    let summary = Summary { events, placard_margin, impact, arrival, closest_approach, ..Summary::new(&sim) };
+    info!(time = max_q.0, pressure = max_q.1, "{:.2} s: max q {:.1} kPa", max_q.0, max_q.1 / 1_000.0);
+    log_impact(&summary);
+    println!("{}", summary);
+    let mut report = start_report(&options);
+    report.summarise(&summary);
+    let recorder = finish_recording(&options, recorder);
+    export(&options, &origin, &recorder);
+
+    // Save a checkpoint at the end if the run stopped before the requested time
+    if let Some((path, scenario)) = checkpoint {
+        if let Err(e) = Checkpoint::save(path, &sim, scenario) {
+            error!("Error saving checkpoint to {}: {}", path, e);
+        }
+    }
+
+    // Plot the data
+    plot_charts(&options, &mut report, &[
+        Chart {
+            title: "Trajectory",
+            x_label: "Distance [m]",
+            y_label: "Altitude [m]",
+            do_aspect: true,
+            data: recorder.channel("trajectory"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Angle of Attack",
+            x_label: "Time [s]",
+            y_label: "Angle [deg]",
+            do_aspect: false,
+            data: recorder.channel("aoa"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Angular Velocity",
+            x_label: "Time [s]",
+            y_label: "Rotation [deg/s]",
+            do_aspect: false,
+            data: recorder.channel("om"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Horizontal Velocity",
+            x_label: "Time [s]",
+            y_label: "Velocity [m/s]",
+            do_aspect: false,
+            data: recorder.channel("dx"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Vertical Velocity",
+            x_label: "Time [s]",
+            y_label: "Velocity [m/s]",
+            do_aspect: false,
+            data: recorder.channel("dy"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "True Airspeed",
+            x_label: "Time [s]",
+            y_label: "Airspeed [m/s]",
+            do_aspect: false,
+            data: recorder.channel("tas"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Mach Number",
+            x_label: "Time [s]",
+            y_label: "Mach [1]",
+            do_aspect: false,
+            data: recorder.channel("mach"),
+            axes: Axes {
+                log_y: false,
+                secondary: Some(Secondary { y_label: "Altitude [m]", log_y: false, data: recorder.channel("altitude") }),
+            },
+        },
+        Chart {
+            title: "Reynolds Number",
+            x_label: "Time [s]",
+            y_label: "Re [1]",
+            do_aspect: false,
+            data: recorder.channel("re"),
+            axes: Axes { log_y: true, secondary: None },
+        },
+        Chart {
+            title: "Elevator Reynolds Number",
+            x_label: "Time [s]",
+            y_label: "Re [1]",
+            do_aspect: false,
+            data: recorder.channel("re_elevator"),
+            axes: Axes { log_y: true, secondary: None },
+        },
+        Chart {
+            title: "Heat Flux",
+            x_label: "Time [s]",
+            y_label: "Stagnation heat flux [W/cm^2]",
+            do_aspect: false,
+            data: recorder.channel("heat_flux"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Heat Load",
+            x_label: "Time [s]",
+            y_label: "Heat load [J/cm^2]",
+            do_aspect: false,
+            data: recorder.channel("heat_load"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "G-force",
+            x_label: "Time [s]",
+            y_label: "G-force [1]",
+            do_aspect: false,
+            data: recorder.channel("gs"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Thrust Force",
+            x_label: "Time [s]",
+            y_label: "Thrust [kN]",
+            do_aspect: false,
+            data: recorder.channel("th"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Horizontal Acceleration",
+            x_label: "Time [s]",
+            y_label: "Acceleration [m/s2]",
+            do_aspect: false,
+            data: recorder.channel("ddx"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Vertical Acceleration",
+            x_label: "Time [s]",
+            y_label: "Acceleration [m/s2]",
+            do_aspect: false,
+            data: recorder.channel("ddy"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Tangental Acceleration",
+            x_label: "Time [s]",
+            y_label: "Acceleration [m/s2]",
+            do_aspect: false,
+            data: recorder.channel("ddt"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Normal Acceleration",
+            x_label: "Time [s]",
+            y_label: "Acceleration [m/s2]",
+            do_aspect: false,
+            data: recorder.channel("ddn"),
+            axes: Axes::PLAIN,
+        },
+    ]);
+
+    // Phase planes, for pitch oscillations and energy management
+    let phases = [
+        ("Pitch Phase Plane", "Angle of attack [deg]", "Rotation [deg/s]", "aoa", "om"),
+        ("Energy Phase Plane", "Airspeed [m/s]", "Altitude [m]", "tas", "altitude"),
+    ];
+    for (title, x_label, y_label, x, y) in phases {
+        let data = phase_plane(recorder.channel(x), recorder.channel(y));
+        if let Err(e) = plot_phase(title, x_label, y_label, &data, &options.plot_style) {
+            error!("Error generating plot: {}", e);
+        }
+    }
+
+    if sensors.is_some() {
+        let sensed = |channels: &[&str]| -> Vec<Sample> {
+            channels.iter().flat_map(|c| recorder.channel(c).iter().copied()).collect()
+        };
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Sensed Altitude",
+                x_label: "Time [s]",
+                y_label: "Altitude [m]",
+                do_aspect: false,
+                data: &sensed(&["altitude", "baro", "gps_altitude"]),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Sensed Airspeed",
+                x_label: "Time [s]",
+                y_label: "Equivalent airspeed [m/s]",
+                do_aspect: false,
+                data: &sensed(&["eas", "pitot"]),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Sensed Pitch Rate",
+                x_label: "Time [s]",
+                y_label: "Pitch rate [deg/s]",
+                do_aspect: false,
+                data: &sensed(&["om", "gyro"]),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Sensed Forward Acceleration",
+                x_label: "Time [s]",
+                y_label: "Specific force [m/s2]",
+                do_aspect: false,
+                data: &sensed(&["specific_forward", "imu_forward"]),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Sensed Normal Acceleration",
+                x_label: "Time [s]",
+                y_label: "Specific force [m/s2]",
+                do_aspect: false,
+                data: &sensed(&["specific_up", "imu_up"]),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if estimator.is_some() {
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Pitch Estimation Error",
+                x_label: "Time [s]",
+                y_label: "Error [deg]",
+                do_aspect: false,
+                data: recorder.channel("error_pitch"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Altitude Estimation Error",
+                x_label: "Time [s]",
+                y_label: "Error [m]",
+                do_aspect: false,
+                data: recorder.channel("error_altitude"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Climb Rate Estimation Error",
+                x_label: "Time [s]",
+                y_label: "Error [m/s]",
+                do_aspect: false,
+                data: recorder.channel("error_climb_rate"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Airspeed Estimation Error",
+                x_label: "Time [s]",
+                y_label: "Error [m/s]",
+                do_aspect: false,
+                data: recorder.channel("error_airspeed"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.flex.is_some() {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Tail Twist",
+            x_label: "Time [s]",
+            y_label: "Twist [deg]",
+            do_aspect: false,
+            data: recorder.channel("twist"),
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if sim.vehicle.speedbrake.is_some() {
+        let deployment: Vec<Sample> = ["speedbrake", "speedbrake_command"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Speedbrake Deployment",
+                x_label: "Time [s]",
+                y_label: "Deployment [1]",
+                do_aspect: false,
+                data: &deployment,
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Speedbrake Drag",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("speedbrake_drag"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.wing.flaps().is_some() {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Flap Setting",
+            x_label: "Time [s]",
+            y_label: "Setting [clean 0, takeoff 1, landing 2]",
+            do_aspect: false,
+            data: recorder.channel("flaps"),
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    match sim.vehicle.elev.hinge() {
+        Some(hinge) if hinge.free => plot_charts(&options, &mut report, &[Chart {
+            title: "Elevator Float Angle",
+            x_label: "Time [s]",
+            y_label: "Deflection [deg]",
+            do_aspect: false,
+            data: recorder.channel("elevator_float"),
+            axes: Axes::PLAIN,
+        }]),
+        Some(_) => plot_charts(&options, &mut report, &[Chart {
+            title: "Elevator Hinge Moment",
+            x_label: "Time [s]",
+            y_label: "Hinge moment [kN m]",
+            do_aspect: false,
+            data: recorder.channel("hinge_moment"),
+            axes: Axes::PLAIN,
+        }]),
+        None => {}
+    }
+
+    if sas.is_some() {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Pitch Damper Elevator",
+            x_label: "Time [s]",
+            y_label: "Elevator [deg]",
+            do_aspect: false,
+            data: recorder.channel("sas_elevator"),
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if autopilot.is_some() {
+        let tracking: Vec<Sample> = ["pitch", "autopilot_target"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Autopilot Pitch Tracking",
+                x_label: "Time [s]",
+                y_label: "Pitch [deg]",
+                do_aspect: false,
+                data: &tracking,
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Scheduled Proportional Gain",
+                x_label: "Time [s]",
+                y_label: "Gain [deg/deg]",
+                do_aspect: false,
+                data: recorder.channel("autopilot_kp"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if pilot.is_some() {
+        let tracking: Vec<Sample> = ["pitch", "pilot_target"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Pilot Pitch Tracking",
+                x_label: "Time [s]",
+                y_label: "Pitch [deg]",
+                do_aspect: false,
+                data: &tracking,
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Pilot Elevator",
+                x_label: "Time [s]",
+                y_label: "Elevator [deg]",
+                do_aspect: false,
+                data: recorder.channel("pilot_elevator"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.atmosphere != Atmosphere::default() {
+        let altitudes: Vec<Sample> = ["pressure_altitude", "density_altitude"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Pressure and Density Altitude",
+            x_label: "Time [s]",
+            y_label: "Altitude [m]",
+            do_aspect: false,
+            data: &altitudes,
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if stall_warning.is_some() {
+        let margin: Vec<Sample> = ["stall_margin", "stall_thresholds", "stall_events"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Stall Margin",
+            x_label: "Time [s]",
+            y_label: "Margin [deg]",
+            do_aspect: false,
+            data: &margin,
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if loads.is_some() {
+        let load: Vec<Sample> = ["load_factor", "load_limits", "load_events"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Normal Load Factor",
+            x_label: "Time [s]",
+            y_label: "Load factor [g]",
+            do_aspect: false,
+            data: &load,
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if placard.is_some() {
+        let speed: Vec<Sample> = ["placard_fraction", "placard_limit", "placard_events"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Fraction of Placard",
+            x_label: "Time [s]",
+            y_label: "Fraction [1]",
+            do_aspect: false,
+            data: &speed,
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if sim.icing.is_some() {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Ice Contamination",
+            x_label: "Time [s]",
+            y_label: "Contamination [1]",
+            do_aspect: false,
+            data: recorder.channel("ice"),
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if vectored {
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Thrust Angle",
+                x_label: "Time [s]",
+                y_label: "Angle [deg]",
+                do_aspect: false,
+                data: recorder.channel("thrust_angle"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Vertical Thrust",
+                x_label: "Time [s]",
+                y_label: "Thrust [kN]",
+                do_aspect: false,
+                data: recorder.channel("thrust_lift"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.engine.is_some() {
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Engine Rating",
+                x_label: "Time [s]",
+                y_label: "Rating [idle 0, military 1, afterburner 2]",
+                do_aspect: false,
+                data: recorder.channel("rating"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Fuel Flow",
+                x_label: "Time [s]",
+                y_label: "Fuel Flow [kg/s]",
+                do_aspect: false,
+                data: recorder.channel("fuel_flow"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.engine.as_ref().is_some_and(|engine| engine.fuel.is_some()) {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Fuel Remaining",
+            x_label: "Time [s]",
+            y_label: "Fuel [kg]",
+            do_aspect: false,
+            data: recorder.channel("fuel_remaining"),
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if let Some(guidance) = &guidance {
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Guidance Demand",
+            x_label: "Time [s]",
+            y_label: "Acceleration [g]",
+            do_aspect: false,
+            data: recorder.channel("guidance_demand"),
+            axes: Axes::PLAIN,
+        }]);
+        if guidance.spec.arrival.is_some() {
+            plot_charts(&options, &mut report, &[Chart {
+                title: "Predicted Arrival",
+                x_label: "Time [s]",
+                y_label: "Early [s]",
+                do_aspect: false,
+                data: recorder.channel("arrival_early"),
+                axes: Axes::PLAIN,
+            }]);
+        }
+    }
+
+    if sim.vehicle.retro.is_some() {
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Retro Thrust",
+                x_label: "Time [s]",
+                y_label: "Thrust [kN]",
+                do_aspect: false,
+                data: recorder.channel("retro_thrust"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Retro Fuel",
+                x_label: "Time [s]",
+                y_label: "Fuel [kg]",
+                do_aspect: false,
+                data: recorder.channel("retro_fuel"),
+                axes: Axes::PLAIN,
+            },
+        ]);
+    }
+
+    if sim.vehicle.electric.is_some() {
+        let charge: Vec<Sample> = ["soc", "soc_events"].iter()
+            .flat_map(|c| recorder.channel(c).iter().copied())
+            .collect();
+        plot_charts(&options, &mut report, &[Chart {
+            title: "Battery State of Charge",
+            x_label: "Time [s]",
+            y_label: "State of Charge [1]",
+            do_aspect: false,
+            data: &charge,
+            axes: Axes::PLAIN,
+        }]);
+    }
+
+    if options.forces {
+        plot_charts(&options, &mut report, &[
+            Chart {
+                title: "Wing Lift",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("wing_lift"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Wing Drag",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("wing_drag"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Elevator Lift",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("elevator_lift"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Elevator Drag",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("elevator_drag"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Weight",
+                x_label: "Time [s]",
+                y_label: "Force [kN]",
+                do_aspect: false,
+                data: recorder.channel("weight"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Wing Moment",
+                x_label: "Time [s]",
+                y_label: "Moment [kN m]",
+                do_aspect: false,
+                data: recorder.channel("wing_moment"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Elevator Moment",
+                x_label: "Time [s]",
+                y_label: "Moment [kN m]",
+                do_aspect: false,
+                data: recorder.channel("elevator_moment"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Tail Moment",
+                x_label: "Time [s]",
+                y_label: "Moment [kN m]",
+                do_aspect: false,
+                data: recorder.channel("tail_moment"),
+                axes: Axes::PLAIN,
+            },
+            Chart {
+                title: "Wing Arm Moment",
+                x_label: "Time [s]",
+                y_label: "Moment [kN m]",
+                do_aspect: false,
+                data: recorder.channel("wing_arm_moment"),
+                axes: Axes::PLAIN,
+            },
+        ]);
     }
 
+    // Animate the trajectory, if asked to
+    if let Some(path) = &options.animation {
+        let animated = animate_trajectory(path, "Trajectory", &frames, start, log_rate, &options.playback_speed, &options.plot_style);
+        if let Err(e) = animated {
+            error!("Error generating animation {}: {}", path, e);
+        }
+    }
+
+    write_report(&options, &report);
+}
+
+// Reads the scenario file or sets up the preset vehicle, if there is one
+fn load_scenario(options: &Options) -> Scenario {
+    if let Some(preset) = options.preset {
+        return preset.scenario();
+    }
+    match &options.scenario {
+        Some(path) => Scenario::load(path).unwrap_or_else(|e| {
+            error!("Error reading scenario {}: {}", path, e);
+            std::process::exit(1);
+        }),
+        None => Scenario::default(),
+    }
+}
+
+// Records each force and moment on the vehicle as its own channel, in kN and
+// kN m. Lift is taken normal to the direction of motion and drag against it,
+// so both keep their sign. Thrust is already recorded as "th", and any
+// external forces go under their own names.
+fn record_forces(recorder: &mut Recorder, second: f64, vehicle: &Vehicle) {
+    let forces = vehicle.forces(&vehicle.position, &vehicle.motion);
+    let motion = vehicle.motion.vec;
+
+    let lift = |force: Vector| force.tangent_normal(motion).1 / 1_000.0;
+    let drag = |force: Vector| -force.tangent_normal(motion).0 / 1_000.0;
+
+    recorder.record("wing_lift", (second, lift(forces.wing_lift), 0.0));
+    recorder.record("wing_drag", (second, drag(forces.wing_drag), 0.0));
+    recorder.record("elevator_lift", (second, lift(forces.elevator_lift), 0.0));
+    recorder.record("elevator_drag", (second, drag(forces.elevator_drag), 0.0));
+    recorder.record("weight", (second, -forces.weight.y() / 1_000.0, 0.0));
+    recorder.record("wing_moment", (second, forces.wing_moment / 1_000.0, 0.0));
+    recorder.record("elevator_moment", (second, forces.elevator_moment / 1_000.0, 0.0));
+    recorder.record("tail_moment", (second, forces.tail_moment / 1_000.0, 0.0));
+    recorder.record("wing_arm_moment", (second, forces.wing_arm_moment / 1_000.0, 0.0));
+    recorder.record_external_forces(second, vehicle);
+}
+
+// Looks up the value in force at `time` from pairs of a time and the value
+// from it on, if the first has come
+fn scheduled<T: Copy>(schedule: &[(f64, T)], time: f64) -> Option<T> {
+    schedule.iter()
+        .take_while(|(start, _)| *start <= time)
+        .last()
+        .map(|&(_, value)| value)
+}
+
+// Renders the charts, reporting any that fail, and adds them to the report
+// if one was asked for. A streamed recording leaves nothing to draw.
+fn plot_charts(options: &Options, report: &mut Report, charts: &[Chart]) {
+    if options.record.is_some() { return; }
+    for result in plot_all(charts, &options.plot_style) {
+        if let Err(e) = result {
+            error!("Error generating plot: {}", e);
+        }
+    }
+
+    if options.report.is_none() { return; }
+    let points = options.plot_style.points;
+    for c in charts {
+        report.add(c.title, c.x_label, c.y_label, c.axes.log_y, decimate(c.data, points));
+        if let Some(s) = &c.axes.secondary {
+            // Under its axis's name, less the units
+            let title = s.y_label.split(" [").next().unwrap_or(s.y_label);
+            report.add(title, c.x_label, s.y_label, s.log_y, decimate(s.data, points));
+        }
+    }
+}
+
+// Logs an event along the run
+fn log_event(event: &Event) {
+    info!(time = event.time, kind = %event.kind, "{}", event);
+}
+
+// Logs the vehicle coming down, if it did
+fn log_impact(summary: &Summary) {
+    if summary.impacted {
+        info!(time = summary.flight_time, speed = summary.final_speed,
+            "{:.2} s: impact at {:.1} m/s", summary.flight_time, summary.final_speed);
+    }
+}
+
+// Acts on the game controller's presses before a step: pausing or resuming,
+// stepping once while paused, and rewinding `rewind` seconds. Waits while
+// paused, keeping the cockpit showing where the run stands, and returns
+// whether the clock stood still or went back, for the pacing to take up
+// from where it is.
+#[cfg(feature = "joystick")]
+fn drive_transport(joystick: &mut Joystick, transport: &mut Transport, cockpit: &mut Cockpit, sim: &mut Simulation, rewind: f64) -> bool {
+    let mut held = false;
+    loop {
+        for press in joystick.presses() {
+            match press {
+                Press::Pause => {
+                    transport.toggle_pause();
+                    info!(time = sim.time, paused = transport.paused(), "{:.2} s: {}",
+                        sim.time, if transport.paused() { "paused" } else { "resumed" });
+                }
+                Press::Step => transport.single_step(),
+                Press::Rewind => if let Some(time) = transport.rewind(sim, rewind) {
+                    info!(time, "{:.2} s: rewound", time);
+                    held = true;
+                },
+            }
+        }
+        if transport.advance() { return held; }
+
+        held = true;
+        cockpit.show(sim, true);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        joystick.poll_buttons();
+    }
+}
+
+// Names the run by the scenario or preset flown
+fn run_name(options: &Options) -> String {
+    match (options.preset, &options.scenario) {
+        (Some(preset), _) => preset.name.to_owned(),
+        (None, Some(path)) => std::path::Path::new(path).file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned()),
+        (None, None) => "default scenario".to_owned(),
+    }
+}
+
+// Starts the run's report, headed by the scenario or preset flown
+fn start_report(options: &Options) -> Report {
+    Report::new(&format!("dynterm: {}", run_name(options)))
+}
+
+// Writes the channels for MATLAB or NumPy, and the trajectory over the globe
+// from `origin`, with the crossrange where the vehicle flies in three
+// dimensions, whichever were asked for
+fn export(options: &Options, origin: &Origin, recorder: &Recorder) {
+    // A streamed recording is read back only if something's to be written
+    let loaded;
+    let recorder = match &options.record {
+        Some(path) if options.export.is_some() || options.track.is_some() => {
+            loaded = Recorder::load(path).unwrap_or_else(|e| {
+                error!("Error reading recording {}: {}", path, e);
+                std::process::exit(1);
+            });
+            &loaded
+        }
+        _ => recorder,
+    };
+
+    if let Some(path) = &options.export {
+        if let Err(e) = write_channels(path, recorder) {
+            error!("Error writing channels to {}: {}", path, e);
+        }
+    }
+
+    let Some(path) = &options.track else { return };
+    let trajectory = recorder.channel("trajectory");
+    let track: Vec<Sample> = match recorder.channel("track") {
+        [] => trajectory.iter().map(|&(x, y, _)| (x, y, 0.0)).collect(),
+        across => trajectory.iter().zip(across).map(|(&(x, y, _), &(_, z, _))| (x, y, z)).collect(),
+    };
+    if let Err(e) = write_track(path, &run_name(options), &geodetic(origin, &track)) {
+        error!("Error writing track to {}: {}", path, e);
+    }
+}
+
+// Writes the report, if one was asked for
+fn write_report(options: &Options, report: &Report) {
+    if let Some(path) = &options.report {
+        if let Err(e) = report.write(path) {
+            error!("Error writing report to {}: {}", path, e);
+        }
+    }
+}
+
+// Records the sensor readings that arrived at `second`, in red against the
+// truth, or darker for GPS. They're plotted when they arrive rather than
+// when they were taken, as whatever reads them would see them.
+fn record_readings(recorder: &mut Recorder, second: f64, readings: &Readings) {
+    if let Some(imu) = readings.imu {
+        recorder.record("imu_forward", (second, imu.forward, 90.0));
+        recorder.record("imu_up", (second, imu.up, 90.0));
+        recorder.record("gyro", (second, imu.pitch_rate.to_degrees(), 90.0));
+    }
+    if let Some(airspeed) = readings.airspeed {
+        recorder.record("pitot", (second, airspeed.value, 90.0));
+    }
+    if let Some(altitude) = readings.altitude {
+        recorder.record("baro", (second, altitude.value, 90.0));
+    }
+    if let Some(gps) = readings.gps {
+        recorder.record("gps_x", (second, gps.x, 45.0));
+        recorder.record("gps_altitude", (second, gps.altitude, 45.0));
+        recorder.record("gps_vx", (second, gps.vx, 45.0));
+        recorder.record("gps_vy", (second, gps.vy, 45.0));
+    }
+}
+
+// Sets up the recorder, streaming to the --record file if there is one
+fn open_recorder(options: &Options) -> Recorder {
+    match &options.record {
+        Some(path) => Recorder::streaming(path).unwrap_or_else(|e| {
+            error!("Error opening recording {}: {}", path, e);
+            std::process::exit(1);
+        }),
+        None => Recorder::new(),
+    }
+}
+
+// Ends the recording, ready for plotting. A streamed recording isn't read
+// back to plot, so each channel is summed up instead.
+fn finish_recording(options: &Options, recorder: Recorder) -> Recorder {
+    let recorder = recorder.finish().unwrap_or_else(|e| {
+        error!("Error writing recording: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(path) = &options.record {
+        for (name, stats) in recorder.stats() {
+            info!(channel = name, samples = stats.samples, "{}: {} samples, {:.3} to {:.3}, ending at {:.3}",
+                name, stats.samples, stats.min.1, stats.max.1, stats.last.1);
+        }
+        info!("Plot the recording with `dynterm compare {}`", path);
+    }
+    recorder
+}
+
+// Runs the built-in verification checks and the golden scenarios, failing
+// if any do. Blessing prints the golden file for the current physics instead.
+fn verify(bless: bool) {
+    use dynterm::verify::{regression, terminal_velocity, SCENARIOS};
+
+    if bless {
+        match serde_json::to_string_pretty(&dynterm::verify::bless()) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                error!("Error writing golden results: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut checks = terminal_velocity();
+    for name in SCENARIOS {
+        match regression(name) {
+            Ok(regression) => checks.extend(regression),
+            Err(e) => {
+                error!("Error checking scenario '{}': {}", name, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    for check in &checks {
+        println!("{}", check);
+    }
+
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    println!("{} of {} checks passed", checks.len() - failed, checks.len());
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+// Drops a family of ballistic bodies from the scenario's release and flies
+// the vehicle itself alongside them, as the default run does
+fn sweep(options: &Options) {
+    use dynterm::ballistic::{log_spaced, sweep};
+
+    let scenario = load_scenario(options);
+    let (mut sim, trimmed) = (scenario.build(), scenario.trimmed());
+    let dt = 1.0 / STEPS_PER_SECOND as f64;
+    let flights = sweep(
+        &log_spaced(options.beta_min, options.beta_max, options.beta_count),
+        sim.vehicle.gravity,
+        &sim.vehicle.atmosphere,
+        sim.vehicle.position.vec,
+        sim.vehicle.motion.vec,
+        dt,
+        MAX_SECONDS as f64);
+
+    // Every ballistic trajectory in black, with the vehicle's in red over them
+    let mut trajectories: Vec<(f64, f64, f64)> = flights.iter()
+        .flat_map(|f| f.trajectory.iter().map(|p| (p.x(), p.y(), 0.0)))
+        .collect();
+    for _ in 0..MAX_INCREMENTS {
+        trimmed.unwrap_or_else(|| Command::pull_up(&sim.vehicle)).apply(&mut sim.vehicle);
+        sim.step(dt);
+        trajectories.push((sim.vehicle.position.x(), sim.vehicle.position.y(), 90.0));
+        if sim.vehicle.grounded() { break; }
+    }
+
+    println!("{:>12} {:>12} {:>12} {:>12}", "Beta [kg/m2]", "Time [s]", "Range [m]", "Speed [m/s]");
+    for f in &flights {
+        println!("{:>12.1} {:>12.2} {:>12.1} {:>12.1}{}",
+            f.beta, f.flight_time, f.range, f.final_speed,
+            if f.impacted { "" } else { " (still flying)" });
+    }
+    let summary = Summary::new(&sim);
+    println!("{}", summary);
+    let mut report = start_report(options);
+    report.summarise(&summary);
+
+    let impact_speed: Vec<_> = flights.iter().map(|f| (f.beta, f.final_speed, 0.0)).collect();
+    let flight_time: Vec<_> = flights.iter().map(|f| (f.beta, f.flight_time, 0.0)).collect();
+    plot_charts(options, &mut report, &[
+        Chart {
+            title: "Ballistic Trajectories",
+            x_label: "Distance [m]",
+            y_label: "Altitude [m]",
+            do_aspect: true,
+            data: &trajectories,
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Ballistic Impact Speed",
+            x_label: "Ballistic coefficient [kg/m^2]",
+            y_label: "Impact speed [m/s]",
+            do_aspect: false,
+            data: &impact_speed,
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Ballistic Time of Flight",
+            x_label: "Ballistic coefficient [kg/m^2]",
+            y_label: "Time of flight [s]",
+            do_aspect: false,
+            data: &flight_time,
+            axes: Axes::PLAIN,
+        },
+    ]);
+    write_report(options, &report);
+}
+
+// Trims the scenario's vehicle across its range of centre of mass, and
+// plots how the trim and static margin move with it
+fn cg_sweep(options: &Options) {
+    let scenario = load_scenario(options);
+    let sweep = dynterm::trim::cg_sweep(&scenario.vehicle, options.cg_count);
+
+    println!("{:>10} {:>10} {:>15} {:>18}", "CG [m]", "AoA [deg]", "Elevator [deg]", "Static margin [%]");
+    for (cg, trim) in &sweep {
+        match trim {
+            Some(trim) => println!("{:>10.2} {:>10.2} {:>15.2} {:>18.1}",
+                cg, trim.aoa, trim.elevator, trim.static_margin * 100.0),
+            None => println!("{:>10.2} {:>10} {:>15} {:>18}", cg, "-", "-", "(untrimmable)"),
+        }
+    }
+
+    let trimmed = || sweep.iter().filter_map(|(cg, trim)| trim.map(|trim| (*cg, trim)));
+    let elevator: Vec<Sample> = trimmed().map(|(cg, trim)| (cg, trim.elevator, 0.0)).collect();
+    let margin: Vec<Sample> = trimmed().map(|(cg, trim)| (cg, trim.static_margin * 100.0, 0.0)).collect();
+    let mut report = start_report(options);
+    plot_charts(options, &mut report, &[
+        Chart {
+            title: "Trim Elevator against CG",
+            x_label: "Centre of mass [m aft of nose]",
+            y_label: "Elevator [deg]",
+            do_aspect: false,
+            data: &elevator,
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Static Margin against CG",
+            x_label: "Centre of mass [m aft of nose]",
+            y_label: "Static margin [% chord]",
+            do_aspect: false,
+            data: &margin,
+            axes: Axes::PLAIN,
+        },
+    ]);
+    write_report(options, &report);
+}
+
+// Charts a channel is compared on, by name: the title, the axes, and
+// whether it's drawn to scale. Anything else is taken to be against time.
+const COMPARED: &[(&str, &str, &str, &str, bool)] = &[
+    ("trajectory", "Trajectory", "Distance [m]", "Altitude [m]", true),
+    ("aoa", "Angle of Attack", "Time [s]", "Angle [deg]", false),
+    ("gs", "Load", "Time [s]", "Acceleration [g]", false),
+    ("om", "Angular Velocity", "Time [s]", "Rotation [deg/s]", false),
+    ("tas", "True Airspeed", "Time [s]", "Airspeed [m/s]", false),
+    ("mach", "Mach Number", "Time [s]", "Mach [1]", false),
+    ("altitude", "Altitude", "Time [s]", "Altitude [m]", false),
+];
+
+// Overlays `channels` of the runs recorded in `paths`, or the trajectory,
+// angle of attack, and load if none are named, each run under the name of
+// its file
+fn compare(paths: &[String], options: &Options) {
+    let recordings: Vec<(String, Recorder)> = paths.iter().map(|path| {
+        let recorder = Recorder::load(path).unwrap_or_else(|e| {
+            error!("Error reading recording {}: {}", path, e);
+            std::process::exit(1);
+        });
+        let name = std::path::Path::new(path).file_stem().map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        (name, recorder)
+    }).collect();
+
+    let defaults = ["trajectory", "aoa", "gs"].map(String::from);
+    let channels = if options.channels.is_empty() { &defaults[..] } else { &options.channels };
+    for channel in channels {
+        let (title, x_label, y_label, do_aspect) = COMPARED.iter()
+            .find(|(name, ..)| name == channel)
+            .map_or((channel.as_str(), "Time [s]", channel.as_str(), false), |&(_, title, x, y, aspect)| (title, x, y, aspect));
+        let series: Vec<Series> = recordings.iter()
+            .map(|(name, recorder)| Series { name, data: recorder.channel(channel) })
+            .collect();
+        if series.iter().all(|s| s.data.is_empty()) {
+            warn!("No run recorded the {} channel", channel);
+            continue;
+        }
+        if let Err(e) = plot_comparison(&format!("{} Comparison", title), x_label, y_label, do_aspect, &series, &options.plot_style) {
+            error!("Error generating plot: {}", e);
+        }
+    }
+}
+
+// Flies a Monte Carlo campaign of the scenario, the planar vehicle or the
+// point mass, scattered by its dispersions, and reports and plots how the
+// impacts spread
+#[cfg(feature = "parallel")]
+fn monte_carlo(options: &Options, point_mass: bool) {
+    use dynterm::batch::{run, run_batch_watched, run_point_mass};
+    use dynterm::dispersion::ImpactDispersion;
+    use dynterm::rng::SimRng;
+
+    let mut scenario = load_scenario(options);
+    scenario.seed = scenario.seed.or_else(|| Some(SimRng::from_clock().seed()));
+    let scenarios: Vec<Scenario> = (0..options.runs as u64)
+        .map(|i| scenario.dispersions.draw(&scenario, i))
+        .collect();
+    let progress = Progress::new("Campaign", scenarios.len() as f64, "runs", show_progress(options));
+    let runs = run_batch_watched(&scenarios, 0, if point_mass { run_point_mass } else { run },
+        |done| progress.set(done as f64))
+        .unwrap_or_else(|e| {
+            error!("Error starting the campaign's threads: {}", e);
+            std::process::exit(1);
+        });
+    progress.finish(runs.len() as f64);
+
+    println!("{:>6} {:>20} {:>10} {:>12} {:>12}", "Run", "Seed", "Time [s]", "Range [m]", "Across [m]");
+    for (i, r) in runs.iter().enumerate() {
+        let s = &r.summary;
+        println!("{:>6} {:>20} {:>10.2} {:>12.1} {:>12.1}{}",
+            i, s.seed, s.flight_time, s.range, s.crossrange,
+            if s.impacted { "" } else { " (still flying)" });
+    }
+
+    let impacts: Vec<(f64, f64)> = runs.iter()
+        .filter(|r| r.summary.impacted)
+        .map(|r| (r.summary.range, r.summary.crossrange))
+        .collect();
+    let Some(dispersion) = ImpactDispersion::fit(&impacts) else {
+        error!("Only {} of {} runs came down, too few to fit their scatter", impacts.len(), runs.len());
+        std::process::exit(1);
+    };
+    println!("{}", dispersion);
+    let mut report = start_report(options);
+    report.summarise(&dispersion);
+
+    // Every impact in black, with the 95% ellipse in red around them
+    let mut scatter: Vec<Sample> = impacts.iter().map(|&(x, z)| (x, z, 0.0)).collect();
+    scatter.extend(dispersion.ellipse_outline(180).into_iter().map(|(x, z)| (x, z, 90.0)));
+    plot_charts(options, &mut report, &[Chart {
+        title: "Impact Scatter",
+        x_label: "Downrange [m]",
+        y_label: "Crossrange [m]",
+        do_aspect: true,
+        data: &scatter,
+        axes: Axes::PLAIN,
+    }]);
+    write_report(options, &report);
+}
+
+#[cfg(not(feature = "parallel"))]
+fn monte_carlo(_: &Options, _: bool) {
+    error!("dynterm was built without the parallel feature");
+    std::process::exit(1);
+}
+
+// A progress bar for a run's simulated time, shown on a terminal unless
+// the log is quieter or busier than the default, or the cockpit's showing
+// a flight by hand in its place
+fn flight_progress(options: &Options) -> Progress {
+    Progress::new("Flying", MAX_SECONDS as f64, "s", show_progress(options) && !options.joystick)
+}
+
+// Whether to draw progress bars, which only make sense on a terminal and
+// would be torn up by per-step trace lines
+fn show_progress(options: &Options) -> bool {
+    options.log_level == LevelFilter::INFO && std::io::stderr().is_terminal()
+}
+
+// Flies the scenario's point mass in the 3-DOF mode
+fn run_point_mass(options: &Options) {
+
+    let scenario = load_scenario(options);
+    let mut sim = scenario.build_point_mass();
+
+    // Set up our records of the flight path
+    let mut recorder = open_recorder(options);
+
+    // Optionally pace the loop to wall-clock time
+    let pacer = options.paced().then(|| Pacer::new(options.speed));
+
+    // Record at the logging rate, whatever the step rate
+    let mut log = LogRate::new(sim.time, options.log_rate);
+
+    // Show how far through the run we are
+    let progress = flight_progress(options);
+
+    for _ in 0..MAX_INCREMENTS {
+
+        let second = sim.time;
+        if second >= MAX_SECONDS as f64 { break; }
+
+        sim.step(1.0 / STEPS_PER_SECOND as f64);
+
+        let position = sim.vehicle.position;
+        if log.due(second) {
+            trace!(time = second, x = position.x(), y = position.y(), z = position.z(), "{:.2}: {:.3}", second, position);
+
+            recorder.record("trajectory", (position.x(), position.y(), 0.0));
+            recorder.record("track", (position.x(), position.z(), 0.0));
+            recorder.record("crossrange", (second, position.z(), 0.0));
+        }
+
+        // Wait for the wall clock to catch up
+        if let Some(pacer) = &pacer {
+            pacer.wait(second);
+        }
+
+        progress.set(sim.time);
+
+        // Terminate if it hits the ground
+        if sim.vehicle.grounded() { break; }
+    }
+    progress.finish(sim.time);
+
+    let summary = Summary::from_point_mass(&sim);
+    log_impact(&summary);
+    println!("{}", summary);
+    let mut report = start_report(options);
+    report.summarise(&summary);
+    let recorder = finish_recording(options, recorder);
+    export(options, &scenario.origin, &recorder);
+
+    // Plot the data
+    plot_charts(options, &mut report, &[
+        Chart {
+            title: "Trajectory",
+            x_label: "Distance [m]",
+            y_label: "Altitude [m]",
+            do_aspect: true,
+            data: recorder.channel("trajectory"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Ground Track",
+            x_label: "Distance [m]",
+            y_label: "Crossrange [m]",
+            do_aspect: true,
+            data: recorder.channel("track"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Crossrange",
+            x_label: "Time [s]",
+            y_label: "Crossrange [m]",
+            do_aspect: false,
+            data: recorder.channel("crossrange"),
+            axes: Axes::PLAIN,
+        },
+    ]);
+    write_report(options, &report);
+}
+
+// Flies the scenario's rigid-body vehicle in the 6-DOF mode
+fn run_six_dof(options: &Options) {
+
+    let scenario = load_scenario(options);
+    let mut sim = scenario.build_six_dof();
+
+    // Set up our records of the flight path and attitude
+    let mut recorder = open_recorder(options);
+
+    // Optionally pace the loop to wall-clock time
+    let pacer = options.paced().then(|| Pacer::new(options.speed));
+
+    // Record at the logging rate, whatever the step rate
+    let mut log = LogRate::new(sim.time, options.log_rate);
+
+    // Show how far through the run we are
+    let progress = flight_progress(options);
+
+    for _ in 0..MAX_INCREMENTS {
+
+        let second = sim.time;
+        if second >= MAX_SECONDS as f64 { break; }
+
+        // Pull up?
+        sim.vehicle.elev.set_pitch(Command::pull_up_at(sim.vehicle.body.position.y()).elevator);
+
+        sim.step(1.0 / STEPS_PER_SECOND as f64);
+
+        let state = sim.state();
+        if log.due(second) {
+            trace!(time = second, x = state.x, y = state.y, z = state.z, "{:.2}: ({:.3}, {:.3}, {:.3})", second, state.x, state.y, state.z);
+
+            recorder.record("trajectory", (state.x, state.y, 0.0));
+            recorder.record("track", (state.x, state.z, 0.0));
+            recorder.record("aoa", (second, state.aoa, 0.0));
+            recorder.record("sideslip", (second, state.sideslip, 0.0));
+            recorder.record("roll", (second, state.roll, 0.0));
+            recorder.record("heading", (second, state.heading, 0.0));
+        }
+
+        // Wait for the wall clock to catch up
+        if let Some(pacer) = &pacer {
+            pacer.wait(second);
+        }
+
+        progress.set(sim.time);
+
+        // Terminate if it hits the ground
+        if state.y <= 0.0 { break; }
+    }
+    progress.finish(sim.time);
+
+    let summary = Summary::from_six_dof(&sim);
+    log_impact(&summary);
+    println!("{}", summary);
+    let mut report = start_report(options);
+    report.summarise(&summary);
+    let recorder = finish_recording(options, recorder);
+    export(options, &scenario.origin, &recorder);
+
     // Plot the data
-    match plot_scatter(
-        "Trajectory", 
-        "Distance [m]", 
-        "Altitude [m]",
-        true, 
-        &data) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Angle of Attack", 
-        "Time [s]",
-        "Angle [deg]",
-        false, 
-        &aoa) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Angular Velocity",
-        "Time [s]",
-        "Rotation [deg/s]", 
-        false, 
-        &om) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Horizontal Velocity",
-        "Time [s]",
-        "Velocity [m/s]", 
-        false, 
-        &dx) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Vertical Velocity",
-        "Time [s]",
-        "Velocity [m/s]", 
-        false, 
-        &dy) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Reynolds Number",
-        "Time [s]",
-        "Re [1]", 
-        false, 
-        &re) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "G-force",
-        "Time [s]",
-        "G-force [1]", 
-        false, 
-        &gs) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Thrust Force",
-        "Time [s]",
-        "Thrust [kN]", 
-        false, 
-        &th) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Horizontal Acceleration",
-        "Time [s]",
-        "Acceleration [m/s2]", 
-        false, 
-        &ddx) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Vertical Acceleration",
-        "Time [s]",
-        "Acceleration [m/s2]", 
-        false, 
-        &ddy) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Tangental Acceleration",
-        "Time [s]",
-        "Acceleration [m/s2]", 
-        false, 
-        &ddt) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-    match plot_scatter(
-        "Normal Acceleration",
-        "Time [s]",
-        "Acceleration [m/s2]", 
-        false, 
-        &ddn) 
-    {
-        Ok(()) => {},
-        Err(e) => eprintln!("Error generating plot: {}", e),
-    }
-
-}
\ No newline at end of file
+    plot_charts(options, &mut report, &[
+        Chart {
+            title: "Trajectory",
+            x_label: "Distance [m]",
+            y_label: "Altitude [m]",
+            do_aspect: true,
+            data: recorder.channel("trajectory"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Ground Track",
+            x_label: "Distance [m]",
+            y_label: "Crossrange [m]",
+            do_aspect: true,
+            data: recorder.channel("track"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Angle of Attack",
+            x_label: "Time [s]",
+            y_label: "Angle [deg]",
+            do_aspect: false,
+            data: recorder.channel("aoa"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Sideslip",
+            x_label: "Time [s]",
+            y_label: "Angle [deg]",
+            do_aspect: false,
+            data: recorder.channel("sideslip"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Roll",
+            x_label: "Time [s]",
+            y_label: "Angle [deg]",
+            do_aspect: false,
+            data: recorder.channel("roll"),
+            axes: Axes::PLAIN,
+        },
+        Chart {
+            title: "Heading",
+            x_label: "Time [s]",
+            y_label: "Angle [deg]",
+            do_aspect: false,
+            data: recorder.channel("heading"),
+            axes: Axes::PLAIN,
+        },
+    ]);
+    write_report(options, &report);
+}
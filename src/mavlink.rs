@@ -0,0 +1,177 @@
+use crate::aero::Vehicle;
+use crate::geo::Origin;
+use crate::atmosphere::RHO0;
+use crate::gravity::G0;
+use crate::vec::{Angle, Vector};
+
+use std::io;
+use std::net::UdpSocket;
+
+// MAVLink 2 framing
+const STX: u8 = 0xFD;
+const SYSTEM_ID: u8 = 1;
+const COMPONENT_ID: u8 = 1;
+
+// Message ids and their CRC_EXTRA seeds, from common.xml
+const HEARTBEAT: (u32, u8) = (0, 50);
+const HIL_SENSOR: (u32, u8) = (107, 108);
+const HIL_STATE_QUATERNION: (u32, u8) = (115, 4);
+
+// HIL_SENSOR fields_updated: accelerometer, gyro, absolute and differential
+// pressure, pressure altitude, and temperature (no magnetometer)
+const SENSOR_FIELDS: u32 = 0b1_1110_0011_1111;
+
+// Streams the vehicle state over UDP as MAVLink HIL messages, so a SITL
+// autopilot can use dynterm as its (longitudinal) plant model. Each step
+// sends HIL_STATE_QUATERNION and HIL_SENSOR, plus a HEARTBEAT once a second.
+pub struct Mavlink {
+    socket: UdpSocket,
+    origin: Origin,
+    sequence: u8,
+    last_heartbeat: Option<f64>,
+}
+
+impl Mavlink {
+
+    // Constructor, binds an ephemeral local port and connects it to `host`
+    // (e.g. "localhost:14560"), placing the vehicle on the globe from
+    // `origin`
+    pub fn connect(host: &str, origin: Origin) -> io::Result<Mavlink> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(host)?;
+        Ok(Mavlink { socket, origin, sequence: 0, last_heartbeat: None })
+    }
+
+    // Sends the state of the vehicle. `accel` is its inertial acceleration,
+    // which the state alone doesn't carry, for the accelerometer.
+    pub fn send(&mut self, vehicle: &Vehicle, second: f64, accel: Vector) -> io::Result<()> {
+        if self.last_heartbeat.is_none_or(|last| second - last >= 1.0) {
+            self.last_heartbeat = Some(second);
+            self.frame(HEARTBEAT, &heartbeat())?;
+        }
+        self.frame(HIL_STATE_QUATERNION,
+            &hil_state_quaternion(&self.origin, vehicle, second, accel))?;
+        self.frame(HIL_SENSOR, &hil_sensor(vehicle, second, accel))
+    }
+
+    // Wraps a payload in a MAVLink 2 frame and sends it. Nobody listening
+    // yet isn't an error, since the autopilot may be started late.
+    fn frame(&mut self, (id, crc_extra): (u32, u8), payload: &[u8]) -> io::Result<()> {
+
+        // MAVLink 2 drops trailing zeros from the payload, keeping at least one byte
+        let len = payload.iter().rposition(|&b| b != 0).map_or(1, |i| i + 1);
+
+        let mut buf: Vec<u8> = Vec::with_capacity(12 + len);
+        buf.extend_from_slice(&[STX, len as u8, 0, 0, self.sequence, SYSTEM_ID, COMPONENT_ID]);
+        buf.extend_from_slice(&id.to_le_bytes()[..3]);
+        buf.extend_from_slice(&payload[..len]);
+
+        let crc = crc_accumulate(crc_accumulate(0xFFFF, &buf[1..]), &[crc_extra]);
+        buf.extend_from_slice(&crc.to_le_bytes());
+
+        self.sequence = self.sequence.wrapping_add(1);
+        match self.socket.send(&buf) {
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+// CRC-16/MCRF4XX, as used by MAVLink
+fn crc_accumulate(mut crc: u16, bytes: &[u8]) -> u16 {
+    for &b in bytes {
+        let mut tmp = b ^ (crc & 0xFF) as u8;
+        tmp ^= tmp << 4;
+        let tmp = tmp as u16;
+        crc = (crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4);
+    }
+    crc
+}
+
+// A fixed wing with no autopilot of its own, active
+fn heartbeat() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(9);
+    buf.extend_from_slice(&0u32.to_le_bytes()); // custom_mode
+    buf.extend_from_slice(&[
+        1, // type: MAV_TYPE_FIXED_WING
+        8, // autopilot: MAV_AUTOPILOT_INVALID
+        0, // base_mode
+        4, // system_status: MAV_STATE_ACTIVE
+        3, // mavlink_version
+    ]);
+    buf
+}
+
+// Fields are in MAVLink wire order, i.e. sorted by size
+fn hil_state_quaternion(
+    origin: &Origin,
+    vehicle: &Vehicle,
+    second: f64,
+    accel: Vector) -> Vec<u8> {
+
+    let pos = &vehicle.position;
+    let vel = &vehicle.motion;
+    let pitch = pos.angle().nice_deg().to_radians();
+    let air = vehicle.airdata();
+
+    // Attitude from zero roll, pitch, and the downrange heading
+    let (sp, cp) = (0.5 * pitch).sin_cos();
+    let (sy, cy) = (0.5 * origin.heading_rad()).sin_cos();
+    let quaternion = [cp * cy, -sp * sy, sp * cy, cp * sy];
+
+    let (latitude, longitude) = origin.locate(pos.x());
+    let (v_north, v_east) = origin.north_east(vel.x());
+
+    let mut buf: Vec<u8> = Vec::with_capacity(64);
+    buf.extend_from_slice(&((second * 1e6) as u64).to_le_bytes());
+    for q in quaternion { buf.extend_from_slice(&(q as f32).to_le_bytes()); }
+    buf.extend_from_slice(&0f32.to_le_bytes());              // rollspeed
+    buf.extend_from_slice(&(vel.angular_velocity() as f32).to_le_bytes());  // pitchspeed
+    buf.extend_from_slice(&0f32.to_le_bytes());              // yawspeed
+    buf.extend_from_slice(&((latitude * 1e7) as i32).to_le_bytes());
+    buf.extend_from_slice(&((longitude * 1e7) as i32).to_le_bytes());
+    buf.extend_from_slice(&((pos.y() * 1e3) as i32).to_le_bytes()); // mm
+    for v in [v_north, v_east, -vel.y()] {
+        buf.extend_from_slice(&((v * 100.0) as i16).to_le_bytes()); // cm/s
+    }
+    let indicated = air.tas * air.sigma.sqrt();
+    buf.extend_from_slice(&((indicated * 100.0) as u16).to_le_bytes());
+    buf.extend_from_slice(&((air.tas * 100.0) as u16).to_le_bytes());
+    for a in body_specific_force(vehicle, pitch, accel) {
+        buf.extend_from_slice(&((a / G0 * 1000.0) as i16).to_le_bytes()); // mG
+    }
+    buf
+}
+
+fn hil_sensor(vehicle: &Vehicle, second: f64, accel: Vector) -> Vec<u8> {
+
+    let pos = &vehicle.position;
+    let atmosphere = &vehicle.atmosphere;
+    let pitch = pos.angle().nice_deg().to_radians();
+    let dynamic_pressure = vehicle.airdata().qbar * RHO0;
+
+    let mut buf: Vec<u8> = Vec::with_capacity(65);
+    buf.extend_from_slice(&((second * 1e6) as u64).to_le_bytes());
+    let floats = body_specific_force(vehicle, pitch, accel).into_iter()
+        .chain([0.0, vehicle.motion.angular_velocity(), 0.0]) // gyro
+        .chain([0.0, 0.0, 0.0])                // mag
+        .chain([
+            atmosphere.pressure(pos.y()) / 100.0,         // abs_pressure, hPa
+            dynamic_pressure / 100.0,                     // diff_pressure, hPa
+            atmosphere.pressure_altitude(pos.y()),        // pressure_alt
+            atmosphere.temperature(pos.y()) - 273.15,     // temperature, degC
+        ]);
+    for f in floats { buf.extend_from_slice(&(f as f32).to_le_bytes()); }
+    buf.extend_from_slice(&SENSOR_FIELDS.to_le_bytes());
+    buf.push(0); // id
+    buf
+}
+
+// Specific force (what an accelerometer reads) in the forward-right-down
+// body frame, given the inertial acceleration in the world frame
+fn body_specific_force(vehicle: &Vehicle, pitch: f64, accel: Vector) -> [f64; 3] {
+    let g = vehicle.gravity.at(vehicle.position.y());
+    let body = Vector::new(accel.x(), accel.y() + g)
+        .to_body(Angle::from_radians(pitch));
+    [body.x(), 0.0, -body.y()]
+}
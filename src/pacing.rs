@@ -0,0 +1,44 @@
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Keeps simulation time in step with wall-clock time by sleeping whenever
+// the simulation gets ahead. `speed` is the number of simulated seconds per
+// wall-clock second, so 2.0 runs twice as fast as real time.
+pub struct Pacer {
+    start: Cell<Instant>,
+    speed: f64,
+}
+
+impl Pacer {
+
+    // Constructor, starts the wall clock
+    pub fn new(speed: f64) -> Pacer {
+        Pacer { start: Cell::new(Instant::now()), speed }
+    }
+
+    // Takes up pacing from `second` now, after the simulation clock has
+    // stood still or jumped, as when it's paused or rewound
+    pub fn resync(&self, second: f64) {
+        let ahead = Duration::from_secs_f64((second / self.speed).max(0.0));
+        let now = Instant::now();
+        self.start.set(now.checked_sub(ahead).unwrap_or(now));
+    }
+
+    // Blocks until `second` seconds of simulation time worth of wall-clock
+    // time have elapsed since the pacer was started. Returns immediately if
+    // we're running behind.
+    pub fn wait(&self, second: f64) {
+        let target = Duration::from_secs_f64((second / self.speed).max(0.0));
+        let elapsed = self.start.get().elapsed();
+        if target > elapsed {
+            thread::sleep(target - elapsed);
+        }
+    }
+}
+
+impl Default for Pacer {
+    fn default() -> Pacer {
+        Pacer::new(1.0)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::control::Command;
+use crate::vec::Angle;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// A human pilot flying the pitch attitude. The gain is degrees of elevator
+// per degree of pitch error, the delay is the pilot's reaction time and the
+// lag the time constant of their arm on the stick, both in seconds. The
+// schedule is pairs of a time and the pitch to fly from it, degrees, and the
+// pilot holds whatever pitch they took over at until its first entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PilotSpec {
+    pub gain: f64,
+    pub delay: f64,
+    pub lag: f64,
+    pub max_elevator: f64,
+    pub schedule: Vec<(f64, f64)>,
+}
+
+impl Default for PilotSpec {
+    fn default() -> PilotSpec {
+        PilotSpec {
+            gain: 0.5,
+            delay: 0.25,
+            lag: 0.1,
+            max_elevator: 10.0,
+            schedule: Vec::new(),
+        }
+    }
+}
+
+// The classic crossover model of a pilot closing the pitch loop: a pure
+// gain on the pitch error they see, acted on after a reaction delay and
+// smoothed by the lag of the neuromuscular system. Raising the gain or the
+// delay shows how close a configuration is to a pilot-induced oscillation,
+// which an ideal autopilot never finds.
+#[derive(Debug, Clone)]
+pub struct Pilot {
+    pub spec: PilotSpec,
+    // Pitch held before the schedule starts, degrees
+    hold: Option<f64>,
+    // Errors seen and when, waiting out the reaction delay
+    seen: VecDeque<(f64, f64)>,
+    reacting_to: f64,
+    elevator: f64,
+    last: Option<f64>,
+}
+
+impl Pilot {
+
+    // Constructor, with the stick centred
+    pub fn new(spec: PilotSpec) -> Pilot {
+        Pilot { spec, hold: None, seen: VecDeque::new(), reacting_to: 0.0, elevator: 0.0, last: None }
+    }
+
+    // Pitch the pilot is trying to fly at `time`, degrees
+    pub fn target(&self, time: f64) -> Option<f64> {
+        self.spec.schedule.iter()
+            .take_while(|(start, _)| *start <= time)
+            .last()
+            .map(|&(_, pitch)| pitch)
+            .or(self.hold)
+    }
+
+    // Elevator deflection the pilot is holding, degrees
+    #[inline] pub fn elevator(&self) -> f64 {
+        self.elevator
+    }
+
+    // Flies the vehicle at `time`, seen pitched at `pitch` degrees, leaving
+    // thrust to the autothrottle
+    pub fn command(&mut self, time: f64, pitch: f64) -> Command {
+        let target = *self.hold.get_or_insert(pitch);
+        let target = self.target(time).unwrap_or(target);
+        let error = Angle::from_degrees(target).signed_diff(Angle::from_degrees(pitch)).to_degrees();
+        self.seen.push_back((time, error));
+
+        // React to the latest error old enough to have been taken in
+        while self.seen.front().is_some_and(|(seen, _)| *seen <= time - self.spec.delay) {
+            self.reacting_to = self.seen.pop_front().map_or(0.0, |(_, error)| error);
+        }
+
+        // Negative elevator incidence pitches the nose up
+        let demand = (-self.spec.gain * self.reacting_to).clamp(-self.spec.max_elevator, self.spec.max_elevator);
+        let dt = self.last.map_or(0.0, |last| time - last);
+        self.last = Some(time);
+        self.elevator += (demand - self.elevator) * (dt / (self.spec.lag + dt).max(f64::EPSILON));
+
+        Command { elevator: Angle::from_degrees(self.elevator), throttle: None, speedbrake: None, flaps: None, rating: None, thrust_angle: None }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::atmosphere::{Atmosphere, RHO0};
+use crate::gravity::{Gravity, EARTH_RADIUS, G0};
+
+use serde::{Deserialize, Serialize};
+
+// A body to fly about, as far as the vehicles can tell: the `gravity` at
+// its surface, m/s^2, its `radius`, m, and its atmosphere's density at the
+// surface, kg/m^3, scale height, m, gas constant, J/(kg K), and ratio of
+// specific heats. The atmosphere is taken as isothermal, at the temperature
+// that makes the scale height hold, which is rough through the lower
+// atmosphere but enough for a descent's loads and timing.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Body {
+    pub gravity: f64,
+    pub radius: f64,
+    pub surface_density: f64,
+    pub scale_height: f64,
+    pub gas_constant: f64,
+    #[serde(default = "diatomic")]
+    pub gamma: f64,
+}
+
+// Ratio of specific heats of a diatomic gas such as air or nitrogen
+fn diatomic() -> f64 {
+    1.4
+}
+
+// The body every vehicle flies about, one of the built-in presets or a
+// custom one. The earth keeps its standard atmosphere rather than the
+// isothermal one.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Planet {
+    #[default]
+    Earth,
+    Mars,
+    Titan,
+    Custom(Body),
+}
+
+impl Planet {
+
+    // Gets the body's properties
+    pub fn body(&self) -> Body {
+        match *self {
+            Planet::Earth => Body {
+                gravity: G0,
+                radius: EARTH_RADIUS,
+                surface_density: RHO0,
+                scale_height: 8_500.0,
+                gas_constant: 287.058,
+                gamma: 1.4,
+            },
+            // Carbon dioxide at about 220 K
+            Planet::Mars => Body {
+                gravity: 3.721,
+                radius: 3_389_500.0,
+                surface_density: 0.020,
+                scale_height: 11_100.0,
+                gas_constant: 188.92,
+                gamma: 1.29,
+            },
+            // Nitrogen at about 94 K, four times as dense as the earth's air
+            Planet::Titan => Body {
+                gravity: 1.352,
+                radius: 2_574_700.0,
+                surface_density: 5.43,
+                scale_height: 20_600.0,
+                gas_constant: 296.8,
+                gamma: 1.4,
+            },
+            Planet::Custom(body) => body,
+        }
+    }
+
+    // Gets the inverse-square gravity field about the body
+    pub fn gravity(&self) -> Gravity {
+        let body = self.body();
+        Gravity::InverseSquare { surface: body.gravity, radius: body.radius }
+    }
+
+    // Gets the body's atmosphere, on a day `offset` K hotter than standard
+    // with a relative `humidity` if it's the earth's
+    pub fn atmosphere(&self, offset: f64, humidity: f64) -> Atmosphere {
+        if let Planet::Earth = self {
+            return Atmosphere::Standard { offset, humidity };
+        }
+        let body = self.body();
+        Atmosphere::Exponential {
+            surface_density: body.surface_density,
+            scale_height: body.scale_height,
+            temperature: body.gravity * body.scale_height / body.gas_constant,
+            gas_constant: body.gas_constant,
+            gamma: body.gamma,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::aero::FlapSetting;
+use crate::control::Command;
+use crate::engine::Rating;
+use crate::error::DyntermError;
+use crate::vec::Angle;
+
+use csv::Reader;
+use serde::Deserialize;
+use std::path::Path;
+
+// A row of a control history on disk. An empty throttle hands thrust to the
+// autothrottle.
+#[derive(Debug, Copy, Clone, Deserialize)]
+struct Row {
+    time: f64,
+    elevator_deg: f64,
+    throttle: Option<f64>,
+    #[serde(default)]
+    speedbrake: Option<f64>,
+    #[serde(default)]
+    flaps: Option<FlapSetting>,
+    #[serde(default)]
+    rating: Option<Rating>,
+    #[serde(default)]
+    thrust_angle_deg: Option<f64>,
+}
+
+// An open-loop control history replayed through the model, as recorded in
+// flight test or worked out by an optimiser. It's read from CSV with the
+// columns time, elevator_deg, and throttle, and optionally speedbrake,
+// flaps, the engine rating, and thrust_angle_deg, interpolated linearly
+// between rows, and held at its first and last rows outside them. The
+// throttle steps rather than ramps into and out of stretches left to the
+// autothrottle, and the speedbrake and thrust angle likewise into and out of
+// stretches where they're left alone. The flaps and rating step at each row.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    rows: Vec<Row>,
+}
+
+impl Playback {
+
+    // Reads a control history, whose times must rise from row to row
+    pub fn load(path: impl AsRef<Path>) -> Result<Playback, DyntermError> {
+        let rows = Reader::from_path(path)?.deserialize()
+            .collect::<Result<Vec<Row>, _>>()?;
+
+        if rows.is_empty() {
+            return Err(DyntermError::Parse("no controls in the history".into()));
+        }
+        if let Some(pair) = rows.windows(2).find(|pair| pair[1].time <= pair[0].time) {
+            return Err(DyntermError::Parse(format!("time doesn't rise at {} s", pair[1].time)));
+        }
+        Ok(Playback { rows })
+    }
+
+    // Controls at `time`
+    pub fn at(&self, time: f64) -> Command {
+        let i = self.rows.partition_point(|row| row.time <= time);
+        let (before, after) = match i {
+            0 => (self.rows[0], self.rows[0]),
+            i if i == self.rows.len() => (self.rows[i - 1], self.rows[i - 1]),
+            i => (self.rows[i - 1], self.rows[i]),
+        };
+
+        let t = if after.time > before.time { (time - before.time) / (after.time - before.time) } else { 0.0 };
+        let between = |before: Option<f64>, after: Option<f64>| match (before, after) {
+            (Some(a), Some(b)) => Some(a + t * (b - a)),
+            (before, _) => before,
+        };
+        Command {
+            elevator: Angle::from_degrees(before.elevator_deg + t * (after.elevator_deg - before.elevator_deg)),
+            throttle: between(before.throttle, after.throttle),
+            speedbrake: between(before.speedbrake, after.speedbrake),
+            flaps: before.flaps,
+            rating: before.rating,
+            thrust_angle: between(before.thrust_angle_deg, after.thrust_angle_deg).map(Angle::from_degrees),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::aero::Aerofoil;
+use crate::rk4::rk4;
+use crate::atmosphere::*;
+use crate::gravity::Gravity;
+use crate::updraft::Updrafts;
+use crate::vec::{Angle, Vector3};
+use crate::wind::WindGrid;
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// `PointMass` is a 3-DOF counterpart to `Vehicle` for studying crossrange,
+/// wind drift, and turning manoeuvres. It has no rotational dynamics: the
+/// wing is flown at a commanded angle of attack and steered by banking its
+/// lift vector about the airflow. Positive bank lowers the right wing and
+/// turns towards +z.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PointMass {
+    pub mass: f64,
+    pub position: Vector3,
+    pub motion: Vector3,
+    pub wing: Aerofoil,
+    pub max_thrust: f64,
+    // Throttle setting as a fraction of max thrust
+    pub throttle: f64,
+    pub aoa: Angle,
+    pub bank: Angle,
+    // Steady wind the vehicle drifts with
+    pub wind: Vector3,
+    // Thermals and slope lift laid over the steady wind
+    #[serde(default)]
+    pub updrafts: Updrafts,
+    // Wind varying downrange and with altitude, laid over it too, if any
+    #[serde(default)]
+    pub wind_grid: Option<Arc<WindGrid>>,
+    // Gravity field the vehicle falls in
+    #[serde(default)]
+    pub gravity: Gravity,
+    // Air the vehicle flies through
+    #[serde(default)]
+    pub atmosphere: Atmosphere,
+}
+
+impl PointMass {
+
+    // Constructor, starting wings level at zero angle of attack and throttle
+    // in still air
+    pub fn new(
+        mass: f64,
+        position: Vector3,
+        motion: Vector3,
+        wing: Aerofoil,
+        max_thrust: f64) -> PointMass {
+        PointMass {
+            mass,
+            position,
+            motion,
+            wing,
+            max_thrust,
+            throttle: 0.0,
+            aoa: Angle::from_radians(0.0),
+            bank: Angle::from_radians(0.0),
+            wind: Vector3::zero(),
+            updrafts: Updrafts::default(),
+            wind_grid: None,
+            gravity: Gravity::default(),
+            atmosphere: Atmosphere::default(),
+        }
+    }
+
+    // Velocity relative to the surrounding air
+    #[inline] pub fn airspeed(&self) -> Vector3 {
+        self.motion - self.wind_at(&self.position)
+    }
+
+    // Wind at `p`: the steady wind, with any measured in the atmosphere, any
+    // from the wind grid, and any updraft
+    #[inline] pub fn wind_at(&self, p: &Vector3) -> Vector3 {
+        let mut wind = match self.atmosphere.wind(p.y()) {
+            Some((along, across)) => self.wind + Vector3::new(along, 0.0, across),
+            None => self.wind,
+        };
+        if let Some(grid) = &self.wind_grid {
+            let (along, up) = grid.at(p.x(), p.y());
+            wind += Vector3::new(along, up, 0.0);
+        }
+        if self.updrafts.is_empty() { return wind; }
+        let up = self.updrafts.at(p.x(), p.y(), p.z(), wind.x());
+        wind + Vector3::new(0.0, up, 0.0)
+    }
+
+    // Height above the ground at `p`, over any ridges
+    #[inline] pub fn height(&self, p: &Vector3) -> f64 {
+        p.y() - self.updrafts.ground(p.x())
+    }
+
+    // Whether the vehicle has come down onto the ground, or a ridge
+    #[inline] pub fn grounded(&self) -> bool {
+        self.height(&self.position) <= 0.0
+    }
+
+    // Calculates the acceleration given the current position and velocity
+    #[allow(non_snake_case)]
+    fn calculate_dynamics(&self, p: &Vector3, v: &Vector3) -> Vector3 {
+
+        // Gravitational force acting on the body
+        let W = self.mass * Vector3::new(0.0, -self.gravity.at(p.y()), 0.0);
+
+        let air = *v - self.wind_at(p);
+        let speed = air.magnitude();
+        if speed == 0.0 { return W / self.mass; }
+        let along = air / speed;
+
+        // Lift acts normal to the airflow, in the vertical plane when wings
+        // are level and rolled about the airflow by the bank angle. Straight
+        // up or down there's no vertical plane, so downrange stands in.
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let level = up - up.dot(along) * along;
+        let level = if level.magnitude() > 1e-9 { level.unit() } else { Vector3::new(1.0, 0.0, 0.0) };
+        let right = along.cross(level);
+        let lift_dir = 
+            self.bank.rad().cos() * level + self.bank.rad().sin() * right;
+
+        // Aerodynamic forces acting on the wing
+        let (cl, cd, _) = self.wing.coefficients(self.aoa);
+        let (cl, cd) = self.wing.in_ground_effect(self.height(p), (cl, cd));
+        let cd = cd + self.wing.wave_drag().increment(speed / self.atmosphere.speed_of_sound(p.y()));
+        let q = 0.5 * self.atmosphere.density(p.y()) * speed.powi(2);
+        let L = self.wing.area() * cl * q * lift_dir;
+        let D = -self.wing.area() * cd * q * along;
+
+        // Thrust along the airflow
+        let T = self.throttle.clamp(0.0, 1.0) * self.max_thrust * along;
+
+        (L + D + T + W) / self.mass
+    }
+
+    // Use RK4 to apply the calculated forces to the object over `dt` seconds,
+    // split into n steps
+    pub fn apply_dynamics(&mut self, dt: f64, n: u16) {
+
+        // Time step
+        let h: f64 = dt / n as f64;
+
+        for _ in 0..n {
+
+            // Update the velocity from the acceleration...
+            let f = 
+                |_: f64, v: Vector3| self.calculate_dynamics(&self.position, &v);
+            self.motion = rk4(f, self.motion, 0.0, h);
+
+            // ...and the position from the velocity
+            let f = 
+                |_: f64, _: Vector3| self.motion;
+            self.position = rk4(f, self.position, 0.0, h);
+        }
+    }
+}
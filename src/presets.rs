@@ -0,0 +1,189 @@
+use crate::aero::WaveDrag;
+use crate::config::AerofoilConfig;
+use crate::gravity::Gravity;
+use crate::planet::Planet;
+use crate::scenario::Scenario;
+
+// How a preset's flight begins
+#[derive(Debug, Clone, Copy)]
+pub enum Start {
+    // Altitude, m, speed, m/s, and flight path angle, degrees
+    Airdrop(f64, f64, f64),
+    // Rail length, m, speed leaving it, m/s, and its inclination, degrees
+    Catapult(f64, f64, f64),
+}
+
+// A ready-made vehicle and the conditions it's typically flown from. The
+// same vehicle is set up for every model, with the rigid-body inertias
+// estimated from its size, and all fly in the inverse-square gravity field
+// so high releases and launches compare fairly with low ones. Their wings
+// feel ground effect. Those for other planets fly in theirs. Areas are in
+// m^2 and lengths in m.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub mass: f64,
+    pub length: f64,
+    pub span: f64,
+    pub max_thrust: f64,
+    pub nose_radius: f64,
+    // Throttle the 3-DOF model holds, which has no autothrottle
+    pub throttle: f64,
+    // Area and chord of each surface
+    pub wing: (f64, f64),
+    pub elevator: (f64, f64),
+    pub fin: (f64, f64),
+    pub wave_drag: WaveDrag,
+    // Body it flies about, if not the earth
+    pub planet: Option<Planet>,
+    pub start: Start,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "glider",
+        description: "Light sailplane, released at 1500 m",
+        mass: 400.0,
+        length: 7.0,
+        span: 15.0,
+        max_thrust: 0.0,
+        nose_radius: 0.3,
+        throttle: 0.0,
+        wing: (12.0, 0.9),
+        elevator: (1.5, 0.5),
+        fin: (1.0, 0.8),
+        wave_drag: WaveDrag::None,
+        planet: None,
+        start: Start::Airdrop(1_500.0, 30.0, -3.0),
+    },
+    Preset {
+        name: "transport",
+        description: "Twin-engined transport aircraft cruising at 9000 m",
+        mass: 70_000.0,
+        length: 38.0,
+        span: 34.0,
+        max_thrust: 240_000.0,
+        nose_radius: 1.5,
+        throttle: 0.35,
+        wing: (125.0, 4.2),
+        elevator: (30.0, 2.5),
+        fin: (25.0, 4.0),
+        wave_drag: WaveDrag::Rise { divergence: 0.82, peak: 0.04 },
+        planet: None,
+        start: Start::Airdrop(9_000.0, 230.0, 0.0),
+    },
+    Preset {
+        name: "bomb",
+        description: "Unpowered guided bomb with strakes, dropped at 10000 m",
+        mass: 900.0,
+        length: 3.8,
+        span: 1.2,
+        max_thrust: 0.0,
+        nose_radius: 0.1,
+        throttle: 0.0,
+        wing: (0.8, 0.3),
+        elevator: (0.25, 0.2),
+        fin: (0.25, 0.2),
+        wave_drag: WaveDrag::Rise { divergence: 0.9, peak: 0.1 },
+        planet: None,
+        start: Start::Airdrop(10_000.0, 250.0, 0.0),
+    },
+    Preset {
+        name: "rocket",
+        description: "Finned sounding rocket, launched from a near-vertical rail",
+        mass: 1_200.0,
+        length: 8.0,
+        span: 1.0,
+        max_thrust: 40_000.0,
+        nose_radius: 0.15,
+        throttle: 1.0,
+        wing: (0.3, 0.3),
+        elevator: (0.8, 0.5),
+        fin: (0.8, 0.5),
+        wave_drag: WaveDrag::Rise { divergence: 0.95, peak: 0.15 },
+        planet: None,
+        start: Start::Catapult(10.0, 40.0, 85.0),
+    },
+    Preset {
+        name: "capsule",
+        description: "Lifting-body capsule gliding in from 25000 m",
+        mass: 8_000.0,
+        length: 9.0,
+        span: 4.0,
+        max_thrust: 0.0,
+        nose_radius: 2.0,
+        throttle: 0.0,
+        wing: (25.0, 7.0),
+        elevator: (3.0, 1.5),
+        fin: (2.0, 1.5),
+        wave_drag: WaveDrag::Rise { divergence: 0.85, peak: 0.3 },
+        planet: None,
+        start: Start::Airdrop(25_000.0, 900.0, -5.0),
+    },
+    Preset {
+        name: "lander",
+        description: "Lifting aeroshell descending through the Martian atmosphere from 20000 m",
+        mass: 3_000.0,
+        length: 4.5,
+        span: 4.5,
+        max_thrust: 0.0,
+        nose_radius: 2.25,
+        throttle: 0.0,
+        wing: (16.0, 4.5),
+        elevator: (1.0, 0.6),
+        fin: (0.8, 0.6),
+        wave_drag: WaveDrag::Rise { divergence: 0.85, peak: 0.3 },
+        planet: Some(Planet::Mars),
+        start: Start::Airdrop(20_000.0, 600.0, -15.0),
+    },
+];
+
+// Returns the preset with the given name
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+impl Preset {
+
+    // Builds the scenario flying this vehicle from its start
+    pub fn scenario(&self) -> Scenario {
+        let mut scenario = Scenario {
+            gravity: Gravity::inverse_square(),
+            planet: self.planet,
+            ..Scenario::default()
+        };
+        let surface = |(area, chord): (f64, f64)| AerofoilConfig { area, chord, incidence: 0.0 };
+
+        let v = &mut scenario.vehicle;
+        (v.mass, v.length, v.max_thrust) = (self.mass, self.length, self.max_thrust);
+        (v.nose_radius, v.span) = (self.nose_radius, Some(self.span));
+        (v.wing, v.elevator) = (surface(self.wing), surface(self.elevator));
+        v.wave_drag = self.wave_drag.clone();
+
+        let p = &mut scenario.point_mass;
+        (p.mass, p.max_thrust, p.throttle) = (self.mass, self.max_thrust, self.throttle);
+        p.span = Some(self.span);
+        p.wing = surface(self.wing);
+        p.wave_drag = self.wave_drag.clone();
+        // Wings level, so the 3-DOF run stays in the plane like the others
+        p.bank = 0.0;
+
+        // Inertias of a slender body with wings across it
+        let v = &mut scenario.six_dof;
+        (v.mass, v.length, v.span, v.max_thrust) = (self.mass, self.length, self.span, self.max_thrust);
+        v.fin_height = (2.0 * self.fin.0).sqrt();
+        v.iyy = self.mass * self.length.powi(2) / 12.0;
+        v.ixx = self.mass * self.span.powi(2) / 24.0;
+        v.izz = v.iyy + v.ixx;
+        (v.wing, v.elevator) = (surface(self.wing), surface(self.elevator));
+        v.fin = AerofoilConfig { incidence: v.fin.incidence, ..surface(self.fin) };
+        v.wave_drag = self.wave_drag.clone();
+        v.ground_effect = true;
+
+        match self.start {
+            Start::Airdrop(altitude, speed, gamma) => scenario.with_airdrop(altitude, speed, gamma),
+            Start::Catapult(length, speed, angle) => scenario.with_catapult(length, speed, angle),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Width of the bar, characters
+const WIDTH: usize = 30;
+
+// Least time between redraws
+const REDRAW: Duration = Duration::from_millis(100);
+
+// A progress bar on stderr, for a run's simulated time against how long it
+// could last or a campaign's runs finished against how many there are, with
+// the time left at the rate so far. A hidden bar does nothing, for output
+// that isn't a terminal or runs logging every step over it. It can be
+// advanced from any thread.
+pub struct Progress {
+    label: String,
+    total: f64,
+    unit: &'static str,
+    start: Instant,
+    visible: bool,
+    // When it was last drawn
+    drawn: Mutex<Option<Instant>>,
+}
+
+impl Progress {
+
+    // Constructor, for `total` of `unit` ("s" or "runs") to go
+    pub fn new(label: &str, total: f64, unit: &'static str, visible: bool) -> Progress {
+        Progress {
+            label: label.to_owned(),
+            total,
+            unit,
+            start: Instant::now(),
+            visible,
+            drawn: Mutex::new(None),
+        }
+    }
+
+    // Shows `done` of the total, if the bar's due to be redrawn
+    pub fn set(&self, done: f64) {
+        if !self.visible { return; }
+        let Ok(mut drawn) = self.drawn.lock() else { return };
+        let now = Instant::now();
+        if drawn.is_some_and(|last| now - last < REDRAW) { return; }
+        *drawn = Some(now);
+        self.draw(done, now);
+    }
+
+    // Shows the whole total done, and moves on to a fresh line
+    pub fn finish(&self, done: f64) {
+        if !self.visible { return; }
+        self.draw(done, Instant::now());
+        // Nowhere left to report a failed write to
+        let _ = writeln!(io::stderr());
+    }
+
+    // Helper function
+    fn draw(&self, done: f64, now: Instant) {
+        let fraction = if self.total > 0.0 { (done / self.total).clamp(0.0, 1.0) } else { 1.0 };
+        let filled = (fraction * WIDTH as f64).round() as usize;
+        let bar = format!("{}{}", "=".repeat(filled), " ".repeat(WIDTH - filled));
+
+        let elapsed = (now - self.start).as_secs_f64();
+        let eta = match fraction > 0.0 {
+            true => clock(elapsed * (1.0 - fraction) / fraction),
+            false => "-:--".to_owned(),
+        };
+        let (precision, width) = if self.unit == "s" { (1, 6) } else { (0, 4) };
+        let _ = write!(io::stderr().lock(), "\r{} [{}] {:>width$.precision$}/{:.precision$} {} {:>3.0}%  ETA {}\x1b[K",
+            self.label, bar, done, self.total, self.unit, 100.0 * fraction, eta,
+            width = width, precision = precision);
+    }
+}
+
+// Formats seconds as m:ss, or h:mm:ss past the hour
+fn clock(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
@@ -0,0 +1,114 @@
+use crate::scalar::Scalar;
+
+use core::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+// Blade elements along each blade, from the hub out to the tip
+const STATIONS: usize = 8;
+
+// Passes settling the induced inflow at each element
+const PASSES: usize = 8;
+
+// Hub radius, as a fraction of the tip radius
+const HUB: f64 = 0.15;
+
+// Blade section: lift curve slope, per radian, the lift it stalls at, and
+// its drag polar
+const LIFT_SLOPE: f64 = 5.7;
+const MAX_LIFT: f64 = 1.2;
+const PROFILE_DRAG: f64 = 0.012;
+const DRAG_FACTOR: f64 = 0.01;
+
+// A fixed-pitch propeller, worked out blade element by blade element with
+// the inflow each induces from momentum theory, so its thrust and torque
+// fall away with airspeed and thinning air. `pitch` is the geometric pitch,
+// m advanced per turn, and `rpm` the speed at full throttle, with the
+// throttle setting the speed in proportion. With too little power to turn
+// it faster, it windmills, at the speed at which the air turns it against
+// its own blades' drag, and drags. The chord, m, left out, is 8% of the
+// diameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Propeller {
+    pub diameter: f64,
+    pub pitch: f64,
+    pub rpm: f64,
+    #[serde(default = "two_blades")]
+    pub blades: u32,
+    #[serde(default)]
+    pub chord: Option<f64>,
+}
+
+// Blades of most light aircraft propellers
+fn two_blades() -> u32 {
+    2
+}
+
+impl Propeller {
+
+    // Constructor
+    pub fn new(diameter: f64, pitch: f64, rpm: f64) -> Propeller {
+        Propeller { diameter, pitch, rpm, blades: 2, chord: None }
+    }
+
+    #[inline] pub fn chord(&self) -> f64 {
+        self.chord.unwrap_or(0.08 * self.diameter)
+    }
+
+    // Gets the speed at which it windmills with air coming at `airspeed`
+    // along its axis, rpm: where the element three quarters of the way out
+    // meets the air just enough below edge on for its lift to turn it
+    // against its drag
+    pub fn windmill_rpm<S: Scalar>(&self, airspeed: S) -> S {
+        let r = 0.375 * self.diameter;
+        let twist = libm::atan(self.pitch / (2.0 * PI * r));
+        let phi = twist + PROFILE_DRAG / (LIFT_SLOPE * libm::tan(twist));
+        airspeed.max(S::ZERO) * S::from_f64(60.0 / (2.0 * PI * r * libm::tan(phi)))
+    }
+
+    // Gets the speed it turns at with the throttle at `throttle`, rpm
+    pub fn operating_rpm<S: Scalar>(&self, airspeed: S, throttle: S) -> S {
+        (throttle.clamp(S::ZERO, S::ONE) * S::from_f64(self.rpm)).max(self.windmill_rpm(airspeed))
+    }
+
+    // Gets the thrust, N, and the torque turning it, N m, at `rpm` with air
+    // of `density`, kg/m^3, coming at `airspeed` along its axis
+    pub fn loads<S: Scalar>(&self, airspeed: S, density: S, rpm: S) -> (S, S) {
+        if density <= S::ZERO { return (S::ZERO, S::ZERO); }
+
+        let radius = self.diameter / 2.0;
+        let step = radius * (1.0 - HUB) / STATIONS as f64;
+        let omega = rpm * S::from_f64(2.0 * PI / 60.0);
+        let solidity = S::from_f64(self.blades as f64 * self.chord());
+        let half = S::from_f64(0.5);
+        let airspeed = airspeed.max(S::ZERO);
+
+        let (mut thrust, mut torque) = (S::ZERO, S::ZERO);
+        for i in 0..STATIONS {
+            let r = radius * HUB + step * (i as f64 + 0.5);
+            let twist = S::from_f64(libm::atan(self.pitch / (2.0 * PI * r)));
+            let spin = omega * S::from_f64(r);
+            let annulus = S::from_f64(4.0 * PI * r) * density;
+
+            // Thrust and torque per unit radius, with the inflow each
+            // element induces settled between blade element and momentum
+            let mut induced = S::ZERO;
+            let mut element = (S::ZERO, S::ZERO);
+            for _ in 0..PASSES {
+                let axial = airspeed + induced;
+                let phi = axial.atan2(spin);
+                let lift = (S::from_f64(LIFT_SLOPE) * (twist - phi)).clamp(S::from_f64(-MAX_LIFT), S::from_f64(MAX_LIFT));
+                let drag = S::from_f64(PROFILE_DRAG) + S::from_f64(DRAG_FACTOR) * lift * lift;
+                let q = half * density * (axial * axial + spin * spin) * solidity;
+                let (sin, cos) = phi.sin_cos();
+                element = (q * (lift * cos - drag * sin), q * (lift * sin + drag * cos) * S::from_f64(r));
+
+                let disc = airspeed * airspeed + S::from_f64(4.0) * element.0 / annulus;
+                let settled = if disc > S::ZERO { half * (disc.sqrt() - airspeed) } else { -half * airspeed };
+                induced = half * (induced + settled);
+            }
+            thrust += element.0 * S::from_f64(step);
+            torque += element.1 * S::from_f64(step);
+        }
+        (thrust, torque)
+    }
+}
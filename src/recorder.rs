@@ -0,0 +1,199 @@
+use crate::aero::Vehicle;
+use crate::error::DyntermError;
+
+use csv::{Reader, Writer};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+// One point of a channel, in the form `plot_scatter` takes
+pub type Sample = (f64, f64, f64);
+
+// A row of a recording on disk
+#[derive(Serialize, Deserialize)]
+struct Row<C> {
+    channel: C,
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+// Running statistics of a channel, kept as its samples are recorded, so a
+// streamed recording can be summed up without reading it back
+#[derive(Debug, Copy, Clone)]
+pub struct ChannelStats {
+    pub samples: usize,
+    // Least and greatest of each coordinate, and the latest sample
+    pub min: Sample,
+    pub max: Sample,
+    pub last: Sample,
+}
+
+impl ChannelStats {
+
+    // Constructor, from a channel's first sample
+    fn new(sample: Sample) -> ChannelStats {
+        ChannelStats { samples: 1, min: sample, max: sample, last: sample }
+    }
+
+    // Helper function
+    fn add(&mut self, (x, y, z): Sample) {
+        self.samples += 1;
+        self.min = (self.min.0.min(x), self.min.1.min(y), self.min.2.min(z));
+        self.max = (self.max.0.max(x), self.max.1.max(y), self.max.2.max(z));
+        self.last = (x, y, z);
+    }
+}
+
+// Where samples go as they're recorded
+enum Sink {
+    Memory,
+    File { writer: Box<Writer<File>>, error: Option<io::Error> },
+}
+
+// Collects named channels of samples over a run, growing only as samples
+// arrive. Samples that aren't finite, such as thrust before the dynamics
+// have first been evaluated, are dropped so they can't upset the plots.
+// Streaming to a file writes each sample out as CSV instead of keeping it,
+// for runs too long to hold in memory. Either way each channel's statistics
+// are kept as it goes.
+pub struct Recorder {
+    channels: Vec<(String, Vec<Sample>)>,
+    stats: Vec<(String, ChannelStats)>,
+    sink: Sink,
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+impl Recorder {
+
+    // Constructor, for a recording kept in memory
+    pub fn new() -> Recorder {
+        Recorder { channels: Vec::new(), stats: Vec::new(), sink: Sink::Memory }
+    }
+
+    // Constructor, for a recording streamed to `path`
+    pub fn streaming(path: impl AsRef<Path>) -> io::Result<Recorder> {
+        let writer = Box::new(Writer::from_path(path)?);
+        Ok(Recorder { channels: Vec::new(), stats: Vec::new(), sink: Sink::File { writer, error: None } })
+    }
+
+    // Reads a streamed recording back into memory
+    pub fn load(path: impl AsRef<Path>) -> Result<Recorder, DyntermError> {
+        let mut recorder = Recorder::new();
+        for row in Reader::from_path(path)?.deserialize() {
+            let row: Row<String> = row?;
+            recorder.record(&row.channel, (row.x, row.y, row.z));
+        }
+        Ok(recorder)
+    }
+
+    // Records a sample on the named channel. Errors writing to the file are
+    // held until `finish`.
+    pub fn record(&mut self, channel: &str, sample: Sample) {
+        if !(sample.0.is_finite() && sample.1.is_finite() && sample.2.is_finite()) {
+            return;
+        }
+
+        match self.stats.iter_mut().find(|(name, _)| name == channel) {
+            Some((_, stats)) => stats.add(sample),
+            None => self.stats.push((channel.to_owned(), ChannelStats::new(sample))),
+        }
+
+        match &mut self.sink {
+            Sink::Memory => self.push(channel, sample),
+            Sink::File { writer, error: error @ None, .. } => {
+                let row = Row { channel, x: sample.0, y: sample.1, z: sample.2 };
+                if let Err(e) = writer.serialize(row) {
+                    *error = Some(e.into());
+                }
+            }
+            Sink::File { .. } => {}
+        }
+    }
+
+    // Records each of the vehicle's external forces at its current state on
+    // a channel of its own name, kN, and its moment on the name followed by
+    // "_moment", kN m
+    pub fn record_external_forces(&mut self, time: f64, vehicle: &Vehicle) {
+        for (name, force, moment) in vehicle.external_forces() {
+            self.record(name, (time, force.magnitude() / 1_000.0, 0.0));
+            self.record(&format!("{}_moment", name), (time, moment / 1_000.0, 0.0));
+        }
+    }
+
+    // Returns the samples recorded on a channel so far, which is none for a
+    // channel that's never been recorded or while streaming to a file
+    pub fn channel(&self, channel: &str) -> &[Sample] {
+        self.channels.iter()
+            .find(|(name, _)| name == channel)
+            .map_or(&[], |(_, samples)| samples)
+    }
+
+    // Returns every channel recorded so far, by name, in the order each was
+    // first recorded
+    pub fn channels(&self) -> impl Iterator<Item = (&str, &[Sample])> {
+        self.channels.iter().map(|(name, samples)| (name.as_str(), &samples[..]))
+    }
+
+    // Returns every channel's statistics so far, in the order each was first
+    // recorded
+    pub fn stats(&self) -> impl Iterator<Item = (&str, &ChannelStats)> {
+        self.stats.iter().map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    // Ends the recording. A streamed recording is flushed, but not read
+    // back, so it holds no samples, only each channel's statistics.
+    pub fn finish(self) -> Result<Recorder, DyntermError> {
+        match self.sink {
+            Sink::Memory => Ok(self),
+            Sink::File { error: Some(e), .. } => Err(e.into()),
+            Sink::File { mut writer, error: None } => {
+                writer.flush()?;
+                Ok(Recorder { channels: self.channels, stats: self.stats, sink: Sink::Memory })
+            }
+        }
+    }
+
+    // Helper function
+    fn push(&mut self, channel: &str, sample: Sample) {
+        match self.channels.iter_mut().find(|(name, _)| name == channel) {
+            Some((_, samples)) => samples.push(sample),
+            None => self.channels.push((channel.to_owned(), vec![sample])),
+        }
+    }
+}
+
+// Picks out the steps to record at a fixed rate, independent of the rate the
+// simulation is stepped at: the first step at or after each tick. Integrate
+// finely and record coarsely, rather than keeping every step.
+pub struct LogRate {
+    start: f64,
+    rate: f64,
+    ticks: u64,
+}
+
+impl LogRate {
+
+    // Constructor, ticking `rate` times a second from `start`
+    pub fn new(start: f64, rate: f64) -> LogRate {
+        LogRate { start, rate, ticks: 0 }
+    }
+
+    // Whether the step at `time` should be recorded
+    pub fn due(&mut self, time: f64) -> bool {
+        // Allow for the rounding in a clock built up from many steps
+        const SLACK: f64 = 1e-9;
+
+        if time < self.start + self.ticks as f64 / self.rate - SLACK {
+            return false;
+        }
+        self.ticks = ((time - self.start + SLACK) * self.rate).floor() as u64 + 1;
+        true
+    }
+}
@@ -0,0 +1,260 @@
+use crate::recorder::Sample;
+
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// One chart of a report, as plot_scatter would draw it
+#[derive(Serialize)]
+struct ReportChart {
+    title: String,
+    x_label: String,
+    y_label: String,
+    log_y: bool,
+    data: Vec<Sample>,
+}
+
+// A run written up as a single HTML page for sharing with people who won't
+// run the simulator: the summary as a table, and every chart plotted over
+// the run, zoomable in the browser. The page carries its data and the few
+// lines of script that draw it, so it needs nothing else to open, offline
+// or mailed on.
+pub struct Report {
+    title: String,
+    summary: Vec<(String, String)>,
+    charts: Vec<ReportChart>,
+}
+
+impl Report {
+
+    // Constructor, for an empty report headed `title`
+    pub fn new(title: &str) -> Report {
+        Report { title: title.to_owned(), summary: Vec::new(), charts: Vec::new() }
+    }
+
+    // Adds the lines of a printed summary to the table, each split into its
+    // label and value where two spaces first part them. Lines without a
+    // value, such as the summary's heading, head the rows after them.
+    pub fn summarise(&mut self, summary: &impl fmt::Display) {
+        for line in summary.to_string().lines() {
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            let row = match line.split_once("  ") {
+                Some((label, value)) => (label.to_owned(), value.trim().to_owned()),
+                None => (line.to_owned(), String::new()),
+            };
+            self.summary.push(row);
+        }
+    }
+
+    // Adds a chart of `data`, on a log scale if `log_y`. Charts without any
+    // data are left out, as they are from the plots.
+    pub fn add(&mut self, title: &str, x_label: &str, y_label: &str, log_y: bool, data: Vec<Sample>) {
+        if data.is_empty() { return; }
+        self.charts.push(ReportChart {
+            title: title.to_owned(),
+            x_label: x_label.to_owned(),
+            y_label: y_label.to_owned(),
+            log_y,
+            data,
+        });
+    }
+
+    // Writes the report to `path`
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.html()?)
+    }
+
+    // Renders the report as a page
+    fn html(&self) -> io::Result<String> {
+        let mut table = String::new();
+        for (label, value) in &self.summary {
+            table.push_str(&match value.is_empty() {
+                true => format!("<tr><th colspan=\"2\">{}</th></tr>\n", escape(label)),
+                false => format!("<tr><td>{}</td><td>{}</td></tr>\n", escape(label), escape(value)),
+            });
+        }
+
+        // A '<' could only be inside a string, where its escape reads the
+        // same, and couldn't close the script early
+        let charts = serde_json::to_string(&self.charts)?.replace('<', "\\u003c");
+
+        Ok(TEMPLATE
+            .replace("{title}", &escape(&self.title))
+            .replace("{summary}", &table)
+            .replace("{charts}", &charts))
+    }
+}
+
+// Escapes text for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// The page, with {title}, {summary}, and {charts} filled in. Points are
+// shaded by their third element as on the PNG charts.
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body { font-family: sans-serif; margin: 2em auto; max-width: 1100px; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th { text-align: left; padding: 0.8em 0 0.3em; }
+td { padding: 0.15em 2em 0.15em 0; font-variant-numeric: tabular-nums; }
+figure { margin: 0 0 2em; }
+figcaption { font-weight: bold; margin-bottom: 0.3em; }
+canvas { width: 100%; height: 340px; cursor: crosshair; display: block; }
+.readout { height: 1.2em; font-size: 0.85em; color: #555; }
+.hint { color: #555; font-size: 0.9em; }
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+{summary}</table>
+<p class="hint">Drag a box to zoom in, scroll to zoom about the pointer, and double-click to zoom back out.</p>
+<div id="charts"></div>
+<script type="application/json" id="data">{charts}</script>
+<script>
+"use strict";
+const margin = { left: 80, right: 20, top: 10, bottom: 45 };
+
+// Round steps for about n ticks between lo and hi
+function ticks(lo, hi, n) {
+  const span = hi - lo, magnitude = Math.pow(10, Math.floor(Math.log10(span / n)));
+  const step = [1, 2, 5, 10].map(f => f * magnitude).find(s => span / s <= n);
+  const values = [];
+  for (let i = Math.ceil(lo / step); i * step <= hi + 1e-9 * step; i++) values.push(i * step);
+  return { values, step };
+}
+
+function label(v, step, log) {
+  if (log) return Math.pow(10, v).toExponential(1);
+  if (v !== 0 && (Math.abs(v) >= 1e6 || Math.abs(v) < 1e-4)) return v.toExponential(2);
+  return v.toFixed(Math.min(Math.max(0, -Math.floor(Math.log10(step))), 6));
+}
+
+function extent(data, i) {
+  let lo = Infinity, hi = -Infinity;
+  for (const p of data) { lo = Math.min(lo, p[i]); hi = Math.max(hi, p[i]); }
+  return hi > lo ? [lo, hi] : [lo - 1, hi + 1];
+}
+
+function shade(z) {
+  return `rgb(${Math.floor(255 * Math.min(Math.max(z / 90, 0), 1))},0,0)`;
+}
+
+for (const chart of JSON.parse(document.getElementById("data").textContent)) {
+  const data = chart.log_y
+    ? chart.data.filter(p => p[1] > 0).map(([x, y, z]) => [x, Math.log10(y), z])
+    : chart.data;
+  if (data.length === 0) continue;
+
+  const figure = document.createElement("figure");
+  figure.innerHTML = "<figcaption></figcaption><canvas></canvas><div class=\"readout\"></div>";
+  figure.querySelector("figcaption").textContent = chart.title;
+  document.getElementById("charts").appendChild(figure);
+  const canvas = figure.querySelector("canvas"), readout = figure.querySelector(".readout");
+  const ctx = canvas.getContext("2d");
+
+  const full = { x: extent(data, 0), y: extent(data, 1) };
+  let view = { x: full.x, y: full.y }, drag = null, w = 0, h = 0;
+  const px = x => margin.left + (x - view.x[0]) / (view.x[1] - view.x[0]) * (w - margin.left - margin.right);
+  const py = y => h - margin.bottom - (y - view.y[0]) / (view.y[1] - view.y[0]) * (h - margin.top - margin.bottom);
+  const ux = X => view.x[0] + (X - margin.left) / (w - margin.left - margin.right) * (view.x[1] - view.x[0]);
+  const uy = Y => view.y[0] + (h - margin.bottom - Y) / (h - margin.top - margin.bottom) * (view.y[1] - view.y[0]);
+
+  function draw() {
+    const ratio = window.devicePixelRatio || 1;
+    w = canvas.clientWidth; h = canvas.clientHeight;
+    canvas.width = w * ratio; canvas.height = h * ratio;
+    ctx.setTransform(ratio, 0, 0, ratio, 0, 0);
+    ctx.clearRect(0, 0, w, h);
+    ctx.font = "12px sans-serif";
+    ctx.lineWidth = 1;
+
+    const xt = ticks(view.x[0], view.x[1], 8), yt = ticks(view.y[0], view.y[1], 6);
+    ctx.strokeStyle = "#ddd"; ctx.fillStyle = "#222";
+    ctx.textAlign = "center"; ctx.textBaseline = "top";
+    for (const t of xt.values) {
+      ctx.beginPath(); ctx.moveTo(px(t), margin.top); ctx.lineTo(px(t), h - margin.bottom); ctx.stroke();
+      ctx.fillText(label(t, xt.step, false), px(t), h - margin.bottom + 5);
+    }
+    ctx.textAlign = "right"; ctx.textBaseline = "middle";
+    for (const t of yt.values) {
+      ctx.beginPath(); ctx.moveTo(margin.left, py(t)); ctx.lineTo(w - margin.right, py(t)); ctx.stroke();
+      ctx.fillText(label(t, yt.step, chart.log_y), margin.left - 5, py(t));
+    }
+    ctx.strokeStyle = "#222";
+    ctx.strokeRect(margin.left, margin.top, w - margin.left - margin.right, h - margin.top - margin.bottom);
+    ctx.textAlign = "center"; ctx.textBaseline = "bottom";
+    ctx.fillText(chart.x_label, (margin.left + w - margin.right) / 2, h - 2);
+    ctx.save();
+    ctx.translate(2, (margin.top + h - margin.bottom) / 2); ctx.rotate(-Math.PI / 2);
+    ctx.textBaseline = "top"; ctx.fillText(chart.y_label, 0, 0);
+    ctx.restore();
+
+    ctx.save();
+    ctx.beginPath();
+    ctx.rect(margin.left, margin.top, w - margin.left - margin.right, h - margin.top - margin.bottom);
+    ctx.clip();
+    for (const [x, y, z] of data) {
+      ctx.fillStyle = shade(z);
+      ctx.fillRect(px(x) - 1.5, py(y) - 1.5, 3, 3);
+    }
+    ctx.restore();
+
+    if (drag && drag.to) {
+      ctx.fillStyle = "rgba(0, 90, 200, 0.15)";
+      ctx.fillRect(drag.from[0], drag.from[1], drag.to[0] - drag.from[0], drag.to[1] - drag.from[1]);
+    }
+  }
+
+  const at = e => { const r = canvas.getBoundingClientRect(); return [e.clientX - r.left, e.clientY - r.top]; };
+  canvas.addEventListener("mousedown", e => { drag = { from: at(e), to: null }; });
+  canvas.addEventListener("mousemove", e => {
+    const p = at(e);
+    if (drag) { drag.to = p; draw(); }
+    // The nearest point within 10 px
+    let nearest = null, best = 100;
+    for (const q of data) {
+      const dx = px(q[0]) - p[0], dy = py(q[1]) - p[1];
+      if (dx * dx + dy * dy < best) { best = dx * dx + dy * dy; nearest = q; }
+    }
+    readout.textContent = nearest
+      ? `${chart.x_label} ${nearest[0].toPrecision(6)}, ${chart.y_label} ${(chart.log_y ? Math.pow(10, nearest[1]) : nearest[1]).toPrecision(6)}`
+      : "";
+  });
+  window.addEventListener("mouseup", () => {
+    if (!drag) return;
+    const { from, to } = drag;
+    drag = null;
+    if (to && Math.abs(to[0] - from[0]) > 4 && Math.abs(to[1] - from[1]) > 4) {
+      view = {
+        x: [ux(from[0]), ux(to[0])].sort((a, b) => a - b),
+        y: [uy(from[1]), uy(to[1])].sort((a, b) => a - b),
+      };
+    }
+    draw();
+  });
+  canvas.addEventListener("wheel", e => {
+    e.preventDefault();
+    const [X, Y] = at(e), k = Math.exp(e.deltaY * 0.001), cx = ux(X), cy = uy(Y);
+    view = {
+      x: [cx + (view.x[0] - cx) * k, cx + (view.x[1] - cx) * k],
+      y: [cy + (view.y[0] - cy) * k, cy + (view.y[1] - cy) * k],
+    };
+    draw();
+  }, { passive: false });
+  canvas.addEventListener("dblclick", () => { view = { x: full.x, y: full.y }; draw(); });
+  window.addEventListener("resize", draw);
+  draw();
+}
+</script>
+</body>
+</html>
+"##;
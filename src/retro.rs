@@ -0,0 +1,81 @@
+use crate::gravity::G0;
+
+use serde::{Deserialize, Serialize};
+
+// What lights the retro-rocket
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetroTrigger {
+    // Descending through `altitude`, m
+    Altitude { altitude: f64 },
+    // With `seconds` to go to the ground at the current descent rate
+    TimeToGo { seconds: f64 },
+    // As late as it can be lit and still get down to the touchdown speed,
+    // with `margin` on the height it needs, as a fraction of it
+    Guided {
+        #[serde(default)]
+        margin: f64,
+    },
+}
+
+// A retro-rocket of `thrust`, N, and specific impulse `isp`, s, with `fuel`,
+// kg, braking the vehicle against its motion over the ground for a
+// touchdown at `touchdown_speed`, m/s. Once lit it burns until the vehicle
+// is down to that speed, or climbing, or the fuel's gone, and lights again
+// whenever the trigger holds and the vehicle is faster, so it holds the
+// touchdown speed down to the ground while the fuel lasts. The fuel it
+// burns comes off the vehicle's mass.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Retro {
+    pub thrust: f64,
+    pub isp: f64,
+    pub fuel: f64,
+    pub touchdown_speed: f64,
+    pub trigger: RetroTrigger,
+    #[serde(default)]
+    pub burning: bool,
+}
+
+impl Retro {
+
+    // Gets the thrust it's giving, N
+    pub fn thrust_now(&self) -> f64 {
+        if self.burning && self.fuel > 0.0 { self.thrust } else { 0.0 }
+    }
+
+    // Gets the height, m, it needs to brake from `speed` to the touchdown
+    // speed descending at `sin_descent` of the way straight down, with
+    // `mass`, kg, in `gravity`, m/s^2, unbounded if it can't
+    pub fn braking_height(&self, speed: f64, sin_descent: f64, mass: f64, gravity: f64) -> f64 {
+        let deceleration = self.thrust / mass - gravity * sin_descent;
+        if deceleration <= 0.0 { return f64::INFINITY; }
+        let distance = (speed * speed - self.touchdown_speed * self.touchdown_speed).max(0.0) / (2.0 * deceleration);
+        distance * sin_descent
+    }
+
+    // Lights or cuts the rocket at `altitude`, m, moving at `velocity`, m/s,
+    // with `mass`, kg, in `gravity`, m/s^2
+    pub fn guide(&mut self, altitude: f64, velocity: (f64, f64), mass: f64, gravity: f64) {
+        let (vx, vy) = velocity;
+        let speed = libm::hypot(vx, vy);
+        if self.fuel <= 0.0 || speed <= self.touchdown_speed || vy >= 0.0 {
+            self.burning = false;
+            return;
+        }
+        if self.burning { return; }
+
+        self.burning = match self.trigger {
+            RetroTrigger::Altitude { altitude: light } => altitude <= light,
+            RetroTrigger::TimeToGo { seconds } => altitude / -vy <= seconds,
+            RetroTrigger::Guided { margin } =>
+                altitude <= self.braking_height(speed, -vy / speed, mass, gravity) * (1.0 + margin),
+        };
+    }
+
+    // Burns for `dt` seconds, returning the fuel it burned, kg
+    pub fn burn(&mut self, dt: f64) -> f64 {
+        let burned = (self.thrust_now() / (self.isp * G0) * dt).min(self.fuel);
+        self.fuel -= burned;
+        burned
+    }
+}
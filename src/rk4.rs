@@ -1,21 +1,313 @@
-use std::ops::{Add, Mul, Div};
-
-pub fn rk4<F, T>(f: F, x: T, t: f64, h: f64) -> T
-where 
-    F: Fn(f64, T) -> T,
-    T: Copy
-        + Add<T, Output = T>
-        + Div<f64, Output = T>,
-    f64: Mul<T, Output = T>,
+use crate::scalar::Scalar;
+
+use alloc::vec;
+use serde::{Deserialize, Serialize};
+
+// Relative perturbation of each component for the finite-difference
+// Jacobians, about the square root of the machine epsilon
+const JACOBIAN_STEP: f64 = 1.5e-8;
+
+// Newton iterations allowed the implicit midpoint rule, and the relative
+// change at which they've converged
+const MAX_NEWTON: usize = 8;
+const NEWTON_TOLERANCE: f64 = 1e-10;
+
+// Pieces a step's dense output is scanned in for where a function of the
+// state first changes sign
+const CROSSING_SCAN: usize = 8;
+
+// How a vehicle's state is stepped on
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    // The classic fourth-order Runge-Kutta method, explicit, in the
+    // simulation's usual substeps
+    #[default]
+    Rk4,
+    // The implicit midpoint rule, solved by Newton iteration on
+    // finite-difference Jacobians, in substeps of up to `substep` s. Stable
+    // however heavily damped the vehicle, where an explicit method would need
+    // far smaller steps.
+    ImplicitMidpoint { substep: f64 },
+}
+
+impl Integrator {
+
+    // Largest substep to take, s, given the explicit methods' `explicit`
+    pub fn max_substep(&self, explicit: f64) -> f64 {
+        match *self {
+            Integrator::Rk4 => explicit,
+            Integrator::ImplicitMidpoint { substep } => substep,
+        }
+    }
+}
+
+// A state the integrators can step: a vector over the scalar S, worked on
+// in place rather than through a fresh value for every operation, and
+// sized by a norm for checking an error against a tolerance
+pub trait State<S: Scalar>: Clone {
+    // Adds `a` times `x` to this, component by component
+    fn axpy(&mut self, a: S, x: &Self);
+
+    // Multiplies every component by `a`
+    fn scale(&mut self, a: S);
+
+    // Divides every component by `d`, exactly rather than through its
+    // reciprocal
+    fn divide(&mut self, d: S);
+
+    // Largest magnitude of any component
+    fn error_norm(&self) -> S;
+
+    // Number of components, and each by index, for the implicit methods'
+    // Jacobians
+    fn dimension(&self) -> usize;
+    fn component(&self, i: usize) -> S;
+    fn set_component(&mut self, i: usize, value: S);
+}
+
+// Steps `x` at time `t` on by `h` along the derivative `f` with the classic
+// fourth-order Runge-Kutta method, in place
+pub fn rk4_step<S, F, T>(mut f: F, x: &mut T, t: S, h: S)
+where
+    S: Scalar,
+    F: FnMut(S, &T) -> T,
+    T: State<S>,
 {
-    let half_h = h/2.0;
+    let half = S::from_f64(0.5);
+    let two = S::from_f64(2.0);
+    let half_h = h/two;
 
-    // Calculate the four intermediate RK4 values (k1, k2, k3, and k4)
-    let k1 = h * f(t, x);
-    let k2 = h * f(t + half_h, x + (0.5 * k1));
-    let k3 = h * f(t + half_h, x + (0.5 * k2));
-    let k4 = h * f(t + h, x + k3);
+    // Calculate the four intermediate RK4 values (k1, k2, k3, and k4), each
+    // from the state nudged along the last
+    let mut k1 = f(t, x);
+    k1.scale(h);
+    let mut probe = x.clone();
+    probe.axpy(half, &k1);
+    let mut k2 = f(t + half_h, &probe);
+    k2.scale(h);
+    probe.clone_from(x);
+    probe.axpy(half, &k2);
+    let mut k3 = f(t + half_h, &probe);
+    k3.scale(h);
+    probe.clone_from(x);
+    probe.axpy(S::ONE, &k3);
+    let mut k4 = f(t + h, &probe);
+    k4.scale(h);
 
     // Update the solution vector with the weighted sum of the intermediate values
-    x + (k1 + (2.0 * k2) + (2.0 * k3) + k4) / 6.0
-}
\ No newline at end of file
+    k1.axpy(two, &k2);
+    k1.axpy(two, &k3);
+    k1.axpy(S::ONE, &k4);
+    k1.divide(S::from_f64(6.0));
+    x.axpy(S::ONE, &k1);
+}
+
+// As `rk4_step`, taking and giving the state by value
+pub fn rk4<S, F, T>(f: F, x: T, t: S, h: S) -> T
+where
+    S: Scalar,
+    F: Fn(S, T) -> T,
+    T: State<S>,
+{
+    let mut x = x;
+    rk4_step(|t, x: &T| f(t, x.clone()), &mut x, t, h);
+    x
+}
+
+// Steps `x` at time `t` on by `h` along the derivative `f` with the implicit
+// midpoint rule, in place, returning the evaluations of `f` it took and
+// whether the Newton iteration converged. The midpoint state m solves
+// m = x + h/2 f(t + h/2, m), found by Newton iteration from x with the
+// Jacobian of f differenced afresh each time, and the step ends at 2m - x.
+// If the iteration runs out or meets a singular Jacobian, the step ends
+// from the last m it reached.
+pub fn implicit_midpoint_step<S, F, T>(mut f: F, x: &mut T, t: S, h: S) -> (usize, bool)
+where
+    S: Scalar,
+    F: FnMut(S, &T) -> T,
+    T: State<S>,
+{
+    let n = x.dimension();
+    let half_h = h * S::from_f64(0.5);
+    let t_mid = t + half_h;
+    let mut evaluations = 0;
+    let mut converged = false;
+
+    let mut m = x.clone();
+    let mut jacobian = vec![S::ZERO; n * n];
+    let mut residual = vec![S::ZERO; n];
+    for _ in 0..MAX_NEWTON {
+        let rate = f(t_mid, &m);
+        evaluations += 1;
+        for (i, r) in residual.iter_mut().enumerate() {
+            *r = m.component(i) - x.component(i) - half_h * rate.component(i);
+        }
+
+        // The Jacobian of the residual, I - h/2 df/dm, a column at a time
+        for j in 0..n {
+            let value = m.component(j);
+            let delta = S::from_f64(JACOBIAN_STEP) * value.abs().max(S::ONE);
+            let mut probe = m.clone();
+            probe.set_component(j, value + delta);
+            let nudged = f(t_mid, &probe);
+            evaluations += 1;
+            for i in 0..n {
+                let identity = if i == j { S::ONE } else { S::ZERO };
+                jacobian[i * n + j] = identity - half_h * (nudged.component(i) - rate.component(i)) / delta;
+            }
+        }
+
+        if !solve(&mut jacobian, &mut residual) { break; }
+        for (i, change) in residual.iter().enumerate() {
+            m.set_component(i, m.component(i) - *change);
+        }
+        let change = residual.iter().fold(S::ZERO, |largest, change| largest.max(change.abs()));
+        if change <= S::from_f64(NEWTON_TOLERANCE) * (S::ONE + m.error_norm()) {
+            converged = true;
+            break;
+        }
+    }
+
+    m.scale(S::from_f64(2.0));
+    m.axpy(-S::ONE, x);
+    *x = m;
+    (evaluations, converged)
+}
+
+// Solves the square system `a` y = `b` by Gaussian elimination with partial
+// pivoting, `a` stored by rows, leaving y in `b`. False if `a` is singular.
+fn solve<S: Scalar>(a: &mut [S], b: &mut [S]) -> bool {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i * n + col].abs().partial_cmp(&a[j * n + col].abs()).unwrap_or(core::cmp::Ordering::Less))
+            .unwrap_or(col);
+        if a[pivot * n + col] == S::ZERO { return false; }
+        if pivot != col {
+            for k in 0..n { a.swap(pivot * n + k, col * n + k); }
+            b.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                let above = a[col * n + k];
+                a[row * n + k] -= factor * above;
+            }
+            let above = b[col];
+            b[row] -= factor * above;
+        }
+    }
+    for row in (0..n).rev() {
+        let known = (row + 1..n).fold(S::ZERO, |sum, k| sum + a[row * n + k] * b[k]);
+        b[row] = (b[row] - known) / a[row * n + row];
+    }
+    true
+}
+
+// A pair of states stepped together, such as a pose and the motion that
+// moves it
+impl<S: Scalar, A: State<S>, B: State<S>> State<S> for (A, B) {
+    fn axpy(&mut self, a: S, x: &(A, B)) {
+        self.0.axpy(a, &x.0);
+        self.1.axpy(a, &x.1);
+    }
+    fn scale(&mut self, a: S) {
+        self.0.scale(a);
+        self.1.scale(a);
+    }
+    fn divide(&mut self, d: S) {
+        self.0.divide(d);
+        self.1.divide(d);
+    }
+    fn error_norm(&self) -> S {
+        self.0.error_norm().max(self.1.error_norm())
+    }
+    fn dimension(&self) -> usize {
+        self.0.dimension() + self.1.dimension()
+    }
+    fn component(&self, i: usize) -> S {
+        let n = self.0.dimension();
+        if i < n { self.0.component(i) } else { self.1.component(i - n) }
+    }
+    fn set_component(&mut self, i: usize, value: S) {
+        let n = self.0.dimension();
+        if i < n { self.0.set_component(i, value) } else { self.1.set_component(i - n, value) }
+    }
+}
+
+// Dense output across one step: the cubic Hermite spline through the state
+// and its rate at either end, giving the state at any time within the step
+// to fourth order without stepping again
+#[derive(Debug, Clone)]
+pub struct Hermite<S, T> {
+    start: S,
+    end: S,
+    x0: T,
+    dx0: T,
+    x1: T,
+    dx1: T,
+}
+
+impl<S: Scalar, T: State<S>> Hermite<S, T> {
+
+    // Constructor, from the state and its rate at `start` and at `end`
+    pub fn new(start: S, (x0, dx0): (T, T), end: S, (x1, dx1): (T, T)) -> Hermite<S, T> {
+        Hermite { start, end, x0, dx0, x1, dx1 }
+    }
+
+    // The state at `t`, which should lie within the step; outside it the
+    // spline is extrapolated
+    pub fn at(&self, t: S) -> T {
+        let h = self.end - self.start;
+        let s = (t - self.start) / h;
+        let (s2, s3) = (s * s, s * s * s);
+        let (two, three) = (S::from_f64(2.0), S::from_f64(3.0));
+
+        // The Hermite basis functions
+        let h00 = two * s3 - three * s2 + S::ONE;
+        let h10 = s3 - two * s2 + s;
+        let h01 = three * s2 - two * s3;
+        let h11 = s3 - s2;
+
+        let mut x = self.x0.clone();
+        x.scale(h00);
+        x.axpy(h10 * h, &self.dx0);
+        x.axpy(h01, &self.x1);
+        x.axpy(h11 * h, &self.dx1);
+        x
+    }
+
+    // The first time within the step at which `g` of the state reaches zero,
+    // if it does. The step is scanned in CROSSING_SCAN pieces for the first
+    // over which `g` changes sign, and the crossing found by bisection on the
+    // spline within it, so only a pair of crossings inside one piece, which
+    // cancel out, goes unseen.
+    pub fn crossing(&self, g: impl Fn(&T) -> S) -> Option<S> {
+        let g0 = g(&self.x0);
+        let piece = (self.end - self.start) / S::from_f64(CROSSING_SCAN as f64);
+        let (mut low, mut high) = (self.start, None);
+        for i in 1..=CROSSING_SCAN {
+            let t = if i == CROSSING_SCAN { self.end } else { self.start + piece * S::from_f64(i as f64) };
+            if (g(&self.at(t)) > S::ZERO) != (g0 > S::ZERO) {
+                high = Some(t);
+                break;
+            }
+            low = t;
+        }
+        let mut high = high?;
+        for _ in 0..60 {
+            let mid = (low + high) / S::from_f64(2.0);
+            if (g(&self.at(mid)) > S::ZERO) == (g0 > S::ZERO) { low = mid } else { high = mid }
+        }
+        Some(high)
+    }
+
+    // Getters
+    #[inline] pub fn start(&self) -> S {
+        self.start
+    }
+    #[inline] pub fn end(&self) -> S {
+        self.end
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// Deterministic random number generator for every stochastic model, so a
+// run is exactly reproducible from its seed. This is xoshiro256++, seeded
+// through SplitMix64. Components should each draw from their own `stream`,
+// so adding draws to one model doesn't shift the numbers another one sees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimRng {
+    seed: u64,
+    state: [u64; 4],
+}
+
+impl SimRng {
+
+    // Constructor
+    pub fn new(seed: u64) -> SimRng {
+        let mut sm = seed;
+        let state = [
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+        ];
+        SimRng { seed, state }
+    }
+
+    // Picks a seed from the system clock, for runs that don't specify one
+    pub fn from_clock() -> SimRng {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        SimRng::new(nanos)
+    }
+
+    // The seed this generator (or the generator it's a stream of) started from
+    #[inline] pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Returns an independent generator for the named component, derived
+    // from this one's seed
+    pub fn stream(&self, name: &str) -> SimRng {
+        // FNV-1a hash of the name
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        let mut rng = SimRng::new(self.seed ^ hash);
+        rng.seed = self.seed;
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = (s[0].wrapping_add(s[3])).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    // Uniform on [0, 1)
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    // Standard normal, by Box-Muller
+    pub fn normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.uniform(); // (0, 1], so the log is finite
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,277 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use core::cmp::Ordering;
+use core::fmt::Debug;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg, Rem};
+
+// The number type the math and dynamics are computed in. Everything defaults
+// to f64; f32 suits embedded and game use, and `Dual` carries a derivative
+// along with each value for exact gradients.
+pub trait Scalar:
+    Copy + Debug + Default + PartialEq + PartialOrd
+    + Serialize + DeserializeOwned
+    + Add<Output = Self> + Sub<Output = Self>
+    + Mul<Output = Self> + Div<Output = Self>
+    + Rem<Output = Self> + Neg<Output = Self>
+    + AddAssign + SubAssign + MulAssign + DivAssign
+    + Sum
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    // Conversions, which for `Dual` take or give the value alone
+    fn from_f64(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn sin_cos(self) -> (Self, Self) { (self.sin(), self.cos()) }
+    fn atan2(self, x: Self) -> Self;
+    fn asin(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn abs(self) -> Self;
+
+    fn to_radians(self) -> Self { self * Self::from_f64(core::f64::consts::PI / 180.0) }
+    fn to_degrees(self) -> Self { self * Self::from_f64(180.0 / core::f64::consts::PI) }
+    fn min(self, other: Self) -> Self { if other < self { other } else { self } }
+    fn max(self, other: Self) -> Self { if other > self { other } else { self } }
+    fn clamp(self, min: Self, max: Self) -> Self { self.max(min).min(max) }
+}
+
+#[cfg(feature = "std")]
+impl Scalar for f64 {
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    #[inline] fn from_f64(v: f64) -> f64 { v }
+    #[inline] fn to_f64(self) -> f64 { self }
+
+    #[inline] fn sqrt(self) -> f64 { f64::sqrt(self) }
+    #[inline] fn sin(self) -> f64 { f64::sin(self) }
+    #[inline] fn cos(self) -> f64 { f64::cos(self) }
+    #[inline] fn sin_cos(self) -> (f64, f64) { f64::sin_cos(self) }
+    #[inline] fn atan2(self, x: f64) -> f64 { f64::atan2(self, x) }
+    #[inline] fn asin(self) -> f64 { f64::asin(self) }
+    #[inline] fn exp(self) -> f64 { f64::exp(self) }
+    #[inline] fn ln(self) -> f64 { f64::ln(self) }
+    #[inline] fn powi(self, n: i32) -> f64 { f64::powi(self, n) }
+    #[inline] fn powf(self, n: f64) -> f64 { f64::powf(self, n) }
+    #[inline] fn abs(self) -> f64 { f64::abs(self) }
+    #[inline] fn to_radians(self) -> f64 { f64::to_radians(self) }
+    #[inline] fn to_degrees(self) -> f64 { f64::to_degrees(self) }
+    #[inline] fn min(self, other: f64) -> f64 { f64::min(self, other) }
+    #[inline] fn max(self, other: f64) -> f64 { f64::max(self, other) }
+    #[inline] fn clamp(self, min: f64, max: f64) -> f64 { f64::clamp(self, min, max) }
+}
+
+#[cfg(feature = "std")]
+impl Scalar for f32 {
+    const ZERO: f32 = 0.0;
+    const ONE: f32 = 1.0;
+
+    #[inline] fn from_f64(v: f64) -> f32 { v as f32 }
+    #[inline] fn to_f64(self) -> f64 { self as f64 }
+
+    #[inline] fn sqrt(self) -> f32 { f32::sqrt(self) }
+    #[inline] fn sin(self) -> f32 { f32::sin(self) }
+    #[inline] fn cos(self) -> f32 { f32::cos(self) }
+    #[inline] fn sin_cos(self) -> (f32, f32) { f32::sin_cos(self) }
+    #[inline] fn atan2(self, x: f32) -> f32 { f32::atan2(self, x) }
+    #[inline] fn asin(self) -> f32 { f32::asin(self) }
+    #[inline] fn exp(self) -> f32 { f32::exp(self) }
+    #[inline] fn ln(self) -> f32 { f32::ln(self) }
+    #[inline] fn powi(self, n: i32) -> f32 { f32::powi(self, n) }
+    #[inline] fn powf(self, n: f32) -> f32 { f32::powf(self, n) }
+    #[inline] fn abs(self) -> f32 { f32::abs(self) }
+    #[inline] fn to_radians(self) -> f32 { f32::to_radians(self) }
+    #[inline] fn to_degrees(self) -> f32 { f32::to_degrees(self) }
+    #[inline] fn min(self, other: f32) -> f32 { f32::min(self, other) }
+    #[inline] fn max(self, other: f32) -> f32 { f32::max(self, other) }
+    #[inline] fn clamp(self, min: f32, max: f32) -> f32 { f32::clamp(self, min, max) }
+}
+
+// Without std the float functions come from libm instead
+#[cfg(not(feature = "std"))]
+impl Scalar for f64 {
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    #[inline] fn from_f64(v: f64) -> f64 { v }
+    #[inline] fn to_f64(self) -> f64 { self }
+
+    #[inline] fn sqrt(self) -> f64 { libm::sqrt(self) }
+    #[inline] fn sin(self) -> f64 { libm::sin(self) }
+    #[inline] fn cos(self) -> f64 { libm::cos(self) }
+    #[inline] fn sin_cos(self) -> (f64, f64) { libm::sincos(self) }
+    #[inline] fn atan2(self, x: f64) -> f64 { libm::atan2(self, x) }
+    #[inline] fn asin(self) -> f64 { libm::asin(self) }
+    #[inline] fn exp(self) -> f64 { libm::exp(self) }
+    #[inline] fn ln(self) -> f64 { libm::log(self) }
+    #[inline] fn powi(self, n: i32) -> f64 { libm::pow(self, n as f64) }
+    #[inline] fn powf(self, n: f64) -> f64 { libm::pow(self, n) }
+    #[inline] fn abs(self) -> f64 { libm::fabs(self) }
+}
+
+#[cfg(not(feature = "std"))]
+impl Scalar for f32 {
+    const ZERO: f32 = 0.0;
+    const ONE: f32 = 1.0;
+
+    #[inline] fn from_f64(v: f64) -> f32 { v as f32 }
+    #[inline] fn to_f64(self) -> f64 { self as f64 }
+
+    #[inline] fn sqrt(self) -> f32 { libm::sqrtf(self) }
+    #[inline] fn sin(self) -> f32 { libm::sinf(self) }
+    #[inline] fn cos(self) -> f32 { libm::cosf(self) }
+    #[inline] fn sin_cos(self) -> (f32, f32) { libm::sincosf(self) }
+    #[inline] fn atan2(self, x: f32) -> f32 { libm::atan2f(self, x) }
+    #[inline] fn asin(self) -> f32 { libm::asinf(self) }
+    #[inline] fn exp(self) -> f32 { libm::expf(self) }
+    #[inline] fn ln(self) -> f32 { libm::logf(self) }
+    #[inline] fn powi(self, n: i32) -> f32 { libm::powf(self, n as f32) }
+    #[inline] fn powf(self, n: f32) -> f32 { libm::powf(self, n) }
+    #[inline] fn abs(self) -> f32 { libm::fabsf(self) }
+}
+
+// A dual number, a value with the derivative of that value with respect to
+// one chosen input. Seed the input with `Dual::variable` and everything
+// computed from it carries its exact derivative, e.g. the sensitivity of
+// range to mass. Comparisons only look at the value.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Dual {
+    pub re: f64,
+    pub eps: f64,
+}
+
+impl Dual {
+
+    // Constructors, for a constant and for the input being differentiated by
+    pub const fn constant(re: f64) -> Dual {
+        Dual { re, eps: 0.0 }
+    }
+    pub const fn variable(re: f64) -> Dual {
+        Dual { re, eps: 1.0 }
+    }
+
+    // Applies a function with value f and derivative df at this value
+    #[inline] fn chain(self, f: f64, df: f64) -> Dual {
+        Dual { re: f, eps: df * self.eps }
+    }
+}
+
+impl PartialEq for Dual {
+    fn eq(&self, other: &Dual) -> bool {
+        self.re == other.re
+    }
+}
+impl PartialOrd for Dual {
+    fn partial_cmp(&self, other: &Dual) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, other: Dual) -> Dual {
+        Dual { re: self.re + other.re, eps: self.eps + other.eps }
+    }
+}
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, other: Dual) -> Dual {
+        Dual { re: self.re - other.re, eps: self.eps - other.eps }
+    }
+}
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, other: Dual) -> Dual {
+        Dual {
+            re: self.re * other.re,
+            eps: self.eps * other.re + self.re * other.eps,
+        }
+    }
+}
+impl Div for Dual {
+    type Output = Dual;
+    fn div(self, other: Dual) -> Dual {
+        Dual {
+            re: self.re / other.re,
+            eps: (self.eps * other.re - self.re * other.eps) / (other.re * other.re),
+        }
+    }
+}
+impl Rem for Dual {
+    type Output = Dual;
+    fn rem(self, other: Dual) -> Dual {
+        let n = libm::trunc(self.re / other.re);
+        Dual { re: self.re % other.re, eps: self.eps - n * other.eps }
+    }
+}
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        Dual { re: -self.re, eps: -self.eps }
+    }
+}
+impl AddAssign for Dual {
+    fn add_assign(&mut self, other: Dual) { *self = *self + other; }
+}
+impl SubAssign for Dual {
+    fn sub_assign(&mut self, other: Dual) { *self = *self - other; }
+}
+impl MulAssign for Dual {
+    fn mul_assign(&mut self, other: Dual) { *self = *self * other; }
+}
+impl DivAssign for Dual {
+    fn div_assign(&mut self, other: Dual) { *self = *self / other; }
+}
+impl Sum for Dual {
+    fn sum<I: Iterator<Item = Dual>>(iter: I) -> Dual {
+        iter.fold(Dual::ZERO, |a, b| a + b)
+    }
+}
+
+impl Scalar for Dual {
+    const ZERO: Dual = Dual::constant(0.0);
+    const ONE: Dual = Dual::constant(1.0);
+
+    #[inline] fn from_f64(v: f64) -> Dual { Dual::constant(v) }
+    #[inline] fn to_f64(self) -> f64 { self.re }
+
+    fn sqrt(self) -> Dual {
+        let r = self.re.sqrt();
+        self.chain(r, 0.5 / r)
+    }
+    fn sin(self) -> Dual { self.chain(self.re.sin(), self.re.cos()) }
+    fn cos(self) -> Dual { self.chain(self.re.cos(), -self.re.sin()) }
+    fn atan2(self, x: Dual) -> Dual {
+        let d = self.re.powi(2) + x.re.powi(2);
+        Dual {
+            re: self.re.atan2(x.re),
+            eps: (x.re * self.eps - self.re * x.eps) / d,
+        }
+    }
+    fn asin(self) -> Dual { self.chain(self.re.asin(), 1.0 / (1.0 - self.re.powi(2)).sqrt()) }
+    fn exp(self) -> Dual {
+        let e = self.re.exp();
+        self.chain(e, e)
+    }
+    fn ln(self) -> Dual { self.chain(self.re.ln(), 1.0 / self.re) }
+    fn powi(self, n: i32) -> Dual {
+        self.chain(self.re.powi(n), n as f64 * self.re.powi(n - 1))
+    }
+    fn powf(self, n: Dual) -> Dual {
+        let re = self.re.powf(n.re);
+        let mut eps = n.re * self.re.powf(n.re - 1.0) * self.eps;
+        if n.eps != 0.0 { eps += re * self.re.ln() * n.eps; }
+        Dual { re, eps }
+    }
+    fn abs(self) -> Dual {
+        if self.re < 0.0 { -self } else { self }
+    }
+}
@@ -0,0 +1,419 @@
+use crate::aero::FlapSetting;
+use crate::engine::Rating;
+use crate::error::DyntermError;
+use crate::control::{AutopilotSpec, Command, SasSpec};
+use crate::config::{naca_0012, AeroModel, AerofoilConfig, PointMassConfig, Vehicle6Config, VehicleConfig};
+use crate::rk4::Integrator;
+use crate::rng::SimRng;
+use crate::sensors::SensorSpec;
+use crate::estimator::EstimatorSpec;
+use crate::events::{DepartureSpec, LoadLimitSpec, PlacardSpec, StallWarningSpec};
+use crate::guidance::{ArrivalMethod, GuidanceSpec};
+use crate::pilot::PilotSpec;
+use crate::sim::{PointMassSimulation, Simulation, Simulation6, MIN_SUBSTEP};
+use crate::atmosphere::Atmosphere;
+use crate::earth::Earth;
+use crate::geo::Origin;
+use crate::gravity::Gravity;
+use crate::planet::Planet;
+use crate::sounding::Sounding;
+use crate::derivatives::{Derivatives, DerivativesFile};
+use crate::dispersion::Dispersions;
+use crate::icing::Icing;
+use crate::updraft::Updrafts;
+use crate::trim::{trim_steady, Trim};
+use crate::wind::WindGridFile;
+use crate::interpolate::Linear;
+use crate::vec::Angle;
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+// How every vehicle starts, in place of the initial conditions given with
+// it
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Start {
+    // In equilibrium at `altitude`, m, flying at `airspeed`, m/s, with the
+    // planar vehicle's elevator held where it trims. A vehicle with thrust
+    // flies along a flight path angle `gamma`, degrees, level if left out,
+    // with its throttle held where the thrust makes up the drag. A glider
+    // glides down the path its airspeed gives, so leaves `gamma` out.
+    Trim {
+        airspeed: f64,
+        altitude: f64,
+        #[serde(default)]
+        gamma: Option<f64>,
+    },
+}
+
+// A complete description of a run, read from a JSON scenario file. Any
+// field left out takes its default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    // Seed for every stochastic model. Left out, one is taken from the clock
+    // and reported in the summary, so the run can still be repeated.
+    pub seed: Option<u64>,
+    pub vehicle: VehicleConfig,
+    // Stability derivatives to build `vehicle`'s surfaces from, if any, read
+    // from AVL's output or a file of the same form
+    pub derivatives: Option<DerivativesFile>,
+    // Flown instead of `vehicle` in the 3-DOF mode
+    pub point_mass: PointMassConfig,
+    // Flown instead of `vehicle` in the 6-DOF mode
+    pub six_dof: Vehicle6Config,
+    // Gravity field every vehicle flies in
+    pub gravity: Gravity,
+    // How much hotter the day is than standard, K, 20 for ISA+20, negative
+    // for a cold day
+    pub isa_offset: f64,
+    // Relative humidity of the earth's air, from 0 for dry to 1 for
+    // saturated
+    pub humidity: f64,
+    // Body every vehicle flies about, if not the earth. It sets the gravity
+    // field, in place of `gravity`, and the atmosphere, and the radius of a
+    // round `earth`.
+    pub planet: Option<Planet>,
+    // A measured atmosphere to fly through, if any, in place of the
+    // standard one, the day's temperature and humidity above, and the
+    // planet's
+    pub sounding: Option<Sounding>,
+    // Shape of the earth under the planar vehicle
+    pub earth: Earth,
+    // Where the release is on the globe and the heading downrange points
+    // along, for the exported track and the FlightGear and MAVLink streams
+    pub origin: Origin,
+    // Thermals and slope lift, for the planar vehicle and the point mass
+    pub updrafts: Updrafts,
+    // Wind varying downrange and with altitude, for the planar vehicle and
+    // the point mass, if any
+    pub wind_grid: Option<WindGridFile>,
+    // Ice building up on every model's surfaces, if any
+    pub icing: Option<Icing>,
+    // Sensors carried by the planar vehicle, if any
+    pub sensors: Option<SensorSpec>,
+    // State estimator working from the sensors, if any
+    pub estimator: Option<EstimatorSpec>,
+    // A human pilot flying the planar vehicle's pitch, if any, instead of
+    // the built-in pull-up
+    pub pilot: Option<PilotSpec>,
+    // Path to a Rhai script flying the planar vehicle, if any, ahead of the
+    // pilot (needs the scripting feature)
+    pub script: Option<String>,
+    // Path to a CSV control history replayed open loop, if any, ahead of the
+    // pilot
+    pub playback: Option<String>,
+    // Guidance into a ground target at an impact angle, if any, ahead of the
+    // autopilot
+    pub guidance: Option<GuidanceSpec>,
+    // A pitch-attitude hold with scheduled gains, if any, ahead of the pilot
+    pub autopilot: Option<AutopilotSpec>,
+    // A pitch damper on top of whatever flies the planar vehicle, if any
+    pub sas: Option<SasSpec>,
+    // Watch for the planar vehicle departing into a deep stall or tumble
+    pub departure: Option<DepartureSpec>,
+    // Warn as the planar vehicle's wing nears the stall
+    pub stall_warning: Option<StallWarningSpec>,
+    // Watch the planar vehicle's load factor against its structural limits
+    pub load_limits: Option<LoadLimitSpec>,
+    // Watch the planar vehicle's speed against a never-exceed placard
+    pub placard: Option<PlacardSpec>,
+    // Pairs of a time and the planar vehicle's speedbrake deployment to
+    // command from it, 0 to 1, unless whatever's flying commands its own
+    pub speedbrake_schedule: Vec<(f64, f64)>,
+    // Pairs of a time and the flap setting to command from it, likewise
+    pub flap_schedule: Vec<(f64, FlapSetting)>,
+    // Pairs of a time and the engine rating to command from it, likewise,
+    // say a dash at afterburner and then idle for the glide
+    pub rating_schedule: Vec<(f64, Rating)>,
+    // Pairs of a time and the angle to turn the thrust line up from the
+    // body axis from it, degrees, likewise, for hover and transition
+    pub thrust_angle_schedule: Vec<(f64, f64)>,
+    // Scatter of the initial conditions from run to run of a Monte Carlo
+    // campaign
+    pub dispersions: Dispersions,
+    // How every vehicle starts, if not from the initial conditions given
+    // with it
+    pub start: Option<Start>,
+    // The planar vehicle's trim, once solved for a trimmed start
+    #[serde(skip)]
+    pub trim: Option<Trim>,
+}
+
+impl Scenario {
+
+    // Reads a scenario file, and any sounding, wind grid, and stability
+    // derivatives it names
+    pub fn load(path: impl AsRef<Path>) -> Result<Scenario, DyntermError> {
+        let file = BufReader::new(File::open(path)?);
+        let mut scenario: Scenario = serde_json::from_reader(file)?;
+        if let Some(sounding) = &mut scenario.sounding {
+            sounding.load().map_err(|e| e.within(format_args!("sounding {}", sounding.path)))?;
+        }
+        if let Some(wind_grid) = &mut scenario.wind_grid {
+            wind_grid.load().map_err(|e| e.within(format_args!("wind grid {}", wind_grid.path)))?;
+        }
+        if let Some(file) = &scenario.derivatives {
+            let derivatives = Derivatives::load(&file.path)
+                .map_err(|e| e.within(format_args!("derivatives {}", file.path)))?;
+            scenario.vehicle.derivatives = Some(derivatives);
+        }
+        let missing = match scenario.vehicle.aero_model {
+            AeroModel::Surfaces => None,
+            AeroModel::Derivatives => scenario.vehicle.derivatives.is_none()
+                .then_some("the derivatives model needs stability derivatives"),
+            AeroModel::Rotor => scenario.vehicle.rotor.is_none().then_some("the rotor model needs a rotor"),
+            AeroModel::Parafoil => scenario.vehicle.parafoil.is_none().then_some("the parafoil model needs a parafoil"),
+        };
+        if let Some(missing) = missing {
+            return Err(DyntermError::Config(missing.into()));
+        }
+        if let Some(engine) = &scenario.vehicle.engine {
+            engine.check().map_err(|e| e.within("engine"))?;
+        }
+        if let Integrator::ImplicitMidpoint { substep } = scenario.vehicle.integrator {
+            if substep.is_nan() || substep < MIN_SUBSTEP {
+                return Err(DyntermError::Config(format!(
+                    "the implicit midpoint rule needs a substep of at least {:e} s", MIN_SUBSTEP)));
+            }
+        }
+        let arrival = scenario.guidance.as_ref().and_then(|guidance| guidance.arrival);
+        if arrival.is_some_and(|arrival| arrival.method == ArrivalMethod::Speedbrake) && scenario.vehicle.speedbrake.is_none() {
+            return Err(DyntermError::Config("arriving on time by speedbrake needs a speedbrake".into()));
+        }
+        if let Some(ArrivalMethod::Weave { period }) = arrival.map(|arrival| arrival.method) {
+            if period <= 0.0 {
+                return Err(DyntermError::Config("a weave needs a period".into()));
+            }
+        }
+        match scenario.start {
+            Some(Start::Trim { airspeed, altitude, gamma }) => scenario.with_trim(airspeed, altitude, gamma),
+            None => Ok(scenario),
+        }
+    }
+
+    // A release at `altitude`, m, moving at `speed`, m/s, along a flight path
+    // angle `gamma`, degrees, without rotating. Each vehicle is pitched to the
+    // angle of attack at which its lift carries the weight across the path,
+    // so it starts out flying straight rather than tumbling.
+    pub fn airdrop(altitude: f64, speed: f64, gamma: f64) -> Scenario {
+        Scenario::default().with_airdrop(altitude, speed, gamma)
+    }
+
+    // Leaving the end of a launch rail `length` m long, inclined at `angle`
+    // degrees, at `end_speed`, m/s, still pointing along the rail
+    pub fn catapult(length: f64, end_speed: f64, angle: f64) -> Scenario {
+        Scenario::default().with_catapult(length, end_speed, angle)
+    }
+
+    // Let go from beneath a balloon at `altitude`, m, from rest and hanging
+    // nose down
+    pub fn balloon_drop(altitude: f64) -> Scenario {
+        Scenario::default().with_balloon_drop(altitude)
+    }
+
+    // As `airdrop`, keeping this scenario's vehicles
+    pub fn with_airdrop(mut self, altitude: f64, speed: f64, gamma: f64) -> Scenario {
+        let v = &self.vehicle;
+        let (gravity, air) = (self.gravity_field(), self.atmosphere());
+        let weight = v.mass * gravity.at(altitude);
+        let ((wing, _, _), (elevator, _, _)) = v.tables();
+        let aoa = trim_aoa(&[(&v.wing, &wing), (&v.elevator, &elevator)], weight, altitude, &air, speed, gamma);
+        self.release(0.0, altitude, speed, gamma, gamma + aoa);
+
+        let p = &self.point_mass;
+        let weight = p.mass * gravity.at(altitude);
+        let (cl, _, _) = naca_0012();
+        self.point_mass.aoa = trim_aoa(&[(&p.wing, &cl)], weight, altitude, &air, speed, gamma);
+
+        let v = &self.six_dof;
+        let weight = v.mass * gravity.at(altitude);
+        self.six_dof.pitch = gamma + trim_aoa(&[(&v.wing, &cl), (&v.elevator, &cl)], weight, altitude, &air, speed, gamma);
+        self
+    }
+
+    // As `catapult`, keeping this scenario's vehicles
+    pub fn with_catapult(mut self, length: f64, end_speed: f64, angle: f64) -> Scenario {
+        let angle_rad = angle.to_radians();
+        self.release(length * angle_rad.cos(), length * angle_rad.sin(), end_speed, angle, angle);
+        self
+    }
+
+    // As `balloon_drop`, keeping this scenario's vehicles
+    pub fn with_balloon_drop(mut self, altitude: f64) -> Scenario {
+        self.release(0.0, altitude, 0.0, -90.0, -90.0);
+        self
+    }
+
+    // As `with_airdrop`, with the planar vehicle trimmed in steady flight as
+    // well, its elevator set where the pitching moment is nil and its
+    // throttle, or for a glider its path, where the forces along the path
+    // balance, so it starts in equilibrium rather than settling from a
+    // transient. `gamma` is as for `Start::Trim`.
+    pub fn with_trim(self, airspeed: f64, altitude: f64, gamma: Option<f64>) -> Result<Scenario, DyntermError> {
+        let scenario = self.with_airdrop(altitude, airspeed, gamma.unwrap_or(0.0));
+        let trim = trim_steady(&scenario.vehicle, gamma, scenario.gravity_field(), &scenario.atmosphere())
+            .ok_or_else(|| DyntermError::Config(match gamma {
+                Some(gamma) => format!(
+                    "the vehicle can't be trimmed at {} m/s and {} m on a {} degree path, with the thrust it has",
+                    airspeed, altitude, gamma),
+                None => format!("the vehicle can't be trimmed at {} m/s and {} m", airspeed, altitude),
+            }))?;
+
+        let mut scenario = scenario.with_airdrop(altitude, airspeed, trim.gamma);
+        scenario.vehicle.pitch = trim.gamma + trim.aoa;
+        scenario.vehicle.elevator.incidence = trim.elevator;
+        scenario.start = Some(Start::Trim { airspeed, altitude, gamma });
+        scenario.trim = Some(trim);
+        Ok(scenario)
+    }
+
+    // What the planar vehicle's controls are held at when nothing else flies
+    // it, if it started trimmed, in place of the built-in pull-up
+    pub fn trimmed(&self) -> Option<Command> {
+        self.trim.map(|trim| Command::hold(Angle::from_degrees(trim.elevator), trim.throttle))
+    }
+
+    // Sets the same initial state for every model, pitched at `pitch` degrees.
+    // The point mass holds that angle of attack instead.
+    fn release(&mut self, x: f64, altitude: f64, speed: f64, gamma: f64, pitch: f64) {
+        let v = &mut self.vehicle;
+        (v.x, v.altitude, v.speed, v.flight_path_angle) = (x, altitude, speed, gamma);
+        (v.pitch, v.pitch_rate) = (pitch, 0.0);
+
+        let p = &mut self.point_mass;
+        (p.x, p.altitude, p.speed, p.flight_path_angle) = (x, altitude, speed, gamma);
+        p.aoa = pitch - gamma;
+
+        let v = &mut self.six_dof;
+        (v.x, v.altitude, v.speed, v.flight_path_angle) = (x, altitude, speed, gamma);
+        (v.pitch, v.pitch_rate) = (pitch, 0.0);
+    }
+
+    // Builds the simulation at its initial conditions
+    pub fn build(&self) -> Simulation {
+        let mut vehicle = self.vehicle.build();
+        vehicle.gravity = self.gravity_field();
+        vehicle.atmosphere = self.atmosphere();
+        vehicle.earth = self.shape();
+        vehicle.updrafts = self.updrafts.clone();
+        vehicle.wind_grid = self.wind_grid.as_ref().and_then(|file| file.grid.clone());
+        Simulation { icing: self.icing, ..Simulation::new(vehicle, self.rng()) }
+    }
+
+    // Builds the 3-DOF simulation at its initial conditions
+    pub fn build_point_mass(&self) -> PointMassSimulation {
+        let mut vehicle = self.point_mass.build();
+        vehicle.gravity = self.gravity_field();
+        vehicle.atmosphere = self.atmosphere();
+        vehicle.updrafts = self.updrafts.clone();
+        vehicle.wind_grid = self.wind_grid.as_ref().and_then(|file| file.grid.clone());
+        PointMassSimulation { icing: self.icing, ..PointMassSimulation::new(vehicle, self.rng()) }
+    }
+
+    // Builds the 6-DOF simulation at its initial conditions
+    pub fn build_six_dof(&self) -> Simulation6 {
+        let mut vehicle = self.six_dof.build();
+        vehicle.gravity = self.gravity_field();
+        vehicle.atmosphere = self.atmosphere();
+        Simulation6 { icing: self.icing, ..Simulation6::new(vehicle, self.rng()) }
+    }
+
+    // Gravity field every vehicle flies in
+    pub fn gravity_field(&self) -> Gravity {
+        self.planet.map_or(self.gravity, |planet| planet.gravity())
+    }
+
+    // Air every vehicle flies through
+    pub fn atmosphere(&self) -> Atmosphere {
+        self.sounding.as_ref().and_then(Sounding::atmosphere)
+            .unwrap_or_else(|| self.planet.unwrap_or_default().atmosphere(self.isa_offset, self.humidity))
+    }
+
+    // Shape of the ground under the planar vehicle
+    pub fn shape(&self) -> Earth {
+        match (self.earth, self.planet) {
+            (Earth::Round { .. }, Some(planet)) => Earth::Round { radius: planet.body().radius },
+            (earth, _) => earth,
+        }
+    }
+
+    // Helper function
+    fn rng(&self) -> SimRng {
+        match self.seed {
+            Some(seed) => SimRng::new(seed),
+            None => SimRng::from_clock(),
+        }
+    }
+}
+
+// A run saved part way through: the simulation as it stood and the scenario
+// it was flying, for resuming with the sensors, controllers, events, and
+// schedules rebuilt from the scenario and the trim held as it was
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub simulation: Simulation,
+    pub scenario: Scenario,
+    // The scenario's trim, which its file leaves out
+    trim: Option<Trim>,
+}
+
+impl Checkpoint {
+
+    // Writes `simulation` and the `scenario` it's flying to a JSON
+    // checkpoint file
+    pub fn save(path: impl AsRef<Path>, simulation: &Simulation, scenario: &Scenario) -> io::Result<()> {
+        // The same fields as a `Checkpoint`, borrowed rather than cloned
+        #[derive(Serialize)]
+        struct Saved<'a> {
+            simulation: &'a Simulation,
+            scenario: &'a Scenario,
+            trim: Option<Trim>,
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let saved = Saved { simulation, scenario, trim: scenario.trim };
+        serde_json::to_writer(file, &saved).map_err(io::Error::from)
+    }
+
+    // Reads a checkpoint back written by `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Checkpoint, DyntermError> {
+        let file = BufReader::new(File::open(path)?);
+        let mut checkpoint: Checkpoint = serde_json::from_reader(file)?;
+        checkpoint.scenario.trim = checkpoint.trim;
+        Ok(checkpoint)
+    }
+}
+
+// Angle of attack, degrees, at which the lift of `surfaces`, each with its
+// lift coefficient table, carries the part of `weight`, N, across a flight
+// path at `gamma` degrees, flying at `speed` m/s at `altitude` m in
+// `atmosphere`.
+// Found by bisection over the range where lift rises with angle of attack,
+// and held at its end if that isn't enough.
+fn trim_aoa(surfaces: &[(&AerofoilConfig, &Linear)], weight: f64, altitude: f64, atmosphere: &Atmosphere, speed: f64, gamma: f64) -> f64 {
+    // Where the bundled tables' lift curve is monotonic, degrees
+    const LOW: f64 = -10.0;
+    const HIGH: f64 = 12.0;
+
+    // Dynamic pressure as the aerofoils take it
+    let q = 0.5 * atmosphere.density(altitude) * speed * speed;
+    if q <= 0.0 { return 0.0; }
+
+    let lift = |aoa: f64| surfaces.iter()
+        .map(|(c, cl)| c.area * cl.interpolate(Angle::from_degrees(aoa + c.incidence).deg()))
+        .sum::<f64>() * q;
+    let load = weight * gamma.to_radians().cos();
+
+    let (mut low, mut high) = (LOW, HIGH);
+    if lift(high) <= load { return high; }
+    if lift(low) >= load { return low; }
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        if lift(mid) < load { low = mid } else { high = mid }
+    }
+    0.5 * (low + high)
+}
@@ -0,0 +1,107 @@
+use crate::control::Command;
+use crate::error::DyntermError;
+use crate::sim::State;
+use crate::vec::Angle;
+
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::path::Path;
+
+// Operations a script may take in a call before it's taken to be stuck
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// A control law written in Rhai, so guidance can be tried out without
+// rebuilding dynterm. The script defines
+//
+//   fn control(state) { ... }
+//
+// which is called every step with a map of the state, as the SIL controller
+// sees it (time, x, y, vx, vy, pitch, pitch_rate, and aoa, in seconds,
+// metres, and degrees), and returns either the elevator deflection in
+// degrees or a map with `elevator` and optionally `throttle` and
+// `speedbrake`, each 0 to 1, `flaps`, "clean", "takeoff", or "landing", and
+// `rating`, "idle", "military", or "afterburner", and `thrust_angle`, the
+// thrust line's angle up from the body axis in degrees. A throttle left out
+// or set to () hands it back to the autothrottle, and anything else left out
+// stays put. Within
+// `control`, `this` is a map kept from one step to the next, for integrators,
+// filters, and the like. The top level of the script runs before every call,
+// so it's the place for constants, read in `control` as `global::NAME`. A
+// call that runs past MAX_OPERATIONS is stopped with an error.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    memory: Dynamic,
+}
+
+impl Script {
+
+    // Constructor, compiles the script at `path` and runs its top level
+    // once, to catch any errors there before the run starts
+    pub fn load(path: impl AsRef<Path>) -> Result<Script, DyntermError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine.compile_file(path.as_ref().to_path_buf())
+            .map_err(|e| DyntermError::Parse(e.to_string()))?;
+        engine.run_ast(&ast).map_err(|e| DyntermError::Config(e.to_string()))?;
+        Ok(Script { engine, ast, memory: Map::new().into() })
+    }
+
+    // Runs the control law on the state
+    pub fn command(&mut self, state: &State) -> Result<Command, DyntermError> {
+        let mut map = Map::new();
+        for (name, value) in [
+            ("time", state.time),
+            ("x", state.x),
+            ("y", state.y),
+            ("vx", state.vx),
+            ("vy", state.vy),
+            ("pitch", state.pitch),
+            ("pitch_rate", state.pitch_rate),
+            ("aoa", state.aoa),
+        ] {
+            map.insert(name.into(), value.into());
+        }
+
+        let options = CallFnOptions::new().bind_this_ptr(&mut self.memory);
+        let reply: Dynamic = self.engine.call_fn_with_options(options, &mut Scope::new(), &self.ast, "control", (map,))
+            .map_err(|e| DyntermError::Config(e.to_string()))?;
+
+        parse_command(&reply).ok_or_else(|| DyntermError::Config(format!("bad command '{}' from script", reply)))
+    }
+}
+
+// Helper function
+fn number(value: &Dynamic) -> Option<f64> {
+    value.as_float().ok().or_else(|| value.as_int().ok().map(|i| i as f64))
+}
+
+// Reads an elevator deflection, or a map of elevator and throttle. None if
+// anything given is of the wrong type or isn't one of the names allowed.
+fn parse_command(reply: &Dynamic) -> Option<Command> {
+    let (elevator, throttle, speedbrake, flaps, rating, thrust_angle) = match reply.read_lock::<Map>() {
+        Some(map) => {
+            let elevator = number(map.get("elevator")?)?;
+            let optional = |name: &str| match map.get(name) {
+                Some(value) if !value.is_unit() => number(value).map(Some),
+                _ => Some(None),
+            };
+            let named = |name: &str| match map.get(name) {
+                Some(value) if !value.is_unit() => value.clone().into_string().ok().map(Some),
+                _ => Some(None),
+            };
+            let flaps = match named("flaps")? {
+                Some(flaps) => Some(flaps.parse().ok()?),
+                None => None,
+            };
+            let rating = match named("rating")? {
+                Some(rating) => Some(rating.parse().ok()?),
+                None => None,
+            };
+            let thrust_angle = optional("thrust_angle")?.map(Angle::from_degrees);
+            (elevator, optional("throttle")?, optional("speedbrake")?, flaps, rating, thrust_angle)
+        }
+        None => (number(reply)?, None, None, None, None, None),
+    };
+
+    Some(Command { elevator: Angle::from_degrees(elevator), throttle, speedbrake, flaps, rating, thrust_angle })
+}
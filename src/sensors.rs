@@ -0,0 +1,295 @@
+use crate::aero::Vehicle;
+use crate::rng::SimRng;
+use crate::vec::Vector;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Slack allowed when comparing times, s, so sample times built up from
+// steps of a hundredth of a second still land on their schedule
+const EPSILON: f64 = 1e-9;
+
+// Errors on one measured quantity, each a standard deviation: white noise on
+// every reading, a bias drawn when the sensor is switched on, and a random
+// walk the bias wanders off on, per square root of a second
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorModel {
+    pub noise: f64,
+    pub bias: f64,
+    pub drift: f64,
+}
+
+// Accelerometers along the body's forward and upward axes, and a pitch-rate
+// gyro. Rates are in Hz and delays in seconds throughout, with a rate of 0
+// sampling at every step.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImuSpec {
+    pub rate: f64,
+    pub delay: f64,
+    pub accelerometer: ErrorModel,
+    pub gyro: ErrorModel,
+}
+
+// A pitot-static probe, read as equivalent airspeed
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PitotSpec {
+    pub rate: f64,
+    pub delay: f64,
+    pub airspeed: ErrorModel,
+}
+
+// A barometer, read as pressure altitude
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaroSpec {
+    pub rate: f64,
+    pub delay: f64,
+    pub altitude: ErrorModel,
+}
+
+// A GPS receiver, with errors on each axis of position and velocity
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpsSpec {
+    pub rate: f64,
+    pub delay: f64,
+    pub position: ErrorModel,
+    pub velocity: ErrorModel,
+}
+
+// The sensors carried by a planar vehicle. The defaults are typical of the
+// MEMS sensors and consumer GPS of a small autopilot.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorSpec {
+    pub imu: ImuSpec,
+    pub pitot: PitotSpec,
+    pub baro: BaroSpec,
+    pub gps: GpsSpec,
+}
+
+impl Default for ImuSpec {
+    fn default() -> ImuSpec {
+        ImuSpec {
+            rate: 100.0,
+            delay: 0.0,
+            accelerometer: ErrorModel { noise: 0.05, bias: 0.1, drift: 0.002 },
+            gyro: ErrorModel { noise: 0.003, bias: 0.002, drift: 0.000_1 },
+        }
+    }
+}
+
+impl Default for PitotSpec {
+    fn default() -> PitotSpec {
+        PitotSpec { rate: 50.0, delay: 0.02, airspeed: ErrorModel { noise: 0.5, bias: 0.3, drift: 0.0 } }
+    }
+}
+
+impl Default for BaroSpec {
+    fn default() -> BaroSpec {
+        BaroSpec { rate: 20.0, delay: 0.05, altitude: ErrorModel { noise: 0.5, bias: 3.0, drift: 0.05 } }
+    }
+}
+
+impl Default for GpsSpec {
+    fn default() -> GpsSpec {
+        GpsSpec {
+            rate: 5.0,
+            delay: 0.2,
+            position: ErrorModel { noise: 2.0, bias: 1.5, drift: 0.1 },
+            velocity: ErrorModel { noise: 0.1, bias: 0.0, drift: 0.0 },
+        }
+    }
+}
+
+// A single measured quantity, and when it was sampled
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Reading {
+    pub time: f64,
+    pub value: f64,
+}
+
+// Specific force along the body's forward and upward axes, m/s^2, and the
+// pitch rate, rad/s
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ImuReading {
+    pub time: f64,
+    pub forward: f64,
+    pub up: f64,
+    pub pitch_rate: f64,
+}
+
+// A GPS position and velocity, m and m/s
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct GpsFix {
+    pub time: f64,
+    pub x: f64,
+    pub altitude: f64,
+    pub vx: f64,
+    pub vy: f64,
+}
+
+// Readings that arrived during a step, each the latest from its sensor, or
+// None where nothing new came in
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Readings {
+    pub imu: Option<ImuReading>,
+    pub airspeed: Option<Reading>,
+    pub altitude: Option<Reading>,
+    pub gps: Option<GpsFix>,
+}
+
+// One sensor's schedule, the biases on each of its axes, and the readings
+// it's taken that are still on their way
+#[derive(Debug, Clone)]
+struct Pipeline<T> {
+    rate: f64,
+    delay: f64,
+    next: f64,
+    last: Option<f64>,
+    biases: Vec<f64>,
+    queue: VecDeque<(f64, T)>,
+}
+
+impl<T> Pipeline<T> {
+
+    // Constructor, drawing a switch-on bias for each axis
+    fn new(rate: f64, delay: f64, errors: &[ErrorModel], rng: &mut SimRng) -> Pipeline<T> {
+        Pipeline {
+            rate,
+            delay,
+            next: f64::NEG_INFINITY,
+            last: None,
+            biases: errors.iter().map(|e| e.bias * rng.normal()).collect(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    // Whether a sample is due at `time`, and if so, moves the schedule on
+    // and returns the time since the last one
+    fn due(&mut self, time: f64) -> Option<f64> {
+        if time + EPSILON < self.next { return None; }
+
+        // Keep to the schedule, unless the steps have outrun it
+        let period = if self.rate > 0.0 { 1.0 / self.rate } else { 0.0 };
+        let scheduled = if self.next.is_finite() { self.next } else { time };
+        self.next = if scheduled + period > time { scheduled + period } else { time + period };
+        let dt = self.last.map_or(0.0, |last| time - last);
+        self.last = Some(time);
+        Some(dt)
+    }
+
+    // Measures `truth` on `axis`, walking its bias on over `dt`
+    fn measure(&mut self, axis: usize, truth: f64, errors: &ErrorModel, dt: f64, rng: &mut SimRng) -> f64 {
+        self.biases[axis] += errors.drift * dt.sqrt() * rng.normal();
+        truth + self.biases[axis] + errors.noise * rng.normal()
+    }
+
+    // Sends off a reading taken at `time`
+    fn send(&mut self, time: f64, reading: T) {
+        self.queue.push_back((time + self.delay, reading));
+    }
+
+    // Returns the latest reading to have arrived by `time`
+    fn arrived(&mut self, time: f64) -> Option<T> {
+        let mut latest = None;
+        while self.queue.front().is_some_and(|(at, _)| *at <= time + EPSILON) {
+            latest = self.queue.pop_front().map(|(_, reading)| reading);
+        }
+        latest
+    }
+}
+
+// Measures a planar vehicle as its sensors would, with noise, bias, and
+// delay, so estimators and controllers can be tried on imperfect data. Each
+// sensor samples on its own schedule, and its readings arrive a fixed delay
+// after they're taken. The errors are drawn from the run's random numbers,
+// so they repeat with its seed.
+#[derive(Debug, Clone)]
+pub struct Sensors {
+    spec: SensorSpec,
+    rng: SimRng,
+    imu: Pipeline<ImuReading>,
+    pitot: Pipeline<Reading>,
+    baro: Pipeline<Reading>,
+    gps: Pipeline<GpsFix>,
+}
+
+impl Sensors {
+
+    // Constructor, switching the sensors on with errors drawn from a stream
+    // of `rng`
+    pub fn new(spec: SensorSpec, rng: &SimRng) -> Sensors {
+        let mut rng = rng.stream("sensors");
+        let (imu, pitot, baro, gps) = (&spec.imu, &spec.pitot, &spec.baro, &spec.gps);
+        Sensors {
+            imu: Pipeline::new(imu.rate, imu.delay, &[imu.accelerometer, imu.accelerometer, imu.gyro], &mut rng),
+            pitot: Pipeline::new(pitot.rate, pitot.delay, &[pitot.airspeed], &mut rng),
+            baro: Pipeline::new(baro.rate, baro.delay, &[baro.altitude], &mut rng),
+            gps: Pipeline::new(gps.rate, gps.delay, &[gps.position, gps.position, gps.velocity, gps.velocity], &mut rng),
+            spec,
+            rng,
+        }
+    }
+
+    // Samples whichever sensors are due at `time`, for the vehicle in its
+    // current state with inertial acceleration `accel`, and returns the
+    // readings that have arrived
+    pub fn sample(&mut self, time: f64, vehicle: &Vehicle, accel: Vector) -> Readings {
+        let spec = self.spec;
+        let rng = &mut self.rng;
+
+        if let Some(dt) = self.imu.due(time) {
+            let force = specific_force(vehicle, accel);
+            let (accelerometer, gyro) = (&spec.imu.accelerometer, &spec.imu.gyro);
+            let reading = ImuReading {
+                time,
+                forward: self.imu.measure(0, force.x(), accelerometer, dt, rng),
+                up: self.imu.measure(1, force.y(), accelerometer, dt, rng),
+                pitch_rate: self.imu.measure(2, vehicle.motion.angular_velocity(), gyro, dt, rng),
+            };
+            self.imu.send(time, reading);
+        }
+
+        if let Some(dt) = self.pitot.due(time) {
+            let airspeed = (2.0 * vehicle.airdata().qbar).sqrt();
+            let value = self.pitot.measure(0, airspeed, &spec.pitot.airspeed, dt, rng);
+            self.pitot.send(time, Reading { time, value });
+        }
+
+        if let Some(dt) = self.baro.due(time) {
+            let altitude = vehicle.atmosphere.pressure_altitude(vehicle.position.y());
+            let value = self.baro.measure(0, altitude, &spec.baro.altitude, dt, rng);
+            self.baro.send(time, Reading { time, value });
+        }
+
+        if let Some(dt) = self.gps.due(time) {
+            let (position, velocity) = (&spec.gps.position, &spec.gps.velocity);
+            let fix = GpsFix {
+                time,
+                x: self.gps.measure(0, vehicle.position.x(), position, dt, rng),
+                altitude: self.gps.measure(1, vehicle.position.y(), position, dt, rng),
+                vx: self.gps.measure(2, vehicle.motion.x(), velocity, dt, rng),
+                vy: self.gps.measure(3, vehicle.motion.y(), velocity, dt, rng),
+            };
+            self.gps.send(time, fix);
+        }
+
+        Readings {
+            imu: self.imu.arrived(time),
+            airspeed: self.pitot.arrived(time),
+            altitude: self.baro.arrived(time),
+            gps: self.gps.arrived(time),
+        }
+    }
+}
+
+// Returns the specific force on the vehicle, what an accelerometer reads,
+// along its forward and upward axes, given its inertial acceleration
+pub fn specific_force(vehicle: &Vehicle, accel: Vector) -> Vector {
+    let g = vehicle.gravity.at(vehicle.position.y());
+    Vector::new(accel.x(), accel.y() + g).to_body(vehicle.position.angle())
+}
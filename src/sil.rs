@@ -0,0 +1,74 @@
+use crate::control::Command;
+use crate::sim::State;
+use crate::vec::Angle;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+// Software-in-the-loop control over TCP. dynterm listens for a single
+// controller connection, then every step it sends one line of state
+//
+//   <t [s]> <x [m]> <y [m]> <vx [m/s]> <vy [m/s]> <pitch [deg]> <q [deg/s]> <aoa [deg]>
+//
+// and waits for one line of commands in reply
+//
+//   <elevator [deg]> [throttle [0-1]]
+//
+// where leaving out the throttle hands it back to the autothrottle. Closing
+// the connection ends the run. Being line-based text, a controller can be
+// written in anything that can open a socket.
+pub struct Sil {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Sil {
+
+    // Constructor, blocks until a controller connects to `addr`
+    pub fn accept(addr: &str) -> io::Result<Sil> {
+        let listener = TcpListener::bind(addr)?;
+        tracing::info!("Waiting for controller on {}", listener.local_addr()?);
+        let (stream, peer) = listener.accept()?;
+        tracing::info!("Controller connected from {}", peer);
+        stream.set_nodelay(true)?;
+        Ok(Sil { reader: BufReader::new(stream.try_clone()?), writer: stream })
+    }
+
+    // Sends the state of the vehicle, as the controller is to see it, and
+    // waits for the controller's reply
+    pub fn exchange(&mut self, state: &State) -> io::Result<Command> {
+        writeln!(
+            self.writer,
+            "{} {} {} {} {} {} {} {}",
+            state.time,
+            state.x,
+            state.y,
+            state.vx,
+            state.vy,
+            state.pitch,
+            state.pitch_rate,
+            state.aoa)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof, "controller disconnected"));
+        }
+
+        parse_command(&line).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData, format!("bad command '{}'", line.trim())))
+    }
+}
+
+// Parses "<elevator> [throttle]"
+fn parse_command(line: &str) -> Option<Command> {
+    let mut fields = line.split_whitespace();
+    let elevator = fields.next()?.parse::<f64>().ok()?;
+    let throttle = match fields.next() {
+        Some(field) => Some(field.parse::<f64>().ok()?),
+        None => None,
+    };
+    if fields.next().is_some() { return None; }
+
+    Some(Command { elevator: Angle::from_degrees(elevator), throttle, speedbrake: None, flaps: None, rating: None, thrust_angle: None })
+}
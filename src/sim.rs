@@ -0,0 +1,464 @@
+use crate::aero::Vehicle;
+use crate::error::DyntermError;
+use crate::icing::Icing;
+use crate::point_mass::PointMass;
+use crate::rk4::Hermite;
+use crate::six_dof::Vehicle6;
+use crate::rng::SimRng;
+use crate::vec::Kinematics;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+// Largest RK4 substep taken inside a call to `step`, s
+pub(crate) const MAX_SUBSTEP: f64 = 1.0 / 5_000.0;
+
+// Longest interval handed to `apply_dynamics` at once, s. Keeps the substep
+// count within a u16.
+const MAX_CHUNK: f64 = 1.0;
+
+// Shortest substep an integrator may be given, s, for the same reason
+pub const MIN_SUBSTEP: f64 = MAX_CHUNK / u16::MAX as f64;
+
+// A vehicle together with the simulation clock, stepped by arbitrary
+// intervals. This is the entry point for embedding the simulator. It holds
+// the full state of a run, so it can be checkpointed with `save` and picked
+// up again with `load`, or cloned to branch several variations off a common
+// starting point.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    pub vehicle: Vehicle,
+    pub time: f64,
+    // Shared by all stochastic models, which should each take a stream
+    pub rng: SimRng,
+    // Aerodynamic heating of the nose so far
+    #[serde(default)]
+    pub heating: Heating,
+    // Ice building up on the surfaces, if any
+    #[serde(default)]
+    pub icing: Option<Icing>,
+    // Work done by the integrator so far. Not part of a checkpoint.
+    #[serde(skip)]
+    pub stats: SimStats,
+    // The last step, interpolated, for the pose and motion at any time
+    // within it. Not part of a checkpoint.
+    #[serde(skip)]
+    pub dense: Option<Hermite<f64, (Kinematics, Kinematics)>>,
+}
+
+// Running totals of the stagnation-point heating, from `Vehicle::heat_flux`
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Heating {
+    // Heat taken in per unit area so far, J/m^2
+    pub load: f64,
+    // Highest heat flux so far, W/m^2, and when it was reached
+    pub peak: f64,
+    pub peak_time: f64,
+}
+
+impl Heating {
+
+    // Adds a step of `dt` seconds ending at `time`, with the heat flux going
+    // from `before` to `after`
+    pub fn add(&mut self, time: f64, dt: f64, before: f64, after: f64) {
+        self.load += 0.5 * (before + after) * dt;
+        for (time, flux) in [(time - dt, before), (time, after)] {
+            if flux > self.peak {
+                self.peak = flux;
+                self.peak_time = time;
+            }
+        }
+    }
+}
+
+// A snapshot of the observable state of a simulation. Angles are in degrees
+// and angular rates in degrees per second.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct State {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub pitch: f64,
+    pub pitch_rate: f64,
+    pub aoa: f64,
+}
+
+impl Simulation {
+
+    // Constructor, starts the clock at zero
+    pub fn new(vehicle: Vehicle, rng: SimRng) -> Simulation {
+        Simulation {
+            vehicle, time: 0.0, rng, heating: Heating::default(), icing: None, stats: SimStats::default(), dense: None,
+        }
+    }
+
+    // Advances the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f64) {
+        if let Some(icing) = &mut self.icing {
+            let ice = icing.step(self.time, self.vehicle.position.y(), &self.vehicle.atmosphere, dt);
+            self.vehicle.wing.set_ice(ice);
+            self.vehicle.elev.set_ice(ice);
+        }
+        let before = self.vehicle.heat_flux();
+        if let Some(retro) = &mut self.vehicle.retro {
+            let v = &self.vehicle.motion;
+            let gravity = self.vehicle.gravity.at(self.vehicle.position.y());
+            retro.guide(self.vehicle.position.y(), (v.x(), v.y()), self.vehicle.mass, gravity);
+        }
+        let (start, from) = (self.time, self.phase());
+        let max_substep = self.vehicle.integrator.max_substep(MAX_SUBSTEP);
+        substeps(dt, max_substep, &mut self.stats, |chunk, n| {
+            let before = (self.vehicle.evaluations, self.vehicle.unconverged);
+            self.vehicle.apply_dynamics(chunk, n);
+            (self.vehicle.evaluations - before.0, self.vehicle.unconverged - before.1)
+        });
+        if self.vehicle.electric.is_some() {
+            let fraction = self.vehicle.thrust_fraction();
+            if let Some(electric) = &mut self.vehicle.electric { electric.drain(fraction, dt); }
+        }
+        if self.vehicle.engine.is_some() {
+            let flow = self.vehicle.fuel_flow();
+            if let Some(engine) = &mut self.vehicle.engine { self.vehicle.mass -= engine.burn(flow, dt); }
+        }
+        if let Some(retro) = &mut self.vehicle.retro {
+            self.vehicle.mass -= retro.burn(dt);
+        }
+        self.time += dt;
+        self.heating.add(self.time, dt, before, self.vehicle.heat_flux());
+        self.dense = Some(Hermite::new(start, from, self.time, self.phase()));
+    }
+
+    // Returns the state at `time`, from the last step's dense output, if it
+    // lies within that step
+    pub fn state_at(&self, time: f64) -> Option<State> {
+        let dense = self.dense.as_ref().filter(|dense| (dense.start()..=dense.end()).contains(&time))?;
+        let (k, dk) = dense.at(time);
+        Some(self.observe(time, &k, &dk))
+    }
+
+    // Returns the state where the vehicle met the ground, from the last
+    // step's dense output, if it came down onto the ground or a ridge during
+    // that step
+    pub fn ground_crossing(&self) -> Option<State> {
+        let dense = self.dense.as_ref()?;
+        let time = dense.crossing(|(k, _)| self.vehicle.height(k))?;
+        let (k, dk) = dense.at(time);
+        Some(self.observe(time, &k, &dk))
+    }
+
+    // Helper function
+    fn phase(&self) -> ((Kinematics, Kinematics), (Kinematics, Kinematics)) {
+        let v = &self.vehicle;
+        let rate = (v.earth.pose_rate(&v.position, &v.motion), v.acceleration());
+        ((v.position, v.motion), rate)
+    }
+
+    // Writes the full simulation state to a JSON checkpoint file
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self).map_err(io::Error::from)
+    }
+
+    // Reads a simulation back from a checkpoint written by `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Simulation, DyntermError> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    // Returns the current state
+    pub fn state(&self) -> State {
+        self.observe(self.time, &self.vehicle.position, &self.vehicle.motion)
+    }
+
+    // Helper function
+    fn observe(&self, time: f64, k: &Kinematics, dk: &Kinematics) -> State {
+        let direction = (dk.vec - self.vehicle.wind_at(k)).orientation();
+        State {
+            time,
+            x: k.x(),
+            y: k.y(),
+            vx: dk.x(),
+            vy: dk.y(),
+            pitch: k.angle().nice_deg(),
+            pitch_rate: dk.angular_velocity_deg(),
+            aoa: (k.angle() - direction).nice_deg(),
+        }
+    }
+}
+
+// The 3-DOF counterpart to `Simulation`, flying a `PointMass`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PointMassSimulation {
+    pub vehicle: PointMass,
+    pub time: f64,
+    pub rng: SimRng,
+    #[serde(default)]
+    pub icing: Option<Icing>,
+    #[serde(skip)]
+    pub stats: SimStats,
+}
+
+// A snapshot of the observable state of a 3-DOF simulation
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct PointMassState {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub vz: f64,
+}
+
+impl PointMassSimulation {
+
+    // Constructor, starts the clock at zero
+    pub fn new(vehicle: PointMass, rng: SimRng) -> PointMassSimulation {
+        PointMassSimulation { vehicle, time: 0.0, rng, icing: None, stats: SimStats::default() }
+    }
+
+    // Advances the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f64) {
+        if let Some(icing) = &mut self.icing {
+            let ice = icing.step(self.time, self.vehicle.position.y(), &self.vehicle.atmosphere, dt);
+            self.vehicle.wing.set_ice(ice);
+        }
+        substeps(dt, MAX_SUBSTEP, &mut self.stats, |chunk, n| {
+            self.vehicle.apply_dynamics(chunk, n);
+            (4 * n as u64, 0)
+        });
+        self.time += dt;
+    }
+
+    // Returns the current state
+    pub fn state(&self) -> PointMassState {
+        let v = &self.vehicle;
+        PointMassState {
+            time: self.time,
+            x: v.position.x(),
+            y: v.position.y(),
+            z: v.position.z(),
+            vx: v.motion.x(),
+            vy: v.motion.y(),
+            vz: v.motion.z(),
+        }
+    }
+}
+
+// The 6-DOF counterpart to `Simulation`, flying a `Vehicle6`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Simulation6 {
+    pub vehicle: Vehicle6,
+    pub time: f64,
+    pub rng: SimRng,
+    #[serde(default)]
+    pub icing: Option<Icing>,
+    #[serde(skip)]
+    pub stats: SimStats,
+}
+
+// A snapshot of the observable state of a 6-DOF simulation. Angles are in
+// degrees and angular rates, about the body axes, in degrees per second.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct State6 {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub vz: f64,
+    pub roll: f64,
+    pub pitch: f64,
+    pub heading: f64,
+    pub roll_rate: f64,
+    pub yaw_rate: f64,
+    pub pitch_rate: f64,
+    pub aoa: f64,
+    pub sideslip: f64,
+}
+
+impl Simulation6 {
+
+    // Constructor, starts the clock at zero
+    pub fn new(vehicle: Vehicle6, rng: SimRng) -> Simulation6 {
+        Simulation6 { vehicle, time: 0.0, rng, icing: None, stats: SimStats::default() }
+    }
+
+    // Advances the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f64) {
+        if let Some(icing) = &mut self.icing {
+            let ice = icing.step(self.time, self.vehicle.body.position.y(), &self.vehicle.atmosphere, dt);
+            let v = &mut self.vehicle;
+            [&mut v.wing, &mut v.elev, &mut v.fin].into_iter().for_each(|foil| foil.set_ice(ice));
+        }
+        substeps(dt, MAX_SUBSTEP, &mut self.stats, |chunk, n| {
+            self.vehicle.apply_dynamics(chunk, n);
+            (4 * n as u64, 0)
+        });
+        self.time += dt;
+    }
+
+    // Returns the current state
+    pub fn state(&self) -> State6 {
+        let v = &self.vehicle;
+        let b = &v.body;
+        let (roll, pitch, heading) = v.euler();
+        State6 {
+            time: self.time,
+            x: b.position.x(),
+            y: b.position.y(),
+            z: b.position.z(),
+            vx: b.velocity.x(),
+            vy: b.velocity.y(),
+            vz: b.velocity.z(),
+            roll: roll.nice_deg(),
+            pitch: pitch.nice_deg(),
+            heading: heading.nice_deg(),
+            roll_rate: b.rates.x().to_degrees(),
+            yaw_rate: b.rates.y().to_degrees(),
+            pitch_rate: b.rates.z().to_degrees(),
+            aoa: v.aoa().nice_deg(),
+            sideslip: v.sideslip().nice_deg(),
+        }
+    }
+}
+
+// Interactive control over a run, for retrying a flare or a pull-up over
+// and over: pausing it, stepping it once at a time while paused, and
+// rewinding it by putting back an earlier state. States are kept every
+// `interval` seconds of simulation time for the last `span` seconds, so
+// rewinding can go back at most that far.
+pub struct Transport {
+    paused: bool,
+    // Single steps asked for while paused and not yet taken
+    steps: usize,
+    history: VecDeque<Simulation>,
+    interval: f64,
+    span: f64,
+}
+
+impl Transport {
+
+    // Constructor, running, with nothing kept yet
+    pub fn new(span: f64, interval: f64) -> Transport {
+        Transport { paused: false, steps: 0, history: VecDeque::new(), interval, span }
+    }
+
+    #[inline] pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // Pauses a running simulation, or runs a paused one
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.steps = 0;
+    }
+
+    // Asks for one step while paused
+    pub fn single_step(&mut self) {
+        if self.paused { self.steps += 1; }
+    }
+
+    // Whether the run should take its next step: always while running, and
+    // while paused once for each single step asked for
+    pub fn advance(&mut self) -> bool {
+        if !self.paused { return true; }
+        if self.steps == 0 { return false; }
+        self.steps -= 1;
+        true
+    }
+
+    // Keeps `sim` if it's `interval` on from the last state kept, and
+    // forgets any more than `span` before it
+    pub fn record(&mut self, sim: &Simulation) {
+        // Allow for the rounding in a clock built up from many steps
+        const SLACK: f64 = 1e-9;
+
+        if self.history.back().is_some_and(|last| sim.time - last.time < self.interval - SLACK) {
+            return;
+        }
+        self.history.push_back(sim.clone());
+        while self.history.front().is_some_and(|first| first.time < sim.time - self.span) {
+            self.history.pop_front();
+        }
+    }
+
+    // Puts `sim` back to the latest state kept at least `seconds` before
+    // it, or the earliest kept, forgetting those after. The integrator's
+    // counts carry on. Returns the time it went back to, or None with
+    // nothing kept.
+    pub fn rewind(&mut self, sim: &mut Simulation, seconds: f64) -> Option<f64> {
+        let target = sim.time - seconds;
+        while self.history.len() > 1 && self.history.back().is_some_and(|last| last.time > target) {
+            self.history.pop_back();
+        }
+        let stats = sim.stats;
+        *sim = self.history.back()?.clone();
+        sim.stats = stats;
+        Some(sim.time)
+    }
+}
+
+// Counts of the work done by a simulation's integrator, for profiling a
+// configuration. The wall time isn't kept on wasm, which has no clock.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SimStats {
+    // Calls to `step`
+    pub steps: u64,
+    // Integrator steps taken inside them
+    pub substeps: u64,
+    // Evaluations of the dynamics, four per RK4 step, and as many as the
+    // Newton iterations take for the implicit midpoint rule
+    pub evaluations: u64,
+    // Implicit midpoint steps whose Newton iteration didn't converge
+    pub unconverged: u64,
+    // Wall time spent in `step`
+    pub elapsed: Duration,
+}
+
+impl SimStats {
+
+    // Returns the integrator steps taken per second of wall time
+    pub fn steps_per_second(&self) -> f64 {
+        self.substeps as f64 / self.elapsed.as_secs_f64()
+    }
+
+    // Returns the evaluations of the dynamics per second of wall time
+    pub fn evaluations_per_second(&self) -> f64 {
+        self.evaluations as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+// Splits `dt` into chunks of at most MAX_CHUNK, each to be integrated in
+// substeps of at most `max_substep`, counting the work in `stats` with the
+// evaluations of the dynamics and unconverged steps `apply` reports
+fn substeps(dt: f64, max_substep: f64, stats: &mut SimStats, mut apply: impl FnMut(f64, u16) -> (u64, u64)) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start = Instant::now();
+
+    let mut remaining = dt;
+    while remaining > 0.0 {
+        let chunk = remaining.min(MAX_CHUNK);
+        let n = (chunk / max_substep).ceil().max(1.0) as u16;
+        let (evaluations, unconverged) = apply(chunk, n);
+        stats.evaluations += evaluations;
+        stats.unconverged += unconverged;
+        stats.substeps += n as u64;
+        remaining -= chunk;
+    }
+
+    stats.steps += 1;
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        stats.elapsed += start.elapsed();
+    }
+}
@@ -0,0 +1,340 @@
+use crate::aero::{Aerofoil, PULL_UP_ALTITUDE};
+use crate::rk4::{rk4, State};
+use crate::atmosphere::*;
+use crate::gravity::Gravity;
+use crate::vec::{Angle, Quaternion, Vector3};
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul, Div};
+
+// Body axes line up with the world axes at zero attitude: x out of the nose,
+// y out of the top, and z out of the right wing. Rotating about +z pitches
+// the nose up, as in the planar model, about +x rolls the right wing down,
+// and about +y yaws the nose left.
+const NOSE: Vector3 = Vector3::new(1.0, 0.0, 0.0);
+const TOP: Vector3 = Vector3::new(0.0, 1.0, 0.0);
+const RIGHT: Vector3 = Vector3::new(0.0, 0.0, 1.0);
+
+// Inertia tensor in body axes, kg m^2. Products of inertia are the integrals
+// of xy, xz, and yz over the mass, and enter the tensor negated.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Inertia {
+    tensor: [[f64; 3]; 3],
+}
+
+impl Inertia {
+
+    // Constructor
+    pub fn new(ixx: f64, iyy: f64, izz: f64, ixy: f64, ixz: f64, iyz: f64) -> Inertia {
+        Inertia {
+            tensor: [
+                [ixx, -ixy, -ixz],
+                [-ixy, iyy, -iyz],
+                [-ixz, -iyz, izz],
+            ]
+        }
+    }
+
+    // Angular momentum for the given body rates
+    pub fn apply(&self, w: Vector3) -> Vector3 {
+        let t = &self.tensor;
+        Vector3::new(
+            t[0][0] * w.x() + t[0][1] * w.y() + t[0][2] * w.z(),
+            t[1][0] * w.x() + t[1][1] * w.y() + t[1][2] * w.z(),
+            t[2][0] * w.x() + t[2][1] * w.y() + t[2][2] * w.z())
+    }
+
+    // Angular acceleration for the given moment, by Cramer's rule
+    pub fn solve(&self, m: Vector3) -> Vector3 {
+        let t = &self.tensor;
+        let column = |i: usize| Vector3::new(t[0][i], t[1][i], t[2][i]);
+        let (a, b, c) = (column(0), column(1), column(2));
+        let det = a.dot(b.cross(c));
+        Vector3::new(
+            m.dot(b.cross(c)),
+            a.dot(m.cross(c)),
+            a.dot(b.cross(m))) / det
+    }
+}
+
+// Full rigid-body state for the 6-DOF model. The attitude rotates body axes
+// into the world frame, and the angular rates are in body axes.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct RigidBody {
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub attitude: Quaternion,
+    pub rates: Vector3,
+}
+
+// Implement arithmetic traits, so the state can be integrated as a whole
+impl Add for RigidBody {
+    type Output = RigidBody;
+    fn add(self, other: RigidBody) -> RigidBody {
+        RigidBody {
+            position: self.position + other.position,
+            velocity: self.velocity + other.velocity,
+            attitude: self.attitude + other.attitude,
+            rates: self.rates + other.rates,
+        }
+    }
+}
+impl Mul<RigidBody> for f64 {
+    type Output = RigidBody;
+    fn mul(self, body: RigidBody) -> RigidBody {
+        RigidBody {
+            position: self * body.position,
+            velocity: self * body.velocity,
+            attitude: self * body.attitude,
+            rates: self * body.rates,
+        }
+    }
+}
+impl Mul<f64> for RigidBody {
+    type Output = RigidBody;
+    fn mul(self, scalar: f64) -> RigidBody {
+        scalar * self
+    }
+}
+impl Div<f64> for RigidBody {
+    type Output = RigidBody;
+    fn div(self, scalar: f64) -> RigidBody {
+        RigidBody {
+            position: self.position / scalar,
+            velocity: self.velocity / scalar,
+            attitude: self.attitude / scalar,
+            rates: self.rates / scalar,
+        }
+    }
+}
+impl State<f64> for RigidBody {
+    fn axpy(&mut self, a: f64, body: &RigidBody) {
+        self.position.axpy(a, &body.position);
+        self.velocity.axpy(a, &body.velocity);
+        self.attitude.axpy(a, &body.attitude);
+        self.rates.axpy(a, &body.rates);
+    }
+    fn scale(&mut self, a: f64) {
+        *self = *self * a;
+    }
+    fn divide(&mut self, d: f64) {
+        *self = *self / d;
+    }
+    fn error_norm(&self) -> f64 {
+        self.position.error_norm()
+            .max(self.velocity.error_norm())
+            .max(self.attitude.error_norm())
+            .max(self.rates.error_norm())
+    }
+    fn dimension(&self) -> usize {
+        13
+    }
+    fn component(&self, i: usize) -> f64 {
+        match i {
+            0..=2 => self.position.component(i),
+            3..=5 => self.velocity.component(i - 3),
+            6..=9 => self.attitude.component(i - 6),
+            _ => self.rates.component(i - 10),
+        }
+    }
+    fn set_component(&mut self, i: usize, value: f64) {
+        match i {
+            0..=2 => self.position.set_component(i, value),
+            3..=5 => self.velocity.set_component(i - 3, value),
+            6..=9 => self.attitude.set_component(i - 6, value),
+            _ => self.rates.set_component(i - 10, value),
+        }
+    }
+}
+
+// `Vehicle6` is the 6-DOF counterpart to `Vehicle`, for when the planar model
+// isn't enough. The wing is split into two halves either side of the centre
+// of mass, so it damps roll, and a fin with a rudder sits above the
+// elevator at the tail. Control surfaces are deflected by setting their
+// pitch, as in the planar model.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Vehicle6 {
+    pub mass: f64,
+    pub length: f64,
+    pub span: f64,
+    pub fin_height: f64,
+    pub inertia: Inertia,
+    pub body: RigidBody,
+    // Each half of the wing
+    pub wing: Aerofoil,
+    pub elev: Aerofoil,
+    pub fin: Aerofoil,
+    pub max_thrust: f64,
+    // Throttle setting as a fraction of max thrust. None leaves thrust to
+    // the built-in autothrottle
+    pub throttle: Option<f64>,
+    // Gravity field the vehicle falls in
+    #[serde(default)]
+    pub gravity: Gravity,
+    // Air the vehicle flies through
+    #[serde(default)]
+    pub atmosphere: Atmosphere,
+}
+
+impl Vehicle6 {
+
+    // Constructor
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        mass: f64,
+        length: f64,
+        span: f64,
+        fin_height: f64,
+        inertia: Inertia,
+        body: RigidBody,
+        wing: Aerofoil,
+        elev: Aerofoil,
+        fin: Aerofoil,
+        max_thrust: f64) -> Vehicle6 {
+        Vehicle6 {
+            mass,
+            length,
+            span,
+            fin_height,
+            inertia,
+            body,
+            wing,
+            elev,
+            fin,
+            max_thrust,
+            throttle: None,
+            gravity: Gravity::default(),
+            atmosphere: Atmosphere::default(),
+        }
+    }
+
+    // Velocity in body axes
+    #[inline] pub fn body_velocity(&self) -> Vector3 {
+        self.body.attitude.conjugate().rotate(self.body.velocity)
+    }
+
+    // Returns the angle of attack, in the body's plane of symmetry
+    pub fn aoa(&self) -> Angle {
+        let v = self.body_velocity();
+        Angle::from_radians(-v.y().atan2(v.x()))
+    }
+
+    // Returns the sideslip angle, positive with the airflow from the right,
+    // or none at all in still air
+    pub fn sideslip(&self) -> Angle {
+        let v = self.body_velocity();
+        let speed = v.magnitude();
+        if speed == 0.0 { return Angle::from_radians(0.0); }
+        Angle::from_radians((v.z() / speed).clamp(-1.0, 1.0).asin())
+    }
+
+    // Returns the roll, pitch, and heading of the body. Heading is measured
+    // from downrange towards +z.
+    pub fn euler(&self) -> (Angle, Angle, Angle) {
+        let q = &self.body.attitude;
+        let (nose, top, right) = (q.rotate(NOSE), q.rotate(TOP), q.rotate(RIGHT));
+        (
+            Angle::from_radians((-right.y()).atan2(top.y())),
+            Angle::from_radians(nose.y().clamp(-1.0, 1.0).asin()),
+            Angle::from_radians(nose.z().atan2(nose.x())),
+        )
+    }
+
+    // Force and moment on a surface at `at` spanning `span`, both in body
+    // axes, for a body moving at `v` and rotating at `w` through air of the
+    // given density and speed of sound, with the surface `height` above the
+    // ground
+    #[allow(clippy::too_many_arguments)]
+    fn surface_loads(
+        foil: &Aerofoil,
+        at: Vector3,
+        span: Vector3,
+        v: Vector3,
+        w: Vector3,
+        density: f64,
+        sound: f64,
+        height: f64) -> (Vector3, Vector3) {
+
+        // Only the airflow across the span counts
+        let v = v + w.cross(at);
+        let v = v - v.dot(span) * span;
+        let speed = v.magnitude();
+        if speed == 0.0 { return (Vector3::zero(), Vector3::zero()); }
+        let along = v / speed;
+
+        // Same conventions as the planar model, turned to suit the surface
+        let normal = span.cross(NOSE);
+        let aoa = Angle::from_radians(-v.dot(normal).atan2(v.dot(NOSE)));
+        let (cl, cd, cm) = foil.coefficients(aoa);
+        let (cl, cd) = foil.in_ground_effect(height, (cl, cd));
+        let cd = cd + foil.wave_drag().increment(speed / sound);
+        let q = 0.5 * density * speed.powi(2);
+
+        let force = foil.area() * q * (cl * span.cross(along) - cd * along);
+        let moment = at.cross(force) + foil.area() * q * cm * foil.chord() * span;
+        (force, moment)
+    }
+
+    // Calculates the rate of change of the rigid-body state
+    #[allow(non_snake_case)]
+    fn calculate_dynamics(&self, s: &RigidBody) -> RigidBody {
+
+        let q = s.attitude;
+        let v = q.conjugate().rotate(s.velocity);
+        let w = s.rates;
+        let density = self.atmosphere.density(s.position.y());
+        let sound = self.atmosphere.speed_of_sound(s.position.y());
+
+        // Surface positions
+        let r_wr = self.span / 4.0 * RIGHT;
+        let r_wl = -r_wr;
+        let r_e = -self.length / 2.0 * NOSE;
+        let r_f = r_e + self.fin_height * TOP;
+
+        // Aerodynamic forces and moments, in body axes
+        let height = |r: Vector3| s.position.y() + q.rotate(r).y();
+        let (F_wl, M_wl) = Vehicle6::surface_loads(&self.wing, r_wl, RIGHT, v, w, density, sound, height(r_wl));
+        let (F_wr, M_wr) = Vehicle6::surface_loads(&self.wing, r_wr, RIGHT, v, w, density, sound, height(r_wr));
+        let (F_e, M_e) = Vehicle6::surface_loads(&self.elev, r_e, RIGHT, v, w, density, sound, height(r_e));
+        let (F_f, M_f) = Vehicle6::surface_loads(&self.fin, r_f, TOP, v, w, density, sound, height(r_f));
+        let F_a = F_wl + F_wr + F_e + F_f;
+
+        // Same autothrottle as the planar model
+        let thrust = match self.throttle {
+            Some(throttle) => throttle.clamp(0.0, 1.0) * self.max_thrust,
+            None if s.position.y() < PULL_UP_ALTITUDE => self.max_thrust,
+            None => (-F_a.dot(NOSE)).clamp(0.0, self.max_thrust),
+        };
+        let T = thrust * NOSE;
+
+        // Gravitational force acting on the body
+        let W = self.mass * Vector3::new(0.0, -self.gravity.at(s.position.y()), 0.0);
+
+        // Euler's equations for the rotation
+        let M = M_wl + M_wr + M_e + M_f;
+        let w_dot = self.inertia.solve(M - w.cross(self.inertia.apply(w)));
+
+        RigidBody {
+            position: s.velocity,
+            velocity: q.rotate(F_a + T) / self.mass + W / self.mass,
+            attitude: 0.5 * (q * Quaternion::from_vector(w)),
+            rates: w_dot,
+        }
+    }
+
+    // Use RK4 to apply the calculated forces and moments to the body over `dt`
+    // seconds, split into n steps
+    pub fn apply_dynamics(&mut self, dt: f64, n: u16) {
+
+        // Time step
+        let h: f64 = dt / n as f64;
+
+        for _ in 0..n {
+            let f = |_: f64, s: RigidBody| self.calculate_dynamics(&s);
+            self.body = rk4(f, self.body, 0.0, h);
+
+            // Keep the attitude a pure rotation
+            self.body.attitude = self.body.attitude.unit();
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::atmosphere::{Atmosphere, Level, Profile};
+use crate::error::DyntermError;
+
+use csv::Reader;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+// A level of a sounding on disk, in the units radiosondes report: altitude
+// in m, temperature in degC, pressure in hPa, relative humidity in percent,
+// and the wind speed in m/s and the direction it blows from in degrees
+// clockwise from north. Humidity left out is dry air.
+#[derive(Debug, Copy, Clone, Deserialize)]
+struct Row {
+    altitude: f64,
+    temperature: f64,
+    pressure: f64,
+    #[serde(default)]
+    humidity: Option<f64>,
+    wind_speed: f64,
+    wind_direction: f64,
+}
+
+// Reads a measured atmospheric profile from CSV with the columns altitude,
+// temperature, pressure, wind_speed, and wind_direction, and optionally
+// humidity, one row per level
+pub fn load_profile(path: impl AsRef<Path>) -> Result<Profile, DyntermError> {
+    let rows = Reader::from_path(path)?.deserialize()
+        .collect::<Result<Vec<Row>, _>>()?;
+
+    if rows.is_empty() {
+        return Err(DyntermError::Parse("no levels in the sounding".into()));
+    }
+    if let Some(row) = rows.iter().find(|row| row.pressure <= 0.0 || row.temperature <= -273.15) {
+        return Err(DyntermError::Parse(format!("impossible air at {} m", row.altitude)));
+    }
+
+    let levels = rows.iter()
+        .map(|row| {
+            let (sin, cos) = row.wind_direction.to_radians().sin_cos();
+            Level {
+                altitude: row.altitude,
+                temperature: row.temperature + 273.15,
+                pressure: row.pressure * 100.0,
+                humidity: row.humidity.unwrap_or(0.0) / 100.0,
+                wind_north: -row.wind_speed * cos,
+                wind_east: -row.wind_speed * sin,
+            }
+        })
+        .collect();
+    Profile::new(levels)
+}
+
+// A sounding to fly through in place of the analytic atmosphere, read from
+// `path` when the scenario is loaded, with downrange pointing along
+// `heading`, degrees clockwise from north
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sounding {
+    pub path: String,
+    #[serde(default = "due_east")]
+    pub heading: f64,
+    #[serde(skip)]
+    pub profile: Option<Arc<Profile>>,
+}
+
+// Downrange of the flat-earth frame when it isn't given
+fn due_east() -> f64 {
+    90.0
+}
+
+impl Sounding {
+
+    // Reads the profile
+    pub fn load(&mut self) -> Result<(), DyntermError> {
+        self.profile = Some(Arc::new(load_profile(&self.path)?));
+        Ok(())
+    }
+
+    // Gets the atmosphere it describes, if it's been read
+    pub fn atmosphere(&self) -> Option<Atmosphere> {
+        let profile = self.profile.clone()?;
+        Some(Atmosphere::Measured { profile, heading: self.heading })
+    }
+}
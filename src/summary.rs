@@ -0,0 +1,158 @@
+use crate::events::Event;
+use crate::guidance::ClosestApproach;
+use crate::sim::{Heating, PointMassSimulation, Simulation, Simulation6};
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Key results of a run, printed at the end and kept alongside its outputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub seed: u64,
+    pub flight_time: f64,
+    pub range: f64,
+    pub crossrange: f64,
+    pub final_altitude: f64,
+    pub final_speed: f64,
+    pub impacted: bool,
+    // Nose heating, for the models that estimate it
+    pub heating: Option<Heating>,
+    // Events along the way, for runs that watched for any
+    #[serde(default)]
+    pub events: Vec<Event>,
+    // Least margin to the never-exceed placard, as a fraction of it, and
+    // when, for runs that watched it
+    #[serde(default)]
+    pub placard_margin: Option<(f64, f64)>,
+    // Retro-rocket fuel left and the touchdown speed it was braking for,
+    // m/s, for vehicles with one
+    #[serde(default)]
+    pub retro: Option<(f64, f64)>,
+    // Impact angle, degrees below the horizontal, the one aimed for, and the
+    // miss distance, m downrange of the target, for guided runs that got
+    // there
+    #[serde(default)]
+    pub impact: Option<(f64, f64, f64)>,
+    // Time of arrival at the target, s, and how late it was, for runs
+    // guided to arrive on time
+    #[serde(default)]
+    pub arrival: Option<(f64, f64)>,
+    // Where a guided vehicle passed closest to its target
+    #[serde(default)]
+    pub closest_approach: Option<ClosestApproach>,
+}
+
+impl Summary {
+
+    // Summarises a simulation as it stands, or as it met the ground if it
+    // came down in the last step, rather than wherever that step ended
+    pub fn new(sim: &Simulation) -> Summary {
+        let v = &sim.vehicle;
+        let impacted = v.grounded();
+        let (flight_time, range, final_altitude, final_speed) = match sim.ground_crossing().filter(|_| impacted) {
+            Some(s) => (s.time, s.x, s.y, s.vx.hypot(s.vy)),
+            None => (sim.time, v.position.x(), v.position.y(), v.motion.magnitude()),
+        };
+        Summary {
+            seed: sim.rng.seed(),
+            flight_time,
+            range,
+            crossrange: 0.0,
+            final_altitude,
+            final_speed,
+            impacted,
+            heating: Some(sim.heating),
+            events: Vec::new(),
+            placard_margin: None,
+            retro: v.retro.as_ref().map(|retro| (retro.fuel, retro.touchdown_speed)),
+            impact: None,
+            arrival: None,
+            closest_approach: None,
+        }
+    }
+
+    // Summarises a 3-DOF simulation as it stands
+    pub fn from_point_mass(sim: &PointMassSimulation) -> Summary {
+        let v = &sim.vehicle;
+        Summary {
+            seed: sim.rng.seed(),
+            flight_time: sim.time,
+            range: v.position.x(),
+            crossrange: v.position.z(),
+            final_altitude: v.position.y(),
+            final_speed: v.motion.magnitude(),
+            impacted: v.grounded(),
+            heating: None,
+            events: Vec::new(),
+            placard_margin: None,
+            retro: None,
+            impact: None,
+            arrival: None,
+            closest_approach: None,
+        }
+    }
+
+    // Summarises a 6-DOF simulation as it stands
+    pub fn from_six_dof(sim: &Simulation6) -> Summary {
+        let b = &sim.vehicle.body;
+        Summary {
+            seed: sim.rng.seed(),
+            flight_time: sim.time,
+            range: b.position.x(),
+            crossrange: b.position.z(),
+            final_altitude: b.position.y(),
+            final_speed: b.velocity.magnitude(),
+            impacted: b.position.y() <= 0.0,
+            heating: None,
+            events: Vec::new(),
+            placard_margin: None,
+            retro: None,
+            impact: None,
+            arrival: None,
+            closest_approach: None,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Summary")?;
+        writeln!(f, "  Seed            {}", self.seed)?;
+        writeln!(f, "  Flight time     {:.2} s", self.flight_time)?;
+        writeln!(f, "  Range           {:.1} m", self.range)?;
+        writeln!(f, "  Crossrange      {:.1} m", self.crossrange)?;
+        writeln!(f, "  Final altitude  {:.1} m", self.final_altitude)?;
+        write!(f, "  {:<16}{:.1} m/s",
+            if self.impacted { "Impact speed" } else { "Final speed" },
+            self.final_speed)?;
+        if let Some(heating) = &self.heating {
+            write!(f, "\n  Peak heating    {:.2} W/cm^2 at {:.2} s", heating.peak / 1e4, heating.peak_time)?;
+            write!(f, "\n  Heat load       {:.1} J/cm^2", heating.load / 1e4)?;
+        }
+        if let Some((fuel, touchdown)) = self.retro {
+            write!(f, "\n  Retro fuel left {:.1} kg", fuel)?;
+            if self.impacted {
+                write!(f, "\n  Brake margin    {:+.1} m/s", touchdown - self.final_speed)?;
+            }
+        }
+        if let Some((angle, aim, miss)) = self.impact {
+            write!(f, "\n  Impact angle    {:.1} deg, aiming for {:.1} deg", angle, aim)?;
+            write!(f, "\n  Miss distance   {:+.1} m", miss)?;
+        }
+        if let Some((time, late)) = self.arrival {
+            write!(f, "\n  Arrival         {:.2} s, {:+.2} s late", time, late)?;
+        }
+        if let Some(closest) = self.closest_approach {
+            write!(f, "\n  Closest pass    {:.3} m at {:.3} s", closest.miss, closest.time)?;
+            write!(f, "\n  Pass angle      {:.2} deg", closest.angle)?;
+            write!(f, "\n  Closing speed   {:.1} m/s", closest.closing_speed)?;
+        }
+        if let Some((margin, time)) = self.placard_margin {
+            write!(f, "\n  Placard margin  {:.1}% at {:.2} s", margin * 100.0, time)?;
+        }
+        for event in &self.events {
+            write!(f, "\n  Event           {}", event)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::aero::Vehicle;
+use crate::atmosphere::Atmosphere;
+use crate::config::VehicleConfig;
+use crate::gravity::Gravity;
+use crate::scalar::{Dual, Scalar};
+use crate::vec::{Angle, Kinematics, Vector};
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+// Elevator incidence searched for trim either way, degrees
+const MAX_ELEVATOR: f64 = 20.0;
+
+// Where the bundled tables' lift curve is monotonic, degrees
+const LOW_AOA: f64 = -10.0;
+const HIGH_AOA: f64 = 12.0;
+
+// Steepest glide searched for, degrees below the horizontal
+const STEEPEST_GLIDE: f64 = 60.0;
+
+// Tries allowed at balancing the forces along the path, and how nearly they
+// must balance, as a fraction of the weight
+const MAX_BALANCE: usize = 40;
+const BALANCE_TOLERANCE: f64 = 1e-9;
+
+// Level flight, trimmed, at a vehicle's starting altitude and airspeed in
+// still air. Angles are in degrees, and the static margin is the neutral
+// point's distance aft of the centre of mass as a fraction of the wing
+// chord, positive when the vehicle is statically stable. The elevator is held where it's trimmed unless it's
+// free, so the margin is stick-fixed or stick-free to match. The flight
+// path angle is `gamma`, and the throttle that holds the speed along it,
+// where it was solved for, is `throttle`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Trim {
+    pub aoa: f64,
+    pub elevator: f64,
+    pub static_margin: f64,
+    pub gamma: f64,
+    pub throttle: Option<f64>,
+}
+
+// Returns the force across the path, the pitching moment, and the force
+// along the path flying at angle of attack `aoa` with the elevator at
+// `elevator`, both radians, and the throttle at `throttle`
+fn loads<S: Scalar>(vehicle: &mut Vehicle<S>, aoa: S, elevator: S, throttle: S) -> (S, S, S) {
+    vehicle.elev.set_pitch(Angle::from_radians(elevator));
+    vehicle.throttle = Some(throttle);
+    let k = Kinematics::new(vehicle.position.vec, Angle::from_radians(aoa));
+    let dk = Kinematics::with_rate(Vector::new(vehicle.motion.magnitude(), S::ZERO), S::ZERO);
+    let forces = vehicle.forces(&k, &dk);
+    let aero = (forces.wing_lift + forces.wing_drag) + (forces.elevator_lift + forces.elevator_drag);
+    let total = aero + forces.thrust;
+    (total.y(), forces.total_moment(), total.x())
+}
+
+// Helper function
+fn bisect(mut low: f64, mut high: f64, mut f: impl FnMut(f64) -> Option<f64>) -> Option<f64> {
+    let (f_low, f_high) = (f(low)?, f(high)?);
+    if (f_low > 0.0) == (f_high > 0.0) { return None; }
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        if (f(mid)? > 0.0) == (f_low > 0.0) { low = mid } else { high = mid }
+    }
+    Some(0.5 * (low + high))
+}
+
+// Helper function, the Illinois method: false position, with the end that
+// stays put halved each time so it converges from either side. None if `f`
+// doesn't change sign, or isn't within `tolerance` of zero in MAX_BALANCE
+// tries.
+fn false_position(mut low: f64, mut high: f64, tolerance: f64, mut f: impl FnMut(f64) -> Option<f64>) -> Option<f64> {
+    let (mut f_low, mut f_high) = (f(low)?, f(high)?);
+    if (f_low > 0.0) == (f_high > 0.0) { return None; }
+    let mut kept = 0;
+    for _ in 0..MAX_BALANCE {
+        let x = (low * f_high - high * f_low) / (f_high - f_low);
+        let fx = f(x)?;
+        if fx.abs() <= tolerance { return Some(x); }
+        if (fx > 0.0) == (f_low > 0.0) {
+            (low, f_low) = (x, fx);
+            if kept == 1 { f_high *= 0.5; }
+            kept = 1;
+        } else {
+            (high, f_high) = (x, fx);
+            if kept == -1 { f_low *= 0.5; }
+            kept = -1;
+        }
+    }
+    None
+}
+
+// Angle of attack at which the force across the path carries `weight` with
+// the elevator at `elevator` and the throttle at `throttle`, degrees
+fn level_aoa(vehicle: &mut Vehicle, elevator: f64, throttle: f64, weight: f64) -> Option<f64> {
+    let lift = |vehicle: &mut Vehicle, aoa: f64| loads(vehicle, aoa.to_radians(), elevator.to_radians(), throttle).0;
+    bisect(LOW_AOA, HIGH_AOA, |aoa| Some(lift(vehicle, aoa) - weight))
+}
+
+// The angle of attack and elevator setting, degrees, at which the force
+// across the path carries `weight` with the pitching moment nil, with the
+// throttle at `throttle`
+fn balance(vehicle: &mut Vehicle, throttle: f64, weight: f64) -> Option<(f64, f64)> {
+    let elevator = bisect(-MAX_ELEVATOR, MAX_ELEVATOR, |elevator| {
+        let aoa = level_aoa(vehicle, elevator, throttle, weight)?;
+        Some(loads(vehicle, aoa.to_radians(), elevator.to_radians(), throttle).1)
+    })?;
+    Some((level_aoa(vehicle, elevator, throttle, weight)?, elevator))
+}
+
+// Force left over along a path `gamma`, degrees, once the vehicle's balanced
+// across it with the throttle at `throttle`, and the balance
+fn along_path(vehicle: &mut Vehicle, gravity: Gravity, gamma: f64, throttle: f64) -> Option<(f64, (f64, f64))> {
+    let weight = vehicle.mass * gravity.at(vehicle.position.y());
+    let (sin, cos) = gamma.to_radians().sin_cos();
+    let (aoa, elevator) = balance(vehicle, throttle, weight * cos)?;
+    let along = loads(vehicle, aoa.to_radians(), elevator.to_radians(), throttle).2;
+    Some((along - weight * sin, (aoa, elevator)))
+}
+
+// How far the neutral point is behind the centre of mass, as a fraction of
+// the chord, from how the moment and lift grow with angle of attack,
+// exactly rather than differenced, which would take in any step in the
+// tables
+fn static_margin(config: &VehicleConfig, gravity: Gravity, atmosphere: &Atmosphere, (aoa, elevator, throttle): (f64, f64, f64)) -> f64 {
+    let mut dual = config.build_as::<Dual>();
+    (dual.gravity, dual.atmosphere) = (gravity, atmosphere.clone());
+    let (lift, moment, _) = loads(&mut dual,
+        Dual::variable(aoa.to_radians()), Dual::constant(elevator.to_radians()), Dual::constant(throttle));
+    -moment.eps / lift.eps / dual.wing.chord().re
+}
+
+// Trims the vehicle in level flight with the lift carrying the weight and
+// the thrust left out: the angle of attack at which it does for each
+// elevator setting, then the setting at which the pitching moment there is
+// nil. None if no setting within 20 degrees either way will do.
+pub fn trim(config: &VehicleConfig) -> Option<Trim> {
+    trim_on_path(config, 0.0, Gravity::default(), &Atmosphere::default())
+}
+
+// As `trim`, on a flight path angle `gamma`, degrees, with the lift carrying
+// the part of the weight across the path, in `gravity` and `atmosphere`
+pub fn trim_on_path(config: &VehicleConfig, gamma: f64, gravity: Gravity, atmosphere: &Atmosphere) -> Option<Trim> {
+    let mut vehicle = config.build();
+    (vehicle.gravity, vehicle.atmosphere) = (gravity, atmosphere.clone());
+    let weight = vehicle.mass * gravity.at(vehicle.position.y()) * gamma.to_radians().cos();
+    let (aoa, elevator) = balance(&mut vehicle, 0.0, weight)?;
+    let static_margin = static_margin(config, gravity, atmosphere, (aoa, elevator, 0.0));
+
+    Some(Trim { aoa, elevator, static_margin, gamma, throttle: None })
+}
+
+// As `trim_on_path`, in steady flight, with the forces along the path
+// balanced too. A vehicle with thrust flies the path `gamma`, degrees, level
+// if None, with the throttle set so the thrust makes up the drag and the
+// weight along it. A glider flies the path down which the weight along it
+// makes up the drag, which its airspeed decides, so `gamma` must be None.
+// None if the vehicle can't, or the forces don't settle.
+pub fn trim_steady(config: &VehicleConfig, gamma: Option<f64>, gravity: Gravity, atmosphere: &Atmosphere) -> Option<Trim> {
+    let mut vehicle = config.build();
+    (vehicle.gravity, vehicle.atmosphere) = (gravity, atmosphere.clone());
+    let tolerance = BALANCE_TOLERANCE * vehicle.mass * gravity.at(vehicle.position.y());
+
+    // Whether it has any thrust to give, flying level at full throttle
+    let full = loads(&mut vehicle, 0.0, 0.0, 1.0).2;
+    let idle = loads(&mut vehicle, 0.0, 0.0, 0.0).2;
+    let powered = full > idle;
+
+    let (gamma, throttle) = match (powered, gamma) {
+        (true, gamma) => {
+            let gamma = gamma.unwrap_or(0.0);
+            let throttle = false_position(0.0, 1.0, tolerance, |throttle| {
+                Some(along_path(&mut vehicle, gravity, gamma, throttle)?.0)
+            })?;
+            (gamma, Some(throttle))
+        }
+        (false, None) => {
+            let gamma = false_position(-STEEPEST_GLIDE, 0.0, tolerance, |gamma| {
+                Some(along_path(&mut vehicle, gravity, gamma, 0.0)?.0)
+            })?;
+            (gamma, None)
+        }
+        (false, Some(_)) => return None,
+    };
+
+    let (_, (aoa, elevator)) = along_path(&mut vehicle, gravity, gamma, throttle.unwrap_or(0.0))?;
+    let static_margin = static_margin(config, gravity, atmosphere, (aoa, elevator, throttle.unwrap_or(0.0)));
+    Some(Trim { aoa, elevator, static_margin, gamma, throttle })
+}
+
+// Trims the vehicle at `count` centres of mass spread evenly between its
+// limits, giving each with its trim, if it can be
+pub fn cg_sweep(config: &VehicleConfig, count: usize) -> Vec<(f64, Option<Trim>)> {
+    let (fore, aft) = config.cg_limits();
+    (0..count)
+        .map(|i| {
+            let cg = fore + (aft - fore) * i as f64 / (count - 1).max(1) as f64;
+            (cg, trim(&VehicleConfig { cg: Some(cg), ..config.clone() }))
+        })
+        .collect()
+}
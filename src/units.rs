@@ -0,0 +1,230 @@
+use crate::aero::{Flaps, Flex, Hinge, Speedbrake, Vehicle, WaveDrag};
+use crate::config::{AeroModel, AerofoilConfig, VehicleConfig};
+use crate::derivatives::Derivatives;
+use crate::electric::Electric;
+use crate::engine::Engine;
+use crate::lifters::{Parafoil, Rotor};
+use crate::propeller::Propeller;
+use crate::retro::Retro;
+use crate::rk4::Integrator;
+use crate::sim::{Simulation, State};
+
+use uom::si::angle::degree;
+use uom::si::angular_velocity::degree_per_second;
+use uom::si::area::square_meter;
+use uom::si::f64::{
+    Angle, AngularVelocity, Area, Force, Length, Mass, MomentOfInertia, Time, Velocity,
+};
+use uom::si::force::newton;
+use uom::si::length::meter;
+use uom::si::mass::kilogram;
+use uom::si::moment_of_inertia::kilogram_square_meter;
+use uom::si::time::second;
+use uom::si::velocity::meter_per_second;
+
+// Typed counterparts to the config and state types, built with the `uom`
+// feature. Internally everything stays in plain SI f64s; these only check
+// units at the boundary, so a force can't be passed where a mass belongs or
+// read back in the wrong scale.
+
+// Geometry of a single lifting surface
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSpec {
+    pub area: Area,
+    pub chord: Length,
+    pub incidence: Angle,
+}
+
+// A `VehicleConfig` in typed quantities
+#[derive(Debug, Clone)]
+pub struct VehicleSpec {
+    pub mass: Mass,
+    pub length: Length,
+    // Left out, the body is taken as a uniform rod
+    pub moment: Option<MomentOfInertia>,
+    pub cg: Option<Length>,
+    pub cg_limits: Option<(Length, Length)>,
+    pub max_thrust: Force,
+    pub nose_radius: Length,
+    pub span: Option<Length>,
+
+    // Initial position and attitude
+    pub x: Length,
+    pub altitude: Length,
+    pub pitch: Angle,
+
+    // Initial motion
+    pub speed: Velocity,
+    pub flight_path_angle: Angle,
+    pub pitch_rate: AngularVelocity,
+
+    pub wing: SurfaceSpec,
+    pub elevator: SurfaceSpec,
+    pub wave_drag: WaveDrag,
+    pub flex: Option<Flex>,
+    pub hinge: Option<Hinge>,
+    pub speedbrake: Option<Speedbrake>,
+    pub flaps: Option<Flaps>,
+    pub propeller: Option<Propeller>,
+    pub electric: Option<Electric>,
+    pub engine: Option<Engine>,
+    pub retro: Option<Retro>,
+    // Dimensionless, so carried as they are
+    pub derivatives: Option<Derivatives>,
+    pub rotor: Option<Rotor>,
+    pub parafoil: Option<Parafoil>,
+    pub aero_model: AeroModel,
+    pub integrator: Integrator,
+}
+
+impl Default for VehicleSpec {
+    fn default() -> VehicleSpec {
+        VehicleSpec::from(&VehicleConfig::default())
+    }
+}
+
+impl VehicleSpec {
+
+    // Builds the vehicle, as `VehicleConfig::build` does
+    pub fn build(&self) -> Vehicle {
+        VehicleConfig::from(self).build()
+    }
+}
+
+impl From<&SurfaceSpec> for AerofoilConfig {
+    fn from(s: &SurfaceSpec) -> AerofoilConfig {
+        AerofoilConfig {
+            area: s.area.get::<square_meter>(),
+            chord: s.chord.get::<meter>(),
+            incidence: s.incidence.get::<degree>(),
+        }
+    }
+}
+
+impl From<&AerofoilConfig> for SurfaceSpec {
+    fn from(c: &AerofoilConfig) -> SurfaceSpec {
+        SurfaceSpec {
+            area: Area::new::<square_meter>(c.area),
+            chord: Length::new::<meter>(c.chord),
+            incidence: Angle::new::<degree>(c.incidence),
+        }
+    }
+}
+
+impl From<&VehicleSpec> for VehicleConfig {
+    fn from(s: &VehicleSpec) -> VehicleConfig {
+        VehicleConfig {
+            mass: s.mass.get::<kilogram>(),
+            length: s.length.get::<meter>(),
+            moment: s.moment.map(|m| m.get::<kilogram_square_meter>()),
+            cg: s.cg.map(|cg| cg.get::<meter>()),
+            cg_limits: s.cg_limits.map(|(fore, aft)| (fore.get::<meter>(), aft.get::<meter>())),
+            max_thrust: s.max_thrust.get::<newton>(),
+            nose_radius: s.nose_radius.get::<meter>(),
+            span: s.span.map(|span| span.get::<meter>()),
+            x: s.x.get::<meter>(),
+            altitude: s.altitude.get::<meter>(),
+            pitch: s.pitch.get::<degree>(),
+            speed: s.speed.get::<meter_per_second>(),
+            flight_path_angle: s.flight_path_angle.get::<degree>(),
+            pitch_rate: s.pitch_rate.get::<degree_per_second>(),
+            wing: AerofoilConfig::from(&s.wing),
+            elevator: AerofoilConfig::from(&s.elevator),
+            wave_drag: s.wave_drag.clone(),
+            flex: s.flex,
+            hinge: s.hinge.clone(),
+            speedbrake: s.speedbrake.clone(),
+            flaps: s.flaps.clone(),
+            propeller: s.propeller.clone(),
+            electric: s.electric.clone(),
+            engine: s.engine.clone(),
+            retro: s.retro.clone(),
+            derivatives: s.derivatives.clone(),
+            rotor: s.rotor.clone(),
+            parafoil: s.parafoil.clone(),
+            aero_model: s.aero_model,
+            integrator: s.integrator,
+        }
+    }
+}
+
+impl From<&VehicleConfig> for VehicleSpec {
+    fn from(c: &VehicleConfig) -> VehicleSpec {
+        VehicleSpec {
+            mass: Mass::new::<kilogram>(c.mass),
+            length: Length::new::<meter>(c.length),
+            moment: c.moment.map(MomentOfInertia::new::<kilogram_square_meter>),
+            cg: c.cg.map(Length::new::<meter>),
+            cg_limits: c.cg_limits.map(|(fore, aft)| (Length::new::<meter>(fore), Length::new::<meter>(aft))),
+            max_thrust: Force::new::<newton>(c.max_thrust),
+            nose_radius: Length::new::<meter>(c.nose_radius),
+            span: c.span.map(Length::new::<meter>),
+            x: Length::new::<meter>(c.x),
+            altitude: Length::new::<meter>(c.altitude),
+            pitch: Angle::new::<degree>(c.pitch),
+            speed: Velocity::new::<meter_per_second>(c.speed),
+            flight_path_angle: Angle::new::<degree>(c.flight_path_angle),
+            pitch_rate: AngularVelocity::new::<degree_per_second>(c.pitch_rate),
+            wing: SurfaceSpec::from(&c.wing),
+            elevator: SurfaceSpec::from(&c.elevator),
+            wave_drag: c.wave_drag.clone(),
+            flex: c.flex,
+            hinge: c.hinge.clone(),
+            speedbrake: c.speedbrake.clone(),
+            flaps: c.flaps.clone(),
+            propeller: c.propeller.clone(),
+            electric: c.electric.clone(),
+            engine: c.engine.clone(),
+            retro: c.retro.clone(),
+            derivatives: c.derivatives.clone(),
+            rotor: c.rotor.clone(),
+            parafoil: c.parafoil.clone(),
+            aero_model: c.aero_model,
+            integrator: c.integrator,
+        }
+    }
+}
+
+// A `State` in typed quantities
+#[derive(Debug, Clone, Copy)]
+pub struct TypedState {
+    pub time: Time,
+    pub x: Length,
+    pub y: Length,
+    pub vx: Velocity,
+    pub vy: Velocity,
+    pub pitch: Angle,
+    pub pitch_rate: AngularVelocity,
+    pub aoa: Angle,
+}
+
+impl From<&State> for TypedState {
+    fn from(s: &State) -> TypedState {
+        TypedState {
+            time: Time::new::<second>(s.time),
+            x: Length::new::<meter>(s.x),
+            y: Length::new::<meter>(s.y),
+            vx: Velocity::new::<meter_per_second>(s.vx),
+            vy: Velocity::new::<meter_per_second>(s.vy),
+            pitch: Angle::new::<degree>(s.pitch),
+            pitch_rate: AngularVelocity::new::<degree_per_second>(s.pitch_rate),
+            aoa: Angle::new::<degree>(s.aoa),
+        }
+    }
+}
+
+impl Simulation {
+
+    // Returns the current state in typed quantities
+    pub fn typed_state(&self) -> TypedState {
+        TypedState::from(&self.state())
+    }
+}
+
+impl Vehicle {
+
+    // Thrust over the last step
+    pub fn thrust(&self) -> Force {
+        Force::new::<newton>(self.diagnostics.thrust)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::scalar::Scalar;
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+// A column of rising air with a Gaussian profile, strongest at its centre
+// and falling to 1/e of that `radius` m out. It's centred `x` m downrange and
+// `z` m across it, and rises at `strength` m/s from the ground up to any
+// `ceiling`, m, usually the top of the convective layer.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Thermal {
+    pub x: f64,
+    #[serde(default)]
+    pub z: f64,
+    pub radius: f64,
+    pub strength: f64,
+    #[serde(default)]
+    pub ceiling: Option<f64>,
+}
+
+// A ridge running across the flight path, with a Gaussian profile `height` m
+// tall and `half_width` m to 1/e of that either side of its crest `x` m
+// downrange. Wind blowing over it is turned up the windward face and down
+// the lee, by the slope of the ground beneath, dying away with height above
+// the ground over the ridge's own width.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Ridge {
+    pub x: f64,
+    pub height: f64,
+    pub half_width: f64,
+}
+
+// Local vertical air motion laid over the steady wind, which the aerofoils
+// see along with it. Slope lift is driven by the downrange component of the
+// steady wind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Updrafts {
+    pub thermals: Vec<Thermal>,
+    pub ridges: Vec<Ridge>,
+}
+
+impl Thermal {
+
+    // Vertical air speed at a point, m/s
+    pub fn at<S: Scalar>(&self, x: S, altitude: S, z: S) -> S {
+        if self.ceiling.is_some_and(|ceiling| altitude > S::from_f64(ceiling)) {
+            return S::ZERO;
+        }
+        let (dx, dz) = (x - S::from_f64(self.x), z - S::from_f64(self.z));
+        let r2 = (dx * dx + dz * dz) / S::from_f64(self.radius * self.radius);
+        S::from_f64(self.strength) * (-r2).exp()
+    }
+}
+
+impl Ridge {
+
+    // Height of the ground at `x`, m
+    pub fn ground<S: Scalar>(&self, x: S) -> S {
+        let u = (x - S::from_f64(self.x)) / S::from_f64(self.half_width);
+        S::from_f64(self.height) * (-u * u).exp()
+    }
+
+    // Vertical air speed at a point with a downrange wind `wind`, m/s
+    pub fn at<S: Scalar>(&self, x: S, altitude: S, wind: S) -> S {
+        let ground = self.ground(x);
+        if altitude < ground { return S::ZERO; }
+
+        let u = (x - S::from_f64(self.x)) / S::from_f64(self.half_width);
+        let slope = S::from_f64(-2.0) * u * ground / S::from_f64(self.half_width);
+        let decay = (-(altitude - ground) / S::from_f64(self.half_width)).exp();
+        wind * slope * decay
+    }
+}
+
+impl Updrafts {
+
+    pub fn is_empty(&self) -> bool {
+        self.thermals.is_empty() && self.ridges.is_empty()
+    }
+
+    // Height of the ground over the ridges at `x`, m
+    pub fn ground<S: Scalar>(&self, x: S) -> S {
+        self.ridges.iter().fold(S::ZERO, |sum, r| sum + r.ground(x))
+    }
+
+    // Vertical air speed at a point, with a downrange wind `wind`, m/s
+    pub fn at<S: Scalar>(&self, x: S, altitude: S, z: S, wind: S) -> S {
+        self.thermals.iter().map(|t| t.at(x, altitude, z))
+            .chain(self.ridges.iter().map(|r| r.at(x, altitude, wind)))
+            .fold(S::ZERO, |sum, w| sum + w)
+    }
+}
@@ -1,131 +1,591 @@
+#[cfg(feature = "plot")]
+use plotters::coord::ranged1d::{AsRangedCoord, ValueFormatter};
+#[cfg(feature = "plot")]
 use plotters::prelude::*;
-use once_cell::sync::Lazy;
-use std::str::FromStr;
+#[cfg(feature = "plot")]
+use plotters::chart::MeshStyle;
+#[cfg(feature = "plot")]
+use plotters::coord::Shift;
+#[cfg(feature = "plot")]
+use core::ops::Range;
+#[cfg(feature = "plot")]
+use serde::{Deserialize, Serialize};
+
+// The standard atmosphere used to live here
+pub use crate::atmosphere::*;
+
+use crate::error::DyntermError;
+use alloc::format;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+// Furthest a chart drawn to scale is stretched out of square
+#[cfg(feature = "plot")]
+const MAX_ASPECT: f64 = 32.0;
+
+// How every chart is drawn, read from a JSON file with --plot-style and
+// otherwise as they've always been drawn. Sizes are in pixels at 96 DPI and
+// grow with `dpi`: `size` is a chart's shorter side, stretched along the
+// other to the chart's aspect ratio. Points are shaded from `colour` to
+// `highlight` by their third element, up to 90, as the angle of attack
+// shades the trajectory; a secondary series is drawn in `secondary`, and the
+// runs in a comparison in `palette` in turn, or plotters' own palette if it's
+// empty. `labels` is the most tick labels on each axis and `light_lines` the
+// fine grid lines between two of them, and `grid` false leaves the grid off
+// altogether. Charts are thinned to about `points` each, or left whole if 0.
+#[cfg(feature = "plot")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlotStyle {
+    pub size: u32,
+    pub dpi: f64,
+    pub margin: u32,
+    pub x_label_area: u32,
+    pub y_label_area: u32,
+    pub font: String,
+    pub caption_size: f64,
+    pub label_size: f64,
+    pub point_size: u32,
+    pub background: (u8, u8, u8),
+    pub colour: (u8, u8, u8),
+    pub highlight: (u8, u8, u8),
+    pub secondary: (u8, u8, u8),
+    pub palette: Vec<(u8, u8, u8)>,
+    pub grid: bool,
+    pub labels: usize,
+    pub light_lines: usize,
+    pub points: usize,
+}
+
+#[cfg(feature = "plot")]
+impl Default for PlotStyle {
+    fn default() -> PlotStyle {
+        PlotStyle {
+            size: 320,
+            dpi: 96.0,
+            margin: 10,
+            x_label_area: 40,
+            y_label_area: 80,
+            font: "sans-serif".to_owned(),
+            caption_size: 16.0,
+            label_size: 12.0,
+            point_size: 2,
+            background: (255, 255, 255),
+            colour: (0, 0, 0),
+            highlight: (255, 0, 0),
+            secondary: (0, 0, 255),
+            palette: Vec::new(),
+            grid: true,
+            labels: 11,
+            light_lines: 10,
+            points: 2_000,
+        }
+    }
+}
+
+#[cfg(feature = "plot")]
+impl PlotStyle {
+
+    // Reads a style from a JSON file, anything left out as by default
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<PlotStyle, DyntermError> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    // Helper function
+    fn px(&self, length: f64) -> u32 {
+        (length * self.dpi / 96.0).round() as u32
+    }
+
+    // Width and height of a chart, px, at least `size` each way, stretched
+    // to `aspect_ratio`
+    pub fn canvas_size(&self, aspect_ratio: f64) -> (u32, u32) {
+        let size = self.size as f64 * self.dpi / 96.0;
+        let x_dim = if aspect_ratio >= 1.0 { (size * aspect_ratio).ceil() } else { size.ceil() };
+        let y_dim = if aspect_ratio <= 1.0 { (size / aspect_ratio).ceil() } else { size.ceil() };
+        (x_dim as u32, y_dim as u32)
+    }
+
+    // Clears `root` to the background and sets up a chart on it, captioned
+    // `title`, with room for the labels
+    pub fn builder<'a, 'b>(&'b self, root: &'a DrawingArea<BitMapBackend<'b>, Shift>, title: &str)
+        -> Result<ChartBuilder<'a, 'b, BitMapBackend<'b>>, DyntermError> {
+        root.fill(&rgb(self.background))?;
+        let mut builder = ChartBuilder::on(root);
+        builder
+            .x_label_area_size(self.px(self.x_label_area as f64))
+            .y_label_area_size(self.px(self.y_label_area as f64))
+            .margin(self.px(self.margin as f64))
+            .caption(title, (self.font.as_str(), self.px(self.caption_size) as f64));
+        Ok(builder)
+    }
+
+    // Sets the labels' font and the grid's density on `mesh`
+    pub fn mesh<'b, X: Ranged, Y: Ranged>(&'b self, mesh: &mut MeshStyle<'_, 'b, X, Y, BitMapBackend<'_>>) {
+        let font = (self.font.as_str(), self.px(self.label_size) as f64);
+        mesh.label_style(font)
+            .axis_desc_style(font)
+            .x_labels(self.labels)
+            .y_labels(self.labels)
+            .max_light_lines(self.light_lines);
+        if !self.grid { mesh.disable_mesh(); }
+    }
+
+    // A point's radius, px
+    pub fn point_size(&self) -> u32 {
+        self.px(self.point_size as f64).max(1)
+    }
+
+    // The colour of a point `z` of the way up to 90
+    pub fn shade(&self, z: f64) -> RGBColor {
+        let t = (z / 90.0).clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + t * (b as f64 - a as f64)) as u8;
+        RGBColor(
+            mix(self.colour.0, self.highlight.0),
+            mix(self.colour.1, self.highlight.1),
+            mix(self.colour.2, self.highlight.2))
+    }
+
+    // The colour of run `i` in a comparison
+    pub fn pick(&self, i: usize) -> RGBAColor {
+        match self.palette.len() {
+            0 => Palette99::pick(i).to_rgba(),
+            n => rgb(self.palette[i % n]).to_rgba(),
+        }
+    }
+}
+
+// Helper function
+#[cfg(feature = "plot")]
+fn rgb((r, g, b): (u8, u8, u8)) -> RGBColor {
+    RGBColor(r, g, b)
+}
+
+// How a chart's y axes are drawn: its y axis on a log scale, for
+// quantities spanning orders of magnitude such as the Reynolds number, and
+// a second series against an axis of its own on the right, if any, such as
+// the altitude under the Mach number. Points that aren't positive can't be
+// drawn on a log scale, and are left off.
+#[cfg(feature = "plot")]
+pub struct Axes<'a> {
+    pub log_y: bool,
+    pub secondary: Option<Secondary<'a>>,
+}
+
+#[cfg(feature = "plot")]
+impl Axes<'_> {
+    // One linear y axis
+    pub const PLAIN: Axes<'static> = Axes { log_y: false, secondary: None };
+}
+
+// A series drawn in blue against the y axis on the right
+#[cfg(feature = "plot")]
+pub struct Secondary<'a> {
+    pub y_label: &'a str,
+    pub log_y: bool,
+    pub data: &'a [(f64, f64, f64)],
+}
 
 // Define a function that takes an array of tuples and generates a scatter plot
+#[cfg(feature = "plot")]
 pub fn plot_scatter(
     title: &str, 
     x_label: &str, 
     y_label: &str, 
     do_aspect: bool, 
-    data: &[(f64, f64, f64)]) -> Result<(), Box<dyn std::error::Error>> {
+    data: &[(f64, f64, f64)],
+    axes: &Axes,
+    style: &PlotStyle) -> Result<(), DyntermError> {
+
+    // Only the points each y axis can show
+    let drawable = |log_y: bool, data: &[(f64, f64, f64)]| -> Vec<(f64, f64, f64)> {
+        data.iter().filter(|p| !log_y || p.1 > 0.0).copied().collect()
+    };
+    let data = drawable(axes.log_y, data);
+    let secondary = axes.secondary.as_ref().map(|s| (s.y_label, s.log_y, drawable(s.log_y, s.data)));
+
+    // Nothing to draw, and no range to draw it over, as when a run ends
+    // before its first step
+    if data.is_empty() { return Ok(()); }
 
     // Calculate the minimum and maximum x and y values in the data array
-    let (x_min, x_max) = data.iter().map(|(x, _, _)| x)
+    let extent = |values: &mut dyn Iterator<Item = f64>| values
         .fold(
             (f64::INFINITY, f64::NEG_INFINITY), 
-            // Find the minimum and maximum values for x
-            |acc, &x| (acc.0.min(x), acc.1.max(x))
+            // Find the minimum and maximum values
+            |acc, v| (acc.0.min(v), acc.1.max(v))
         );
-    let (y_min, y_max) = data.iter().map(|(_, y, _)| y)
-        .fold(
-            (f64::INFINITY, f64::NEG_INFINITY), 
-            // Find the minimum and maximum values for y
-            |acc, &y| (acc.0.min(y), acc.1.max(y))
-        );
-    let aspect_ratio = if do_aspect { (x_max-x_min) / (y_max-y_min) } else { 3.0 };
+    let extra = secondary.as_ref().map_or(&[][..], |(_, _, data)| &data[..]);
+    let (x_min, x_max) = extent(&mut data.iter().chain(extra).map(|p| p.0));
+    let (y_min, y_max) = extent(&mut data.iter().map(|p| p.1));
+    // A flat axis can't be drawn to scale, and a nearly flat one is drawn
+    // no further out of square than MAX_ASPECT, so level flight doesn't ask
+    // for a canvas gigapixels wide
+    let flat = !(x_max > x_min && y_max > y_min);
+    let aspect_ratio = match do_aspect && !flat {
+        true => ((x_max-x_min) / (y_max-y_min)).clamp(1.0 / MAX_ASPECT, MAX_ASPECT),
+        false => 3.0,
+    };
 
     // Create a new bitmap backend with a specified filename and dimensions
     let mut path: String = "".to_owned();
     path.push_str(title);
     path.push_str(".png");
 
-    let x_dim = if aspect_ratio >= 1.0 { 
-                         (320.0 * aspect_ratio).ceil() as u32 
-                     } else { 320 };
-    let y_dim = if aspect_ratio <= 1.0 { 
-                         (320.0 / aspect_ratio).ceil() as u32
-                     } else { 320 };
+    let (x_dim, y_dim) = style.canvas_size(aspect_ratio);
 
     let root = BitMapBackend::new(&path, (x_dim, y_dim)).into_drawing_area();
 
-    // Fill the backend with white color
-    root.fill(&WHITE)?;
-
-    // Create a new chart builder with specified dimensions and margins
-    let mut chart = ChartBuilder::on(&root)
-        .x_label_area_size(40)
-        .y_label_area_size(80)
-        .margin(10)
-        .caption(title, ("sans-serif", 16))
-        // Set the limits of the chart to the calculated minimum and maximum 
-        // values for x and y
-        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+    // Fill the backend with the background and set up the chart with the
+    // style's margins and caption
+    let mut builder = style.builder(&root, title)?;
+    if secondary.is_some() {
+        builder.right_y_label_area_size(style.px(style.y_label_area as f64));
+    }
 
-    // Configure the chart's mesh (grid lines), add labels, and draw it
-    chart.configure_mesh()
-        .x_desc(x_label)
-        .y_desc(y_label)
-        .draw()?;
+    // Set the limits of the chart to the calculated minimum and maximum
+    // values for x and y, on whichever scales were asked for
+    let x = x_min..x_max;
+    let y = y_min..y_max;
+    let labels = (x_label, y_label);
+    match (axes.log_y, secondary) {
+        (false, None) => draw_scatter(&mut builder, style, labels, x, (y, false), &data, None::<(Range<f64>, _)>),
+        (true, None) => draw_scatter(&mut builder, style, labels, x, (y.log_scale(), true), &data, None::<(Range<f64>, _)>),
+        (log_y, Some((y_label, log_y2, data2))) => {
+            let (min, max) = extent(&mut data2.iter().map(|p| p.1));
+            let y2 = min..max;
+            let s = Secondary { y_label, log_y: log_y2, data: &data2 };
+            match (log_y, log_y2) {
+                (false, false) => draw_scatter(&mut builder, style, labels, x, (y, false), &data, Some((y2, s))),
+                (false, true) => draw_scatter(&mut builder, style, labels, x, (y, false), &data, Some((y2.log_scale(), s))),
+                (true, false) => draw_scatter(&mut builder, style, labels, x, (y.log_scale(), true), &data, Some((y2, s))),
+                (true, true) => draw_scatter(&mut builder, style, labels, x, (y.log_scale(), true), &data, Some((y2.log_scale(), s))),
+            }
+        }
+    }
+}
+
+// Draws `data` against `y` on the chart `builder` sets up, labelling a log
+// axis in powers of ten, and any secondary series against its own axis on
+// the right
+#[cfg(feature = "plot")]
+fn draw_scatter<Y, Y2>(
+    builder: &mut ChartBuilder<BitMapBackend>,
+    style: &PlotStyle,
+    (x_label, y_label): (&str, &str),
+    x: Range<f64>,
+    (y, log_y): (Y, bool),
+    data: &[(f64, f64, f64)],
+    secondary: Option<(Y2, Secondary)>) -> Result<(), DyntermError>
+where
+    Y: AsRangedCoord<Value = f64>,
+    Y::CoordDescType: ValueFormatter<f64>,
+    Y2: AsRangedCoord<Value = f64>,
+    Y2::CoordDescType: ValueFormatter<f64>,
+{
+    let chart = builder.build_cartesian_2d(x.clone(), y)?;
+    let exponent = |v: &f64| format!("{:.0e}", v);
+
+    // Draw the data points as circles shaded by the AoA indicated as the
+    // third element of the tuple in the input data
+    let points = || data.iter().map(
+        |(x, y, aoa)| Circle::new(
+            (*x, *y), 
+            style.point_size(), 
+            style.shade(*aoa).filled())
+    );
+
+    match secondary {
+        None => {
+            let mut chart = chart;
 
-    // Draw the data points as circles with radius 2 and a color corresponding 
-    // to the AoA indicated as the third element of the tuple in the input data
-    chart.draw_series(
-        data.iter().map(
-            |(x, y, aoa)| Circle::new(
-                (*x, *y), 
-                2, 
-                RGBColor((255.0 * aoa/90.0) as u8, 0, 0).filled())
-        )
-    ).unwrap();
+            // Configure the chart's mesh (grid lines), add labels, and draw it
+            let mut mesh = chart.configure_mesh();
+            mesh.x_desc(x_label).y_desc(y_label);
+            style.mesh(&mut mesh);
+            if log_y { mesh.y_label_formatter(&exponent); }
+            mesh.draw()?;
+            chart.draw_series(points())?;
+        }
+        Some((y2, s)) => {
+            // With the right given over to the secondary axis first, so the
+            // mesh labels the primary on the left alone
+            let mut chart = chart.set_secondary_coord(x, y2);
+            let mut mesh = chart.configure_mesh();
+            mesh.x_desc(x_label).y_desc(y_label);
+            style.mesh(&mut mesh);
+            if log_y { mesh.y_label_formatter(&exponent); }
+            mesh.draw()?;
+            chart.draw_series(points())?;
+
+            let mut axes = chart.configure_secondary_axes();
+            axes.y_desc(s.y_label)
+                .label_style((style.font.as_str(), style.px(style.label_size) as f64))
+                .axis_desc_style((style.font.as_str(), style.px(style.label_size) as f64));
+            if s.log_y { axes.y_label_formatter(&exponent); }
+            axes.draw()?;
+            chart.draw_secondary_series(s.data.iter().map(|&(x, y, _)| Circle::new((x, y), style.point_size(), rgb(style.secondary).filled())))?;
+        }
+    }
 
     // Return success status
     Ok(())
+}
+
+// Downsamples `data` to `threshold` points by largest-triangle-three-
+// buckets: the first and last points are kept, the rest are split into
+// equal buckets in order, and from each is kept the point making the
+// largest triangle with the point kept from the bucket before and the mean
+// of the bucket after. Peaks and turns survive where evenly spaced samples
+// would lose them. The buckets go by order rather than by x, so a path that
+// doubles back, such as a trajectory or a phase plane, thins as well as a
+// time history.
+pub fn lttb(data: &[(f64, f64, f64)], threshold: usize) -> Vec<(f64, f64, f64)> {
+    let n = data.len();
+    if threshold < 3 || threshold >= n { return data.to_vec(); }
+
+    let every = (n - 2) as f64 / (threshold - 2) as f64;
+    let bucket = |i: usize| ((i as f64 * every) as usize + 1).min(n - 1)..(((i + 1) as f64 * every) as usize + 1).min(n - 1);
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(data[0]);
+    let mut a = data[0];
+    for i in 0..threshold - 2 {
+        // The mean of the next bucket, or the last point after the last
+        let next = bucket(i + 1);
+        let (cx, cy) = if next.is_empty() {
+            (data[n - 1].0, data[n - 1].1)
+        } else {
+            let count = next.len() as f64;
+            let (sx, sy) = data[next].iter().fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+            (sx / count, sy / count)
+        };
 
+        let area = |b: &(f64, f64, f64)| ((a.0 - cx) * (b.1 - a.1) - (a.0 - b.0) * (cy - a.1)).abs();
+        if let Some(&b) = data[bucket(i)].iter().max_by(|p, q| area(p).total_cmp(&area(q))) {
+            sampled.push(b);
+            a = b;
+        }
+    }
+    sampled.push(data[n - 1]);
+    sampled
 }
 
-// https://ntrs.nasa.gov/archive/nasa/casi.ntrs.nasa.gov/19770009539.pdf
-pub fn isa_density(altitude: f64) -> f64 {
-    const RHO0: f64 = 1.225; // Density at sea level, kg/m^3
-    const T0: f64 = 288.15; // Temperature at sea level, K
-    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
-    const G: f64 = 9.80665; // Acceleration due to gravity, m/s^2
-    const R: f64 = 287.058; // Gas constant for air, J/(kg K)
+// Thins `data` to about `points` for drawing, by `lttb`, leaving it be if
+// `points` is 0 or it's no more than that already. A chart made of a few
+// series told apart by the third element is thinned a series at a time, so
+// they don't pick from one another's points.
+pub fn decimate(data: &[(f64, f64, f64)], points: usize) -> Vec<(f64, f64, f64)> {
+    // Most series a chart is taken to be made of
+    const SERIES: usize = 8;
 
-    let temp = if altitude <= 11000.0 {
-        T0 - L * altitude
-    } else {
-        216.65
-    };
+    if points == 0 || data.len() <= points { return data.to_vec(); }
 
-    let press = if altitude <= 11000.0 {
-        101325.0 * (temp / T0).powf(G / (L * R))
-    } else {
-        22632.0 * (-G * (altitude - 11000.0) / (R * 216.65)).exp()
-    };
+    let mut styles: Vec<f64> = Vec::new();
+    for p in data {
+        if !styles.contains(&p.2) {
+            if styles.len() == SERIES { return lttb(data, points); }
+            styles.push(p.2);
+        }
+    }
 
-    RHO0 * (press / 101325.0)
+    styles.iter().flat_map(|&style| {
+        let series: Vec<_> = data.iter().filter(|p| p.2 == style).copied().collect();
+        let share = (points * series.len()).div_ceil(data.len()).max(3);
+        lttb(&series, share)
+    }).collect()
 }
 
-const SEA_LEVEL_DENSITY: Lazy<f64> = Lazy::new(|| isa_density(0.0));
+// Pairs the samples of one channel against time, `x`, with those of another
+// taken at the same times, `y`, into points of the second against the
+// first, with the time kept as the third element, for `plot_phase`
+pub fn phase_plane(x: &[(f64, f64, f64)], y: &[(f64, f64, f64)]) -> Vec<(f64, f64, f64)> {
+    // Allow for the rounding in times taken from different clocks
+    const SLACK: f64 = 1e-9;
 
-#[inline] pub fn atmo_density(altitude: f64) -> f64 {
-    isa_density(altitude) / *SEA_LEVEL_DENSITY
+    let mut points = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < x.len() && j < y.len() {
+        let (tx, ty) = (x[i].0, y[j].0);
+        if tx < ty - SLACK {
+            i += 1;
+        } else if ty < tx - SLACK {
+            j += 1;
+        } else {
+            points.push((x[i].1, y[j].1, tx));
+            i += 1;
+            j += 1;
+        }
+    }
+    points
 }
 
-pub fn isa_dynamic_viscosity(altitude: f64) -> f64 {
-    const T0: f64 = 288.15; // Temperature at sea level, K
-    const L: f64 = 0.0065;  // Temperature lapse rate, K/m
-    const S: f64 = 11.4;    // Sutherland's constant, K
-    const BETA: f64 = 1.458e-6; // kg/s/m/K^0.5
+// Plots a phase plane, one channel against another, each point coloured by
+// the time in the third element, from blue at the start to red at the end,
+// so limit cycles and the trade of height for speed can be followed. It's
+// thinned to about the style's `points`, or left whole if 0.
+#[cfg(feature = "plot")]
+pub fn plot_phase(
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    data: &[(f64, f64, f64)],
+    style: &PlotStyle) -> Result<(), DyntermError> {
 
-    let temp = if altitude <= 11000.0 {
-        T0 - L * altitude
-    } else {
-        216.65
-    };
+    if data.is_empty() { return Ok(()); }
 
-    BETA * temp.powf(1.5) / (temp + S) // Ns/m^2
+    let extent = |axis: fn(&(f64, f64, f64)) -> f64|
+        data.iter().map(axis).fold((f64::INFINITY, f64::NEG_INFINITY), |acc, v| (acc.0.min(v), acc.1.max(v)));
+    let (x_min, x_max) = extent(|p| p.0);
+    let (y_min, y_max) = extent(|p| p.1);
+    let (t_min, t_max) = extent(|p| p.2);
+
+    let path = format!("{}.png", title);
+    let (x_dim, y_dim) = style.canvas_size(1.0);
+    let root = BitMapBackend::new(&path, (x_dim, y_dim)).into_drawing_area();
+    let mut chart = style.builder(&root, title)?
+        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+    let mut mesh = chart.configure_mesh();
+    mesh.x_desc(x_label).y_desc(y_label);
+    style.mesh(&mut mesh);
+    mesh.draw()?;
+
+    let span = if t_max > t_min { t_max - t_min } else { 1.0 };
+    chart.draw_series(decimate(data, style.points).into_iter().map(|(x, y, t)| {
+        let hue = 2.0 / 3.0 * (1.0 - (t - t_min) / span);
+        Circle::new((x, y), style.point_size(), HSLColor(hue, 1.0, 0.45).filled())
+    }))?;
+
+    Ok(())
 }
 
+// One run's samples in a comparison, under its name in the legend
+#[cfg(feature = "plot")]
+pub struct Series<'a> {
+    pub name: &'a str,
+    pub data: &'a [(f64, f64, f64)],
+}
+
+// Overlays several runs' samples of the same channel on shared axes, each
+// run in a colour of its own with its name in the legend, sized as
+// `plot_scatter` sizes a chart of them all, in the style's palette. Each run
+// is thinned to about the style's `points`, or left whole if 0.
+#[cfg(feature = "plot")]
+pub fn plot_comparison(
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    do_aspect: bool,
+    series: &[Series],
+    style: &PlotStyle) -> Result<(), DyntermError> {
+
+    let all = || series.iter().flat_map(|s| s.data.iter());
+    if all().next().is_none() { return Ok(()); }
+
+    let (x_min, x_max) = all().fold((f64::INFINITY, f64::NEG_INFINITY), |acc, &(x, _, _)| (acc.0.min(x), acc.1.max(x)));
+    let (y_min, y_max) = all().fold((f64::INFINITY, f64::NEG_INFINITY), |acc, &(_, y, _)| (acc.0.min(y), acc.1.max(y)));
+    let flat = !(x_max > x_min && y_max > y_min);
+    let aspect_ratio = if do_aspect && !flat { (x_max-x_min) / (y_max-y_min) } else { 3.0 };
+    let (x_dim, y_dim) = style.canvas_size(aspect_ratio);
+
+    let path = format!("{}.png", title);
+    let root = BitMapBackend::new(&path, (x_dim, y_dim)).into_drawing_area();
+    let mut chart = style.builder(&root, title)?
+        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+    let mut mesh = chart.configure_mesh();
+    mesh.x_desc(x_label).y_desc(y_label);
+    style.mesh(&mut mesh);
+    mesh.draw()?;
+
+    let radius = style.point_size();
+    for (i, s) in series.iter().enumerate() {
+        let colour = style.pick(i);
+        chart.draw_series(decimate(s.data, style.points).into_iter().map(|(x, y, _)| Circle::new((x, y), radius, colour.filled())))?
+            .label(s.name)
+            .legend(move |(x, y)| Circle::new((x, y), 2 * radius, colour.filled()));
+    }
+
+    chart.configure_series_labels()
+        .label_font((style.font.as_str(), style.px(style.label_size) as f64))
+        .background_style(rgb(style.background).mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+// One chart for plot_all, with the arguments plot_scatter takes
+#[cfg(feature = "plot")]
+pub struct Chart<'a> {
+    pub title: &'a str,
+    pub x_label: &'a str,
+    pub y_label: &'a str,
+    pub do_aspect: bool,
+    pub data: &'a [(f64, f64, f64)],
+    pub axes: Axes<'a>,
+}
+
+// Renders every chart concurrently in `style`, each thinned to about its
+// `points`, or every point if 0. A few worker threads take charts in turn,
+// each keeping its fonts loaded from one chart to the next. Every chart goes
+// to its own file, so the output doesn't depend on which finishes first, and
+// the results come back in the order of `charts`. A chart that panics in
+// plotters is reported as failing, and the rest are still drawn.
+#[cfg(feature = "plot")]
+pub fn plot_all(charts: &[Chart], style: &PlotStyle) -> Vec<Result<(), DyntermError>> {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(charts.len());
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, Result<(), DyntermError>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| {
+            let mut done = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(c) = charts.get(i) else { break done };
+                let data = decimate(c.data, style.points);
+                let secondary = c.axes.secondary.as_ref().map(|s| (s, decimate(s.data, style.points)));
+                let axes = Axes {
+                    log_y: c.axes.log_y,
+                    secondary: secondary.as_ref().map(|(s, data)| Secondary { data, ..**s }),
+                };
+                let result = catch_unwind(AssertUnwindSafe(|| {
+                    plot_scatter(c.title, c.x_label, c.y_label, c.do_aspect, &data, &axes, style)
+                }));
+                done.push((i, result.unwrap_or_else(|_| Err(DyntermError::Plot(format!("{}: plotting panicked", c.title))))));
+            }
+        })).collect();
+
+        handles.into_iter()
+            // Each chart's panic is caught, so a worker can't panic itself
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
 
-// Reads a string literal as if it were a csv
-pub fn parse_string_as_csv(s: &str) -> Vec<(f64, f64)> {
-    s.lines().map(|line| {
-            let values: Vec<&str> = line.split(',').collect();
-            let x = f64::from_str(values[0].trim()).unwrap();
-            let y = f64::from_str(values[1].trim()).unwrap();
-            (x, y)
+// Reads a string literal as if it were a csv of x and y, failing on the
+// first line without two numbers
+pub fn parse_string_as_csv(s: &str) -> Result<Vec<(f64, f64)>, DyntermError> {
+    s.lines().enumerate().map(|(i, line)| {
+            let mut values = line.split(',');
+            let mut number = |column: &str| {
+                let value = values.next()
+                    .ok_or_else(|| DyntermError::Parse(format!("line {}: no {}", i + 1, column)))?;
+                f64::from_str(value.trim())
+                    .map_err(|e| DyntermError::Parse(format!("line {}: {} '{}': {}", i + 1, column, value.trim(), e)))
+            };
+            let x = number("x")?;
+            let y = number("y")?;
+            Ok((x, y))
         })
-        .collect::<Vec<(f64, f64)>>()
+        .collect()
 }
\ No newline at end of file
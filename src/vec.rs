@@ -1,251 +1,1062 @@
-use std::f64::consts::PI;
-use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, Div};
+use crate::rk4::State;
+use crate::scalar::{Dual, Scalar};
+
+use serde::{Deserialize, Serialize};
+use core::f64::consts::PI;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg};
+
+// Every type here is generic over the scalar it's built from, defaulting to
+// f64. See `scalar` for the alternatives.
 
 // A 2d vector object
-#[derive(Debug, Copy, Clone)]
-pub struct Vector {
-    x: f64,
-    y: f64,
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Vector<S = f64> {
+    x: S,
+    y: S,
 }
 
-impl Vector {
+impl<S: Scalar> Vector<S> {
 
     // Constructors
-    pub const fn new(x: f64, y: f64) -> Self {
+    pub const fn new(x: S, y: S) -> Self {
         Vector { x, y }
     }
-    pub fn from_radians(m: f64, r: f64) -> Self {
-        Vector { 
-            x: m * r.cos(), 
-            y: m * r.sin() 
+    pub const fn zero() -> Self {
+        Vector { x: S::ZERO, y: S::ZERO }
+    }
+    pub fn from_radians(m: S, r: S) -> Self {
+        Vector {
+            x: m * r.cos(),
+            y: m * r.sin()
         }
     }
-    pub fn from_degrees(m: f64, r: f64) -> Self {
-        Vector { 
-            x: m * r.to_radians().cos(), 
-            y: m * r.to_radians().sin() 
+    pub fn from_degrees(m: S, r: S) -> Self {
+        Vector {
+            x: m * r.to_radians().cos(),
+            y: m * r.to_radians().sin()
         }
     }
 
-    // Helper function 
-    pub fn unit(&self) -> Vector {
-        Vector::from_radians(1.0, self.orientation().rad())
+    // Helper function
+    pub fn unit(&self) -> Vector<S> {
+        Vector::from_radians(S::ONE, self.orientation().rad())
+    }
+
+    // Frame changes, between world axes and the axes of a body pitched at
+    // `attitude`, with x along the body and y normal to it
+    #[inline] pub fn to_body(self, attitude: Angle<S>) -> Vector<S> {
+        attitude.rotation().inverse() * self
+    }
+    #[inline] pub fn to_world(self, attitude: Angle<S>) -> Vector<S> {
+        attitude.rotation() * self
+    }
+
+    // Splits into the components along `direction` and normal to it, the
+    // normal being a quarter turn anticlockwise. A zero `direction` is
+    // taken to point along x.
+    pub fn tangent_normal(self, direction: Vector<S>) -> (S, S) {
+        let t = direction.unit();
+        (self.dot(t), self.dot(Rotation2::quarter() * t))
     }
 
     // Math
-    #[inline] pub fn dot(self, other: Vector) -> f64 {
+    #[inline] pub fn dot(self, other: Vector<S>) -> S {
         self.x * other.x + self.y * other.y
     }
 
-    #[inline] pub fn cross(self, other: Vector) -> f64 {
+    #[inline] pub fn cross(self, other: Vector<S>) -> S {
         self.x * other.y - self.y * other.x
     }
 
+    // Whether the two are within `tolerance` of each other
+    pub fn approx_eq(&self, other: Vector<S>, tolerance: S) -> bool {
+        (*self - other).magnitude() <= tolerance
+    }
+
+    // For display with a unit, e.g. `format!("{:.1}", v.with_unit("m/s"))`
+    pub fn with_unit<'a>(&'a self, unit: &'a str) -> WithUnit<'a, Vector<S>> {
+        WithUnit { value: self, unit }
+    }
+
     // Getters
-    #[inline] pub fn x(&self) -> f64 {
+    #[inline] pub fn x(&self) -> S {
         self.x
     }
-    #[inline] pub fn y(&self) -> f64 {
+    #[inline] pub fn y(&self) -> S {
         self.y
     }
-    #[inline] pub fn magnitude(&self) -> f64 {
+    #[inline] pub fn magnitude(&self) -> S {
         (self.x.powi(2) + self.y.powi(2)).sqrt()
     }
-    #[inline] pub fn orientation(&self) -> Angle {
+    #[inline] pub fn orientation(&self) -> Angle<S> {
         Angle::from_radians(self.y.atan2(self.x))
     }
-    
+
 }
 
 // Implement Add trait for Vector
-impl Add<Vector> for Vector {
-    type Output = Vector;
+impl<S: Scalar> Add<Vector<S>> for Vector<S> {
+    type Output = Vector<S>;
 
-    fn add(self, other: Vector) -> Vector {
+    fn add(self, other: Vector<S>) -> Vector<S> {
         Vector {
             x: self.x + other.x,
             y: self.y + other.y,
         }
     }
 }
-impl AddAssign<Vector> for Vector {
-    fn add_assign(self: &mut Vector, other: Vector) {
+impl<S: Scalar> AddAssign<Vector<S>> for Vector<S> {
+    fn add_assign(self: &mut Vector<S>, other: Vector<S>) {
         self.x += other.x;
         self.y += other.y;
     }
 }
 
 // Implement Sub trait for Vector
-impl Sub<Vector> for Vector {
-    type Output = Vector;
+impl<S: Scalar> Sub<Vector<S>> for Vector<S> {
+    type Output = Vector<S>;
 
-    fn sub(self, other: Vector) -> Vector {
+    fn sub(self, other: Vector<S>) -> Vector<S> {
         Vector {
             x: self.x - other.x,
             y: self.y - other.y,
         }
     }
 }
-impl SubAssign<Vector> for Vector {
-    fn sub_assign(self: &mut Vector, other: Vector) {
+impl<S: Scalar> SubAssign<Vector<S>> for Vector<S> {
+    fn sub_assign(self: &mut Vector<S>, other: Vector<S>) {
         self.x -= other.x;
         self.y -= other.y;
     }
 }
 
-// Implement Mul trait for Vector and f64 (scalar multiplication)
-impl Mul<Vector> for f64 {
-    type Output = Vector;
+// Implement Mul trait for Vector and a scalar (scalar multiplication)
+impl<S: Scalar> Mul<S> for Vector<S> {
+    type Output = Vector<S>;
 
-    fn mul(self, vector: Vector) -> Vector {
+    fn mul(self, scalar: S) -> Vector<S> {
         Vector {
-            x: vector.x * self,
-            y: vector.y * self,
+            x: self.x * scalar,
+            y: self.y * scalar,
         }
     }
 }
 
-// Implement Div trait for Vector and f64 (scalar division)
-impl Div<f64> for Vector {
-    type Output = Vector;
+// Implement Div trait for Vector and a scalar (scalar division)
+impl<S: Scalar> Div<S> for Vector<S> {
+    type Output = Vector<S>;
+
+    fn div(self, scalar: S) -> Vector<S> {
+        Vector {
+            x: self.x / scalar,
+            y: self.y / scalar,
+        }
+    }
+}
+
+// Implement the remaining arithmetic traits for Vector
+impl<S: Scalar> Neg for Vector<S> {
+    type Output = Vector<S>;
+
+    fn neg(self) -> Vector<S> {
+        Vector {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+impl<S: Scalar> MulAssign<S> for Vector<S> {
+    fn mul_assign(&mut self, scalar: S) {
+        self.x *= scalar;
+        self.y *= scalar;
+    }
+}
+impl<S: Scalar> DivAssign<S> for Vector<S> {
+    fn div_assign(&mut self, scalar: S) {
+        self.x /= scalar;
+        self.y /= scalar;
+    }
+}
+impl<S: Scalar> Sum for Vector<S> {
+    fn sum<I: Iterator<Item = Vector<S>>>(iter: I) -> Vector<S> {
+        iter.fold(Vector::zero(), |a, b| a + b)
+    }
+}
+
+// A rotation in the plane, positive anticlockwise. Kept as a cosine and sine
+// so composing and applying rotations needs no trigonometry.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Rotation2<S = f64> {
+    cos: S,
+    sin: S,
+}
+
+impl<S: Scalar> Rotation2<S> {
+
+    // Constructors
+    pub fn from_radians(radians: S) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Rotation2 { cos, sin }
+    }
+    pub const fn identity() -> Self {
+        Rotation2 { cos: S::ONE, sin: S::ZERO }
+    }
+    pub const fn quarter() -> Self {
+        Rotation2 { cos: S::ZERO, sin: S::ONE }
+    }
+    pub fn half() -> Self {
+        Rotation2 { cos: -S::ONE, sin: S::ZERO }
+    }
 
-    fn div(self, scalar: f64) -> Vector {
+    // The rotation undoing this one
+    pub fn inverse(&self) -> Rotation2<S> {
+        Rotation2 { cos: self.cos, sin: -self.sin }
+    }
+
+    // Rotates a vector
+    #[inline] pub fn rotate(&self, v: Vector<S>) -> Vector<S> {
         Vector {
+            x: self.cos * v.x - self.sin * v.y,
+            y: self.sin * v.x + self.cos * v.y,
+        }
+    }
+
+    // Getters
+    #[inline] pub fn angle(&self) -> Angle<S> {
+        Angle::from_radians(self.sin.atan2(self.cos))
+    }
+}
+
+// Composition, applying the right-hand rotation first
+impl<S: Scalar> Mul<Rotation2<S>> for Rotation2<S> {
+    type Output = Rotation2<S>;
+
+    fn mul(self, other: Rotation2<S>) -> Rotation2<S> {
+        Rotation2 {
+            cos: self.cos * other.cos - self.sin * other.sin,
+            sin: self.sin * other.cos + self.cos * other.sin,
+        }
+    }
+}
+
+// Applying a rotation to a vector
+impl<S: Scalar> Mul<Vector<S>> for Rotation2<S> {
+    type Output = Vector<S>;
+
+    fn mul(self, v: Vector<S>) -> Vector<S> {
+        self.rotate(v)
+    }
+}
+
+impl<S: Scalar> From<Angle<S>> for Rotation2<S> {
+    fn from(angle: Angle<S>) -> Rotation2<S> {
+        Rotation2::from_radians(angle.rad())
+    }
+}
+impl<S: Scalar> From<Rotation2<S>> for Angle<S> {
+    fn from(rotation: Rotation2<S>) -> Angle<S> {
+        rotation.angle()
+    }
+}
+
+// A 3d vector object. Axes follow the planar model, with x downrange and y
+// up, and z completes the right-handed set, pointing to the right of downrange.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Vector3<S = f64> {
+    x: S,
+    y: S,
+    z: S,
+}
+
+impl<S: Scalar> Vector3<S> {
+
+    // Constructors
+    pub const fn new(x: S, y: S, z: S) -> Self {
+        Vector3 { x, y, z }
+    }
+    pub const fn zero() -> Self {
+        Vector3 { x: S::ZERO, y: S::ZERO, z: S::ZERO }
+    }
+
+    // Lifts a planar vector into the x-y plane
+    pub const fn from_planar(v: Vector<S>) -> Self {
+        Vector3 { x: v.x, y: v.y, z: S::ZERO }
+    }
+
+    // Helper function
+    pub fn unit(&self) -> Vector3<S> {
+        *self / self.magnitude()
+    }
+
+    // Math
+    #[inline] pub fn dot(self, other: Vector3<S>) -> S {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[inline] pub fn cross(self, other: Vector3<S>) -> Vector3<S> {
+        Vector3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    // Whether the two are within `tolerance` of each other
+    pub fn approx_eq(&self, other: Vector3<S>, tolerance: S) -> bool {
+        (*self - other).magnitude() <= tolerance
+    }
+
+    // For display with a unit
+    pub fn with_unit<'a>(&'a self, unit: &'a str) -> WithUnit<'a, Vector3<S>> {
+        WithUnit { value: self, unit }
+    }
+
+    // Getters
+    #[inline] pub fn x(&self) -> S {
+        self.x
+    }
+    #[inline] pub fn y(&self) -> S {
+        self.y
+    }
+    #[inline] pub fn z(&self) -> S {
+        self.z
+    }
+    #[inline] pub fn magnitude(&self) -> S {
+        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    }
+}
+
+// Implement Add trait for Vector3
+impl<S: Scalar> Add<Vector3<S>> for Vector3<S> {
+    type Output = Vector3<S>;
+
+    fn add(self, other: Vector3<S>) -> Vector3<S> {
+        Vector3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+impl<S: Scalar> AddAssign<Vector3<S>> for Vector3<S> {
+    fn add_assign(self: &mut Vector3<S>, other: Vector3<S>) {
+        self.x += other.x;
+        self.y += other.y;
+        self.z += other.z;
+    }
+}
+
+// Implement Sub trait for Vector3
+impl<S: Scalar> Sub<Vector3<S>> for Vector3<S> {
+    type Output = Vector3<S>;
+
+    fn sub(self, other: Vector3<S>) -> Vector3<S> {
+        Vector3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+impl<S: Scalar> SubAssign<Vector3<S>> for Vector3<S> {
+    fn sub_assign(self: &mut Vector3<S>, other: Vector3<S>) {
+        self.x -= other.x;
+        self.y -= other.y;
+        self.z -= other.z;
+    }
+}
+
+// Implement Mul trait for Vector3 and a scalar (scalar multiplication)
+impl<S: Scalar> Mul<S> for Vector3<S> {
+    type Output = Vector3<S>;
+
+    fn mul(self, scalar: S) -> Vector3<S> {
+        Vector3 {
+            x: self.x * scalar,
+            y: self.y * scalar,
+            z: self.z * scalar,
+        }
+    }
+}
+
+// Implement Div trait for Vector3 and a scalar (scalar division)
+impl<S: Scalar> Div<S> for Vector3<S> {
+    type Output = Vector3<S>;
+
+    fn div(self, scalar: S) -> Vector3<S> {
+        Vector3 {
+            x: self.x / scalar,
+            y: self.y / scalar,
+            z: self.z / scalar,
+        }
+    }
+}
+
+// Implement the remaining arithmetic traits for Vector3
+impl<S: Scalar> Neg for Vector3<S> {
+    type Output = Vector3<S>;
+
+    fn neg(self) -> Vector3<S> {
+        Vector3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+impl<S: Scalar> MulAssign<S> for Vector3<S> {
+    fn mul_assign(&mut self, scalar: S) {
+        self.x *= scalar;
+        self.y *= scalar;
+        self.z *= scalar;
+    }
+}
+impl<S: Scalar> DivAssign<S> for Vector3<S> {
+    fn div_assign(&mut self, scalar: S) {
+        self.x /= scalar;
+        self.y /= scalar;
+        self.z /= scalar;
+    }
+}
+impl<S: Scalar> Sum for Vector3<S> {
+    fn sum<I: Iterator<Item = Vector3<S>>>(iter: I) -> Vector3<S> {
+        iter.fold(Vector3::zero(), |a, b| a + b)
+    }
+}
+
+// A quaternion, used as a rotation when normalised. Rotating by `q * r`
+// applies r first, then q.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Quaternion<S = f64> {
+    w: S,
+    x: S,
+    y: S,
+    z: S,
+}
+
+impl<S: Scalar> Quaternion<S> {
+
+    // Constructors
+    pub const fn new(w: S, x: S, y: S, z: S) -> Self {
+        Quaternion { w, x, y, z }
+    }
+    pub const fn identity() -> Self {
+        Quaternion { w: S::ONE, x: S::ZERO, y: S::ZERO, z: S::ZERO }
+    }
+    pub fn from_axis_angle(axis: Vector3<S>, radians: S) -> Self {
+        let axis = axis.unit();
+        let (s, c) = (radians / S::from_f64(2.0)).sin_cos();
+        Quaternion { w: c, x: s * axis.x, y: s * axis.y, z: s * axis.z }
+    }
+
+    // A rotation about +z, the axis the planar model rotates about
+    pub fn from_planar(rotation: Rotation2<S>) -> Self {
+        Quaternion::from_axis_angle(
+            Vector3::new(S::ZERO, S::ZERO, S::ONE), rotation.angle().rad())
+    }
+
+    // A pure quaternion holding a vector
+    pub const fn from_vector(v: Vector3<S>) -> Self {
+        Quaternion { w: S::ZERO, x: v.x, y: v.y, z: v.z }
+    }
+
+    // Helper functions
+    pub fn conjugate(&self) -> Quaternion<S> {
+        Quaternion { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+    pub fn unit(&self) -> Quaternion<S> {
+        *self / self.magnitude()
+    }
+
+    // Rotates a vector, assuming this is a unit quaternion
+    pub fn rotate(&self, v: Vector3<S>) -> Vector3<S> {
+        (*self * Quaternion::from_vector(v) * self.conjugate()).vector()
+    }
+
+    // Getters
+    #[inline] pub fn w(&self) -> S {
+        self.w
+    }
+    #[inline] pub fn vector(&self) -> Vector3<S> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    #[inline] pub fn magnitude(&self) -> S {
+        (self.w.powi(2) + self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    }
+}
+
+impl<S: Scalar> From<Angle<S>> for Quaternion<S> {
+    fn from(angle: Angle<S>) -> Quaternion<S> {
+        Quaternion::from_planar(angle.into())
+    }
+}
+
+// Implement Mul trait for Quaternion (the Hamilton product)
+impl<S: Scalar> Mul<Quaternion<S>> for Quaternion<S> {
+    type Output = Quaternion<S>;
+
+    fn mul(self, other: Quaternion<S>) -> Quaternion<S> {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+// Implement Add trait for Quaternion
+impl<S: Scalar> Add<Quaternion<S>> for Quaternion<S> {
+    type Output = Quaternion<S>;
+
+    fn add(self, other: Quaternion<S>) -> Quaternion<S> {
+        Quaternion {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+// Implement Mul trait for Quaternion and a scalar (scalar multiplication)
+impl<S: Scalar> Mul<S> for Quaternion<S> {
+    type Output = Quaternion<S>;
+
+    fn mul(self, scalar: S) -> Quaternion<S> {
+        Quaternion {
+            w: self.w * scalar,
+            x: self.x * scalar,
+            y: self.y * scalar,
+            z: self.z * scalar,
+        }
+    }
+}
+
+// Implement Div trait for Quaternion and a scalar (scalar division)
+impl<S: Scalar> Div<S> for Quaternion<S> {
+    type Output = Quaternion<S>;
+
+    fn div(self, scalar: S) -> Quaternion<S> {
+        Quaternion {
+            w: self.w / scalar,
             x: self.x / scalar,
             y: self.y / scalar,
+            z: self.z / scalar,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Angle {
-    radians: f64,
+// Angles are kept in [0, 2pi), so deserialising goes through the same
+// wrapping as the constructors
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(from = "RawAngle<S>", bound(deserialize = "S: Scalar"))]
+pub struct Angle<S = f64> {
+    radians: S,
 }
 
-impl Angle {
+#[derive(Deserialize)]
+struct RawAngle<S> {
+    radians: S,
+}
+
+impl<S: Scalar> From<RawAngle<S>> for Angle<S> {
+    fn from(raw: RawAngle<S>) -> Angle<S> {
+        Angle::from_radians(raw.radians)
+    }
+}
+
+impl<S: Scalar> Angle<S> {
 
     // Constructors
-    pub fn from_degrees(degrees: f64) -> Self {
+    pub fn from_degrees(degrees: S) -> Self {
         Self { radians: Angle::clamp(degrees.to_radians()) }
     }
-    pub fn from_radians(radians: f64) -> Self {
+    pub fn from_radians(radians: S) -> Self {
         Self { radians: Angle::clamp(radians) }
     }
-    pub fn from_vector(vector: &Vector) -> Self {
+    pub fn from_vector(vector: &Vector<S>) -> Self {
         vector.orientation()
     }
     pub fn pi() -> Self {
-        Self { radians: PI }
+        Self { radians: S::from_f64(PI) }
+    }
+    pub fn unit(&self) -> Vector<S> {
+        Vector::from_radians(S::ONE, self.radians)
+    }
+    pub fn rotation(&self) -> Rotation2<S> {
+        Rotation2::from(*self)
     }
-    pub fn unit(&self) -> Vector {
-        Vector::from_radians(1.0, self.radians)
+
+    // The shortest rotation from `other` to this angle, in radians in
+    // (-pi, pi], so differences across the 0/2pi wrap stay small
+    pub fn signed_diff(&self, other: Angle<S>) -> S {
+        let diff = Angle::clamp(self.radians - other.radians);
+        if diff > S::from_f64(PI) { diff - S::from_f64(2.0 * PI) } else { diff }
+    }
+
+    // Interpolates towards `other` the short way round, with t from 0 to 1
+    pub fn lerp_shortest(&self, other: Angle<S>, t: S) -> Angle<S> {
+        Angle::from_radians(self.radians + t * other.signed_diff(*self))
+    }
+
+    // Whether the two are within `tolerance` radians, either way round
+    pub fn approx_eq(&self, other: Angle<S>, tolerance: S) -> bool {
+        self.signed_diff(other).abs() <= tolerance
+    }
+
+    // For display with a unit. Degrees are shown by default, radians with
+    // the alternate flag.
+    pub fn with_unit<'a>(&'a self, unit: &'a str) -> WithUnit<'a, Angle<S>> {
+        WithUnit { value: self, unit }
+    }
+
+    // Whether this lies on the anticlockwise arc from `start` to `end`
+    pub fn is_between(&self, start: Angle<S>, end: Angle<S>) -> bool {
+        Angle::clamp(self.radians - start.radians) <= Angle::clamp(end.radians - start.radians)
     }
 
     // Helper function
-    fn clamp(mut radians: f64) -> f64 { 
-        radians %= 2.0 * PI;
-        if radians < 0.0 { radians += 2.0 * PI; }
+    fn clamp(radians: S) -> S {
+        let two_pi = S::from_f64(2.0 * PI);
+        let mut radians = radians % two_pi;
+        if radians < S::ZERO { radians += two_pi; }
         radians
     }
 
     // Getters
-    #[inline] pub fn rad(&self) -> f64 {
+    #[inline] pub fn rad(&self) -> S {
         self.radians
     }
-    #[inline] pub fn deg(&self) -> f64 {
+    #[inline] pub fn deg(&self) -> S {
         let mut deg = self.radians.to_degrees();
-        if deg == 360.0 { deg = 0.0; }
+        if deg == S::from_f64(360.0) { deg = S::ZERO; }
         deg
     }
-    #[inline] pub fn nice_deg(&self) -> f64 {
+    #[inline] pub fn nice_deg(&self) -> S {
         let mut deg = self.radians.to_degrees();
-        if deg > 180.0 { deg -= 360.0; }
+        if deg > S::from_f64(180.0) { deg -= S::from_f64(360.0); }
         deg
     }
+    #[inline] pub fn signed_rad(&self) -> S {
+        if self.radians > S::from_f64(PI) { self.radians - S::from_f64(2.0 * PI) } else { self.radians }
+    }
 }
 
 // Implement Add trait for Angle
-impl Add for Angle {
-    type Output = Angle;
+impl<S: Scalar> Add for Angle<S> {
+    type Output = Angle<S>;
 
-    fn add(self, other: Angle) -> Angle {
+    fn add(self, other: Angle<S>) -> Angle<S> {
         Angle::from_radians(self.radians + other.radians)
     }
 }
-impl AddAssign for Angle {
-    fn add_assign(&mut self, other: Angle) {
+impl<S: Scalar> AddAssign for Angle<S> {
+    fn add_assign(&mut self, other: Angle<S>) {
         self.radians = Angle::clamp(self.radians + other.radians);
     }
 }
 
 // Implement Sub trait for Angle
-impl Sub for Angle {
-    type Output = Angle;
+impl<S: Scalar> Sub for Angle<S> {
+    type Output = Angle<S>;
 
-    fn sub(self, other: Angle) -> Angle {
+    fn sub(self, other: Angle<S>) -> Angle<S> {
         Angle::from_radians(self.radians - other.radians)
     }
 }
-impl SubAssign for Angle {
-    fn sub_assign(&mut self, other: Angle) {
+impl<S: Scalar> SubAssign for Angle<S> {
+    fn sub_assign(&mut self, other: Angle<S>) {
         self.radians = Angle::clamp(self.radians - other.radians);
     }
 }
 
+// Implement the remaining arithmetic traits for Angle. Scaling works on the
+// signed angle, so half of -10 degrees is -5 rather than 175.
+impl<S: Scalar> Neg for Angle<S> {
+    type Output = Angle<S>;
+
+    fn neg(self) -> Angle<S> {
+        Angle::from_radians(-self.radians)
+    }
+}
+impl<S: Scalar> Mul<S> for Angle<S> {
+    type Output = Angle<S>;
+
+    fn mul(self, scalar: S) -> Angle<S> {
+        Angle::from_radians(self.signed_rad() * scalar)
+    }
+}
+impl<S: Scalar> Div<S> for Angle<S> {
+    type Output = Angle<S>;
+
+    fn div(self, scalar: S) -> Angle<S> {
+        Angle::from_radians(self.signed_rad() / scalar)
+    }
+}
+impl<S: Scalar> MulAssign<S> for Angle<S> {
+    fn mul_assign(&mut self, scalar: S) {
+        *self = *self * scalar;
+    }
+}
+impl<S: Scalar> DivAssign<S> for Angle<S> {
+    fn div_assign(&mut self, scalar: S) {
+        *self = *self / scalar;
+    }
+}
+impl<S: Scalar> Sum for Angle<S> {
+    fn sum<I: Iterator<Item = Angle<S>>>(iter: I) -> Angle<S> {
+        iter.fold(Angle::from_radians(S::ZERO), |a, b| a + b)
+    }
+}
+
 // Make a container that holds them both
-#[derive(Debug, Copy, Clone)]
-pub struct Kinematics {
-    pub vec: Vector,
-    pub ang: f64,
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Kinematics<S = f64> {
+    pub vec: Vector<S>,
+    pub ang: S,
 }
-impl Kinematics {
-    pub fn new_raw(vec: Vector, ang: f64) -> Self { Kinematics { vec, ang } }
-    pub fn new(vec: Vector, ang: Angle) -> Self { Kinematics { vec, ang: ang.rad() } }
+// The same container serves for a pose (position and attitude), motion
+// (velocity and angular velocity), and the derivative of motion (acceleration
+// and angular acceleration). Attitudes are `Angle`s; rates are plain scalars
+// in radians per second, or per second squared, since they mustn't wrap.
+impl<S: Scalar> Kinematics<S> {
+    pub fn new(vec: Vector<S>, ang: Angle<S>) -> Self { Kinematics { vec, ang: ang.rad() } }
+    pub fn with_rate(vec: Vector<S>, rate: S) -> Self { Kinematics { vec, ang: rate } }
+    #[deprecated(note = "use `Kinematics::with_rate`")]
+    pub fn new_raw(vec: Vector<S>, ang: S) -> Self { Kinematics::with_rate(vec, ang) }
+    pub const fn zero() -> Self { Kinematics { vec: Vector::zero(), ang: S::ZERO } }
+
+    // Motion along a flight path angle, without rotating
+    pub fn from_speed_and_gamma(speed: S, gamma: Angle<S>) -> Self {
+        Kinematics::with_rate(Vector::from_radians(speed, gamma.rad()), S::ZERO)
+    }
 
     // Getters & helper functions
-    pub fn x(&self) -> f64 { self.vec.x() }
-    pub fn y(&self) -> f64 { self.vec.y() }
-    pub fn magnitude(&self) -> f64 { self.vec.magnitude() }
-    pub fn direction(&self) -> Angle { self.vec.orientation() }
-    pub fn angle(&self) -> Angle { Angle::from_radians(self.ang) }
+    pub fn x(&self) -> S { self.vec.x() }
+    pub fn y(&self) -> S { self.vec.y() }
+    pub fn horizontal(&self) -> S { self.vec.x() }
+    pub fn vertical(&self) -> S { self.vec.y() }
+    pub fn magnitude(&self) -> S { self.vec.magnitude() }
+    pub fn direction(&self) -> Angle<S> { self.vec.orientation() }
+    pub fn angle(&self) -> Angle<S> { Angle::from_radians(self.ang) }
+
+    // The angular part of motion, in radians or degrees per second
+    pub fn angular_velocity(&self) -> S { self.ang }
+    pub fn angular_velocity_deg(&self) -> S { self.ang.to_degrees() }
+
+    // The angular part of the derivative of motion, in radians or degrees
+    // per second squared
+    pub fn angular_acceleration(&self) -> S { self.ang }
+    pub fn angular_acceleration_deg(&self) -> S { self.ang.to_degrees() }
+
+    // Frame changes & decomposition of the linear part, as for `Vector`
+    pub fn to_body(&self, attitude: Angle<S>) -> Vector<S> { self.vec.to_body(attitude) }
+    pub fn to_world(&self, attitude: Angle<S>) -> Vector<S> { self.vec.to_world(attitude) }
+    pub fn tangent_normal(&self, direction: Vector<S>) -> (S, S) {
+        self.vec.tangent_normal(direction)
+    }
+
+    // Whether both parts are within `tolerance` of each other
+    pub fn approx_eq(&self, other: Kinematics<S>, tolerance: S) -> bool {
+        self.vec.approx_eq(other.vec, tolerance) && (self.ang - other.ang).abs() <= tolerance
+    }
+
+    // For display with units for the linear and angular parts
+    pub fn with_unit<'a>(&'a self, unit: &'a str) -> WithUnit<'a, Kinematics<S>> {
+        WithUnit { value: self, unit }
+    }
 }
 
 // Implement arithmetic traits
-impl Add for Kinematics {
-    type Output = Kinematics; 
-    fn add(self, other: Kinematics) -> Kinematics { 
+impl<S: Scalar> Add for Kinematics<S> {
+    type Output = Kinematics<S>;
+    fn add(self, other: Kinematics<S>) -> Kinematics<S> {
         Kinematics {
-            vec: self.vec + other.vec, 
+            vec: self.vec + other.vec,
             ang: self.ang + other.ang
         }
     }
 }
-impl Sub for Kinematics {
-    type Output = Kinematics; 
-    fn sub(self, other: Kinematics) -> Kinematics { 
+impl<S: Scalar> Sub for Kinematics<S> {
+    type Output = Kinematics<S>;
+    fn sub(self, other: Kinematics<S>) -> Kinematics<S> {
         Kinematics {
-            vec: self.vec - other.vec, 
+            vec: self.vec - other.vec,
             ang: self.ang - other.ang
         }
     }
 }
-impl Mul<Kinematics> for f64 {
-    type Output = Kinematics;
+impl<S: Scalar> Mul<S> for Kinematics<S> {
+    type Output = Kinematics<S>;
 
-    fn mul(self, kinematics: Kinematics) -> Kinematics {
+    fn mul(self, scalar: S) -> Kinematics<S> {
         Kinematics {
-            vec: self * kinematics.vec, 
-            ang: kinematics.ang * self
+            vec: self.vec * scalar,
+            ang: self.ang * scalar
         }
     }
 }
-impl Div<f64> for Kinematics {
-    type Output = Kinematics;
+impl<S: Scalar> Div<S> for Kinematics<S> {
+    type Output = Kinematics<S>;
 
-    fn div(self, scalar: f64) -> Kinematics {
+    fn div(self, scalar: S) -> Kinematics<S> {
         Kinematics {
             vec: self.vec / scalar,
             ang: self.ang / scalar,
         }
     }
-}
\ No newline at end of file
+}
+impl<S: Scalar> Neg for Kinematics<S> {
+    type Output = Kinematics<S>;
+
+    fn neg(self) -> Kinematics<S> {
+        Kinematics {
+            vec: -self.vec,
+            ang: -self.ang,
+        }
+    }
+}
+impl<S: Scalar> AddAssign for Kinematics<S> {
+    fn add_assign(&mut self, other: Kinematics<S>) {
+        self.vec += other.vec;
+        self.ang += other.ang;
+    }
+}
+impl<S: Scalar> SubAssign for Kinematics<S> {
+    fn sub_assign(&mut self, other: Kinematics<S>) {
+        self.vec -= other.vec;
+        self.ang -= other.ang;
+    }
+}
+impl<S: Scalar> MulAssign<S> for Kinematics<S> {
+    fn mul_assign(&mut self, scalar: S) {
+        self.vec *= scalar;
+        self.ang *= scalar;
+    }
+}
+impl<S: Scalar> DivAssign<S> for Kinematics<S> {
+    fn div_assign(&mut self, scalar: S) {
+        self.vec /= scalar;
+        self.ang /= scalar;
+    }
+}
+impl<S: Scalar> Sum for Kinematics<S> {
+    fn sum<I: Iterator<Item = Kinematics<S>>>(iter: I) -> Kinematics<S> {
+        iter.fold(Kinematics::zero(), |a, b| a + b)
+    }
+}
+
+// Implement State, so each can be stepped in place by the integrators
+impl<S: Scalar> State<S> for Vector<S> {
+    fn axpy(&mut self, a: S, x: &Vector<S>) {
+        self.x += x.x * a;
+        self.y += x.y * a;
+    }
+    fn scale(&mut self, a: S) {
+        *self *= a;
+    }
+    fn divide(&mut self, d: S) {
+        *self /= d;
+    }
+    fn error_norm(&self) -> S {
+        self.x.abs().max(self.y.abs())
+    }
+    fn dimension(&self) -> usize {
+        2
+    }
+    fn component(&self, i: usize) -> S {
+        match i {
+            0 => self.x,
+            _ => self.y,
+        }
+    }
+    fn set_component(&mut self, i: usize, value: S) {
+        match i {
+            0 => self.x = value,
+            _ => self.y = value,
+        }
+    }
+}
+impl<S: Scalar> State<S> for Vector3<S> {
+    fn axpy(&mut self, a: S, x: &Vector3<S>) {
+        self.x += x.x * a;
+        self.y += x.y * a;
+        self.z += x.z * a;
+    }
+    fn scale(&mut self, a: S) {
+        *self *= a;
+    }
+    fn divide(&mut self, d: S) {
+        *self /= d;
+    }
+    fn error_norm(&self) -> S {
+        self.x.abs().max(self.y.abs()).max(self.z.abs())
+    }
+    fn dimension(&self) -> usize {
+        3
+    }
+    fn component(&self, i: usize) -> S {
+        match i {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+    fn set_component(&mut self, i: usize, value: S) {
+        match i {
+            0 => self.x = value,
+            1 => self.y = value,
+            _ => self.z = value,
+        }
+    }
+}
+impl<S: Scalar> State<S> for Quaternion<S> {
+    fn axpy(&mut self, a: S, q: &Quaternion<S>) {
+        self.w += q.w * a;
+        self.x += q.x * a;
+        self.y += q.y * a;
+        self.z += q.z * a;
+    }
+    fn scale(&mut self, a: S) {
+        *self = *self * a;
+    }
+    fn divide(&mut self, d: S) {
+        *self = *self / d;
+    }
+    fn error_norm(&self) -> S {
+        self.w.abs().max(self.x.abs()).max(self.y.abs()).max(self.z.abs())
+    }
+    fn dimension(&self) -> usize {
+        4
+    }
+    fn component(&self, i: usize) -> S {
+        match i {
+            0 => self.w,
+            1 => self.x,
+            2 => self.y,
+            _ => self.z,
+        }
+    }
+    fn set_component(&mut self, i: usize, value: S) {
+        match i {
+            0 => self.w = value,
+            1 => self.x = value,
+            2 => self.y = value,
+            _ => self.z = value,
+        }
+    }
+}
+impl<S: Scalar> State<S> for Kinematics<S> {
+    fn axpy(&mut self, a: S, k: &Kinematics<S>) {
+        self.vec.axpy(a, &k.vec);
+        self.ang += k.ang * a;
+    }
+    fn scale(&mut self, a: S) {
+        *self *= a;
+    }
+    fn divide(&mut self, d: S) {
+        *self /= d;
+    }
+    fn error_norm(&self) -> S {
+        self.vec.error_norm().max(self.ang.abs())
+    }
+    fn dimension(&self) -> usize {
+        3
+    }
+    fn component(&self, i: usize) -> S {
+        if i < 2 { self.vec.component(i) } else { self.ang }
+    }
+    fn set_component(&mut self, i: usize, value: S) {
+        if i < 2 { self.vec.set_component(i, value) } else { self.ang = value }
+    }
+}
+
+// Scalar multiplication with the scalar on the left. The orphan rules mean
+// this has to be spelled out for each scalar type.
+macro_rules! left_scalar_mul {
+    ($($s:ty),*) => {$(
+        impl Mul<Vector<$s>> for $s {
+            type Output = Vector<$s>;
+            fn mul(self, vector: Vector<$s>) -> Vector<$s> { vector * self }
+        }
+        impl Mul<Vector3<$s>> for $s {
+            type Output = Vector3<$s>;
+            fn mul(self, vector: Vector3<$s>) -> Vector3<$s> { vector * self }
+        }
+        impl Mul<Quaternion<$s>> for $s {
+            type Output = Quaternion<$s>;
+            fn mul(self, q: Quaternion<$s>) -> Quaternion<$s> { q * self }
+        }
+        impl Mul<Angle<$s>> for $s {
+            type Output = Angle<$s>;
+            fn mul(self, angle: Angle<$s>) -> Angle<$s> { angle * self }
+        }
+        impl Mul<Kinematics<$s>> for $s {
+            type Output = Kinematics<$s>;
+            fn mul(self, kinematics: Kinematics<$s>) -> Kinematics<$s> { kinematics * self }
+        }
+    )*}
+}
+left_scalar_mul!(f32, f64, Dual);
+
+// Writes a number at the formatter's precision, if it has one
+fn write_num<S: Scalar>(f: &mut fmt::Formatter, v: S) -> fmt::Result {
+    match f.precision() {
+        Some(p) => write!(f, "{:.*}", p, v.to_f64()),
+        None => write!(f, "{}", v.to_f64()),
+    }
+}
+
+// Display traits, which honour the precision, e.g. `{:.2}`
+impl<S: Scalar> fmt::Display for Vector<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        write_num(f, self.x)?;
+        write!(f, ", ")?;
+        write_num(f, self.y)?;
+        write!(f, ")")
+    }
+}
+impl<S: Scalar> fmt::Display for Vector3<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        write_num(f, self.x)?;
+        write!(f, ", ")?;
+        write_num(f, self.y)?;
+        write!(f, ", ")?;
+        write_num(f, self.z)?;
+        write!(f, ")")
+    }
+}
+impl<S: Scalar> fmt::Display for Angle<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write_num(f, self.signed_rad())
+        } else {
+            write_num(f, self.nice_deg())
+        }
+    }
+}
+impl<S: Scalar> fmt::Display for Kinematics<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.vec, f)?;
+        write!(f, ", ")?;
+        write_num(f, self.ang)
+    }
+}
+
+// A value displayed with its unit after it
+pub struct WithUnit<'a, T> {
+    value: &'a T,
+    unit: &'a str,
+}
+
+impl<T: fmt::Display> fmt::Display for WithUnit<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.value, f)?;
+        write!(f, " {}", self.unit)
+    }
+}
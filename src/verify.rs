@@ -0,0 +1,227 @@
+use crate::control::Command;
+use crate::error::DyntermError;
+use crate::gravity::G0;
+use crate::rk4::rk4;
+use crate::scenario::Scenario;
+use crate::summary::Summary;
+use crate::atmosphere::isa_density;
+use crate::vec::Vector;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Gravitational acceleration, as the vehicles use by default, m/s^2
+const G: f64 = G0;
+
+// Step the drops are integrated with, and the scenarios flown at, s
+const DT: f64 = 0.01;
+
+// Longest a scenario is flown for, s
+const MAX_TIME: f64 = 180.0;
+
+// Largest relative error in a scenario's results that passes. Loose enough
+// for the maths libraries of other platforms, tight enough for any change
+// to the physics to show.
+const GOLDEN_TOLERANCE: f64 = 1e-6;
+
+// Results the named scenarios gave when last blessed
+const GOLDEN: &str = include_str!("../data/golden.json");
+
+// The named scenarios with golden results
+pub const SCENARIOS: &[&str] = &["planar", "planar_heavy", "planar_low", "point_mass", "six_dof"];
+
+// Key results of a scenario, as stored in the golden file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Golden {
+    pub name: String,
+    pub flight_time: f64,
+    pub range: f64,
+    // Impact speed, if it came down
+    pub final_speed: f64,
+}
+
+// A comparison of something the simulator worked out against a known answer
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub expected: f64,
+    pub actual: f64,
+    // Largest relative error that passes
+    pub tolerance: f64,
+}
+
+impl Check {
+
+    // Returns the relative error
+    pub fn error(&self) -> f64 {
+        ((self.actual - self.expected) / self.expected).abs()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error() <= self.tolerance
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:<40} expected {:.6}, got {:.6} (error {:.1e}, tolerance {:.0e})",
+            if self.passed() { "PASS" } else { "FAIL" },
+            self.name, self.expected, self.actual, self.error(), self.tolerance)
+    }
+}
+
+// A body falling straight down with a constant drag coefficient, the one
+// case of flight through air with a closed-form solution
+struct Drop {
+    mass: f64,
+    // Drag coefficient times reference area, m^2
+    drag_area: f64,
+}
+
+impl Drop {
+
+    // Terminal velocity at air density `rho`
+    fn terminal_velocity(&self, rho: f64) -> f64 {
+        (2.0 * self.mass * G / (rho * self.drag_area)).sqrt()
+    }
+
+    // Falls from `altitude` for `time` seconds, with the air density given
+    // by `rho` at each altitude. The state is (altitude, downward speed).
+    fn fall(&self, altitude: f64, time: f64, rho: impl Fn(f64) -> f64) -> Vector {
+        let f = |_: f64, s: Vector| Vector::new(
+            -s.y(),
+            G - 0.5 * rho(s.x()) * self.drag_area * s.y() * s.y() / self.mass);
+
+        let mut state = Vector::new(altitude, 0.0);
+        for _ in 0..(time / DT).round() as usize {
+            state = rk4(f, state, 0.0, DT);
+        }
+        state
+    }
+}
+
+// Checks the integrator and the atmosphere against the terminal velocity
+// solution. In air of constant density the speed and distance fallen from
+// rest are v = vt tanh(g t / vt) and d = vt^2 / g ln cosh(g t / vt). Through
+// the standard atmosphere a slow enough body instead keeps to the terminal
+// velocity of the air it's in; for one settling at about 9 m/s it lags by a
+// few parts in 10^4.
+pub fn terminal_velocity() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let skydiver = Drop { mass: 80.0, drag_area: 0.5 };
+    let rho = isa_density(0.0);
+    let vt = skydiver.terminal_velocity(rho);
+    for time in [2.0, 5.0, 10.0, 30.0] {
+        let state = skydiver.fall(0.0, time, |_| rho);
+        let x = G * time / vt;
+        checks.push(Check {
+            name: format!("Speed after {} s at constant density", time),
+            expected: vt * x.tanh(),
+            actual: state.y(),
+            tolerance: 1e-9,
+        });
+        checks.push(Check {
+            name: format!("Distance after {} s at constant density", time),
+            expected: vt * vt / G * x.cosh().ln(),
+            actual: -state.x(),
+            tolerance: 1e-9,
+        });
+    }
+
+    let parachute = Drop { mass: 80.0, drag_area: 15.0 };
+    for time in [60.0, 120.0, 240.0] {
+        let state = parachute.fall(3_000.0, time, isa_density);
+        checks.push(Check {
+            name: format!("Speed after {} s through the atmosphere", time),
+            expected: parachute.terminal_velocity(isa_density(state.x())),
+            actual: state.y(),
+            tolerance: 1e-3,
+        });
+    }
+
+    checks
+}
+
+// Flies a named scenario to the ground or MAX_TIME, on the same built-in
+// pull-up as the CLI, or None if there's no scenario by that name
+pub fn fly(name: &str) -> Option<Golden> {
+    let scenario = Scenario { seed: Some(0), ..Scenario::default() };
+    let steps = (MAX_TIME / DT).round() as usize;
+
+    let summary = match name {
+        "planar" | "planar_heavy" | "planar_low" => {
+            let mut scenario = scenario;
+            match name {
+                "planar_heavy" => scenario.vehicle.mass *= 1.4,
+                // Too low to recover from the dive, so it comes down
+                "planar_low" => scenario.vehicle.altitude = 1_000.0,
+                _ => {}
+            }
+            let mut sim = scenario.build();
+            for _ in 0..steps {
+                Command::pull_up(&sim.vehicle).apply(&mut sim.vehicle);
+                sim.step(DT);
+                if sim.vehicle.grounded() { break; }
+            }
+            Summary::new(&sim)
+        }
+        "point_mass" => {
+            let mut sim = scenario.build_point_mass();
+            for _ in 0..steps {
+                sim.step(DT);
+                if sim.vehicle.grounded() { break; }
+            }
+            Summary::from_point_mass(&sim)
+        }
+        "six_dof" => {
+            let mut sim = scenario.build_six_dof();
+            for _ in 0..steps {
+                sim.vehicle.elev.set_pitch(Command::pull_up_at(sim.vehicle.body.position.y()).elevator);
+                sim.step(DT);
+                if sim.vehicle.body.position.y() <= 0.0 { break; }
+            }
+            Summary::from_six_dof(&sim)
+        }
+        _ => return None,
+    };
+
+    Some(Golden {
+        name: name.to_owned(),
+        flight_time: summary.flight_time,
+        range: summary.range,
+        final_speed: summary.final_speed,
+    })
+}
+
+// Returns the golden results, as stored
+pub fn goldens() -> Result<Vec<Golden>, DyntermError> {
+    serde_json::from_str(GOLDEN).map_err(|e| DyntermError::from(e).within("golden file"))
+}
+
+// Flies a named scenario and checks its results against the golden ones
+pub fn regression(name: &str) -> Result<Vec<Check>, DyntermError> {
+    let golden = goldens()?.into_iter()
+        .find(|g| g.name == name)
+        .ok_or_else(|| DyntermError::Config(format!("no golden results for scenario '{}'", name)))?;
+    let flown = fly(name)
+        .ok_or_else(|| DyntermError::Config(format!("no scenario named '{}'", name)))?;
+
+    let check = |quantity: &str, expected: f64, actual: f64| Check {
+        name: format!("{} {}", name, quantity),
+        expected,
+        actual,
+        tolerance: GOLDEN_TOLERANCE,
+    };
+    Ok(vec![
+        check("flight time", golden.flight_time, flown.flight_time),
+        check("range", golden.range, flown.range),
+        check("final speed", golden.final_speed, flown.final_speed),
+    ])
+}
+
+// Flies every named scenario, for writing out as the new goldens after a
+// deliberate change to the physics
+pub fn bless() -> Vec<Golden> {
+    SCENARIOS.iter().filter_map(|name| fly(name)).collect()
+}
@@ -0,0 +1,49 @@
+use crate::config::VehicleConfig;
+use crate::rng::SimRng;
+use crate::sim::Simulation;
+
+use wasm_bindgen::prelude::*;
+
+// JavaScript bindings, built with the `wasm` feature for
+// wasm32-unknown-unknown:
+//
+//   cargo rustc --lib --release --target wasm32-unknown-unknown \
+//       --no-default-features --features wasm --crate-type cdylib
+//
+// From JS:
+//
+//   const vehicle = newVehicle({ altitude: 5000, speed: 200 });
+//   vehicle.step(0.01);
+//   const { time, x, y, pitch, aoa } = vehicle.state();
+//
+// The config takes the same fields as `VehicleConfig`, and anything left
+// out takes its default.
+#[wasm_bindgen(js_name = Vehicle)]
+pub struct JsVehicle {
+    sim: Simulation,
+}
+
+// Builds a vehicle from a plain JS object (or undefined for the defaults)
+#[wasm_bindgen(js_name = newVehicle)]
+pub fn new_vehicle(config: JsValue) -> Result<JsVehicle, JsError> {
+    let config: VehicleConfig = if config.is_undefined() || config.is_null() {
+        VehicleConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config)?
+    };
+    Ok(JsVehicle { sim: Simulation::new(config.build(), SimRng::new(0)) })
+}
+
+#[wasm_bindgen(js_class = Vehicle)]
+impl JsVehicle {
+
+    // Advances the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f64) {
+        self.sim.step(dt);
+    }
+
+    // Returns the current state as a plain JS object
+    pub fn state(&self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.sim.state())?)
+    }
+}
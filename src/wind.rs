@@ -0,0 +1,118 @@
+#[cfg(feature = "std")]
+use crate::error::DyntermError;
+use crate::scalar::Scalar;
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use std::{path::Path, sync::Arc};
+
+// Wind varying downrange and with altitude, as worked out over terrain or
+// taken from a forecast, on a grid of `x`, m, and `altitude`, m, both
+// rising. Each node has the wind downrange, `along`, and upwards, `up`,
+// m/s, stored a row of x for each altitude in turn. Between nodes it's
+// interpolated bilinearly, and beyond the grid held at its edge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindGrid {
+    x: Vec<f64>,
+    altitude: Vec<f64>,
+    along: Vec<f64>,
+    up: Vec<f64>,
+}
+
+// Index of the cell along `axis` holding `value`, and how far across it, held
+// at the ends
+fn cell<S: Scalar>(axis: &[f64], value: S) -> (usize, S) {
+    if axis.len() == 1 { return (0, S::ZERO); }
+    let i = axis.partition_point(|&a| S::from_f64(a) <= value).clamp(1, axis.len() - 1) - 1;
+    let t = (value - S::from_f64(axis[i])) / S::from_f64(axis[i + 1] - axis[i]);
+    (i, t.max(S::ZERO).min(S::ONE))
+}
+
+impl WindGrid {
+
+    // Constructor, for winds given a row of `x` at each `altitude`. None if
+    // the axes don't rise or the winds don't fill the grid.
+    pub fn new(x: Vec<f64>, altitude: Vec<f64>, along: Vec<f64>, up: Vec<f64>) -> Option<WindGrid> {
+        let rising = |axis: &[f64]| !axis.is_empty() && axis.windows(2).all(|pair| pair[1] > pair[0]);
+        let nodes = x.len() * altitude.len();
+        if !rising(&x) || !rising(&altitude) || along.len() != nodes || up.len() != nodes {
+            return None;
+        }
+        Some(WindGrid { x, altitude, along, up })
+    }
+
+    // Gets the wind at `x` and `altitude`, downrange and upwards, m/s
+    pub fn at<S: Scalar>(&self, x: S, altitude: S) -> (S, S) {
+        let (i, s) = cell(&self.x, x);
+        let (j, t) = cell(&self.altitude, altitude);
+        let (i1, j1) = ((i + 1).min(self.x.len() - 1), (j + 1).min(self.altitude.len() - 1));
+        let n = self.x.len();
+        let blend = |values: &[f64]| {
+            let node = |i: usize, j: usize| S::from_f64(values[j * n + i]);
+            let below = node(i, j) + s * (node(i1, j) - node(i, j));
+            let above = node(i, j1) + s * (node(i1, j1) - node(i, j1));
+            below + t * (above - below)
+        };
+        (blend(&self.along), blend(&self.up))
+    }
+
+    // Reads a grid from CSV with the columns x, altitude, along, and up, one
+    // row per node in any order
+    #[cfg(feature = "std")]
+    pub fn load(path: impl AsRef<Path>) -> Result<WindGrid, DyntermError> {
+        #[derive(Deserialize)]
+        struct Row {
+            x: f64,
+            altitude: f64,
+            along: f64,
+            up: f64,
+        }
+
+        let rows = csv::Reader::from_path(path)?.deserialize()
+            .collect::<Result<Vec<Row>, _>>()?;
+
+        let axis = |value: fn(&Row) -> f64| {
+            let mut axis: Vec<f64> = rows.iter().map(value).collect();
+            axis.sort_by(f64::total_cmp);
+            axis.dedup();
+            axis
+        };
+        let (x, altitude) = (axis(|row| row.x), axis(|row| row.altitude));
+
+        let n = x.len();
+        let (mut along, mut up) = (vec![f64::NAN; n * altitude.len()], vec![f64::NAN; n * altitude.len()]);
+        for row in &rows {
+            let i = x.partition_point(|&a| a < row.x);
+            let j = altitude.partition_point(|&a| a < row.altitude);
+            (along[j * n + i], up[j * n + i]) = (row.along, row.up);
+        }
+        if let Some(k) = along.iter().position(|v| v.is_nan()) {
+            return Err(DyntermError::Parse(
+                format!("no wind at x = {} m, altitude = {} m", x[k % n], altitude[k / n])));
+        }
+
+        WindGrid::new(x, altitude, along, up)
+            .ok_or_else(|| DyntermError::Parse("no winds in the grid".into()))
+    }
+}
+
+// A wind grid to fly through, read from `path` when the scenario is loaded
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindGridFile {
+    pub path: String,
+    #[serde(skip)]
+    pub grid: Option<Arc<WindGrid>>,
+}
+
+#[cfg(feature = "std")]
+impl WindGridFile {
+
+    // Reads the grid
+    pub fn load(&mut self) -> Result<(), DyntermError> {
+        self.grid = Some(Arc::new(WindGrid::load(&self.path)?));
+        Ok(())
+    }
+}
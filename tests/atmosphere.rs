@@ -0,0 +1,59 @@
+// Checks the atmosphere models against one another
+
+use dynterm::atmosphere::{isa_pressure, isa_relative_density, isa_temperature, Atmosphere, AtmosphereTable, Level, Profile};
+
+use std::sync::Arc;
+
+#[test]
+fn standard_day_profile_matches_standard_density() {
+    let levels = (0..=20)
+        .map(|i| {
+            let altitude = i as f64 * 1000.0;
+            Level {
+                altitude,
+                temperature: isa_temperature(altitude),
+                pressure: isa_pressure(altitude),
+                humidity: 0.0,
+                wind_north: 0.0,
+                wind_east: 0.0,
+            }
+        })
+        .collect();
+    let measured = Atmosphere::Measured { profile: Arc::new(Profile::new(levels).unwrap()), heading: 0.0 };
+    let standard = Atmosphere::default();
+
+    for altitude in [0.0, 1000.0, 5000.0, 11000.0, 15000.0, 20000.0] {
+        let (m, s): (f64, f64) = (measured.density(altitude), standard.density(altitude));
+        assert!((m - s).abs() < 1e-6 * s, "{} against {} at {} m", m, s, altitude);
+    }
+}
+
+// The table behind atmo_density, sampled every 0.1 m, holds to within about
+// 3e-7 of the model, but for the grid cell just above the tropopause, which
+// bridges the step the model's pressure takes there
+#[test]
+fn table_matches_model() {
+    let table = AtmosphereTable::new(-1000.0, 50000.0, 10.0);
+    for i in 0..=510_000 {
+        let altitude = -1000.0 + i as f64 * 0.1;
+        if (11000.0..11010.0).contains(&altitude) { continue; }
+        let (t, m): (f64, f64) = (table.density(altitude), isa_relative_density(altitude));
+        assert!(((t - m) / m).abs() < 3.2e-7, "{} against {} at {} m", t, m, altitude);
+    }
+}
+
+#[test]
+fn profile_is_checked_however_it_is_made() {
+    assert!(Profile::new(Vec::new()).is_err());
+    assert!(serde_json::from_str::<Profile>(r#"{"levels": []}"#).is_err());
+
+    let level = |altitude: f64, pressure: f64| format!(
+        r#"{{"altitude": {}, "temperature": 288.15, "pressure": {}, "humidity": 0, "wind_north": 0, "wind_east": 0}}"#,
+        altitude, pressure);
+    let json = format!(r#"{{"levels": [{}, {}]}}"#, level(1000.0, 89874.6), level(0.0, 101325.0));
+    let profile: Profile = serde_json::from_str(&json).unwrap();
+    assert_eq!(profile.levels()[0].altitude, 0.0);
+
+    let json = format!(r#"{{"levels": [{}]}}"#, level(0.0, -1.0));
+    assert!(serde_json::from_str::<Profile>(&json).is_err());
+}
@@ -0,0 +1,30 @@
+// Checks the C interface reports failures rather than printing them
+
+use dynterm::ffi::{dynterm_free, dynterm_last_error, dynterm_new, dynterm_state, dynterm_step, DYNTERM_STATE_LEN};
+
+use std::ffi::CStr;
+
+#[test]
+fn invalid_config_is_reported_through_last_error() {
+    unsafe {
+        let sim = dynterm_new(c"{\"mass\": \"heavy\"}".as_ptr());
+        assert!(sim.is_null());
+        let error = dynterm_last_error();
+        assert!(!error.is_null());
+        let message = CStr::from_ptr(error).to_str().unwrap();
+        assert!(message.starts_with("invalid config"), "{}", message);
+    }
+}
+
+#[test]
+fn default_vehicle_steps() {
+    unsafe {
+        let sim = dynterm_new(std::ptr::null());
+        assert!(!sim.is_null());
+        dynterm_step(sim, 0.5);
+        let mut state = [0.0; DYNTERM_STATE_LEN];
+        assert_eq!(dynterm_state(sim, state.as_mut_ptr(), state.len()), DYNTERM_STATE_LEN);
+        assert_eq!(state[0], 0.5);
+        dynterm_free(sim);
+    }
+}
@@ -0,0 +1,47 @@
+// Checks the fleet's vectorised dynamics against the simulation's
+
+use dynterm::control::Command;
+use dynterm::earth::Earth;
+use dynterm::fleet::Fleet;
+use dynterm::scenario::Scenario;
+use dynterm::sim::Simulation;
+use dynterm::vec::Vector;
+
+// Step both fly at, and how long they fly for, s
+const DT: f64 = 0.01;
+const FLIGHT: f64 = 30.0;
+
+// Flies `sim` and a fleet of it alone under the built-in pull-up, checking
+// they stay together
+fn fly_both(mut sim: Simulation) {
+    let mut fleet = Fleet::new(&sim.vehicle);
+    fleet.push(&sim.vehicle);
+
+    while sim.time < FLIGHT {
+        Command::pull_up(&sim.vehicle).apply(&mut sim.vehicle);
+        fleet.pull_up();
+        sim.step(DT);
+        fleet.step(DT);
+
+        let (a, b) = (sim.state(), fleet.state(0));
+        // The fleet steps the position after the motion, where the
+        // simulation steps them together, which over a round earth parts
+        // them by a few parts in a billion
+        let close = |x: f64, y: f64| (x - y).abs() <= 1e-8 * x.abs().max(1.0);
+        assert!(close(a.x, b.x) && close(a.y, b.y), "position {:?} against {:?}", a, b);
+        assert!(close(a.vx, b.vx) && close(a.vy, b.vy), "velocity {:?} against {:?}", a, b);
+        assert!(close(a.pitch, b.pitch) && close(a.pitch_rate, b.pitch_rate), "attitude {:?} against {:?}", a, b);
+    }
+}
+
+#[test]
+fn fleet_follows_simulation() {
+    fly_both(Scenario::default().build());
+}
+
+#[test]
+fn fleet_follows_simulation_in_wind_over_round_earth() {
+    let mut sim = Scenario { earth: Earth::round(), ..Scenario::default() }.build();
+    sim.vehicle.wind = Vector::new(-15.0, 2.0);
+    fly_both(sim);
+}
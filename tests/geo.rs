@@ -0,0 +1,17 @@
+// Checks the geographic origin's conventions
+
+use dynterm::geo::Origin;
+
+use std::f64::consts::PI;
+
+#[test]
+fn heading_wraps_into_half_open_turn() {
+    for (heading, expected) in [
+        (0.0, 0.0), (90.0, PI / 2.0), (180.0, PI), (-180.0, PI), (270.0, -PI / 2.0),
+        (-270.0, PI / 2.0), (-90.0, -PI / 2.0), (540.0, PI), (-450.0, -PI / 2.0),
+    ] {
+        let found = Origin { heading, ..Origin::default() }.heading_rad();
+        assert!(found > -PI && found <= PI, "{} degrees gave {}", heading, found);
+        assert!((found - expected).abs() < 1e-12, "{} degrees gave {} rather than {}", heading, found, expected);
+    }
+}
@@ -0,0 +1,49 @@
+// Flies the named scenarios and checks their results against the goldens in
+// data/golden.json. Slow, so only built with `cargo test --features regression`.
+#![cfg(feature = "regression")]
+
+use dynterm::verify::{regression, terminal_velocity};
+
+fn assert_passes(name: &str) {
+    let checks = regression(name).unwrap();
+    for check in &checks {
+        assert!(check.passed(), "{}", check);
+    }
+}
+
+#[test]
+fn terminal_velocity_matches_closed_form() {
+    for check in terminal_velocity() {
+        assert!(check.passed(), "{}", check);
+    }
+}
+
+#[test]
+fn planar() {
+    assert_passes("planar");
+}
+
+#[test]
+fn planar_heavy() {
+    assert_passes("planar_heavy");
+}
+
+#[test]
+fn planar_low() {
+    assert_passes("planar_low");
+}
+
+#[test]
+fn point_mass() {
+    assert_passes("point_mass");
+}
+
+#[test]
+fn six_dof() {
+    assert_passes("six_dof");
+}
+
+#[test]
+fn unknown_scenario_is_an_error() {
+    assert!(regression("no_such_scenario").is_err());
+}
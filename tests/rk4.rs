@@ -0,0 +1,121 @@
+// Checks the integrators on problems with known behaviour
+
+use dynterm::rk4::{implicit_midpoint_step, rk4_step, Hermite, Integrator};
+use dynterm::scenario::Scenario;
+use dynterm::vec::Vector;
+
+// Rate of decay of the stiff test problem, 1/s
+const STIFF: f64 = 1000.0;
+
+// A step ten times the stiff problem's time constant, well outside RK4's
+// stability region, which ends near 2.8 time constants
+const STIFF_STEP: f64 = 0.01;
+
+// y' = -1000 y, from y = 1
+fn decay(_: f64, y: &Vector) -> Vector {
+    Vector::new(-STIFF * y.x(), -STIFF * y.y())
+}
+
+#[test]
+fn implicit_midpoint_is_stable_where_rk4_diverges() {
+    let (mut explicit, mut implicit) = (Vector::new(1.0, -1.0), Vector::new(1.0, -1.0));
+    for i in 0..100 {
+        let t = i as f64 * STIFF_STEP;
+        rk4_step(decay, &mut explicit, t, STIFF_STEP);
+        let (_, converged) = implicit_midpoint_step(decay, &mut implicit, t, STIFF_STEP);
+        assert!(converged, "a linear problem should converge at step {}", i);
+    }
+    assert!(explicit.x().abs() > 1e10, "RK4 should diverge, reaching {}", explicit.x());
+    assert!(implicit.x().abs() < 1e-15 && implicit.y().abs() < 1e-15, "implicit midpoint should decay, reaching {:?}", implicit);
+}
+
+#[test]
+fn implicit_midpoint_matches_its_linear_amplification() {
+    // For y' = A y each step multiplies y by (I - h A/2)^-1 (I + h A/2). A
+    // is coupled, and its first entry zeroes the first pivot of I - h A/2,
+    // so the solve has to swap rows.
+    let a = [[2.0 / STIFF_STEP, 300.0], [-400.0, -STIFF]];
+    let coupled = |_: f64, y: &Vector| Vector::new(
+        a[0][0] * y.x() + a[0][1] * y.y(),
+        a[1][0] * y.x() + a[1][1] * y.y());
+    let mut y = Vector::new(1.0, 2.0);
+    let (_, converged) = implicit_midpoint_step(coupled, &mut y, 0.0, STIFF_STEP);
+    assert!(converged);
+
+    let half = 0.5 * STIFF_STEP;
+    let (m, p) = (
+        [[1.0 - half * a[0][0], -half * a[0][1]], [-half * a[1][0], 1.0 - half * a[1][1]]],
+        [[1.0 + half * a[0][0], half * a[0][1]], [half * a[1][0], 1.0 + half * a[1][1]]],
+    );
+    let rhs = [p[0][0] + 2.0 * p[0][1], p[1][0] + 2.0 * p[1][1]];
+    let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    let expected = (
+        (m[1][1] * rhs[0] - m[0][1] * rhs[1]) / det,
+        (m[0][0] * rhs[1] - m[1][0] * rhs[0]) / det,
+    );
+    assert!((y.x() - expected.0).abs() < 1e-9 && (y.y() - expected.1).abs() < 1e-9,
+        "{:?} against {:?}", y, expected);
+}
+
+#[test]
+fn implicit_midpoint_reports_newton_running_out() {
+    // y' = -1000 y^3 over a long step from far out: Newton closes on the
+    // midpoint by about a third each time, too slowly to converge
+    let cubic = |_: f64, y: &Vector| Vector::new(-STIFF * y.x().powi(3), -STIFF * y.y().powi(3));
+    let mut y = Vector::new(10.0, 10.0);
+    let (evaluations, converged) = implicit_midpoint_step(cubic, &mut y, 0.0, 1.0);
+    assert!(!converged);
+    assert!(evaluations > 0);
+}
+
+#[test]
+fn simulation_counts_unconverged_steps() {
+    let mut converging = Scenario::default().build();
+    converging.vehicle.integrator = Integrator::ImplicitMidpoint { substep: 0.002 };
+    // A tonne pitching about an inertia of 1 kg m^2 is stiff, and
+    // second-long steps leave Newton too far from the midpoint to close on it
+    let mut stretched = Scenario::default().build();
+    (stretched.vehicle.mass, stretched.vehicle.moment) = (1000.0, 1.0);
+    stretched.vehicle.integrator = Integrator::ImplicitMidpoint { substep: 1.0 };
+    for _ in 0..10 {
+        converging.step(0.1);
+        stretched.step(1.0);
+    }
+    assert_eq!(converging.stats.unconverged, 0);
+    assert!(stretched.stats.unconverged > 0);
+}
+
+// x = t^3 - 2t^2 + t + 1 and y = t^2 - t, and their rates
+fn cubic(t: f64) -> (Vector, Vector) {
+    (
+        Vector::new(t * t * t - 2.0 * t * t + t + 1.0, t * t - t),
+        Vector::new(3.0 * t * t - 4.0 * t + 1.0, 2.0 * t - 1.0),
+    )
+}
+
+#[test]
+fn hermite_reproduces_a_cubic() {
+    let dense = Hermite::new(0.5, cubic(0.5), 2.0, cubic(2.0));
+    for i in 0..=30 {
+        let t = 0.5 + 0.05 * i as f64;
+        let (expected, _) = cubic(t);
+        let found = dense.at(t);
+        assert!((found.x() - expected.x()).abs() < 1e-12 && (found.y() - expected.y()).abs() < 1e-12,
+            "{:?} against {:?} at {}", found, expected, t);
+    }
+}
+
+#[test]
+fn hermite_finds_the_first_crossing() {
+    // (t - 0.2)(t - 0.5)(t - 0.8) crosses zero three times in the step
+    let g = |t: f64| (t - 0.2) * (t - 0.5) * (t - 0.8);
+    let rate = |t: f64| 3.0 * t * t - 3.0 * t + 0.66;
+    let dense = Hermite::new(
+        0.0, (Vector::new(g(0.0), 0.0), Vector::new(rate(0.0), 0.0)),
+        1.0, (Vector::new(g(1.0), 0.0), Vector::new(rate(1.0), 0.0)));
+    let crossing = dense.crossing(|y| y.x()).unwrap();
+    assert!((crossing - 0.2).abs() < 1e-12, "crossed at {}", crossing);
+
+    // and a step that stays clear of zero doesn't cross
+    assert!(dense.crossing(|y| y.x() + 1.0).is_none());
+}
@@ -0,0 +1,57 @@
+// Checks the dual numbers' derivatives against finite differences
+
+use dynterm::config::VehicleConfig;
+use dynterm::scalar::{Dual, Scalar};
+
+// Step of the central differences, and how closely they should agree
+const H: f64 = 1e-6;
+const TOLERANCE: f64 = 1e-6;
+
+// Checks the derivative `f` carries through at `x` against a central
+// difference of its value
+fn check(name: &str, f: impl Fn(Dual) -> Dual, x: f64) {
+    let exact = f(Dual::variable(x)).eps;
+    let estimate = (f(Dual::constant(x + H)).re - f(Dual::constant(x - H)).re) / (2.0 * H);
+    assert!((exact - estimate).abs() <= TOLERANCE * estimate.abs().max(1.0),
+        "{} at {}: {} against {}", name, x, exact, estimate);
+}
+
+#[test]
+fn derivatives_match_finite_differences() {
+    let c = Dual::constant;
+    for x in [-0.7, 0.3, 0.9] {
+        check("atan2 of y", |y| y.atan2(c(0.4)), x);
+        check("atan2 of x", |x| c(0.4).atan2(x), x);
+        check("atan2 of both", |t| (t * c(2.0)).atan2(t + c(1.5)), x);
+        check("asin", |x| x.asin(), x);
+    }
+    for x in [0.5, 1.3, 4.0] {
+        check("powf of base", |x| x.powf(c(2.7)), x);
+        check("powf of exponent", |n| c(1.8).powf(n), x);
+        check("powf of both", |t| t.powf(t), x);
+    }
+    for x in [2.3, 5.1, -3.7] {
+        check("rem of dividend", |x| x % c(1.1), x);
+        check("rem of divisor", |d| c(7.9) % d, x);
+    }
+}
+
+#[test]
+fn range_sensitivity_to_mass_matches_finite_differences() {
+    let config = VehicleConfig::default();
+    let range = |mass: Dual| {
+        let mut vehicle = config.build_as::<Dual>();
+        vehicle.mass = mass;
+        for _ in 0..20 {
+            vehicle.apply_dynamics(Dual::constant(1.0), 100);
+        }
+        vehicle.position.x()
+    };
+
+    let mass = config.mass;
+    let exact = range(Dual::variable(mass)).eps;
+    let step = 1e-4 * mass;
+    let estimate = (range(Dual::constant(mass + step)).re - range(Dual::constant(mass - step)).re) / (2.0 * step);
+    assert!(exact != 0.0);
+    assert!((exact - estimate).abs() <= 1e-4 * estimate.abs(), "{} m/kg against {} m/kg", exact, estimate);
+}